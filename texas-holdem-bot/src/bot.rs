@@ -0,0 +1,528 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_renet::renet::{ClientAuthentication, RenetClient};
+use texas_holdem_common::{
+    betting::{BettingStructure, ForcedBets, RakeConfig},
+    channel::{
+        CreateRoomMessage, EnterRoomMessage, GetRoomsMessage, HandshakeMessage,
+        PlayerActionMessage, RequestError, RoomSyncMessage, RunItTwiceMessage, SetRoomStateMessage,
+        SwitchPlayerRoleMessage, CREATE_ROOM_CHANNEL_ID, ENTER_ROOT_CHANNEL_ID,
+        GET_ROOMS_CHANNEL_ID, HANDSHAKE_CHANNEL_ID, PLAYER_ACTION_CHANNEL_ID, ROOM_SYNC_CHANNEL_ID,
+        RUN_IT_TWICE_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID, SWITCH_PLAYER_ROLE_CHANNEL_ID,
+    },
+    connection_config,
+    lobby::{RoomFilter, RoomSort, MAX_ROOMS_PAGE_SIZE},
+    spectator::SpectatorConfig,
+    sync::{RoomSyncParts, RoomView, SyncResult},
+    tournament::TournamentConfig,
+    variant::GameVariant,
+    PlayerRole, RoomState, RoundAction, PROTOCOL_ID, PROTOCOL_VERSION,
+};
+
+use crate::strategy::{Decision, DecisionContext, Strategy};
+
+// 查询房间列表的间隔（秒）
+const GET_ROOMS_INTERVAL_SECS: f32 = 1.0;
+// 重试开始游戏的间隔（秒）
+const START_PLAY_INTERVAL_SECS: f32 = 1.0;
+
+// 机器人配置
+#[derive(Debug, Clone, Resource)]
+pub struct BotSettings {
+    pub server_addr: SocketAddr,
+    pub player_name: String,
+    pub room_name: String,
+    pub room_password: String,
+    // 创建房间，否则按名称查找并进入房间
+    pub create_room: bool,
+    // 房主在参与者人数足够时自动开始游戏
+    pub auto_start: bool,
+    // 完成指定局数后退出
+    pub max_hands: Option<u32>,
+    // 创建房间时的玩法
+    pub variant: GameVariant,
+    // 创建房间时的下注结构
+    pub betting: BettingStructure,
+    // 创建房间时允许发两次
+    pub run_it_twice: bool,
+    // 创建房间时的前注和跨注
+    pub forced_bets: ForcedBets,
+    // 创建现金桌时的抽水
+    pub rake: RakeConfig,
+    // 创建锦标赛房间，坐满后自动开始
+    pub tournament: Option<TournamentConfig>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BotPhase {
+    // 查找或创建房间
+    #[default]
+    Lobby,
+    // 已发出创建或进入房间请求
+    Joining,
+    // 已在房间内
+    InRoom,
+}
+
+// 机器人当前状态
+#[derive(Debug, Default, Resource)]
+pub struct BotState {
+    pub phase: BotPhase,
+    pub view: RoomView,
+    pub my_role: PlayerRole,
+    next_request_id: u64,
+    // 已行动的 (对局id, 同步序号)，避免同一状态重复行动
+    acted_at: Option<(u64, Option<u64>)>,
+    // 已表决过两次发牌的对局id
+    answered_run_it_twice: Option<u64>,
+    // 已完成的局数
+    pub hands_played: u32,
+    last_play_id: Option<u64>,
+}
+
+impl BotState {
+    fn next_request_id(&mut self) -> u64 {
+        self.next_request_id += 1;
+        self.next_request_id
+    }
+}
+
+#[derive(Resource)]
+pub struct BotStrategy(pub Box<dyn Strategy>);
+
+pub fn new_renet_client(server_addr: SocketAddr) -> std::io::Result<RenetClient> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    // 同时启动多个机器人时时间戳会重复，使用随机id
+    let client_id = rand::random();
+    let authentication = ClientAuthentication::Unsecure {
+        client_id,
+        protocol_id: PROTOCOL_ID,
+        server_addr,
+        user_data: Some(PROTOCOL_VERSION.to_user_data()),
+    };
+    RenetClient::new(current_time, socket, connection_config(), authentication)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+pub fn receive_handshake(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut app_exit_ew: EventWriter<AppExit>,
+) {
+    while let Some(message) = client.receive_message(HANDSHAKE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<HandshakeMessage>(&message) {
+            info!(
+                "{} received handshake message: {:?}",
+                settings.player_name, message
+            );
+            if !message.accepted {
+                error!(
+                    "{} rejected by server: {:?}",
+                    settings.player_name, message.reason
+                );
+                app_exit_ew.send(AppExit);
+            }
+        }
+    }
+    if let Some(reason) = client.disconnected() {
+        error!("{} disconnected: {}", settings.player_name, reason);
+        app_exit_ew.send(AppExit);
+    }
+}
+
+// 创建房间，或查询房间列表并进入同名房间
+pub fn join_room(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut bot_state: ResMut<BotState>,
+    mut refresh_cd: Local<f32>,
+    time: Res<Time>,
+) {
+    if !client.is_connected() {
+        return;
+    }
+
+    if bot_state.phase == BotPhase::Lobby {
+        if settings.create_room {
+            let message = CreateRoomMessage {
+                request_id: bot_state.next_request_id(),
+                room_name: settings.room_name.clone(),
+                room_password: settings.room_password.clone(),
+                player_name: settings.player_name.clone(),
+                variant: settings.variant,
+                betting: settings.betting,
+                run_it_twice: settings.run_it_twice,
+                forced_bets: settings.forced_bets,
+                rake: settings.rake,
+                spectators: SpectatorConfig::default(),
+                tournament: settings.tournament.clone(),
+                room_id: 0,
+                error: None,
+            };
+            client.send_message(
+                CREATE_ROOM_CHANNEL_ID,
+                serde_json::to_vec(&message).unwrap(),
+            );
+            bot_state.phase = BotPhase::Joining;
+        } else {
+            *refresh_cd -= time.delta_seconds();
+            if *refresh_cd < 0.0 {
+                // 按房间名搜索，同名房间不会超过一页
+                let message = GetRoomsMessage {
+                    request_id: bot_state.next_request_id(),
+                    filter: RoomFilter {
+                        search: settings.room_name.clone(),
+                        ..Default::default()
+                    },
+                    sort: RoomSort::default(),
+                    page: 0,
+                    page_size: MAX_ROOMS_PAGE_SIZE,
+                    rooms: Vec::new(),
+                    total: 0,
+                };
+                client.send_message(GET_ROOMS_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+                *refresh_cd = GET_ROOMS_INTERVAL_SECS;
+            }
+        }
+    }
+
+    while let Some(message) = client.receive_message(GET_ROOMS_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<GetRoomsMessage>(&message) {
+            if bot_state.phase != BotPhase::Lobby {
+                continue;
+            }
+            if let Some(room) = message
+                .rooms
+                .iter()
+                .find(|room| room.room_name == settings.room_name)
+            {
+                let message = EnterRoomMessage {
+                    request_id: bot_state.next_request_id(),
+                    room_id: room.room_id,
+                    room_password: settings.room_password.clone(),
+                    invite_code: String::new(),
+                    player_name: settings.player_name.clone(),
+                    error: None,
+                };
+                client.send_message(ENTER_ROOT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+                bot_state.phase = BotPhase::Joining;
+            }
+        }
+    }
+
+    let mut joined_room_id = None;
+    while let Some(message) = client.receive_message(CREATE_ROOM_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<CreateRoomMessage>(&message) {
+            info!(
+                "{} received create room message: {:?}",
+                settings.player_name, message
+            );
+            match message.error {
+                Some(error) => error!(
+                    "{} failed to create room: {}",
+                    settings.player_name,
+                    error.message()
+                ),
+                None => joined_room_id = Some(message.room_id),
+            }
+        }
+    }
+    while let Some(message) = client.receive_message(ENTER_ROOT_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<EnterRoomMessage>(&message) {
+            info!(
+                "{} received enter room message: {:?}",
+                settings.player_name, message
+            );
+            match message.error {
+                Some(error) => {
+                    // 进入失败后重新查找房间
+                    warn!(
+                        "{} failed to enter room: {}",
+                        settings.player_name,
+                        error.message()
+                    );
+                    bot_state.phase = BotPhase::Lobby;
+                }
+                None => joined_room_id = Some(message.room_id),
+            }
+        }
+    }
+
+    // 进入房间后切换为参与者
+    if let Some(room_id) = joined_room_id {
+        bot_state.phase = BotPhase::InRoom;
+        bot_state.view = RoomView::new(room_id);
+        let message = SwitchPlayerRoleMessage {
+            request_id: bot_state.next_request_id(),
+            room_id,
+            target_player_role: PlayerRole::Participant,
+            error: None,
+        };
+        client.send_message(
+            SWITCH_PLAYER_ROLE_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+    }
+}
+
+pub fn receive_room_sync(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut bot_state: ResMut<BotState>,
+    mut app_exit_ew: EventWriter<AppExit>,
+    mut parts: Local<RoomSyncParts>,
+) {
+    while let Some(message) = client.receive_message(ROOM_SYNC_CHANNEL_ID) {
+        if let Some(message) = parts.decode(&message) {
+            if bot_state.view.apply(message) == SyncResult::Gap {
+                // 序号出现缺口，重新请求快照
+                warn!(
+                    "{} room sync gap, requesting snapshot",
+                    settings.player_name
+                );
+                let message = RoomSyncMessage::RequestSnapshot {
+                    room_id: bot_state.view.room_id,
+                };
+                client.send_message(ROOM_SYNC_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+                continue;
+            }
+        }
+    }
+
+    let client_id = client.client_id();
+    if let Some(player) = bot_state
+        .view
+        .players
+        .iter()
+        .find(|player| player.player_client_id == client_id)
+    {
+        bot_state.my_role = player.player_role;
+    }
+
+    // 对局结束后计数
+    let play_id = bot_state.view.play.as_ref().map(|play| play.play_id);
+    if bot_state.last_play_id.is_some() && play_id != bot_state.last_play_id {
+        bot_state.hands_played += 1;
+        info!(
+            "{} finished hand {}",
+            settings.player_name, bot_state.hands_played
+        );
+        if settings
+            .max_hands
+            .is_some_and(|max_hands| bot_state.hands_played >= max_hands)
+        {
+            app_exit_ew.send(AppExit);
+        }
+    }
+    bot_state.last_play_id = play_id;
+
+    // 锦标赛结束后退出
+    if bot_state.view.room_state == RoomState::Finished {
+        if let Some(result) = bot_state.view.tournament.as_ref().and_then(|tournament| {
+            tournament
+                .results
+                .iter()
+                .find(|result| result.player_name == settings.player_name)
+        }) {
+            info!(
+                "{} finished #{} with prize {}",
+                settings.player_name, result.place, result.prize
+            );
+        }
+        app_exit_ew.send(AppExit);
+    }
+
+    while let Some(message) = client.receive_message(SWITCH_PLAYER_ROLE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<SwitchPlayerRoleMessage>(&message) {
+            if let Some(error) = message.error {
+                error!(
+                    "{} failed to switch role: {}",
+                    settings.player_name,
+                    error.message()
+                );
+            }
+        }
+    }
+}
+
+// 房主在参与者人数足够时开始游戏
+pub fn auto_start_play(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut bot_state: ResMut<BotState>,
+    mut start_cd: Local<f32>,
+    time: Res<Time>,
+) {
+    *start_cd -= time.delta_seconds();
+    let participant_count = bot_state
+        .view
+        .players
+        .iter()
+        .filter(|player| player.player_role == PlayerRole::Participant)
+        .count();
+    // 锦标赛房间坐满后由服务端开始
+    if settings.auto_start
+        && settings.create_room
+        && bot_state.phase == BotPhase::InRoom
+        && bot_state.view.tournament.is_none()
+        && bot_state.view.room_state != RoomState::Playing
+        && participant_count >= 3
+        && *start_cd < 0.0
+    {
+        let message = SetRoomStateMessage {
+            request_id: bot_state.next_request_id(),
+            room_id: bot_state.view.room_id,
+            target_room_state: RoomState::Playing,
+            error: None,
+        };
+        client.send_message(
+            SET_ROOM_STATE_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+        *start_cd = START_PLAY_INTERVAL_SECS;
+    }
+
+    while let Some(message) = client.receive_message(SET_ROOM_STATE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<SetRoomStateMessage>(&message) {
+            if let Some(error) = message.error {
+                error!(
+                    "{} failed to set room state: {}",
+                    settings.player_name,
+                    error.message()
+                );
+            }
+        }
+    }
+}
+
+// 轮到自己时按策略行动
+pub fn play_turn(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut bot_state: ResMut<BotState>,
+    mut strategy: ResMut<BotStrategy>,
+) {
+    let bot_state = &mut *bot_state;
+    if let Some(play) = bot_state.view.play.as_ref() {
+        let acted_at = (play.play_id, bot_state.view.seq);
+        if play.current_player_name.as_deref() == Some(settings.player_name.as_str())
+            && bot_state.acted_at != Some(acted_at)
+        {
+            let context = DecisionContext {
+                betting: bot_state.view.betting,
+                play,
+                hole_cards: &bot_state.view.hole_cards,
+                state: play.bet_state(&settings.player_name),
+            };
+            let decision = strategy.0.decide(&context);
+            info!("{} decided {:?}", settings.player_name, decision);
+            bot_state.next_request_id += 1;
+            send_action(
+                &mut client,
+                bot_state.next_request_id,
+                &bot_state.view,
+                decision,
+            );
+            bot_state.acted_at = Some(acted_at);
+        }
+    }
+
+    while let Some(message) = client.receive_message(PLAYER_ACTION_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<PlayerActionMessage>(&message) {
+            // 策略给出非法行动时改为过牌或弃牌
+            if let Some(error) = message.error {
+                warn!(
+                    "{} action {:?} rejected: {}",
+                    settings.player_name,
+                    message.action,
+                    error.message()
+                );
+                if error == RequestError::InvalidAction {
+                    let fallback = match bot_state.view.play.as_ref() {
+                        Some(play)
+                            if play.bets.get(&settings.player_name).copied().unwrap_or(0)
+                                >= play.current_bet =>
+                        {
+                            RoundAction::Check
+                        }
+                        _ => RoundAction::Fold,
+                    };
+                    bot_state.next_request_id += 1;
+                    send_action(
+                        &mut client,
+                        bot_state.next_request_id,
+                        &bot_state.view,
+                        Decision::new(fallback),
+                    );
+                }
+            }
+        }
+    }
+}
+
+// 全下后总是同意发两次
+pub fn answer_run_it_twice(
+    mut client: ResMut<RenetClient>,
+    settings: Res<BotSettings>,
+    mut bot_state: ResMut<BotState>,
+) {
+    let waiting_play_id = bot_state.view.play.as_ref().and_then(|play| {
+        play.run_it_twice_vote
+            .as_ref()
+            .filter(|vote| vote.is_waiting_for(&settings.player_name))
+            .map(|_| play.play_id)
+    });
+    if let Some(play_id) = waiting_play_id {
+        if bot_state.answered_run_it_twice != Some(play_id) {
+            let message = RunItTwiceMessage {
+                request_id: bot_state.next_request_id(),
+                room_id: bot_state.view.room_id,
+                play_id,
+                accept: true,
+                error: None,
+            };
+            client.send_message(
+                RUN_IT_TWICE_CHANNEL_ID,
+                serde_json::to_vec(&message).unwrap(),
+            );
+            bot_state.answered_run_it_twice = Some(play_id);
+        }
+    }
+
+    while let Some(message) = client.receive_message(RUN_IT_TWICE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<RunItTwiceMessage>(&message) {
+            if let Some(error) = message.error {
+                warn!(
+                    "{} run it twice answer rejected: {}",
+                    settings.player_name,
+                    error.message()
+                );
+            }
+        }
+    }
+}
+
+fn send_action(client: &mut RenetClient, request_id: u64, view: &RoomView, decision: Decision) {
+    let Some(play) = view.play.as_ref() else {
+        return;
+    };
+    let message = PlayerActionMessage {
+        request_id,
+        room_id: view.room_id,
+        play_id: play.play_id,
+        action: decision.action,
+        amount: decision.amount,
+        error: None,
+    };
+    client.send_message(
+        PLAYER_ACTION_CHANNEL_ID,
+        serde_json::to_vec(&message).unwrap(),
+    );
+}
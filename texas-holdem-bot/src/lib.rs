@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use bevy::{app::ScheduleRunnerSettings, log::LogPlugin, prelude::*};
+use bevy_renet::RenetClientPlugin;
+use bot::{
+    answer_run_it_twice, auto_start_play, join_room, new_renet_client, play_turn,
+    receive_handshake, receive_room_sync, BotSettings, BotState, BotStrategy,
+};
+use strategy::Strategy;
+
+pub mod bot;
+pub mod strategy;
+
+// 主循环间隔，避免空转占满CPU
+const TICK_INTERVAL: Duration = Duration::from_millis(16);
+
+// 构建无界面的机器人客户端，多个机器人在同一进程内运行时只需一个添加日志插件
+pub fn build_bot_app(
+    settings: BotSettings,
+    strategy: Box<dyn Strategy>,
+    log_plugin: Option<LogPlugin>,
+) -> std::io::Result<App> {
+    let client = new_renet_client(settings.server_addr)?;
+    let mut app = App::new();
+    app.insert_resource(ScheduleRunnerSettings::run_loop(TICK_INTERVAL))
+        .add_plugins(MinimalPlugins);
+    if let Some(log_plugin) = log_plugin {
+        app.add_plugin(log_plugin);
+    }
+    app.add_plugin(RenetClientPlugin::default())
+        .insert_resource(client)
+        .insert_resource(settings)
+        .insert_resource(BotState::default())
+        .insert_resource(BotStrategy(strategy))
+        .add_systems((
+            receive_handshake,
+            join_room,
+            receive_room_sync,
+            auto_start_play,
+            play_turn,
+            answer_run_it_twice,
+        ));
+    Ok(app)
+}
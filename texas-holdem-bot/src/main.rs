@@ -0,0 +1,222 @@
+use std::{net::SocketAddr, process, thread};
+
+use bevy::log::{Level, LogPlugin};
+use clap::{Parser, ValueEnum};
+use texas_holdem_bot::{bot::BotSettings, build_bot_app, strategy::StrategyKind};
+use texas_holdem_common::{
+    betting::{AnteKind, BettingStructure, ForcedBets, RakeConfig},
+    lobby::{is_valid_name, MAX_PLAYER_NAME_CHARS, MAX_ROOM_NAME_CHARS},
+    tournament::{TournamentConfig, MAX_TOURNAMENT_SEATS, MAX_TOURNAMENT_TABLES},
+    variant::GameVariant,
+};
+
+// 命令行参数
+#[derive(Debug, Parser)]
+#[command(version, about = "Headless Texas Holdem bot")]
+struct Cli {
+    /// 服务端地址
+    #[arg(long, default_value = "127.0.0.1:5000")]
+    server: SocketAddr,
+    /// 玩家名称，启动多个机器人时追加序号
+    #[arg(long, default_value = "Bot")]
+    name: String,
+    /// 房间名称
+    #[arg(long, default_value = "bots")]
+    room: String,
+    /// 房间密码
+    #[arg(long, default_value = "")]
+    password: String,
+    /// 由第一个机器人创建房间，否则进入已有的同名房间
+    #[arg(long)]
+    create: bool,
+    /// 房主在参与者不少于3人时自动开始游戏
+    #[arg(long)]
+    auto_start: bool,
+    /// 行动策略
+    #[arg(long, value_enum, default_value_t = StrategyKind::Passive)]
+    strategy: StrategyKind,
+    /// 同一进程内启动的机器人数量
+    #[arg(long, default_value_t = 1)]
+    count: usize,
+    /// 完成指定局数后退出
+    #[arg(long)]
+    hands: Option<u32>,
+    /// 创建锦标赛房间，报名人数为机器人数量，坐满后自动开始
+    #[arg(long)]
+    tournament: bool,
+    /// 创建房间时的玩法
+    #[arg(long, value_enum, default_value_t = VariantArg::Holdem)]
+    variant: VariantArg,
+    /// 创建房间时的下注结构，默认为玩法的常用结构（德州无限注、奥马哈底池限注）
+    #[arg(long, value_enum)]
+    betting: Option<BettingArg>,
+    /// 创建房间时允许全下后发两次（机器人总是同意）
+    #[arg(long)]
+    run_it_twice: bool,
+    /// 创建房间时的前注金额
+    #[arg(long, default_value_t = 0)]
+    ante: u32,
+    /// 前注方式
+    #[arg(long, value_enum, default_value_t = AnteArg::PerPlayer)]
+    ante_kind: AnteArg,
+    /// 创建房间时枪口位强制跨注
+    #[arg(long)]
+    straddle: bool,
+    /// 创建现金桌时的抽水百分比
+    #[arg(long, default_value_t = 0)]
+    rake: u32,
+    /// 每局抽水上限，0为不设上限
+    #[arg(long, default_value_t = 0)]
+    rake_cap: u32,
+    /// 锦标赛桌数，机器人平均分到各桌
+    #[arg(long, default_value_t = 1)]
+    tables: u32,
+    /// 日志级别（trace/debug/info/warn/error）
+    #[arg(long, default_value = "info")]
+    log_level: Level,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum VariantArg {
+    Holdem,
+    Omaha,
+    ShortDeck,
+}
+
+impl From<VariantArg> for GameVariant {
+    fn from(variant: VariantArg) -> Self {
+        match variant {
+            VariantArg::Holdem => GameVariant::Holdem,
+            VariantArg::Omaha => GameVariant::Omaha,
+            VariantArg::ShortDeck => GameVariant::ShortDeck,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BettingArg {
+    Nl,
+    Pl,
+    Fl,
+}
+
+impl From<BettingArg> for BettingStructure {
+    fn from(betting: BettingArg) -> Self {
+        match betting {
+            BettingArg::Nl => BettingStructure::NoLimit,
+            BettingArg::Pl => BettingStructure::PotLimit,
+            BettingArg::Fl => BettingStructure::FixedLimit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AnteArg {
+    PerPlayer,
+    BigBlind,
+}
+
+impl From<AnteArg> for AnteKind {
+    fn from(ante_kind: AnteArg) -> Self {
+        match ante_kind {
+            AnteArg::PerPlayer => AnteKind::PerPlayer,
+            AnteArg::BigBlind => AnteKind::BigBlind,
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if cli.count == 0 {
+        eprintln!("count must be greater than 0");
+        process::exit(1);
+    }
+    // 每桌座位数
+    let seats = (cli.count as u32).div_ceil(cli.tables.max(1));
+    if cli.tournament
+        && (!(2..=MAX_TOURNAMENT_SEATS).contains(&seats)
+            || !(1..=MAX_TOURNAMENT_TABLES).contains(&cli.tables))
+    {
+        eprintln!(
+            "tournament requires 2 to {} bots per table and at most {} tables",
+            MAX_TOURNAMENT_SEATS, MAX_TOURNAMENT_TABLES
+        );
+        process::exit(1);
+    }
+    // 追加序号后的名字也不能超过服务端的上限
+    let longest_name = match cli.count {
+        1 => cli.name.clone(),
+        count => format!("{}{}", cli.name, count),
+    };
+    if !is_valid_name(&longest_name, MAX_PLAYER_NAME_CHARS)
+        || !is_valid_name(&cli.room, MAX_ROOM_NAME_CHARS)
+    {
+        eprintln!(
+            "name must be at most {} characters including the index, room at most {}",
+            MAX_PLAYER_NAME_CHARS, MAX_ROOM_NAME_CHARS
+        );
+        process::exit(1);
+    }
+
+    let variant: GameVariant = cli.variant.into();
+    let betting = cli
+        .betting
+        .map_or(variant.default_betting(), BettingStructure::from);
+
+    let forced_bets = ForcedBets {
+        ante_kind: if cli.ante > 0 {
+            cli.ante_kind.into()
+        } else {
+            AnteKind::None
+        },
+        ante: cli.ante,
+        straddle: cli.straddle,
+    };
+
+    let handles: Vec<_> = (0..cli.count)
+        .map(|index| {
+            let settings = BotSettings {
+                server_addr: cli.server,
+                player_name: if cli.count == 1 {
+                    cli.name.clone()
+                } else {
+                    format!("{}{}", cli.name, index + 1)
+                },
+                room_name: cli.room.clone(),
+                room_password: cli.password.clone(),
+                // 只有第一个机器人创建房间
+                create_room: cli.create && index == 0,
+                auto_start: cli.auto_start,
+                max_hands: cli.hands,
+                variant,
+                betting,
+                run_it_twice: cli.run_it_twice,
+                forced_bets,
+                rake: RakeConfig {
+                    percent: cli.rake,
+                    cap: cli.rake_cap,
+                },
+                tournament: cli.tournament.then(|| TournamentConfig {
+                    seats,
+                    tables: cli.tables,
+                    ..Default::default()
+                }),
+            };
+            let strategy = cli.strategy.build();
+            let log_plugin = (index == 0).then(|| LogPlugin {
+                level: cli.log_level,
+                ..Default::default()
+            });
+            thread::spawn(
+                move || match build_bot_app(settings, strategy, log_plugin) {
+                    Ok(mut app) => app.run(),
+                    Err(e) => eprintln!("Failed to start bot: {}", e),
+                },
+            )
+        })
+        .collect();
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
@@ -0,0 +1,149 @@
+use clap::ValueEnum;
+use rand::Rng;
+use texas_holdem_common::{
+    betting::{BetState, BettingStructure},
+    channel::PlaySnapshot,
+    Card, RoundAction,
+};
+
+// 轮到机器人行动时可见的信息
+#[derive(Debug)]
+pub struct DecisionContext<'a> {
+    pub betting: BettingStructure,
+    pub play: &'a PlaySnapshot,
+    pub hole_cards: &'a [Card],
+    // 自己的下注状态
+    pub state: BetState,
+}
+
+impl<'a> DecisionContext<'a> {
+    // 跟注还需的筹码
+    pub fn to_call(&self) -> u32 {
+        self.state.to_call()
+    }
+
+    // 最小加注到的总额
+    pub fn min_raise_to(&self) -> u32 {
+        self.betting.min_raise_to(&self.state)
+    }
+
+    // 最多加注到的总额，受筹码和下注结构限制
+    pub fn max_raise_to(&self) -> u32 {
+        self.betting
+            .raise_range(&self.state)
+            .map_or(self.state.all_in_to(), |(_, max)| max)
+    }
+
+    // 是否可以下注或加注（有足够筹码且未达到加注封顶）
+    pub fn can_raise(&self) -> bool {
+        self.betting.raise_range(&self.state).is_some()
+    }
+
+    // 不加注时的行动：过牌、跟注或筹码不足时全下
+    pub fn check_or_call(&self) -> Decision {
+        match self.to_call() {
+            0 => Decision::new(RoundAction::Check),
+            to_call if to_call >= self.state.chips => Decision::new(RoundAction::AllIn),
+            _ => Decision::new(RoundAction::Call),
+        }
+    }
+
+    // 加注到指定总额，首次下注为Bet，否则为Raise
+    pub fn raise_to(&self, amount: u32) -> Decision {
+        if !self.can_raise() {
+            return self.check_or_call();
+        }
+        let amount = amount.max(self.min_raise_to()).min(self.max_raise_to());
+        if amount >= self.state.all_in_to() {
+            Decision::new(RoundAction::AllIn)
+        } else if self.play.current_bet == 0 {
+            Decision::with_amount(RoundAction::Bet, amount)
+        } else {
+            Decision::with_amount(RoundAction::Raise, amount)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub action: RoundAction,
+    // 下注或加注到的总额
+    pub amount: u32,
+}
+
+impl Decision {
+    pub fn new(action: RoundAction) -> Self {
+        Decision { action, amount: 0 }
+    }
+
+    pub fn with_amount(action: RoundAction, amount: u32) -> Self {
+        Decision { action, amount }
+    }
+}
+
+pub trait Strategy: Send + Sync {
+    fn decide(&mut self, context: &DecisionContext) -> Decision;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StrategyKind {
+    // 只过牌或跟注
+    Passive,
+    // 随机行动
+    Random,
+    // 能加注就加注
+    Aggressive,
+}
+
+impl StrategyKind {
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Passive => Box::new(PassiveStrategy),
+            StrategyKind::Random => Box::new(RandomStrategy),
+            StrategyKind::Aggressive => Box::new(AggressiveStrategy),
+        }
+    }
+}
+
+pub struct PassiveStrategy;
+
+impl Strategy for PassiveStrategy {
+    fn decide(&mut self, context: &DecisionContext) -> Decision {
+        context.check_or_call()
+    }
+}
+
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn decide(&mut self, context: &DecisionContext) -> Decision {
+        let mut rng = rand::thread_rng();
+        match rng.gen_range(0..100) {
+            // 需要跟注时偶尔弃牌
+            0..=14 if context.to_call() > 0 => Decision::new(RoundAction::Fold),
+            // 加注额不超过底池
+            0..=19 if context.can_raise() => {
+                let max_raise_to = context
+                    .max_raise_to()
+                    .min(context.play.current_bet + context.play.pot)
+                    .max(context.min_raise_to());
+                let amount = rng.gen_range(context.min_raise_to()..=max_raise_to);
+                context.raise_to(amount)
+            }
+            20 if rng.gen_bool(0.2) => context.raise_to(context.state.all_in_to()),
+            _ => context.check_or_call(),
+        }
+    }
+}
+
+pub struct AggressiveStrategy;
+
+impl Strategy for AggressiveStrategy {
+    fn decide(&mut self, context: &DecisionContext) -> Decision {
+        if context.can_raise() {
+            context.raise_to(context.min_raise_to())
+        } else {
+            context.check_or_call()
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientAuthentication, RenetClient};
+use texas_holdem_common::{
+    directory::{
+        directory_connection_config, DirectoryRoomsMessage, LocateServerMessage,
+        DIRECTORY_PROTOCOL_ID, DIRECTORY_ROOMS_CHANNEL_ID, LOCATE_SERVER_CHANNEL_ID,
+    },
+    PROTOCOL_VERSION,
+};
+
+use crate::{
+    lobby::{
+        ConnectDirectoryEvent, ConnectionError, CurrentServer, DirectoryRoomList, EnterRoomEvent,
+        FindServerEvent, JoinDirectoryRoomEvent, QuickSeatState, RoomList, RoomQuery,
+        SessionTokens,
+    },
+    network::new_renet_client,
+    request::{RequestKind, Requests},
+};
+
+// 连接目录服务的客户端，与游戏服务端的RenetClient同时存在，需要手动更新
+#[derive(Resource, Deref, DerefMut)]
+pub struct DirectoryClient(pub RenetClient);
+
+// 切换服务端后要进入的房间，连接成功后发送
+#[derive(Debug, Default, Resource)]
+pub struct PendingJoin(pub Option<EnterRoomEvent>);
+
+fn new_directory_client(directory_addr: SocketAddr) -> std::io::Result<DirectoryClient> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let authentication = ClientAuthentication::Unsecure {
+        client_id: rand::random(),
+        protocol_id: DIRECTORY_PROTOCOL_ID,
+        server_addr: directory_addr,
+        // 目录只返回协议版本兼容的服务端
+        user_data: Some(PROTOCOL_VERSION.to_user_data()),
+    };
+    let client = RenetClient::new(
+        current_time,
+        socket,
+        directory_connection_config(),
+        authentication,
+    )
+    .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(DirectoryClient(client))
+}
+
+pub fn connect_directory(
+    mut commands: Commands,
+    mut connect_directory_er: EventReader<ConnectDirectoryEvent>,
+    mut connection_error: ResMut<ConnectionError>,
+) {
+    for event in connect_directory_er.iter() {
+        let directory_addr = match event.directory_addr.trim().parse::<SocketAddr>() {
+            Ok(directory_addr) => directory_addr,
+            Err(_) => {
+                connection_error.0 = Some(format!(
+                    "Invalid directory address: {}",
+                    event.directory_addr
+                ));
+                continue;
+            }
+        };
+        match new_directory_client(directory_addr) {
+            Ok(client) => {
+                info!("Connecting to directory {}", directory_addr);
+                connection_error.0 = None;
+                commands.insert_resource(client);
+            }
+            Err(e) => {
+                connection_error.0 = Some(format!("Failed to connect directory: {}", e));
+            }
+        }
+    }
+}
+
+pub fn update_directory_client(mut client: ResMut<DirectoryClient>, time: Res<Time>) {
+    if let Err(e) = client.update(time.delta()) {
+        warn!("Directory connection error: {}", e);
+    }
+}
+
+pub fn send_directory_packets(mut client: ResMut<DirectoryClient>) {
+    if let Err(e) = client.send_packets() {
+        warn!("Failed to send packets to directory: {}", e);
+    }
+}
+
+pub fn directory_rooms(
+    mut client: ResMut<DirectoryClient>,
+    mut directory_room_list: ResMut<DirectoryRoomList>,
+    room_query: Res<RoomQuery>,
+    mut requests: Requests,
+    mut refresh_cd: Local<f32>,
+    time: Res<Time>,
+) {
+    *refresh_cd -= time.delta_seconds();
+
+    if client.is_connected() && (*refresh_cd < 0.0 || room_query.is_changed()) {
+        let message = DirectoryRoomsMessage {
+            request_id: requests.start(RequestKind::DirectoryRooms),
+            filter: room_query.filter.clone(),
+            sort: room_query.sort,
+            page: room_query.page,
+            page_size: room_query.page_size,
+            rooms: Vec::new(),
+            total: 0,
+        };
+        client.send_message(
+            DIRECTORY_ROOMS_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+        *refresh_cd = 5.0;
+    }
+
+    while let Some(message) = client.receive_message(DIRECTORY_ROOMS_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<DirectoryRoomsMessage>(&message) {
+            if requests.finish(message.request_id, RequestKind::DirectoryRooms, None) {
+                info!("Received directory rooms message: {:?}", message);
+                directory_room_list.rooms = message.rooms;
+                directory_room_list.total = message.total;
+            }
+        }
+    }
+}
+
+// 向目录查询服务端，切换连接后进入房间
+#[allow(clippy::too_many_arguments)]
+pub fn locate_server(
+    mut commands: Commands,
+    mut join_directory_room_er: EventReader<JoinDirectoryRoomEvent>,
+    mut find_server_er: EventReader<FindServerEvent>,
+    mut client: ResMut<DirectoryClient>,
+    mut game_client: Option<ResMut<RenetClient>>,
+    mut requests: Requests,
+    mut current_server: ResMut<CurrentServer>,
+    mut pending_join: ResMut<PendingJoin>,
+    mut quick_seat_state: ResMut<QuickSeatState>,
+    mut room_list: ResMut<RoomList>,
+    mut connection_error: ResMut<ConnectionError>,
+    session_tokens: Res<SessionTokens>,
+    mut join_target: Local<Option<JoinDirectoryRoomEvent>>,
+) {
+    let mut messages = Vec::new();
+    for event in join_directory_room_er.iter() {
+        if requests.is_pending(RequestKind::LocateServer) {
+            continue;
+        }
+        messages.push(LocateServerMessage {
+            request_id: requests.start(RequestKind::LocateServer),
+            room: Some((event.server_name.clone(), event.room_id)),
+            server_name: String::new(),
+            server_addr: None,
+            error: None,
+        });
+        *join_target = Some(event.clone());
+    }
+    for _ in find_server_er.iter() {
+        if requests.is_pending(RequestKind::LocateServer) {
+            continue;
+        }
+        messages.push(LocateServerMessage {
+            request_id: requests.start(RequestKind::LocateServer),
+            room: None,
+            server_name: String::new(),
+            server_addr: None,
+            error: None,
+        });
+        *join_target = None;
+    }
+    for message in messages {
+        client.send_message(
+            LOCATE_SERVER_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+    }
+
+    while let Some(message) = client.receive_message(LOCATE_SERVER_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<LocateServerMessage>(&message) {
+            info!("Received locate server message: {:?}", message);
+            if !requests.finish(message.request_id, RequestKind::LocateServer, message.error) {
+                continue;
+            }
+            let Some(server_addr) = message.server_addr else {
+                continue;
+            };
+            pending_join.0 = join_target.take().map(|target| EnterRoomEvent {
+                room_id: target.room_id,
+                room_password: target.room_password,
+            });
+            current_server.server_name = Some(message.server_name);
+            // 已连接到该服务端时不必重连
+            let connected = current_server.server_addr == Some(server_addr)
+                && game_client
+                    .as_ref()
+                    .is_some_and(|client| client.disconnected().is_none());
+            if connected {
+                continue;
+            }
+            if let Some(game_client) = game_client.as_mut() {
+                game_client.disconnect();
+            }
+            match new_renet_client(server_addr, session_tokens.0.get(&server_addr).copied()) {
+                Ok(new_client) => {
+                    info!("Switching to server {}", server_addr);
+                    connection_error.0 = None;
+                    current_server.server_addr = Some(server_addr);
+                    // 原服务端的请求、排队和房间列表都作废
+                    requests.cancel_all_except(&[
+                        RequestKind::DirectoryRooms,
+                        RequestKind::LocateServer,
+                    ]);
+                    quick_seat_state.waiting = None;
+                    *room_list = RoomList::default();
+                    commands.insert_resource(new_client);
+                }
+                Err(e) => {
+                    connection_error.0 = Some(format!("Failed to connect: {}", e));
+                    pending_join.0 = None;
+                }
+            }
+        }
+    }
+}
+
+// 连接到新的服务端后进入房间
+pub fn enter_located_room(
+    client: Res<RenetClient>,
+    mut pending_join: ResMut<PendingJoin>,
+    mut enter_room_ew: EventWriter<EnterRoomEvent>,
+) {
+    if client.is_connected() {
+        if let Some(event) = pending_join.0.take() {
+            enter_room_ew.send(event);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy_renet::{renet::RenetClient, RenetClientPlugin};
+use directory::{
+    connect_directory, directory_rooms, enter_located_room, locate_server, send_directory_packets,
+    update_directory_client, DirectoryClient, PendingJoin,
+};
+use lobby::{
+    CancelQuickSeatEvent, ConnectDirectoryEvent, ConnectServerEvent, ConnectionError,
+    CreateRoomEvent, CurrentServer, DirectoryRoomList, EnterRoomEvent, FindServerEvent,
+    JoinByInviteEvent, JoinDirectoryRoomEvent, NewRoomSettings, PlayerName, QuickSeatEvent,
+    QuickSeatState, RoomList, RoomQuery, SessionTokens,
+};
+use network::{
+    bank, chat, connect_server, create_room, enter_room, get_rooms, player_action, quick_seat,
+    receive_handshake, receive_room_sync, room_admin, run_it_twice, set_room_state, sit_out,
+    switch_player_role,
+};
+use request::{expire_pending_requests, PendingRequests, RequestFailedEvent};
+use room::{
+    BankEvent, Bankroll, ChatEvent, CurrentRoomInfo, PlayerActionEvent, RoomAdminEvent,
+    RunItTwiceEvent, SetRoomStateEvent, SitOutEvent, SwitchPlayerRoleEvent,
+};
+use texas_holdem_common::util::timestamp;
+
+pub mod directory;
+pub mod lobby;
+pub mod network;
+pub mod request;
+pub mod room;
+pub mod table;
+
+#[derive(Debug, Default, Hash, PartialEq, Eq, Clone, Copy, States)]
+pub enum AppState {
+    #[default]
+    Lobby,
+    Gaming,
+}
+
+// 客户端网络同步逻辑，不依赖渲染和UI，可在MinimalPlugins下运行
+pub struct ClientNetworkPlugin;
+
+impl Plugin for ClientNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RenetClientPlugin::default())
+            .add_state::<AppState>()
+            .add_event::<ConnectServerEvent>()
+            .add_event::<ConnectDirectoryEvent>()
+            .add_event::<JoinDirectoryRoomEvent>()
+            .add_event::<FindServerEvent>()
+            .add_event::<CreateRoomEvent>()
+            .add_event::<EnterRoomEvent>()
+            .add_event::<JoinByInviteEvent>()
+            .add_event::<QuickSeatEvent>()
+            .add_event::<CancelQuickSeatEvent>()
+            .add_event::<SwitchPlayerRoleEvent>()
+            .add_event::<SetRoomStateEvent>()
+            .add_event::<PlayerActionEvent>()
+            .add_event::<RunItTwiceEvent>()
+            .add_event::<SitOutEvent>()
+            .add_event::<BankEvent>()
+            .add_event::<ChatEvent>()
+            .add_event::<RoomAdminEvent>()
+            .add_event::<RequestFailedEvent>()
+            .insert_resource(RoomList::default())
+            .insert_resource(RoomQuery::default())
+            .insert_resource(QuickSeatState::default())
+            .insert_resource(PlayerName(format!("Player{}", timestamp())))
+            .insert_resource(NewRoomSettings::default())
+            .insert_resource(ConnectionError::default())
+            .insert_resource(CurrentRoomInfo::default())
+            .insert_resource(Bankroll::default())
+            .insert_resource(PendingRequests::default())
+            .insert_resource(DirectoryRoomList::default())
+            .insert_resource(CurrentServer::default())
+            .insert_resource(SessionTokens::default())
+            .insert_resource(PendingJoin::default())
+            .add_system(expire_pending_requests)
+            .add_system(connect_server.in_set(OnUpdate(AppState::Lobby)))
+            .add_system(connect_directory.in_set(OnUpdate(AppState::Lobby)))
+            // 目录客户端不由RenetClientPlugin更新
+            .add_system(
+                update_directory_client
+                    .run_if(resource_exists::<DirectoryClient>())
+                    .in_base_set(CoreSet::PreUpdate),
+            )
+            .add_system(
+                send_directory_packets
+                    .run_if(resource_exists::<DirectoryClient>())
+                    .in_base_set(CoreSet::PostUpdate),
+            )
+            .add_systems(
+                (directory_rooms, locate_server)
+                    .distributive_run_if(resource_exists::<DirectoryClient>())
+                    .in_set(OnUpdate(AppState::Lobby)),
+            )
+            .add_systems(
+                (
+                    receive_handshake,
+                    get_rooms,
+                    create_room,
+                    enter_room,
+                    quick_seat,
+                    enter_located_room,
+                )
+                    .distributive_run_if(resource_exists::<RenetClient>())
+                    .in_set(OnUpdate(AppState::Lobby)),
+            )
+            .add_systems(
+                (
+                    switch_player_role,
+                    receive_room_sync,
+                    set_room_state,
+                    player_action,
+                    run_it_twice,
+                    sit_out,
+                    bank,
+                    chat,
+                    room_admin,
+                )
+                    .in_set(OnUpdate(AppState::Gaming)),
+            );
+    }
+}
@@ -1,14 +1,59 @@
+use std::{collections::HashMap, net::SocketAddr};
+
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_egui::{
     egui::{self, pos2},
     EguiContexts,
 };
+use bevy_renet::renet::RenetClient;
 use egui_extras::{Column, TableBuilder};
-use texas_holdem_common::RoomDTO;
+use texas_holdem_common::{
+    betting::{AnteKind, BettingStructure, ForcedBets, RakeConfig, MAX_RAKE_PERCENT},
+    directory::DirectoryRoom,
+    lobby::{
+        truncate_name, RoomFilter, RoomSort, RoomSortKey, MAX_PLAYER_NAME_CHARS,
+        MAX_ROOM_NAME_CHARS,
+    },
+    spectator::{SpectatorConfig, SpectatorDelay, MAX_SPECTATOR_DELAY_SECS},
+    tournament::{TournamentConfig, MAX_TOURNAMENT_SEATS, MAX_TOURNAMENT_TABLES},
+    variant::GameVariant,
+    RoomDTO, RoomState, SessionToken,
+};
 
-// 房间列表
-#[derive(Debug, Resource)]
-pub struct RoomList(pub Vec<RoomDTO>);
+// 房间列表的当前页
+#[derive(Debug, Default, Resource)]
+pub struct RoomList {
+    pub rooms: Vec<RoomDTO>,
+    // 符合筛选条件的房间总数
+    pub total: u32,
+}
+
+// 目录服务汇总的各服务端房间列表的当前页，与房间列表使用相同的筛选条件
+#[derive(Debug, Default, Resource)]
+pub struct DirectoryRoomList {
+    pub rooms: Vec<DirectoryRoom>,
+    pub total: u32,
+}
+
+// 房间列表的筛选、排序和页码，修改后立即刷新
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct RoomQuery {
+    pub filter: RoomFilter,
+    pub sort: RoomSort,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl Default for RoomQuery {
+    fn default() -> Self {
+        RoomQuery {
+            filter: RoomFilter::default(),
+            sort: RoomSort::default(),
+            page: 0,
+            page_size: 10,
+        }
+    }
+}
 
 // 玩家名称
 #[derive(Debug, Resource)]
@@ -19,6 +64,18 @@ pub struct PlayerName(pub String);
 pub struct NewRoomSettings {
     pub room_name: String,
     pub room_password: String,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    // 全下后允许剩余公共牌发两次
+    pub run_it_twice: bool,
+    // 前注和跨注
+    pub forced_bets: ForcedBets,
+    // 现金桌抽水
+    pub rake: RakeConfig,
+    // 旁观人数上限、延迟和隐藏名字
+    pub spectators: SpectatorConfig,
+    // 锦标赛设置，为None时创建现金桌
+    pub tournament: Option<TournamentConfig>,
 }
 
 // 准备进入的房间
@@ -26,12 +83,71 @@ pub struct NewRoomSettings {
 pub struct RoomToEnter {
     pub room_id: Option<u64>,
     pub room_password: String,
+    // 目录中的房间所在的服务端
+    pub server_name: Option<String>,
+}
+
+// 服务端地址
+#[derive(Debug, Resource)]
+pub struct ServerAddress(pub String);
+
+impl Default for ServerAddress {
+    fn default() -> Self {
+        ServerAddress("127.0.0.1:5000".to_string())
+    }
+}
+
+// 目录服务地址
+#[derive(Debug, Resource)]
+pub struct DirectoryAddress(pub String);
+
+impl Default for DirectoryAddress {
+    fn default() -> Self {
+        DirectoryAddress("127.0.0.1:4000".to_string())
+    }
 }
 
+// 当前连接的服务端，通过目录连接时记录服务端名称
+#[derive(Debug, Default, Resource)]
+pub struct CurrentServer {
+    pub server_name: Option<String>,
+    pub server_addr: Option<SocketAddr>,
+}
+
+// 各服务端分配的会话令牌，重连时带上以恢复房主等身份
+#[derive(Debug, Default, Resource)]
+pub struct SessionTokens(pub HashMap<SocketAddr, SessionToken>);
+
+// 与服务端的连接错误（如版本不兼容）
+#[derive(Debug, Default, Resource)]
+pub struct ConnectionError(pub Option<String>);
+
 // 是否打开输入密码的弹窗
 #[derive(Debug, Default, Resource)]
 pub struct InputPasswordModalOpen(pub bool);
 
+#[derive(Debug)]
+pub struct ConnectServerEvent {
+    pub server_addr: String,
+}
+
+#[derive(Debug)]
+pub struct ConnectDirectoryEvent {
+    pub directory_addr: String,
+}
+
+// 进入目录中的房间，先切换到房间所在的服务端
+#[derive(Debug, Clone)]
+pub struct JoinDirectoryRoomEvent {
+    pub server_name: String,
+    pub room_id: u64,
+    pub room_password: String,
+}
+
+// 切换到目录中负载最低的服务端，用于创建房间
+#[derive(Debug, Default)]
+pub struct FindServerEvent;
+
 #[derive(Debug, Default)]
 pub struct CreateRoomEvent;
 
@@ -41,22 +157,76 @@ pub struct EnterRoomEvent {
     pub room_password: String,
 }
 
+// 使用邀请码进入房间，不需要密码
+#[derive(Debug)]
+pub struct JoinByInviteEvent {
+    pub invite_code: String,
+}
+
+// 由服务端按玩法和大盲注范围找桌入座
+#[derive(Debug, Default, Clone)]
+pub struct QuickSeatEvent {
+    pub variant: GameVariant,
+    pub min_big_blind: Option<u32>,
+    pub max_big_blind: Option<u32>,
+}
+
+// 退出等候队列
+#[derive(Debug, Default)]
+pub struct CancelQuickSeatEvent;
+
+// 快速入座时在满桌的等候队列中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuickSeatWaiting {
+    // 排到时服务端用该请求id通知
+    pub request_id: u64,
+    pub room_id: u64,
+    // 从1开始
+    pub position: u32,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct QuickSeatState {
+    pub waiting: Option<QuickSeatWaiting>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn lobby_room_list_ui(
     mut contexts: EguiContexts,
     room_list: Res<RoomList>,
+    mut room_query: ResMut<RoomQuery>,
     mut room_to_enter: ResMut<RoomToEnter>,
     mut input_password_modal_open: ResMut<InputPasswordModalOpen>,
+    mut enter_room_ew: EventWriter<EnterRoomEvent>,
+    mut join_by_invite_ew: EventWriter<JoinByInviteEvent>,
+    mut invite_code: Local<String>,
 ) {
     egui::Window::new("Hello").show(contexts.ctx_mut(), |ui| {
         ui.label("Room List");
+        ui.horizontal(|ui| {
+            ui.label("Invite Code: ");
+            ui.add(egui::TextEdit::singleline(&mut *invite_code));
+            if ui.button("Join").clicked() && !invite_code.trim().is_empty() {
+                join_by_invite_ew.send(JoinByInviteEvent {
+                    invite_code: invite_code.trim().to_string(),
+                });
+                invite_code.clear();
+            }
+        });
         ui.separator();
-        let mut table = TableBuilder::new(ui)
+        // 在副本上修改，避免每帧都触发刷新
+        let mut query = room_query.clone();
+        room_query_ui(ui, &mut query);
+        ui.separator();
+        let table = TableBuilder::new(ui)
             .striped(true)
             // .resizable(true)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
             .column(Column::auto())
             .column(Column::initial(100.0).range(40.0..=300.0))
             .column(Column::initial(100.0).at_least(40.0).clip(true))
+            .column(Column::initial(100.0).at_least(40.0).clip(true))
+            .column(Column::initial(100.0).at_least(40.0).clip(true))
             .column(Column::remainder())
             .min_scrolled_height(0.0);
 
@@ -69,14 +239,20 @@ pub fn lobby_room_list_ui(
                     ui.strong("Owner Name");
                 });
                 header.col(|ui| {
-                    ui.strong("Player Count");
+                    ui.strong("State");
+                });
+                header.col(|ui| {
+                    ui.strong("Stakes");
+                });
+                header.col(|ui| {
+                    ui.strong("Seated");
                 });
                 header.col(|ui| {
                     ui.strong("Operations");
                 });
             })
             .body(|mut body| {
-                for room in room_list.0.iter() {
+                for room in room_list.rooms.iter() {
                     let row_height = 18.0;
                     body.row(row_height, |mut row| {
                         row.col(|ui| {
@@ -86,18 +262,188 @@ pub fn lobby_room_list_ui(
                             ui.label(room.owner_name.clone());
                         });
                         row.col(|ui| {
-                            ui.label(room.player_count.to_string());
+                            ui.label(room.room_state.name());
                         });
                         row.col(|ui| {
-                            if ui.button("Enter").clicked() {
+                            let stakes = ui.label(format!(
+                                "{}{} {}/{}",
+                                room.betting.short_name(),
+                                room.variant.short_name(),
+                                room.small_blind,
+                                room.big_blind
+                            ));
+                            if room.rake.is_enabled() {
+                                stakes.on_hover_text(format!(
+                                    "{} - collected {}",
+                                    room.rake.describe(),
+                                    room.rake_collected
+                                ));
+                            }
+                        });
+                        row.col(|ui| {
+                            // 悬停显示包括旁观者在内的房间人数
+                            ui.label(format!("{}/{}", room.participant_count, room.seats))
+                                .on_hover_text(format!("{} in room", room.player_count));
+                        });
+                        row.col(|ui| {
+                            // 房主锁定的房间不能进入
+                            if room.locked {
+                                ui.label("Locked");
+                            } else if ui.button("Enter").clicked() {
                                 println!("Enter Room: {}", room.room_name);
-                                room_to_enter.room_id = Some(room.room_id);
-                                input_password_modal_open.0 = true;
+                                // 只有私人房间需要输入密码
+                                if room.is_private {
+                                    room_to_enter.room_id = Some(room.room_id);
+                                    room_to_enter.server_name = None;
+                                    input_password_modal_open.0 = true;
+                                } else {
+                                    enter_room_ew.send(EnterRoomEvent {
+                                        room_id: room.room_id,
+                                        room_password: String::new(),
+                                    });
+                                }
                             }
                         });
                     });
                 }
             });
+        ui.separator();
+        let pages = room_list.total.div_ceil(query.page_size).max(1);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(query.page > 0, egui::Button::new("<"))
+                .clicked()
+            {
+                query.page -= 1;
+            }
+            ui.label(format!(
+                "Page {} / {} ({} rooms)",
+                query.page + 1,
+                pages,
+                room_list.total
+            ));
+            if ui
+                .add_enabled(query.page + 1 < pages, egui::Button::new(">"))
+                .clicked()
+            {
+                query.page += 1;
+            }
+        });
+        if query != *room_query {
+            *room_query = query;
+        }
+    });
+}
+
+// 搜索、筛选和排序，条件变化时回到第一页
+fn room_query_ui(ui: &mut egui::Ui, query: &mut RoomQuery) {
+    let (filter, sort) = (query.filter.clone(), query.sort);
+    ui.horizontal(|ui| {
+        ui.label("Search: ");
+        ui.add(egui::TextEdit::singleline(&mut query.filter.search).hint_text("room or owner"));
+        query.filter.truncate_search();
+        ui.label("Sort: ");
+        egui::ComboBox::from_id_source("room_sort")
+            .selected_text(query.sort.key.name())
+            .show_ui(ui, |ui| {
+                for key in RoomSortKey::ALL {
+                    ui.selectable_value(&mut query.sort.key, key, key.name());
+                }
+            });
+        ui.checkbox(&mut query.sort.descending, "Descending");
+    });
+    ui.horizontal(|ui| {
+        let filter = &mut query.filter;
+        ui.label("Variant: ");
+        egui::ComboBox::from_id_source("room_filter_variant")
+            .selected_text(filter.variant.map_or("Any", |variant| variant.name()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.variant, None, "Any");
+                for variant in GameVariant::ALL {
+                    ui.selectable_value(&mut filter.variant, Some(variant), variant.name());
+                }
+            });
+        ui.label("State: ");
+        egui::ComboBox::from_id_source("room_filter_state")
+            .selected_text(filter.room_state.map_or("Any", |state| state.name()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.room_state, None, "Any");
+                for state in [
+                    RoomState::Waiting,
+                    RoomState::Playing,
+                    RoomState::Paused,
+                    RoomState::Registering,
+                    RoomState::Running,
+                    RoomState::Finished,
+                ] {
+                    ui.selectable_value(&mut filter.room_state, Some(state), state.name());
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        let filter = &mut query.filter;
+        // 大盲注范围，0表示不限
+        let mut min_big_blind = filter.min_big_blind.unwrap_or(0);
+        let mut max_big_blind = filter.max_big_blind.unwrap_or(0);
+        ui.label("Big Blind: ");
+        ui.add(egui::DragValue::new(&mut min_big_blind).clamp_range(0..=1_000_000));
+        ui.label("to");
+        ui.add(egui::DragValue::new(&mut max_big_blind).clamp_range(0..=1_000_000));
+        filter.min_big_blind = (min_big_blind > 0).then_some(min_big_blind);
+        filter.max_big_blind = (max_big_blind > 0).then_some(max_big_blind);
+        ui.label("Free Seats: ");
+        ui.add(egui::DragValue::new(&mut filter.min_free_seats).clamp_range(0..=10));
+        ui.checkbox(&mut filter.hide_full, "Hide Full");
+        ui.checkbox(&mut filter.hide_private, "Hide Private");
+    });
+    if query.filter != filter || query.sort != sort {
+        query.page = 0;
+    }
+}
+
+pub fn lobby_quick_seat_ui(
+    mut contexts: EguiContexts,
+    quick_seat_state: Res<QuickSeatState>,
+    mut quick_seat_ew: EventWriter<QuickSeatEvent>,
+    mut cancel_quick_seat_ew: EventWriter<CancelQuickSeatEvent>,
+    mut request: Local<QuickSeatEvent>,
+) {
+    egui::Window::new("Quick Seat").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Game: ");
+            egui::ComboBox::from_id_source("quick_seat_variant")
+                .selected_text(request.variant.name())
+                .show_ui(ui, |ui| {
+                    for variant in GameVariant::ALL {
+                        ui.selectable_value(&mut request.variant, variant, variant.name());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            // 大盲注范围，0表示不限
+            let mut min_big_blind = request.min_big_blind.unwrap_or(0);
+            let mut max_big_blind = request.max_big_blind.unwrap_or(0);
+            ui.label("Big Blind: ");
+            ui.add(egui::DragValue::new(&mut min_big_blind).clamp_range(0..=1_000_000));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut max_big_blind).clamp_range(0..=1_000_000));
+            request.min_big_blind = (min_big_blind > 0).then_some(min_big_blind);
+            request.max_big_blind = (max_big_blind > 0).then_some(max_big_blind);
+        });
+        if ui.button("Find a Seat").clicked() {
+            quick_seat_ew.send(request.clone());
+        }
+        if let Some(waiting) = quick_seat_state.waiting {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Waiting for a seat in room {} (position {})",
+                    waiting.room_id, waiting.position
+                ));
+                if ui.button("Cancel").clicked() {
+                    cancel_quick_seat_ew.send_default();
+                }
+            });
+        }
     });
 }
 
@@ -106,6 +452,7 @@ pub fn lobby_enter_room_modal_ui(
     mut room_to_enter: ResMut<RoomToEnter>,
     mut input_password_modal_open: ResMut<InputPasswordModalOpen>,
     mut enter_room_ew: EventWriter<EnterRoomEvent>,
+    mut join_directory_room_ew: EventWriter<JoinDirectoryRoomEvent>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
 ) {
     let primary_window = primary_window.single();
@@ -119,10 +466,19 @@ pub fn lobby_enter_room_modal_ui(
             ui.label("Input Password");
             ui.add(egui::TextEdit::singleline(&mut room_to_enter.room_password));
             if ui.button("Enter").clicked() {
-                enter_room_ew.send(EnterRoomEvent {
-                    room_id: room_to_enter.room_id.unwrap(),
-                    room_password: room_to_enter.room_password.clone(),
-                });
+                let room_id = room_to_enter.room_id.unwrap();
+                let room_password = room_to_enter.room_password.clone();
+                match room_to_enter.server_name.take() {
+                    Some(server_name) => join_directory_room_ew.send(JoinDirectoryRoomEvent {
+                        server_name,
+                        room_id,
+                        room_password,
+                    }),
+                    None => enter_room_ew.send(EnterRoomEvent {
+                        room_id,
+                        room_password,
+                    }),
+                }
                 room_to_enter.room_id = None;
                 room_to_enter.room_password = "".to_string();
             }
@@ -140,6 +496,11 @@ pub fn lobby_create_room_ui(
         ui.horizontal(|ui| {
             ui.label("Room Name: ");
             ui.add(egui::TextEdit::singleline(&mut new_room_settings.room_name));
+            // 服务端拒绝过长的房间名
+            if new_room_settings.room_name.chars().count() > MAX_ROOM_NAME_CHARS {
+                new_room_settings.room_name =
+                    truncate_name(&new_room_settings.room_name, MAX_ROOM_NAME_CHARS);
+            }
         });
         ui.horizontal(|ui| {
             ui.label("Room Password: ");
@@ -147,6 +508,131 @@ pub fn lobby_create_room_ui(
                 &mut new_room_settings.room_password,
             ));
         });
+        ui.horizontal(|ui| {
+            ui.label("Game: ");
+            egui::ComboBox::from_id_source("variant")
+                .selected_text(new_room_settings.variant.name())
+                .show_ui(ui, |ui| {
+                    for variant in GameVariant::ALL {
+                        // 切换玩法时改为该玩法常用的下注结构
+                        if ui
+                            .selectable_value(
+                                &mut new_room_settings.variant,
+                                variant,
+                                variant.name(),
+                            )
+                            .changed()
+                        {
+                            new_room_settings.betting = variant.default_betting();
+                        }
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Betting: ");
+            egui::ComboBox::from_id_source("betting")
+                .selected_text(new_room_settings.betting.name())
+                .show_ui(ui, |ui| {
+                    for betting in BettingStructure::ALL {
+                        ui.selectable_value(
+                            &mut new_room_settings.betting,
+                            betting,
+                            betting.name(),
+                        );
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            let forced_bets = &mut new_room_settings.forced_bets;
+            ui.label("Ante: ");
+            egui::ComboBox::from_id_source("ante")
+                .selected_text(forced_bets.ante_kind.name())
+                .show_ui(ui, |ui| {
+                    for ante_kind in AnteKind::ALL {
+                        ui.selectable_value(
+                            &mut forced_bets.ante_kind,
+                            ante_kind,
+                            ante_kind.name(),
+                        );
+                    }
+                });
+            if forced_bets.ante_kind != AnteKind::None {
+                ui.add(egui::DragValue::new(&mut forced_bets.ante).clamp_range(1..=1_000));
+            }
+        });
+        ui.checkbox(&mut new_room_settings.forced_bets.straddle, "Straddle");
+        ui.checkbox(&mut new_room_settings.run_it_twice, "Run It Twice");
+        let mut is_tournament = new_room_settings.tournament.is_some();
+        if ui.checkbox(&mut is_tournament, "Tournament").changed() {
+            new_room_settings.tournament = is_tournament.then(TournamentConfig::default);
+        }
+        if new_room_settings.tournament.is_none() {
+            ui.horizontal(|ui| {
+                let rake = &mut new_room_settings.rake;
+                ui.label("Rake %: ");
+                ui.add(egui::DragValue::new(&mut rake.percent).clamp_range(0..=MAX_RAKE_PERCENT));
+                if rake.is_enabled() {
+                    ui.label("Cap: ");
+                    ui.add(egui::DragValue::new(&mut rake.cap).clamp_range(0..=1_000));
+                }
+            });
+        }
+        if let Some(config) = new_room_settings.tournament.as_mut() {
+            let min_stack = config.blind_level(0).big_blind;
+            ui.horizontal(|ui| {
+                ui.label("Seats: ");
+                ui.add(
+                    egui::DragValue::new(&mut config.seats).clamp_range(2..=MAX_TOURNAMENT_SEATS),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Tables: ");
+                ui.add(
+                    egui::DragValue::new(&mut config.tables).clamp_range(1..=MAX_TOURNAMENT_TABLES),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Starting Stack: ");
+                ui.add(
+                    egui::DragValue::new(&mut config.starting_stack)
+                        .clamp_range(min_stack..=1_000_000),
+                );
+            });
+        }
+        ui.horizontal(|ui| {
+            let spectators = &mut new_room_settings.spectators;
+            ui.label("Max Spectators: ");
+            ui.add(egui::DragValue::new(&mut spectators.max_spectators).clamp_range(0..=100));
+            if spectators.max_spectators == 0 {
+                ui.label("(unlimited)");
+            }
+        });
+        ui.horizontal(|ui| {
+            let delay = &mut new_room_settings.spectators.delay;
+            ui.label("Spectator Delay: ");
+            egui::ComboBox::from_id_source("spectator_delay")
+                .selected_text(delay.describe())
+                .show_ui(ui, |ui| {
+                    for option in [
+                        SpectatorDelay::Off,
+                        SpectatorDelay::Seconds(30),
+                        SpectatorDelay::Hand,
+                    ] {
+                        let selected =
+                            std::mem::discriminant(delay) == std::mem::discriminant(&option);
+                        if ui.selectable_label(selected, option.describe()).clicked() && !selected {
+                            *delay = option;
+                        }
+                    }
+                });
+            if let SpectatorDelay::Seconds(secs) = delay {
+                ui.add(egui::DragValue::new(secs).clamp_range(1..=MAX_SPECTATOR_DELAY_SECS));
+            }
+        });
+        ui.checkbox(
+            &mut new_room_settings.spectators.hide_names,
+            "Hide Spectator Names",
+        );
         if ui.button("Create").clicked() {
             create_room_ew.send_default();
         }
@@ -162,6 +648,143 @@ pub fn lobby_set_player_name_ui(mut contexts: EguiContexts, mut player_name: Res
             ui.add(
                 egui::TextEdit::singleline(&mut player_name.0).hint_text("Write something here"),
             );
+            if player_name.0.chars().count() > MAX_PLAYER_NAME_CHARS {
+                player_name.0 = truncate_name(&player_name.0, MAX_PLAYER_NAME_CHARS);
+            }
+        });
+    });
+}
+
+pub fn lobby_server_ui(
+    mut contexts: EguiContexts,
+    mut server_address: ResMut<ServerAddress>,
+    connection_error: Res<ConnectionError>,
+    client: Option<Res<RenetClient>>,
+    mut connect_server_ew: EventWriter<ConnectServerEvent>,
+) {
+    egui::Window::new("Server").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Server Address: ");
+            ui.add(egui::TextEdit::singleline(&mut server_address.0));
+            if ui.button("Connect").clicked() {
+                connect_server_ew.send(ConnectServerEvent {
+                    server_addr: server_address.0.clone(),
+                });
+            }
+        });
+        if let Some(reason) = connection_error.0.as_ref() {
+            ui.colored_label(egui::Color32::RED, reason);
+        } else if let Some(client) = client {
+            if client.is_connected() {
+                ui.label("Connected");
+            } else {
+                ui.label("Connecting...");
+            }
+        } else {
+            ui.label("Not connected");
+        }
+    });
+}
+
+// 通过目录服务浏览所有服务端的房间，进入房间时自动切换到所在的服务端
+#[allow(clippy::too_many_arguments)]
+pub fn lobby_directory_ui(
+    mut contexts: EguiContexts,
+    mut directory_address: ResMut<DirectoryAddress>,
+    directory_room_list: Res<DirectoryRoomList>,
+    mut room_query: ResMut<RoomQuery>,
+    current_server: Res<CurrentServer>,
+    mut room_to_enter: ResMut<RoomToEnter>,
+    mut input_password_modal_open: ResMut<InputPasswordModalOpen>,
+    mut connect_directory_ew: EventWriter<ConnectDirectoryEvent>,
+    mut join_directory_room_ew: EventWriter<JoinDirectoryRoomEvent>,
+    mut find_server_ew: EventWriter<FindServerEvent>,
+) {
+    egui::Window::new("Directory").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Directory Address: ");
+            ui.add(egui::TextEdit::singleline(&mut directory_address.0));
+            if ui.button("Connect").clicked() {
+                connect_directory_ew.send(ConnectDirectoryEvent {
+                    directory_addr: directory_address.0.clone(),
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            match current_server.server_name.as_ref() {
+                Some(server_name) => ui.label(format!("Current server: {}", server_name)),
+                None => ui.label("Current server: -"),
+            };
+            // 在负载最低的服务端上创建房间
+            if ui.button("Find Server").clicked() {
+                find_server_ew.send_default();
+            }
+        });
+        ui.separator();
+        egui::Grid::new("directory_rooms")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Server");
+                ui.strong("Room Name");
+                ui.strong("Stakes");
+                ui.strong("Seated");
+                ui.strong("Operations");
+                ui.end_row();
+                for entry in directory_room_list.rooms.iter() {
+                    let room = &entry.room;
+                    ui.label(&entry.server_name);
+                    ui.label(&room.room_name);
+                    ui.label(format!(
+                        "{}{} {}/{}",
+                        room.betting.short_name(),
+                        room.variant.short_name(),
+                        room.small_blind,
+                        room.big_blind
+                    ));
+                    ui.label(format!("{}/{}", room.participant_count, room.seats));
+                    if room.locked {
+                        ui.label("Locked");
+                    } else if ui.button("Enter").clicked() {
+                        if room.is_private {
+                            room_to_enter.room_id = Some(room.room_id);
+                            room_to_enter.server_name = Some(entry.server_name.clone());
+                            input_password_modal_open.0 = true;
+                        } else {
+                            join_directory_room_ew.send(JoinDirectoryRoomEvent {
+                                server_name: entry.server_name.clone(),
+                                room_id: room.room_id,
+                                room_password: String::new(),
+                            });
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.separator();
+        // 筛选条件在房间列表窗口中修改
+        let pages = directory_room_list
+            .total
+            .div_ceil(room_query.page_size)
+            .max(1);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(room_query.page > 0, egui::Button::new("<"))
+                .clicked()
+            {
+                room_query.page -= 1;
+            }
+            ui.label(format!(
+                "Page {} / {} ({} rooms)",
+                room_query.page + 1,
+                pages,
+                directory_room_list.total
+            ));
+            if ui
+                .add_enabled(room_query.page + 1 < pages, egui::Button::new(">"))
+                .clicked()
+            {
+                room_query.page += 1;
+            }
         });
     });
 }
@@ -1,35 +1,149 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
 use bevy::prelude::*;
-use bevy_renet::renet::RenetClient;
+use bevy_renet::renet::{ClientAuthentication, RenetClient};
 use texas_holdem_common::{
+    betting::RakeConfig,
     channel::{
-        BroadcastPlayInfoMessage, BroadcastRoomInfoMessage, CreateRoomMessage, EnterRoomMessage,
-        GetRoomsMessage, SetRoomStateMessage, SwitchPlayerRoleMessage,
-        BROADCAST_PLAY_INFO_CHANNEL_ID, BROADCAST_ROOM_INFO_CHANNEL_ID, CREATE_ROOM_CHANNEL_ID,
-        ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID,
-        SWITCH_PLAYER_ROLE_CHANNEL_ID,
+        BankMessage, BankRequest, ChatMessage, CreateRoomMessage, EnterRoomMessage,
+        GetRoomsMessage, HandshakeMessage, PlayerActionMessage, QuickSeatMessage, RequestError,
+        RoomAdminMessage, RoomSyncMessage, RunItTwiceMessage, SetRoomStateMessage, SitOutMessage,
+        SwitchPlayerRoleMessage, BANK_CHANNEL_ID, CHAT_CHANNEL_ID, CREATE_ROOM_CHANNEL_ID,
+        ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID, HANDSHAKE_CHANNEL_ID,
+        PLAYER_ACTION_CHANNEL_ID, QUICK_SEAT_CHANNEL_ID, ROOM_ADMIN_CHANNEL_ID,
+        ROOM_SYNC_CHANNEL_ID, RUN_IT_TWICE_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID,
+        SIT_OUT_CHANNEL_ID, SWITCH_PLAYER_ROLE_CHANNEL_ID,
     },
-    util::timestamp,
+    connection_config,
+    sync::{RoomSyncParts, SyncResult},
+    PlayerRole, SessionToken, PROTOCOL_ID, PROTOCOL_VERSION,
 };
 
 use crate::{
-    lobby::{CreateRoomEvent, EnterRoomEvent, NewRoomSettings, PlayerName, RoomList},
-    play::CurrentPlayInfo,
-    room::{CurrentRoomInfo, SetRoomStateEvent, SwitchPlayerRoleEvent},
+    lobby::{
+        CancelQuickSeatEvent, ConnectServerEvent, ConnectionError, CreateRoomEvent, CurrentServer,
+        EnterRoomEvent, JoinByInviteEvent, NewRoomSettings, PlayerName, QuickSeatEvent,
+        QuickSeatState, QuickSeatWaiting, RoomList, RoomQuery, SessionTokens,
+    },
+    request::{RequestFailedEvent, RequestKind, Requests},
+    room::{
+        BankEvent, Bankroll, ChatEvent, CurrentRoomInfo, PlayerActionEvent, RoomAdminEvent,
+        RunItTwiceEvent, SetRoomStateEvent, SitOutEvent, SwitchPlayerRoleEvent,
+    },
     AppState,
 };
 
+pub(crate) fn new_renet_client(
+    server_addr: SocketAddr,
+    session_token: Option<SessionToken>,
+) -> std::io::Result<RenetClient> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    // 同时启动多个客户端时时间戳可能重复，使用随机id
+    let client_id = rand::random();
+    let mut user_data = PROTOCOL_VERSION.to_user_data();
+    if let Some(session_token) = session_token {
+        session_token.write_user_data(&mut user_data);
+    }
+    let authentication = ClientAuthentication::Unsecure {
+        client_id,
+        protocol_id: PROTOCOL_ID,
+        server_addr,
+        user_data: Some(user_data),
+    };
+    RenetClient::new(current_time, socket, connection_config(), authentication)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+pub fn connect_server(
+    mut commands: Commands,
+    mut connect_server_er: EventReader<ConnectServerEvent>,
+    mut connection_error: ResMut<ConnectionError>,
+    mut current_server: ResMut<CurrentServer>,
+    session_tokens: Res<SessionTokens>,
+) {
+    for event in connect_server_er.iter() {
+        let server_addr = match event.server_addr.trim().parse::<SocketAddr>() {
+            Ok(server_addr) => server_addr,
+            Err(_) => {
+                connection_error.0 = Some(format!("Invalid server address: {}", event.server_addr));
+                continue;
+            }
+        };
+        match new_renet_client(server_addr, session_tokens.0.get(&server_addr).copied()) {
+            Ok(client) => {
+                info!("Connecting to server {}", server_addr);
+                connection_error.0 = None;
+                *current_server = CurrentServer {
+                    server_name: None,
+                    server_addr: Some(server_addr),
+                };
+                commands.insert_resource(client);
+            }
+            Err(e) => {
+                connection_error.0 = Some(format!("Failed to connect: {}", e));
+            }
+        }
+    }
+}
+
+pub fn receive_handshake(
+    mut client: ResMut<RenetClient>,
+    mut connection_error: ResMut<ConnectionError>,
+    current_server: Res<CurrentServer>,
+    mut session_tokens: ResMut<SessionTokens>,
+) {
+    while let Some(message) = client.receive_message(HANDSHAKE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<HandshakeMessage>(&message) {
+            info!("Received handshake message: {:?}", message);
+            if let Some((server_addr, session_token)) =
+                current_server.server_addr.zip(message.session_token)
+            {
+                session_tokens.0.insert(server_addr, session_token);
+            }
+            if !message.accepted {
+                connection_error.0 = Some(message.reason.unwrap_or_else(|| {
+                    format!(
+                        "Rejected by server (server version {})",
+                        message.server_version
+                    )
+                }));
+            }
+        }
+    }
+    // 服务端未给出原因的断开
+    if connection_error.0.is_none() {
+        if let Some(reason) = client.disconnected() {
+            connection_error.0 = Some(format!("Disconnected: {}", reason));
+        }
+    }
+}
+
 pub fn get_rooms(
     mut client: ResMut<RenetClient>,
     mut room_list: ResMut<RoomList>,
+    mut room_query: ResMut<RoomQuery>,
+    mut requests: Requests,
     mut refresh_cd: Local<f32>,
     time: Res<Time>,
 ) {
     *refresh_cd -= time.delta_seconds();
 
-    if *refresh_cd < 0.0 {
+    // 筛选条件或页码变化时立即刷新
+    if *refresh_cd < 0.0 || room_query.is_changed() {
         let message = GetRoomsMessage {
-            timestamp: timestamp(),
+            request_id: requests.start(RequestKind::GetRooms),
+            filter: room_query.filter.clone(),
+            sort: room_query.sort,
+            page: room_query.page,
+            page_size: room_query.page_size,
             rooms: Vec::new(),
+            total: 0,
         };
         client.send_message(GET_ROOMS_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
         *refresh_cd = 5.0;
@@ -37,8 +151,16 @@ pub fn get_rooms(
 
     while let Some(message) = client.receive_message(GET_ROOMS_CHANNEL_ID) {
         if let Ok(message) = serde_json::from_slice::<GetRoomsMessage>(&message) {
-            info!("Received get rooms message: {:?}", message);
-            room_list.0 = message.rooms;
+            if requests.finish(message.request_id, RequestKind::GetRooms, None) {
+                info!("Received get rooms message: {:?}", message);
+                // 房间减少后当前页可能已超出范围
+                let pages = message.total.div_ceil(room_query.page_size);
+                if message.rooms.is_empty() && room_query.page > 0 && pages > 0 {
+                    room_query.page = pages - 1;
+                }
+                room_list.rooms = message.rooms;
+                room_list.total = message.total;
+            }
         }
     }
 }
@@ -48,32 +170,45 @@ pub fn create_room(
     mut client: ResMut<RenetClient>,
     new_room_settings: Res<NewRoomSettings>,
     player_name: Res<PlayerName>,
-    mut last_timestamp: Local<u64>,
+    mut requests: Requests,
     mut app_state: ResMut<NextState<AppState>>,
     mut current_room_info: ResMut<CurrentRoomInfo>,
 ) {
-    // TODO 防止重复创建房间
     for _ in create_room_er.iter() {
-        let timestamp = timestamp();
+        // 防止重复创建房间
+        if requests.is_pending(RequestKind::CreateRoom) {
+            continue;
+        }
         let message = CreateRoomMessage {
-            timestamp,
+            request_id: requests.start(RequestKind::CreateRoom),
             room_name: new_room_settings.room_name.clone(),
             room_password: new_room_settings.room_password.clone(),
+            variant: new_room_settings.variant,
+            betting: new_room_settings.betting,
+            run_it_twice: new_room_settings.run_it_twice,
+            forced_bets: new_room_settings.forced_bets,
+            // 锦标赛不抽水
+            rake: match new_room_settings.tournament {
+                Some(_) => RakeConfig::default(),
+                None => new_room_settings.rake,
+            },
+            spectators: new_room_settings.spectators,
+            tournament: new_room_settings.tournament.clone(),
             player_name: player_name.0.clone(),
             room_id: 0,
+            error: None,
         };
         client.send_message(
             CREATE_ROOM_CHANNEL_ID,
             serde_json::to_vec(&message).unwrap(),
         );
-        *last_timestamp = timestamp;
     }
 
     while let Some(message) = client.receive_message(CREATE_ROOM_CHANNEL_ID) {
         if let Ok(message) = serde_json::from_slice::<CreateRoomMessage>(&message) {
-            if message.timestamp == *last_timestamp {
-                info!("Received create room message: {:?}", message);
-                current_room_info.room_id = message.room_id;
+            info!("Received create room message: {:?}", message);
+            if requests.finish(message.request_id, RequestKind::CreateRoom, message.error) {
+                current_room_info.enter(message.room_id);
                 app_state.set(AppState::Gaming);
             }
         }
@@ -82,138 +217,439 @@ pub fn create_room(
 
 pub fn enter_room(
     mut enter_room_er: EventReader<EnterRoomEvent>,
+    mut join_by_invite_er: EventReader<JoinByInviteEvent>,
     mut client: ResMut<RenetClient>,
     player_name: Res<PlayerName>,
-    mut last_timestamp: Local<u64>,
+    mut requests: Requests,
     mut app_state: ResMut<NextState<AppState>>,
     mut current_room_info: ResMut<CurrentRoomInfo>,
 ) {
     for event in enter_room_er.iter() {
-        let timestamp = timestamp();
         let message = EnterRoomMessage {
-            timestamp,
+            request_id: requests.start(RequestKind::EnterRoom),
             room_id: event.room_id,
             room_password: event.room_password.clone(),
+            invite_code: String::new(),
             player_name: player_name.0.clone(),
-            success: false,
+            error: None,
+        };
+        client.send_message(ENTER_ROOT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+    // 房间id由服务端按邀请码填入响应
+    for event in join_by_invite_er.iter() {
+        let message = EnterRoomMessage {
+            request_id: requests.start(RequestKind::EnterRoom),
+            room_id: 0,
+            room_password: String::new(),
+            invite_code: event.invite_code.clone(),
+            player_name: player_name.0.clone(),
+            error: None,
         };
         client.send_message(ENTER_ROOT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
-        *last_timestamp = timestamp;
     }
 
     while let Some(message) = client.receive_message(ENTER_ROOT_CHANNEL_ID) {
         if let Ok(message) = serde_json::from_slice::<EnterRoomMessage>(&message) {
-            if message.timestamp == *last_timestamp && message.success {
-                info!("Received enter room message: {:?}", message);
-                current_room_info.room_id = message.room_id;
+            info!("Received enter room message: {:?}", message);
+            if requests.finish(message.request_id, RequestKind::EnterRoom, message.error) {
+                current_room_info.enter(message.room_id);
                 app_state.set(AppState::Gaming);
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn quick_seat(
+    mut quick_seat_er: EventReader<QuickSeatEvent>,
+    mut cancel_quick_seat_er: EventReader<CancelQuickSeatEvent>,
+    mut client: ResMut<RenetClient>,
+    player_name: Res<PlayerName>,
+    mut requests: Requests,
+    mut quick_seat_state: ResMut<QuickSeatState>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut current_room_info: ResMut<CurrentRoomInfo>,
+) {
+    let mut messages = Vec::new();
+    for event in quick_seat_er.iter() {
+        // 防止重复入座
+        if requests.is_pending(RequestKind::QuickSeat) {
+            continue;
+        }
+        messages.push(QuickSeatMessage {
+            request_id: requests.start(RequestKind::QuickSeat),
+            player_name: player_name.0.clone(),
+            variant: event.variant,
+            min_big_blind: event.min_big_blind,
+            max_big_blind: event.max_big_blind,
+            cancel: false,
+            room_id: 0,
+            seated: false,
+            waiting_position: 0,
+            error: None,
+        });
+    }
+    // 收到响应前仍可能排到座位，所以等响应后再清除排队状态
+    for _ in cancel_quick_seat_er.iter() {
+        messages.push(QuickSeatMessage {
+            request_id: requests.start(RequestKind::QuickSeat),
+            player_name: player_name.0.clone(),
+            variant: Default::default(),
+            min_big_blind: None,
+            max_big_blind: None,
+            cancel: true,
+            room_id: 0,
+            seated: false,
+            waiting_position: 0,
+            error: None,
+        });
+    }
+    for message in messages {
+        client.send_message(QUICK_SEAT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+
+    while let Some(message) = client.receive_message(QUICK_SEAT_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<QuickSeatMessage>(&message) {
+            info!("Received quick seat message: {:?}", message);
+            // 排到座位的通知使用排队时的请求id
+            let waited = quick_seat_state
+                .waiting
+                .is_some_and(|waiting| waiting.request_id == message.request_id);
+            if waited {
+                quick_seat_state.waiting = None;
+                if let Some(error) = message.error {
+                    requests.fail(RequestKind::QuickSeat, error);
+                    continue;
+                }
+            } else if !requests.finish(message.request_id, RequestKind::QuickSeat, message.error) {
+                continue;
+            }
+            if message.seated {
+                quick_seat_state.waiting = None;
+                current_room_info.enter(message.room_id);
+                app_state.set(AppState::Gaming);
+            } else if message.cancel {
+                quick_seat_state.waiting = None;
+            } else {
+                quick_seat_state.waiting = Some(QuickSeatWaiting {
+                    request_id: message.request_id,
+                    room_id: message.room_id,
+                    position: message.waiting_position,
+                });
+            }
+        }
+    }
+}
+
 pub fn switch_player_role(
     mut switch_player_role_er: EventReader<SwitchPlayerRoleEvent>,
     mut client: ResMut<RenetClient>,
-    mut last_timestamp: Local<u64>,
+    mut requests: Requests,
     mut current_room_info: ResMut<CurrentRoomInfo>,
 ) {
     for event in switch_player_role_er.iter() {
-        let timestamp = timestamp();
         let message = SwitchPlayerRoleMessage {
-            timestamp,
+            request_id: requests.start(RequestKind::SwitchPlayerRole),
             room_id: event.room_id,
             target_player_role: event.target_player_role,
-            success: false,
+            error: None,
         };
         client.send_message(
             SWITCH_PLAYER_ROLE_CHANNEL_ID,
             serde_json::to_vec(&message).unwrap(),
         );
-        *last_timestamp = timestamp;
     }
 
     while let Some(message) = client.receive_message(SWITCH_PLAYER_ROLE_CHANNEL_ID) {
         if let Ok(message) = serde_json::from_slice::<SwitchPlayerRoleMessage>(&message) {
-            if message.timestamp == *last_timestamp && message.success {
-                info!("Received switch player role message: {:?}", message);
+            info!("Received switch player role message: {:?}", message);
+            if requests.finish(
+                message.request_id,
+                RequestKind::SwitchPlayerRole,
+                message.error,
+            ) {
                 current_room_info.my_role = message.target_player_role;
             }
         }
     }
 }
 
-pub fn receive_room_info(
-    mut client: ResMut<RenetClient>,
-    mut last_timestamp: Local<u64>,
-    mut current_room_info: ResMut<CurrentRoomInfo>,
-    player_name: Res<PlayerName>,
-) {
-    while let Some(message) = client.receive_message(BROADCAST_ROOM_INFO_CHANNEL_ID) {
-        if let Ok(message) = serde_json::from_slice::<BroadcastRoomInfoMessage>(&message) {
-            if message.timestamp > *last_timestamp {
-                info!("Received room info message: {:?}", message);
-                if let Some(player) = current_room_info
-                    .players
-                    .iter()
-                    .find(|player| player.player_name == player_name.0)
-                {
-                    current_room_info.my_role = player.player_role;
-                }
-                current_room_info.room_state = message.room_state;
-                current_room_info.players = message.players;
-                *last_timestamp = message.timestamp;
-            }
-        }
-    }
-}
-
 pub fn set_room_state(
     mut set_room_state_er: EventReader<SetRoomStateEvent>,
     mut client: ResMut<RenetClient>,
-    mut last_timestamp: Local<u64>,
+    mut requests: Requests,
     mut current_room_info: ResMut<CurrentRoomInfo>,
-    player_name: Res<PlayerName>,
 ) {
     for event in set_room_state_er.iter() {
-        let timestamp = timestamp();
         let message = SetRoomStateMessage {
-            timestamp,
-            room_id: current_room_info.room_id,
-            player_name: player_name.0.clone(),
+            request_id: requests.start(RequestKind::SetRoomState),
+            room_id: current_room_info.view.room_id,
             target_room_state: event.target_room_state,
-            success: false,
+            error: None,
         };
         client.send_message(
             SET_ROOM_STATE_CHANNEL_ID,
             serde_json::to_vec(&message).unwrap(),
         );
-        *last_timestamp = timestamp;
     }
 
     while let Some(message) = client.receive_message(SET_ROOM_STATE_CHANNEL_ID) {
         if let Ok(message) = serde_json::from_slice::<SetRoomStateMessage>(&message) {
-            if message.timestamp == *last_timestamp && message.success {
-                info!("Received set room state message: {:?}", message);
-                current_room_info.room_state = message.target_room_state;
+            info!("Received set room state message: {:?}", message);
+            if requests.finish(message.request_id, RequestKind::SetRoomState, message.error) {
+                current_room_info.view.room_state = message.target_room_state;
+            }
+        }
+    }
+}
+
+pub fn player_action(
+    mut player_action_er: EventReader<PlayerActionEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    for event in player_action_er.iter() {
+        let Some(play) = current_room_info.view.play.as_ref() else {
+            continue;
+        };
+        let message = PlayerActionMessage {
+            request_id: requests.start(RequestKind::PlayerAction),
+            room_id: current_room_info.view.room_id,
+            play_id: play.play_id,
+            action: event.action,
+            amount: event.amount,
+            error: None,
+        };
+        client.send_message(
+            PLAYER_ACTION_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+    }
+
+    while let Some(message) = client.receive_message(PLAYER_ACTION_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<PlayerActionMessage>(&message) {
+            info!("Received player action message: {:?}", message);
+            // 行动结果通过房间同步更新，这里只处理错误
+            requests.finish(message.request_id, RequestKind::PlayerAction, message.error);
+        }
+    }
+}
+
+pub fn run_it_twice(
+    mut run_it_twice_er: EventReader<RunItTwiceEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    for event in run_it_twice_er.iter() {
+        let Some(play) = current_room_info.view.play.as_ref() else {
+            continue;
+        };
+        let message = RunItTwiceMessage {
+            request_id: requests.start(RequestKind::RunItTwice),
+            room_id: current_room_info.view.room_id,
+            play_id: play.play_id,
+            accept: event.accept,
+            error: None,
+        };
+        client.send_message(
+            RUN_IT_TWICE_CHANNEL_ID,
+            serde_json::to_vec(&message).unwrap(),
+        );
+    }
+
+    while let Some(message) = client.receive_message(RUN_IT_TWICE_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<RunItTwiceMessage>(&message) {
+            info!("Received run it twice message: {:?}", message);
+            requests.finish(message.request_id, RequestKind::RunItTwice, message.error);
+        }
+    }
+}
+
+pub fn sit_out(
+    mut sit_out_er: EventReader<SitOutEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    for event in sit_out_er.iter() {
+        let message = SitOutMessage {
+            request_id: requests.start(RequestKind::SitOut),
+            room_id: current_room_info.view.room_id,
+            sitting_out: event.sitting_out,
+            error: None,
+        };
+        client.send_message(SIT_OUT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+
+    while let Some(message) = client.receive_message(SIT_OUT_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<SitOutMessage>(&message) {
+            info!("Received sit out message: {:?}", message);
+            // 坐出状态通过房间同步更新，这里只处理错误
+            requests.finish(message.request_id, RequestKind::SitOut, message.error);
+        }
+    }
+}
+
+pub fn bank(
+    mut bank_er: EventReader<BankEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    mut bankroll: ResMut<Bankroll>,
+    // 上次查询余额时的 (房间id, 自己的角色, 桌上筹码)
+    mut last_query: Local<Option<(u64, PlayerRole, u32)>>,
+    current_room_info: Res<CurrentRoomInfo>,
+    player_name: Res<PlayerName>,
+) {
+    let room_id = current_room_info.view.room_id;
+    let chips = current_room_info
+        .view
+        .players
+        .iter()
+        .find(|player| player.player_name == player_name.0)
+        .map_or(0, |player| player.chips);
+    // 进入房间、坐下离开或筹码变化时重新查询余额
+    let query = (room_id, current_room_info.my_role, chips);
+    let mut requests_to_send: Vec<BankRequest> =
+        bank_er.iter().map(|event| event.request).collect();
+    if *last_query != Some(query) {
+        *last_query = Some(query);
+        requests_to_send.push(BankRequest::Balance);
+    }
+    for request in requests_to_send {
+        let message = BankMessage {
+            request_id: requests.start(RequestKind::Bank),
+            room_id,
+            request,
+            balance: 0,
+            min_buy_in: 0,
+            max_buy_in: 0,
+            error: None,
+        };
+        client.send_message(BANK_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+
+    while let Some(message) = client.receive_message(BANK_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<BankMessage>(&message) {
+            info!("Received bank message: {:?}", message);
+            if message.room_id != room_id {
+                continue;
             }
+            // 失败时同样带回最新余额
+            requests.finish(message.request_id, RequestKind::Bank, message.error);
+            *bankroll = Bankroll {
+                balance: Some(message.balance),
+                min_buy_in: message.min_buy_in,
+                max_buy_in: message.max_buy_in,
+            };
         }
     }
 }
 
-pub fn receive_play_info(
+pub fn receive_room_sync(
     mut client: ResMut<RenetClient>,
-    mut last_timestamp: Local<u64>,
-    mut current_play_info: ResMut<CurrentPlayInfo>,
+    mut current_room_info: ResMut<CurrentRoomInfo>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut request_failed_ew: EventWriter<RequestFailedEvent>,
+    mut parts: Local<RoomSyncParts>,
 ) {
-    while let Some(message) = client.receive_message(BROADCAST_PLAY_INFO_CHANNEL_ID) {
-        if let Ok(message) = serde_json::from_slice::<BroadcastPlayInfoMessage>(&message) {
-            if message.timestamp > *last_timestamp {
-                info!("Received play info message: {:?}", message);
-                current_play_info.play_id = Some(message.play_id);
-                current_play_info.round = message.round;
-                current_play_info.participants = message.participants;
-                *last_timestamp = message.timestamp;
+    while let Some(message) = client.receive_message(ROOM_SYNC_CHANNEL_ID) {
+        if let Some(message) = parts.decode(&message) {
+            info!("Received room sync message: {:?}", message);
+            let message = match message {
+                RoomSyncMessage::Chat(line) => {
+                    current_room_info.push_chat(line);
+                    continue;
+                }
+                // 被请出房间，回到大厅
+                RoomSyncMessage::Kicked { room_id } => {
+                    if room_id == current_room_info.view.room_id {
+                        *current_room_info = CurrentRoomInfo::default();
+                        app_state.set(AppState::Lobby);
+                        request_failed_ew.send(RequestFailedEvent {
+                            kind: RequestKind::Chat,
+                            error: RequestError::Kicked,
+                        });
+                    }
+                    continue;
+                }
+                message => message,
+            };
+            if current_room_info.view.apply(message) == SyncResult::Gap {
+                // 序号出现缺口，重新请求快照
+                warn!("Room sync gap, requesting snapshot");
+                let message = RoomSyncMessage::RequestSnapshot {
+                    room_id: current_room_info.view.room_id,
+                };
+                client.send_message(ROOM_SYNC_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+                continue;
+            }
+            // 根据最新的玩家列表更新自己的角色
+            let client_id = client.client_id();
+            current_room_info.is_owner = current_room_info.view.owner_client_id == Some(client_id);
+            if let Some(player) = current_room_info
+                .view
+                .players
+                .iter()
+                .find(|player| player.player_client_id == client_id)
+            {
+                current_room_info.my_role = player.player_role;
+            }
+        }
+    }
+}
+
+pub fn chat(
+    mut chat_er: EventReader<ChatEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    for event in chat_er.iter() {
+        let message = ChatMessage {
+            request_id: requests.start(RequestKind::Chat),
+            room_id: current_room_info.view.room_id,
+            text: event.text.clone(),
+            error: None,
+        };
+        client.send_message(CHAT_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+
+    // 发送成功的消息由服务端广播回来
+    while let Some(message) = client.receive_message(CHAT_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<ChatMessage>(&message) {
+            info!("Received chat message: {:?}", message);
+            requests.finish(message.request_id, RequestKind::Chat, message.error);
+        }
+    }
+}
+
+pub fn room_admin(
+    mut room_admin_er: EventReader<RoomAdminEvent>,
+    mut client: ResMut<RenetClient>,
+    mut requests: Requests,
+    mut current_room_info: ResMut<CurrentRoomInfo>,
+) {
+    for event in room_admin_er.iter() {
+        let message = RoomAdminMessage {
+            request_id: requests.start(RequestKind::RoomAdmin),
+            room_id: current_room_info.view.room_id,
+            command: event.command.clone(),
+            invite: None,
+            error: None,
+        };
+        client.send_message(ROOM_ADMIN_CHANNEL_ID, serde_json::to_vec(&message).unwrap());
+    }
+
+    // 结果通过房间状态同步更新，邀请码在响应中返回
+    while let Some(message) = client.receive_message(ROOM_ADMIN_CHANNEL_ID) {
+        if let Ok(message) = serde_json::from_slice::<RoomAdminMessage>(&message) {
+            info!("Received room admin message: {:?}", message);
+            if requests.finish(message.request_id, RequestKind::RoomAdmin, message.error) {
+                if let Some(invite) = message.invite {
+                    current_room_info.invite = Some(invite);
+                }
             }
         }
     }
@@ -0,0 +1,194 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts};
+use texas_holdem_common::channel::RequestError;
+
+// 请求超时时间（秒）
+const REQUEST_TIMEOUT_SECS: f64 = 5.0;
+// 错误提示显示时长（秒）
+const ERROR_NOTICE_SECS: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    GetRooms,
+    CreateRoom,
+    EnterRoom,
+    SwitchPlayerRole,
+    SetRoomState,
+    PlayerAction,
+    RunItTwice,
+    SitOut,
+    Bank,
+    Chat,
+    RoomAdmin,
+    QuickSeat,
+    DirectoryRooms,
+    LocateServer,
+}
+
+impl RequestKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RequestKind::GetRooms => "Get rooms",
+            RequestKind::CreateRoom => "Create room",
+            RequestKind::EnterRoom => "Enter room",
+            RequestKind::SwitchPlayerRole => "Switch role",
+            RequestKind::SetRoomState => "Set room state",
+            RequestKind::PlayerAction => "Action",
+            RequestKind::RunItTwice => "Run it twice",
+            RequestKind::SitOut => "Sit out",
+            RequestKind::Bank => "Bank",
+            RequestKind::Chat => "Chat",
+            RequestKind::RoomAdmin => "Room admin",
+            RequestKind::QuickSeat => "Quick seat",
+            RequestKind::DirectoryRooms => "Directory rooms",
+            RequestKind::LocateServer => "Find server",
+        }
+    }
+}
+
+// 已发出、尚未收到响应的请求
+#[derive(Debug, Default, Resource)]
+pub struct PendingRequests {
+    next_request_id: u64,
+    // request_id -> (请求类型, 发送时间)
+    pending: HashMap<u64, (RequestKind, f64)>,
+}
+
+impl PendingRequests {
+    // 登记新请求，返回请求id
+    pub fn start(&mut self, kind: RequestKind, now: f64) -> u64 {
+        self.next_request_id += 1;
+        self.pending.insert(self.next_request_id, (kind, now));
+        self.next_request_id
+    }
+
+    // 收到响应，请求仍在等待中且类型一致时返回true
+    pub fn finish(&mut self, request_id: u64, kind: RequestKind) -> bool {
+        match self.pending.get(&request_id) {
+            Some((pending_kind, _)) if *pending_kind == kind => {
+                self.pending.remove(&request_id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_pending(&self, kind: RequestKind) -> bool {
+        self.pending
+            .values()
+            .any(|(pending_kind, _)| *pending_kind == kind)
+    }
+
+    // 切换服务端后丢弃发给原服务端的请求，不再等待响应
+    pub fn cancel_all_except(&mut self, kinds: &[RequestKind]) {
+        self.pending
+            .retain(|_, (pending_kind, _)| kinds.contains(pending_kind));
+    }
+}
+
+// 请求失败（服务端返回错误或超时）
+#[derive(Debug)]
+pub struct RequestFailedEvent {
+    pub kind: RequestKind,
+    pub error: RequestError,
+}
+
+// 发送请求、处理响应时使用
+#[derive(SystemParam)]
+pub struct Requests<'w> {
+    time: Res<'w, Time>,
+    pending_requests: ResMut<'w, PendingRequests>,
+    request_failed_ew: EventWriter<'w, RequestFailedEvent>,
+}
+
+impl<'w> Requests<'w> {
+    pub fn start(&mut self, kind: RequestKind) -> u64 {
+        let now = self.time.elapsed_seconds_f64();
+        self.pending_requests.start(kind, now)
+    }
+
+    pub fn is_pending(&self, kind: RequestKind) -> bool {
+        self.pending_requests.is_pending(kind)
+    }
+
+    pub fn cancel_all_except(&mut self, kinds: &[RequestKind]) {
+        self.pending_requests.cancel_all_except(kinds);
+    }
+
+    // 处理响应，请求成功时返回true，失败时发送RequestFailedEvent
+    pub fn finish(
+        &mut self,
+        request_id: u64,
+        kind: RequestKind,
+        error: Option<RequestError>,
+    ) -> bool {
+        if !self.pending_requests.finish(request_id, kind) {
+            return false;
+        }
+        match error {
+            Some(error) => {
+                self.fail(kind, error);
+                false
+            }
+            None => true,
+        }
+    }
+
+    // 服务端主动通知的失败（如排队后入座失败）
+    pub fn fail(&mut self, kind: RequestKind, error: RequestError) {
+        self.request_failed_ew
+            .send(RequestFailedEvent { kind, error });
+    }
+}
+
+// 当前显示的错误提示 (提示内容, 产生时间)
+#[derive(Debug, Default, Resource)]
+pub struct RequestErrorNotices(pub Vec<(String, f64)>);
+
+pub fn expire_pending_requests(
+    time: Res<Time>,
+    mut pending_requests: ResMut<PendingRequests>,
+    mut request_failed_ew: EventWriter<RequestFailedEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+    pending_requests
+        .pending
+        .retain(|request_id, (kind, sent_at)| {
+            if now - *sent_at > REQUEST_TIMEOUT_SECS {
+                warn!("Request {} ({}) timed out", request_id, kind.name());
+                request_failed_ew.send(RequestFailedEvent {
+                    kind: *kind,
+                    error: RequestError::Timeout,
+                });
+                false
+            } else {
+                true
+            }
+        });
+}
+
+pub fn request_error_ui(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    mut request_failed_er: EventReader<RequestFailedEvent>,
+    mut notices: ResMut<RequestErrorNotices>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for event in request_failed_er.iter() {
+        notices.0.push((
+            format!("{}: {}", event.kind.name(), event.error.message()),
+            now,
+        ));
+    }
+    notices
+        .0
+        .retain(|(_, created_at)| now - *created_at < ERROR_NOTICE_SECS);
+
+    if !notices.0.is_empty() {
+        egui::Window::new("Error").show(contexts.ctx_mut(), |ui| {
+            for (notice, _) in notices.0.iter() {
+                ui.colored_label(egui::Color32::RED, notice);
+            }
+        });
+    }
+}
@@ -1,7 +1,16 @@
 use bevy::prelude::*;
-use texas_holdem_common::{Player, PlayerRole, RoomState};
+use bevy_egui::{egui, EguiContexts};
+use texas_holdem_common::{
+    channel::{BankRequest, ChatLine, ChatScope, InviteCode, RoomAdminCommand},
+    sync::RoomView,
+    Card, PlayerRole, RoomState, RoundAction,
+};
 
-use crate::{lobby::PlayerName, play::CurrentPlayInfo};
+use crate::lobby::PlayerName;
+
+// 进入房间时创建的实体，离开房间时删除
+#[derive(Debug, Component)]
+pub struct RoomEntity;
 
 #[derive(Debug, Component)]
 pub struct ParticipantRoleButton;
@@ -31,20 +40,83 @@ pub struct SetRoomStateEvent {
     pub target_room_state: RoomState,
 }
 
+#[derive(Debug)]
+pub struct RunItTwiceEvent {
+    pub accept: bool,
+}
+
+#[derive(Debug)]
+pub struct SitOutEvent {
+    pub sitting_out: bool,
+}
+
+#[derive(Debug)]
+pub struct BankEvent {
+    pub request: BankRequest,
+}
+
+// 自己的账户余额和买入范围，由服务端响应更新
+#[derive(Debug, Default, Resource)]
+pub struct Bankroll {
+    pub balance: Option<u64>,
+    pub min_buy_in: u32,
+    pub max_buy_in: u32,
+}
+
+#[derive(Debug)]
+pub struct RoomAdminEvent {
+    pub command: RoomAdminCommand,
+}
+
+#[derive(Debug)]
+pub struct ChatEvent {
+    pub text: String,
+}
+
+// 聊天记录保留的条数
+const MAX_CHAT_LINES: usize = 100;
+
+#[derive(Debug)]
+pub struct PlayerActionEvent {
+    pub action: RoundAction,
+    // Bet/Raise时为本轮下注总额
+    pub amount: u32,
+}
+
 // 当前房间信息
 #[derive(Debug, Default, Resource)]
 pub struct CurrentRoomInfo {
-    pub room_id: u64,
-    pub room_state: RoomState,
     pub my_role: PlayerRole,
-    pub players: Vec<Player>,
+    // 自己是否为房主
+    pub is_owner: bool,
+    // 房间和对局状态，由服务端同步
+    pub view: RoomView,
+    // 收到的聊天消息
+    pub chat: Vec<ChatLine>,
+    // 房主最近生成的邀请码
+    pub invite: Option<InviteCode>,
 }
 
 impl CurrentRoomInfo {
-    pub fn contains_player(&self, player_name: &str) -> bool {
-        self.players
-            .iter()
-            .any(|player| player.player_name == player_name)
+    // 进入新房间，清空旧房间的同步状态
+    pub fn enter(&mut self, room_id: u64) {
+        *self = CurrentRoomInfo {
+            my_role: PlayerRole::default(),
+            is_owner: false,
+            view: RoomView::new(room_id),
+            chat: Vec::new(),
+            invite: None,
+        };
+    }
+
+    pub fn push_chat(&mut self, line: ChatLine) {
+        if line.room_id != self.view.room_id {
+            return;
+        }
+        self.chat.push(line);
+        if self.chat.len() > MAX_CHAT_LINES {
+            self.chat.remove(0);
+        }
     }
 }
 
@@ -64,13 +136,16 @@ pub fn setup_room_ui(
     current_room_info: Res<CurrentRoomInfo>,
 ) {
     commands
-        .spawn((NodeBundle {
-            style: Style {
-                size: Size::all(Val::Percent(100.0)),
+        .spawn((
+            RoomEntity,
+            NodeBundle {
+                style: Style {
+                    size: Size::all(Val::Percent(100.0)),
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        },))
+        ))
         .with_children(|parent| {
             // 左侧布局
             parent
@@ -209,7 +284,7 @@ pub fn setup_room_ui(
                                 RoomStateUIText,
                                 TextBundle {
                                     text: Text::from_section(
-                                        current_room_info.room_state.name(),
+                                        current_room_info.view.room_state.name(),
                                         TextStyle {
                                             font: asset_server.load("fonts/ThaleahFat_TTF.ttf"),
                                             font_size: 40.0,
@@ -356,6 +431,7 @@ pub fn setup_room_ui(
         });
 }
 
+#[allow(clippy::type_complexity)]
 pub fn player_role_ui_system(
     mut q_participant_role_button: Query<
         (Ref<Interaction>, &mut BackgroundColor),
@@ -379,7 +455,7 @@ pub fn player_role_ui_system(
     {
         println!("participant_role_button clicked");
         switch_player_role_ew.send(SwitchPlayerRoleEvent {
-            room_id: current_room_info.room_id,
+            room_id: current_room_info.view.room_id,
             target_player_role: PlayerRole::Participant,
         });
     } else if Interaction::Clicked == *spectator_role_button_interaction
@@ -387,7 +463,7 @@ pub fn player_role_ui_system(
     {
         println!("spectator_role_button clicked");
         switch_player_role_ew.send(SwitchPlayerRoleEvent {
-            room_id: current_room_info.room_id,
+            room_id: current_room_info.view.room_id,
             target_player_role: PlayerRole::Spectator,
         });
     }
@@ -415,13 +491,16 @@ pub fn player_list_ui_system(
     let parent = q_player_list_ui.single_mut();
     // 删除已经不在房间的玩家
     for (entity, text) in &q_player_list_ui_item {
-        if !current_room_info.contains_player(&text.sections[0].value) {
+        if !current_room_info
+            .view
+            .contains_player(&text.sections[0].value)
+        {
             commands.entity(entity).despawn_recursive();
         }
     }
 
     // 新增玩家
-    for player in current_room_info.players.iter() {
+    for player in current_room_info.view.players.iter() {
         if !q_player_list_ui_item
             .iter()
             .any(|(_, text)| text.sections[0].value == player.player_name)
@@ -460,26 +539,73 @@ pub fn room_state_ui_system(
     mut q_room_state_text: Query<&mut Text, With<RoomStateUIText>>,
     current_room_info: Res<CurrentRoomInfo>,
 ) {
+    let view = &current_room_info.view;
     for mut text in &mut q_room_state_text {
-        text.sections[0].value = current_room_info.room_state.name().to_string();
+        // 锦标赛显示盲注级别和奖池
+        text.sections[0].value = match view.tournament.as_ref() {
+            Some(tournament) => format!(
+                "{} - Level {} ({}/{}) - Prize Pool {}",
+                view.room_state.name(),
+                tournament.level + 1,
+                view.small_blind,
+                view.big_blind,
+                tournament.prize_pool
+            ),
+            None => view.room_state.name().to_string(),
+        };
     }
 }
 
 pub fn play_round_ui_system(
     mut q_play_round_ui: Query<&mut Visibility, With<PlayRoundUI>>,
     mut q_play_round_ui_text: Query<&mut Text, With<PlayRoundUIText>>,
-    current_play_info: Res<CurrentPlayInfo>,
+    current_room_info: Res<CurrentRoomInfo>,
 ) {
+    let play = current_room_info.view.play.as_ref();
     for mut visibility in &mut q_play_round_ui {
-        *visibility = if current_play_info.play_id.is_some() {
+        *visibility = if play.is_some() {
             Visibility::Visible
         } else {
             Visibility::Hidden
         };
     }
 
-    for mut text in &mut q_play_round_ui_text {
-        text.sections[0].value = format!("Round - {}", current_play_info.round.name());
+    if let Some(play) = play {
+        for mut text in &mut q_play_round_ui_text {
+            // 摊牌时显示公共牌，发两次时显示两组
+            let board_names = |cards: &[Card]| {
+                cards
+                    .iter()
+                    .map(|card| card.name())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            text.sections[0].value = match play.showdown.as_ref() {
+                Some(showdown) => {
+                    let rake = if showdown.rake > 0 {
+                        format!(" Rake {}", showdown.rake)
+                    } else {
+                        String::new()
+                    };
+                    match showdown.second_board.as_ref() {
+                        Some(second_board) => format!(
+                            "Round - {} [{}] [{}]{}",
+                            play.round.name(),
+                            board_names(&play.board_cards),
+                            board_names(second_board),
+                            rake
+                        ),
+                        None => format!(
+                            "Round - {} [{}]{}",
+                            play.round.name(),
+                            board_names(&play.board_cards),
+                            rake
+                        ),
+                    }
+                }
+                None => format!("Round - {}", play.round.name()),
+            };
+        }
     }
 }
 
@@ -493,6 +619,7 @@ pub fn set_room_state_ui_system(
         ),
     >,
     mut set_room_state_ew: EventWriter<SetRoomStateEvent>,
+    current_room_info: Res<CurrentRoomInfo>,
 ) {
     let set_room_state_playing_interaction = q_set_room_state_playing_button.single();
     let set_room_state_paused_interaction = q_set_room_state_paused_button.single();
@@ -502,9 +629,13 @@ pub fn set_room_state_ui_system(
         && set_room_state_playing_interaction.is_changed()
     {
         println!("set_room_state_playing_button clicked");
-        set_room_state_ew.send(SetRoomStateEvent {
-            target_room_state: RoomState::Playing,
-        });
+        // 锦标赛房间开始或继续比赛
+        let target_room_state = if current_room_info.view.tournament.is_some() {
+            RoomState::Running
+        } else {
+            RoomState::Playing
+        };
+        set_room_state_ew.send(SetRoomStateEvent { target_room_state });
     } else if Interaction::Clicked == *set_room_state_paused_interaction
         && set_room_state_paused_interaction.is_changed()
     {
@@ -514,3 +645,376 @@ pub fn set_room_state_ui_system(
         });
     }
 }
+
+// 轮到自己时显示行动按钮，下注额按房间的下注结构限制
+pub fn player_action_ui_system(
+    mut contexts: EguiContexts,
+    mut player_action_ew: EventWriter<PlayerActionEvent>,
+    mut raise_amount: Local<u32>,
+    current_room_info: Res<CurrentRoomInfo>,
+    player_name: Res<PlayerName>,
+) {
+    let view = &current_room_info.view;
+    let Some(play) = view.play.as_ref() else {
+        return;
+    };
+    if play.current_player_name.as_deref() != Some(player_name.0.as_str()) {
+        return;
+    }
+    let state = play.bet_state(&player_name.0);
+    let raise_range = view.betting.raise_range(&state);
+    if let Some((min, max)) = raise_range {
+        *raise_amount = (*raise_amount).clamp(min, max);
+    }
+
+    egui::Window::new("Action").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!(
+            "{} - Pot {} - To Call {}",
+            view.betting.name(),
+            play.pot,
+            state.to_call()
+        ));
+        ui.horizontal(|ui| {
+            if state.to_call() > 0 && ui.button("Fold").clicked() {
+                player_action_ew.send(PlayerActionEvent {
+                    action: RoundAction::Fold,
+                    amount: 0,
+                });
+            }
+            let (action, text) = match state.to_call() {
+                0 => (RoundAction::Check, "Check".to_string()),
+                // 筹码不足跟注时全下
+                to_call if to_call >= state.chips => {
+                    (RoundAction::AllIn, format!("Call All-in {}", state.chips))
+                }
+                to_call => (RoundAction::Call, format!("Call {}", to_call)),
+            };
+            if ui.button(text).clicked() {
+                player_action_ew.send(PlayerActionEvent { action, amount: 0 });
+            }
+            let all_in_to = state.all_in_to();
+            if all_in_to > state.current_bet
+                && view.betting.allows_all_in(&state)
+                && ui.button(format!("All-in {}", all_in_to)).clicked()
+            {
+                player_action_ew.send(PlayerActionEvent {
+                    action: RoundAction::AllIn,
+                    amount: 0,
+                });
+            }
+        });
+        if let Some((min, max)) = raise_range {
+            ui.horizontal(|ui| {
+                // 固定限注只能按固定额度下注
+                if min < max {
+                    ui.add(egui::Slider::new(&mut *raise_amount, min..=max));
+                }
+                let action = if state.current_bet == 0 {
+                    RoundAction::Bet
+                } else {
+                    RoundAction::Raise
+                };
+                if ui
+                    .button(format!("{} {}", action.name(), *raise_amount))
+                    .clicked()
+                {
+                    // 下注额等于全部筹码时按全下处理
+                    let action = if *raise_amount == state.all_in_to() {
+                        RoundAction::AllIn
+                    } else {
+                        action
+                    };
+                    player_action_ew.send(PlayerActionEvent {
+                        action,
+                        amount: *raise_amount,
+                    });
+                }
+            });
+        }
+    });
+}
+
+// 所有人全下后询问是否将剩余公共牌发两次
+pub fn run_it_twice_ui_system(
+    mut contexts: EguiContexts,
+    mut run_it_twice_ew: EventWriter<RunItTwiceEvent>,
+    current_room_info: Res<CurrentRoomInfo>,
+    player_name: Res<PlayerName>,
+) {
+    let Some(vote) = current_room_info
+        .view
+        .play
+        .as_ref()
+        .and_then(|play| play.run_it_twice_vote.as_ref())
+    else {
+        return;
+    };
+    if !vote.is_waiting_for(&player_name.0) {
+        return;
+    }
+    egui::Window::new("Run It Twice").show(contexts.ctx_mut(), |ui| {
+        ui.label("All in. Deal the remaining board twice?");
+        ui.horizontal(|ui| {
+            if ui.button("Yes").clicked() {
+                run_it_twice_ew.send(RunItTwiceEvent { accept: true });
+            }
+            if ui.button("No").clicked() {
+                run_it_twice_ew.send(RunItTwiceEvent { accept: false });
+            }
+        });
+    });
+}
+
+// 参与者可以坐出（保留座位和筹码，不发牌）或回到牌桌
+pub fn sit_out_ui_system(
+    mut contexts: EguiContexts,
+    mut sit_out_ew: EventWriter<SitOutEvent>,
+    current_room_info: Res<CurrentRoomInfo>,
+    player_name: Res<PlayerName>,
+) {
+    let view = &current_room_info.view;
+    if current_room_info.my_role != PlayerRole::Participant || view.tournament.is_some() {
+        return;
+    }
+    let Some(player) = view
+        .players
+        .iter()
+        .find(|player| player.player_name == player_name.0)
+    else {
+        return;
+    };
+    egui::Window::new("Seat").show(contexts.ctx_mut(), |ui| {
+        if player.sitting_out {
+            ui.label("Sitting out");
+            let missed_blinds = player.missed_blinds;
+            if missed_blinds.is_owed() {
+                ui.label(format!(
+                    "Missed blinds: {}{}",
+                    if missed_blinds.big_blinds > 0 {
+                        format!("BB {} ", view.big_blind)
+                    } else {
+                        String::new()
+                    },
+                    if missed_blinds.small_blind {
+                        format!("SB {} (dead)", view.small_blind)
+                    } else {
+                        String::new()
+                    },
+                ));
+            }
+            if ui.button("I'm Back").clicked() {
+                sit_out_ew.send(SitOutEvent { sitting_out: false });
+            }
+        } else if ui.button("Sit Out").clicked() {
+            sit_out_ew.send(SitOutEvent { sitting_out: true });
+        }
+    });
+}
+
+// 显示账户余额，两局之间买入或补充筹码
+pub fn bank_ui_system(
+    mut contexts: EguiContexts,
+    mut bank_ew: EventWriter<BankEvent>,
+    mut amount: Local<u32>,
+    bankroll: Res<Bankroll>,
+    current_room_info: Res<CurrentRoomInfo>,
+    player_name: Res<PlayerName>,
+) {
+    let view = &current_room_info.view;
+    if view.tournament.is_some() {
+        return;
+    }
+    let Some(balance) = bankroll.balance else {
+        return;
+    };
+    egui::Window::new("Bank").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("Balance: {}", balance));
+        if current_room_info.my_role != PlayerRole::Participant {
+            return;
+        }
+        let Some(player) = view
+            .players
+            .iter()
+            .find(|player| player.player_name == player_name.0)
+        else {
+            return;
+        };
+        // 手牌进行中不能买入
+        let in_hand = view.play.as_ref().is_some_and(|play| {
+            play.participants
+                .iter()
+                .any(|participant| participant.player_name == player.player_name)
+        });
+        let room = bankroll.max_buy_in.saturating_sub(player.chips);
+        let max = room.min(balance.min(u32::MAX as u64) as u32);
+        if max == 0 || in_hand {
+            return;
+        }
+        *amount = (*amount).clamp(1, max);
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut *amount).clamp_range(1..=max));
+            let label = if player.chips == 0 {
+                "Buy In"
+            } else {
+                "Top Up"
+            };
+            if ui.button(label).clicked() {
+                bank_ew.send(BankEvent {
+                    request: BankRequest::BuyIn(*amount),
+                });
+            }
+        });
+        ui.label(format!(
+            "Table stack {} to {}",
+            bankroll.min_buy_in, bankroll.max_buy_in
+        ));
+    });
+}
+
+// 离开房间（被房主请出）时删除房间内的界面和牌桌
+pub fn cleanup_room(mut commands: Commands, q_room_entity: Query<Entity, With<RoomEntity>>) {
+    for entity in &q_room_entity {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 玩家列表旁的聊天面板，旁观者的消息只有旁观者能看到
+pub fn chat_ui_system(
+    mut contexts: EguiContexts,
+    mut chat_ew: EventWriter<ChatEvent>,
+    mut text: Local<String>,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    egui::Window::new("Chat")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .default_width(260.0)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in current_room_info.chat.iter() {
+                        match &line.sender_name {
+                            None => ui.colored_label(egui::Color32::GRAY, &line.text),
+                            Some(sender_name) if line.scope == ChatScope::Spectators => ui
+                                .colored_label(
+                                    egui::Color32::LIGHT_BLUE,
+                                    format!("[Spectators] {}: {}", sender_name, line.text),
+                                ),
+                            Some(sender_name) => {
+                                ui.label(format!("{}: {}", sender_name, line.text))
+                            }
+                        };
+                    }
+                });
+            if current_room_info.my_role == PlayerRole::Spectator {
+                ui.label("Only spectators can see your messages");
+                // 旁观延迟时提示看到的牌局不是实时的
+                let delay = current_room_info.view.spectators.delay;
+                if delay.is_enabled() {
+                    ui.colored_label(
+                        egui::Color32::GRAY,
+                        format!("Table view: {}", delay.describe()),
+                    );
+                }
+            }
+            ui.horizontal(|ui| {
+                let response = ui.text_edit_singleline(&mut *text);
+                let submitted =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if (ui.button("Send").clicked() || submitted) && !text.trim().is_empty() {
+                    chat_ew.send(ChatEvent {
+                        text: std::mem::take(&mut *text),
+                    });
+                }
+            });
+        });
+}
+
+// 房主管理房间：请出或禁止玩家、锁定房间、修改密码、生成邀请码
+pub fn room_admin_ui_system(
+    mut contexts: EguiContexts,
+    mut room_admin_ew: EventWriter<RoomAdminEvent>,
+    mut room_password: Local<String>,
+    current_room_info: Res<CurrentRoomInfo>,
+) {
+    if !current_room_info.is_owner {
+        return;
+    }
+    let view = &current_room_info.view;
+    egui::Window::new("Room Admin").show(contexts.ctx_mut(), |ui| {
+        let mut locked = view.locked;
+        if ui.checkbox(&mut locked, "Locked").changed() {
+            room_admin_ew.send(RoomAdminEvent {
+                command: RoomAdminCommand::Lock(locked),
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut *room_password).password(true));
+            if ui.button("Change Password").clicked() {
+                room_admin_ew.send(RoomAdminEvent {
+                    command: RoomAdminCommand::ChangePassword(std::mem::take(&mut *room_password)),
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Create Invite").clicked() {
+                room_admin_ew.send(RoomAdminEvent {
+                    command: RoomAdminCommand::CreateInvite,
+                });
+            }
+            if ui.button("Revoke Invites").clicked() {
+                room_admin_ew.send(RoomAdminEvent {
+                    command: RoomAdminCommand::RevokeInvites,
+                });
+            }
+        });
+        if let Some(invite) = current_room_info.invite.as_ref() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Invite ({} min): ", invite.valid_secs / 60));
+                // 可选中复制
+                ui.add(egui::TextEdit::singleline(&mut invite.code.as_str()));
+            });
+        }
+        ui.separator();
+        for player in view
+            .players
+            .iter()
+            .filter(|player| Some(player.player_client_id) != view.owner_client_id)
+        {
+            ui.horizontal(|ui| {
+                ui.label(&player.player_name);
+                if ui.button("Kick").clicked() {
+                    room_admin_ew.send(RoomAdminEvent {
+                        command: RoomAdminCommand::Kick {
+                            player_client_id: player.player_client_id,
+                        },
+                    });
+                }
+                if ui.button("Ban").clicked() {
+                    room_admin_ew.send(RoomAdminEvent {
+                        command: RoomAdminCommand::Ban {
+                            player_client_id: player.player_client_id,
+                        },
+                    });
+                }
+            });
+        }
+        if !view.banned_names.is_empty() {
+            ui.separator();
+            ui.label("Banned");
+            for player_name in view.banned_names.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(player_name);
+                    if ui.button("Unban").clicked() {
+                        room_admin_ew.send(RoomAdminEvent {
+                            command: RoomAdminCommand::Unban {
+                                player_name: player_name.clone(),
+                            },
+                        });
+                    }
+                });
+            }
+        }
+    });
+}
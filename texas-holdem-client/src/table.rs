@@ -1,11 +1,16 @@
 use bevy::prelude::*;
 
+use crate::room::RoomEntity;
+
 pub fn setup_table(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(SpriteBundle {
-        texture: asset_server.load("textures/table.png"),
-        // transform: Transform::from_scale(Vec3::splat(0.1)),
-        ..default()
-    });
+    commands.spawn((
+        RoomEntity,
+        SpriteBundle {
+            texture: asset_server.load("textures/table.png"),
+            // transform: Transform::from_scale(Vec3::splat(0.1)),
+            ..default()
+        },
+    ));
 }
 
 pub fn setup_one_card(
@@ -17,9 +22,12 @@ pub fn setup_one_card(
     let texture_atlas =
         TextureAtlas::from_grid(texture_handle, Vec2::new(64.0, 89.0), 13, 4, None, None);
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    commands.spawn(SpriteSheetBundle {
-        sprite: TextureAtlasSprite::new(0),
-        texture_atlas: texture_atlas_handle,
-        ..default()
-    });
+    commands.spawn((
+        RoomEntity,
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite::new(0),
+            texture_atlas: texture_atlas_handle,
+            ..default()
+        },
+    ));
 }
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{channel::PlaySnapshot, Round};
+
+// 固定限注每轮最多下注次数（一次下注加三次加注，翻牌前大盲注算作第一次下注）
+pub const FIXED_LIMIT_RAISE_CAP: u32 = 4;
+
+// 房间的下注结构
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BettingStructure {
+    // 无限注
+    #[default]
+    NoLimit,
+    // 底池限注
+    PotLimit,
+    // 固定限注
+    FixedLimit,
+}
+
+// 前注方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnteKind {
+    // 无前注
+    #[default]
+    None,
+    // 每位玩家各下一份前注
+    PerPlayer,
+    // 大盲注位替所有人下一份前注（死钱）
+    BigBlind,
+}
+
+impl AnteKind {
+    pub const ALL: [AnteKind; 3] = [AnteKind::None, AnteKind::PerPlayer, AnteKind::BigBlind];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnteKind::None => "None",
+            AnteKind::PerPlayer => "Per Player",
+            AnteKind::BigBlind => "Big Blind Ante",
+        }
+    }
+}
+
+// 盲注之外的强制下注
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForcedBets {
+    pub ante_kind: AnteKind,
+    // 前注金额
+    pub ante: u32,
+    // 枪口位强制跨注（两倍大盲注），至少3人时生效
+    pub straddle: bool,
+}
+
+impl ForcedBets {
+    pub fn has_ante(&self) -> bool {
+        self.ante_kind != AnteKind::None && self.ante > 0
+    }
+}
+
+// 房间抽水比例上限
+pub const MAX_RAKE_PERCENT: u32 = 10;
+
+// 现金桌抽水，不翻牌不抽水
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RakeConfig {
+    // 抽取底池的百分比，0为不抽水
+    pub percent: u32,
+    // 每局抽水上限，0为不设上限
+    pub cap: u32,
+}
+
+impl RakeConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.percent > 0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.percent > MAX_RAKE_PERCENT {
+            return Err(format!("rake must not exceed {}%", MAX_RAKE_PERCENT));
+        }
+        Ok(())
+    }
+
+    // 按比例向下取整，不超过上限
+    pub fn rake(&self, pot: u32) -> u32 {
+        let rake = (pot as u64 * self.percent as u64 / 100) as u32;
+        if self.cap > 0 {
+            rake.min(self.cap)
+        } else {
+            rake
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.percent, self.cap) {
+            (0, _) => "No rake".to_string(),
+            (percent, 0) => format!("Rake {}%", percent),
+            (percent, cap) => format!("Rake {}% cap {}", percent, cap),
+        }
+    }
+}
+
+// 计算下注范围所需的本轮状态
+#[derive(Debug, Clone, Copy)]
+pub struct BetState {
+    pub round: Round,
+    pub big_blind: u32,
+    // 底池（包含本轮下注）
+    pub pot: u32,
+    // 本轮最高下注
+    pub current_bet: u32,
+    // 最小加注额
+    pub min_raise: u32,
+    // 本轮已下注和加注的次数
+    pub raises: u32,
+    // 自己本轮已下注
+    pub bet: u32,
+    // 自己剩余筹码
+    pub chips: u32,
+}
+
+impl BetState {
+    pub fn to_call(&self) -> u32 {
+        self.current_bet.saturating_sub(self.bet)
+    }
+
+    // 全下时本轮下注总额
+    pub fn all_in_to(&self) -> u32 {
+        self.bet + self.chips
+    }
+}
+
+impl BettingStructure {
+    pub const ALL: [BettingStructure; 3] = [
+        BettingStructure::NoLimit,
+        BettingStructure::PotLimit,
+        BettingStructure::FixedLimit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BettingStructure::NoLimit => "No-Limit",
+            BettingStructure::PotLimit => "Pot-Limit",
+            BettingStructure::FixedLimit => "Fixed-Limit",
+        }
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            BettingStructure::NoLimit => "NL",
+            BettingStructure::PotLimit => "PL",
+            BettingStructure::FixedLimit => "FL",
+        }
+    }
+
+    // 固定限注的下注单位：翻牌前和翻牌圈为小注（大盲注），转牌圈和河牌圈为大注（两倍大盲注）
+    pub fn fixed_bet_size(round: Round, big_blind: u32) -> u32 {
+        match round {
+            Round::Turn | Round::River => big_blind * 2,
+            _ => big_blind,
+        }
+    }
+
+    // 最少加注到的总额
+    pub fn min_raise_to(&self, state: &BetState) -> u32 {
+        match self {
+            BettingStructure::FixedLimit => {
+                state.current_bet + Self::fixed_bet_size(state.round, state.big_blind)
+            }
+            _ => state.current_bet + state.min_raise,
+        }
+    }
+
+    // 不考虑筹码时最多加注到的总额，None表示不限
+    pub fn max_raise_to(&self, state: &BetState) -> Option<u32> {
+        match self {
+            BettingStructure::NoLimit => None,
+            // 先跟注，再加注到跟注后的底池大小
+            BettingStructure::PotLimit => Some(state.current_bet + state.pot + state.to_call()),
+            // 达到封顶后不能再加注
+            BettingStructure::FixedLimit => {
+                if state.raises >= FIXED_LIMIT_RAISE_CAP {
+                    Some(state.current_bet)
+                } else {
+                    Some(self.min_raise_to(state))
+                }
+            }
+        }
+    }
+
+    // 可以下注或加注到的总额范围，不能加注或筹码不足最小加注时为None（只能全下）
+    pub fn raise_range(&self, state: &BetState) -> Option<(u32, u32)> {
+        let min = self.min_raise_to(state);
+        let max = self
+            .max_raise_to(state)
+            .unwrap_or(u32::MAX)
+            .min(state.all_in_to());
+        if min <= max {
+            Some((min, max))
+        } else {
+            None
+        }
+    }
+
+    // 全下是否符合限注：不超过跟注额，或不超过最多加注额
+    pub fn allows_all_in(&self, state: &BetState) -> bool {
+        let total = state.all_in_to();
+        total <= state.current_bet || self.max_raise_to(state).is_none_or(|max| total <= max)
+    }
+}
+
+impl PlaySnapshot {
+    // 指定玩家当前的下注状态
+    pub fn bet_state(&self, player_name: &str) -> BetState {
+        BetState {
+            round: self.round,
+            big_blind: self.big_blind,
+            pot: self.pot,
+            current_bet: self.current_bet,
+            min_raise: self.min_raise,
+            raises: self.raises,
+            bet: self.bets.get(player_name).copied().unwrap_or(0),
+            chips: self
+                .participants
+                .iter()
+                .find(|player| player.player_name == player_name)
+                .map_or(0, |player| player.chips),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 盲注1/2，翻牌前轮到未下注的玩家
+    fn preflop_state() -> BetState {
+        BetState {
+            round: Round::Preflop,
+            big_blind: 2,
+            pot: 3,
+            current_bet: 2,
+            min_raise: 2,
+            raises: 1,
+            bet: 0,
+            chips: 100,
+        }
+    }
+
+    #[test]
+    fn pot_limit_raises_to_the_pot_after_calling() {
+        let state = preflop_state();
+        // 跟注2后底池为5，最多加注到2 + 5
+        assert_eq!(BettingStructure::PotLimit.max_raise_to(&state), Some(7));
+        assert_eq!(BettingStructure::PotLimit.raise_range(&state), Some((4, 7)));
+        assert_eq!(BettingStructure::NoLimit.max_raise_to(&state), None);
+        assert_eq!(
+            BettingStructure::NoLimit.raise_range(&state),
+            Some((4, 100))
+        );
+
+        // 已下小盲注时只需再跟1
+        let small_blind = BetState {
+            bet: 1,
+            chips: 99,
+            ..state
+        };
+        assert_eq!(
+            BettingStructure::PotLimit.max_raise_to(&small_blind),
+            Some(6)
+        );
+        assert!(!BettingStructure::PotLimit.allows_all_in(&small_blind));
+        assert!(BettingStructure::NoLimit.allows_all_in(&small_blind));
+    }
+
+    #[test]
+    fn fixed_limit_caps_raises_per_round() {
+        let state = BetState {
+            round: Round::Turn,
+            pot: 40,
+            current_bet: 12,
+            raises: FIXED_LIMIT_RAISE_CAP - 1,
+            ..preflop_state()
+        };
+        // 转牌圈使用大注
+        assert_eq!(
+            BettingStructure::FixedLimit.raise_range(&state),
+            Some((16, 16))
+        );
+
+        let capped = BetState {
+            current_bet: 16,
+            raises: FIXED_LIMIT_RAISE_CAP,
+            ..state
+        };
+        assert_eq!(BettingStructure::FixedLimit.max_raise_to(&capped), Some(16));
+        assert_eq!(BettingStructure::FixedLimit.raise_range(&capped), None);
+        assert!(!BettingStructure::FixedLimit.allows_all_in(&capped));
+        // 筹码不足跟注时仍可全下
+        let short = BetState {
+            chips: 10,
+            ..capped
+        };
+        assert!(BettingStructure::FixedLimit.allows_all_in(&short));
+    }
+}
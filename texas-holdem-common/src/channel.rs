@@ -1,4 +1,15 @@
-use crate::{Player, PlayerRole, RoomDTO, RoomState, Round};
+use std::collections::HashMap;
+
+use crate::{
+    betting::{BettingStructure, ForcedBets, RakeConfig},
+    hand::HandCategory,
+    lobby::{RoomFilter, RoomSort},
+    spectator::SpectatorConfig,
+    tournament::{TournamentConfig, TournamentResult, TournamentSnapshot},
+    variant::GameVariant,
+    Card, MissedBlinds, Player, PlayerRole, ProtocolVersion, RoomDTO, RoomState, Round,
+    RoundAction, SessionToken,
+};
 use serde::{Deserialize, Serialize};
 
 // 获取房间列表
@@ -9,78 +20,640 @@ pub const CREATE_ROOM_CHANNEL_ID: u8 = 1;
 pub const ENTER_ROOT_CHANNEL_ID: u8 = 2;
 // 切换角色
 pub const SWITCH_PLAYER_ROLE_CHANNEL_ID: u8 = 3;
-// 房间信息
-pub const BROADCAST_ROOM_INFO_CHANNEL_ID: u8 = 4;
+// 房间状态同步（快照 + 增量，可靠有序）
+pub const ROOM_SYNC_CHANNEL_ID: u8 = 4;
 // 设置房间状态
 pub const SET_ROOM_STATE_CHANNEL_ID: u8 = 5;
-// 对局信息
-pub const BROADCAST_PLAY_INFO_CHANNEL_ID: u8 = 6;
+// 6 原为对局信息广播，已并入房间状态同步
+// 版本握手
+pub const HANDSHAKE_CHANNEL_ID: u8 = 7;
+// 玩家行动
+pub const PLAYER_ACTION_CHANNEL_ID: u8 = 8;
+// 两次发牌表决
+pub const RUN_IT_TWICE_CHANNEL_ID: u8 = 9;
+// 坐出和回到牌桌
+pub const SIT_OUT_CHANNEL_ID: u8 = 10;
+// 账户余额和买入
+pub const BANK_CHANNEL_ID: u8 = 11;
+// 房间内聊天和房主命令
+pub const CHAT_CHANNEL_ID: u8 = 12;
+// 房主管理房间
+pub const ROOM_ADMIN_CHANNEL_ID: u8 = 13;
+// 快速入座
+pub const QUICK_SEAT_CHANNEL_ID: u8 = 14;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetRoomsMessage {
-    pub timestamp: u64,
+    pub request_id: u64,
     // req
+    pub filter: RoomFilter,
+    pub sort: RoomSort,
+    // 页码（从0开始）和每页房间数
+    pub page: u32,
+    pub page_size: u32,
+    // resp
     pub rooms: Vec<RoomDTO>,
+    // 符合筛选条件的房间总数
+    pub total: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateRoomMessage {
-    pub timestamp: u64,
+    pub request_id: u64,
     // req
     pub room_name: String,
     pub room_password: String,
     pub player_name: String,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    // 全下后允许剩余公共牌发两次
+    pub run_it_twice: bool,
+    // 前注和跨注
+    pub forced_bets: ForcedBets,
+    // 现金桌抽水
+    pub rake: RakeConfig,
+    // 旁观人数上限、延迟和隐藏名字
+    pub spectators: SpectatorConfig,
+    // 创建锦标赛房间，None为现金桌
+    pub tournament: Option<TournamentConfig>,
     // resp
     pub room_id: u64,
+    pub error: Option<RequestError>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EnterRoomMessage {
-    pub timestamp: u64,
+    pub request_id: u64,
     // req
     pub room_id: u64,
     pub room_password: String,
+    // 邀请码，不为空时按邀请码查找房间且不需要密码，响应中填入房间id
+    pub invite_code: String,
     pub player_name: String,
     // resp
-    pub success: bool,
+    pub error: Option<RequestError>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SwitchPlayerRoleMessage {
-    pub timestamp: u64,
+    pub request_id: u64,
     // req
     pub room_id: u64,
     pub target_player_role: PlayerRole,
     // resp
-    pub success: bool,
+    pub error: Option<RequestError>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct BroadcastRoomInfoMessage {
-    pub timestamp: u64,
+pub struct SetRoomStateMessage {
+    pub request_id: u64,
+    // req
     pub room_id: u64,
-    pub room_name: String,
-    pub room_state: RoomState,
-    pub players: Vec<Player>,
-    // pub operation_log: Vec<String>,
+    pub target_room_state: RoomState,
+    // resp
+    pub error: Option<RequestError>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct SetRoomStateMessage {
+pub struct PlayerActionMessage {
+    pub request_id: u64,
+    // req
+    pub room_id: u64,
+    pub play_id: u64,
+    pub action: RoundAction,
+    // Bet/Raise时为本轮下注总额（raise to）
+    pub amount: u32,
+    // resp
+    pub error: Option<RequestError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunItTwiceMessage {
+    pub request_id: u64,
+    // req
+    pub room_id: u64,
+    pub play_id: u64,
+    pub accept: bool,
+    // resp
+    pub error: Option<RequestError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SitOutMessage {
+    pub request_id: u64,
+    // req
+    pub room_id: u64,
+    pub sitting_out: bool,
+    // resp
+    pub error: Option<RequestError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BankRequest {
+    // 查询余额
+    Balance,
+    // 两局之间从账户买入或补充筹码
+    BuyIn(u32),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankMessage {
+    pub request_id: u64,
+    // req
+    pub room_id: u64,
+    pub request: BankRequest,
+    // resp
+    pub balance: u64,
+    // 买入后桌上筹码的范围
+    pub min_buy_in: u32,
+    pub max_buy_in: u32,
+    pub error: Option<RequestError>,
+}
+
+// 房间内发送聊天消息，以/开头的为房主命令：/mute、/unmute、/kick 加玩家名
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub request_id: u64,
+    // req
+    pub room_id: u64,
+    pub text: String,
+    // resp
+    pub error: Option<RequestError>,
+}
+
+// 聊天频道，旁观者的消息只发给旁观者，避免场外指导
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatScope {
+    Table,
+    Spectators,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub room_id: u64,
+    // 系统消息为None
+    pub sender_name: Option<String>,
+    pub scope: ChatScope,
+    // 过滤后的内容
+    pub text: String,
     pub timestamp: u64,
+}
+
+// 房主命令，按发送者的连接校验房主身份
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomAdminCommand {
+    // 请出玩家：对局中先弃牌，筹码兑回账户
+    Kick { player_client_id: u64 },
+    // 请出玩家并禁止再次进入
+    Ban { player_client_id: u64 },
+    Unban { player_name: String },
+    // 锁定后不允许新玩家进入
+    Lock(bool),
+    // 空密码表示取消密码
+    ChangePassword(String),
+    // 生成有效期内可以不输密码进入房间的邀请码
+    CreateInvite,
+    // 使所有邀请码失效
+    RevokeInvites,
+}
+
+// 房主生成的邀请码
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InviteCode {
+    pub code: String,
+    // 有效期（秒）
+    pub valid_secs: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomAdminMessage {
+    pub request_id: u64,
     // req
     pub room_id: u64,
+    pub command: RoomAdminCommand,
+    // resp
+    // CreateInvite生成的邀请码
+    pub invite: Option<InviteCode>,
+    pub error: Option<RequestError>,
+}
+
+// 按玩法和盲注范围自动找桌入座，没有空座位时排队，排到时服务端用同一request_id再次通知
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuickSeatMessage {
+    pub request_id: u64,
+    // req
     pub player_name: String,
-    pub target_room_state: RoomState,
+    pub variant: GameVariant,
+    // 大盲注范围
+    pub min_big_blind: Option<u32>,
+    pub max_big_blind: Option<u32>,
+    // 退出所有等候队列
+    pub cancel: bool,
     // resp
-    pub success: bool,
+    pub room_id: u64,
+    // 已入座；为false时在room_id的等候队列中
+    pub seated: bool,
+    // 在等候队列中的位置（从1开始）
+    pub waiting_position: u32,
+    pub error: Option<RequestError>,
+}
+
+// 请求失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestError {
+    // 房间不存在
+    RoomNotFound,
+    // 密码错误
+    WrongPassword,
+    // 房间内已有同名玩家
+    NameTaken,
+    // 不在房间内
+    NotInRoom,
+    // 不是房主
+    NotRoomOwner,
+    // 房间内没有进行中的对局
+    NoActivePlay,
+    // 还没轮到你行动
+    NotYourTurn,
+    // 不合法的行动或下注金额
+    InvalidAction,
+    // 当前房间不能切换到该状态
+    InvalidRoomState,
+    // 锦标赛已开始，不能报名或退出（包括进入其他房间）
+    RegistrationClosed,
+    // 锦标赛座位已满
+    RoomFull,
+    // 锦标赛设置不合法
+    InvalidTournament,
+    // 抽水设置不合法（超过上限或用于锦标赛）
+    InvalidRake,
+    // 旁观设置不合法
+    InvalidSpectatorSettings,
+    // 旁观人数已满
+    SpectatorsFull,
+    // 邀请码不存在或已过期
+    InvalidInvite,
+    // 输错密码或邀请码的次数过多，稍后再试
+    TooManyAttempts,
+    // 不是参与者
+    NotSeated,
+    // 账户余额不足
+    InsufficientFunds,
+    // 买入后的筹码不在允许范围内
+    InvalidBuyIn,
+    // 对局进行中不能操作
+    HandInProgress,
+    // 账户数据无法保存
+    BankUnavailable,
+    // 聊天消息为空或过长
+    InvalidChatMessage,
+    // 发送聊天消息过于频繁
+    RateLimited,
+    // 被房主禁言
+    Muted,
+    // 房间内没有该玩家
+    PlayerNotFound,
+    // 房间已被房主锁定
+    RoomLocked,
+    // 被房主禁止进入该房间
+    Banned,
+    // 没有符合条件的牌桌，也不能新开
+    NoTableAvailable,
+    // 目录中没有可用的服务端
+    ServerNotFound,
+    // 服务端正在排空，不接受新房间和新玩家
+    ServerDraining,
+    // 房间名或玩家名为空、过长或包含控制字符
+    InvalidName,
+    // 同一地址短时间内开户过多
+    TooManyAccounts,
+    // 等待响应超时（客户端本地产生）
+    Timeout,
+    // 被房主请出房间（客户端本地产生）
+    Kicked,
 }
 
+impl RequestError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            RequestError::RoomNotFound => "Room not found",
+            RequestError::WrongPassword => "Wrong password",
+            RequestError::NameTaken => "Name already taken in this room",
+            RequestError::NotInRoom => "You are not in this room",
+            RequestError::NotRoomOwner => "Only the room owner can do this",
+            RequestError::NoActivePlay => "No hand in progress",
+            RequestError::NotYourTurn => "It's not your turn",
+            RequestError::InvalidAction => "Invalid action",
+            RequestError::InvalidRoomState => "Invalid room state",
+            RequestError::RegistrationClosed => "Tournament registration is closed",
+            RequestError::RoomFull => "All seats are taken",
+            RequestError::InvalidTournament => "Invalid tournament settings",
+            RequestError::InvalidRake => "Invalid rake settings",
+            RequestError::InvalidSpectatorSettings => "Invalid spectator settings",
+            RequestError::SpectatorsFull => "No spectator places left",
+            RequestError::InvalidInvite => "Invalid or expired invite code",
+            RequestError::TooManyAttempts => "Too many failed attempts, try again later",
+            RequestError::NotSeated => "You are not seated",
+            RequestError::InsufficientFunds => "Not enough chips in your bankroll",
+            RequestError::InvalidBuyIn => "Buy-in amount out of range",
+            RequestError::HandInProgress => "Not allowed during a hand",
+            RequestError::BankUnavailable => "Bank is unavailable",
+            RequestError::InvalidChatMessage => "Message is empty or too long",
+            RequestError::RateLimited => "You are sending messages too fast",
+            RequestError::Muted => "You have been muted by the room owner",
+            RequestError::PlayerNotFound => "No such player in this room",
+            RequestError::RoomLocked => "Room is locked",
+            RequestError::Banned => "You are banned from this room",
+            RequestError::NoTableAvailable => "No matching table available",
+            RequestError::ServerNotFound => "No game server available",
+            RequestError::ServerDraining => "Server is shutting down",
+            RequestError::InvalidName => "Name is empty, too long or contains invalid characters",
+            RequestError::TooManyAccounts => {
+                "Too many new accounts from your address, try again later"
+            }
+            RequestError::Timeout => "Request timed out",
+            RequestError::Kicked => "You were removed from the room by the owner",
+        }
+    }
+}
+
+// 房间状态同步消息
 #[derive(Debug, Serialize, Deserialize)]
-pub struct BroadcastPlayInfoMessage {
-    pub timestamp: u64,
+pub enum RoomSyncMessage {
+    // server -> client 完整快照（进入房间或客户端发现序号缺口时发送）
+    Snapshot(Box<RoomSnapshot>),
+    // server -> client 增量
+    Delta(RoomDeltaMessage),
+    // client -> server 请求完整快照
+    RequestSnapshot {
+        room_id: u64,
+    },
+    // server -> client 多桌锦标赛中被移到另一张桌，随后发送新桌的快照
+    TableChanged {
+        room_id: u64,
+    },
+    // server -> client 自己的底牌（不计入增量序号）
+    HoleCards {
+        room_id: u64,
+        play_id: u64,
+        cards: Vec<Card>,
+    },
+    // server -> client 聊天消息（不计入增量序号）
+    Chat(ChatLine),
+    // server -> client 被房主请出房间
+    Kicked {
+        room_id: u64,
+    },
+    // server -> client 超过单条消息大小上限的消息拆分后的分片，按序拼接后解析
+    Part {
+        index: u32,
+        last: bool,
+        data: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
     pub room_id: u64,
+    // 快照对应的最新增量序号
+    pub seq: u64,
+    pub room_name: String,
+    pub room_state: RoomState,
+    // 服务端按模板开的牌桌没有房主
+    pub owner_client_id: Option<u64>,
+    pub locked: bool,
+    // 被禁止进入的玩家
+    pub banned_names: Vec<String>,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub run_it_twice: bool,
+    pub forced_bets: ForcedBets,
+    pub rake: RakeConfig,
+    pub spectators: SpectatorConfig,
+    // 当前盲注
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub players: Vec<Player>,
+    pub play: Option<PlaySnapshot>,
+    pub tournament: Option<TournamentSnapshot>,
+    // 接收者自己的底牌
+    pub hole_cards: Vec<Card>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaySnapshot {
     pub play_id: u64,
     pub round: Round,
+    // 参与者，chips为剩余筹码
     pub participants: Vec<Player>,
+    pub dealer_name: Option<String>,
+    pub small_blind_name: Option<String>,
+    pub big_blind_name: Option<String>,
+    // 跨注的玩家
+    pub straddle_name: Option<String>,
+    // 本局大盲注（锦标赛升级盲注不影响进行中的对局）
+    pub big_blind: u32,
+    // 公共牌
+    pub board_cards: Vec<Card>,
+    // 底池（包含本轮下注）
+    pub pot: u32,
+    // 本轮下注
+    pub bets: HashMap<String, u32>,
+    // 已弃牌的玩家
+    pub folded_names: Vec<String>,
+    // 当前行动的玩家
+    pub current_player_name: Option<String>,
+    // 本轮最高下注
+    pub current_bet: u32,
+    // 最小加注额
+    pub min_raise: u32,
+    // 本轮已下注和加注的次数
+    pub raises: u32,
+    // 两次发牌表决
+    pub run_it_twice_vote: Option<RunItTwiceVote>,
+    // 摊牌结果
+    pub showdown: Option<ShowdownResult>,
+}
+
+// 全下后是否将剩余公共牌发两次，所有仍在牌局中的玩家都同意才发两次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunItTwiceVote {
+    // 需要表决的玩家
+    pub player_names: Vec<String>,
+    // 已表决的结果
+    pub answers: HashMap<String, bool>,
+}
+
+impl RunItTwiceVote {
+    pub fn new(player_names: Vec<String>) -> Self {
+        RunItTwiceVote {
+            player_names,
+            answers: HashMap::new(),
+        }
+    }
+
+    // 等待表决：无人拒绝且有人未表决
+    pub fn is_pending(&self) -> bool {
+        self.answers.values().all(|accept| *accept) && self.answers.len() < self.player_names.len()
+    }
+
+    // 是否需要该玩家表决
+    pub fn is_waiting_for(&self, player_name: &str) -> bool {
+        self.is_pending()
+            && self.player_names.iter().any(|name| name == player_name)
+            && !self.answers.contains_key(player_name)
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.answers.len() == self.player_names.len() && self.answers.values().all(|accept| *accept)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowdownResult {
+    // 亮牌
+    pub shown_hands: Vec<ShownHand>,
+    // 赢得的筹码
+    pub winnings: HashMap<String, u32>,
+    // 发两次时的第二组公共牌
+    pub second_board: Option<Vec<Card>>,
+    // 从底池中抽取的水钱
+    pub rake: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShownHand {
+    pub player_name: String,
+    pub cards: Vec<Card>,
+    pub category: HandCategory,
+    // 发两次时第二组公共牌上的牌型
+    pub second_category: Option<HandCategory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomDeltaMessage {
+    pub room_id: u64,
+    // 每个房间内从1开始连续递增
+    pub seq: u64,
+    pub delta: RoomDelta,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RoomDelta {
+    // 玩家进入房间
+    PlayerJoined(Player),
+    // 玩家离开房间
+    PlayerLeft {
+        player_client_id: u64,
+    },
+    // 玩家切换角色
+    PlayerRoleChanged {
+        player_client_id: u64,
+        player_role: PlayerRole,
+    },
+    // 玩家坐出或回到牌桌，以及错过的盲注变化
+    PlayerSeatChanged {
+        player_client_id: u64,
+        sitting_out: bool,
+        missed_blinds: MissedBlinds,
+    },
+    // 房间状态变更
+    RoomStateChanged(RoomState),
+    // 房主锁定房间或修改禁止进入的玩家
+    RoomAccessChanged {
+        locked: bool,
+        banned_names: Vec<String>,
+    },
+    // 新对局开始
+    PlayStarted(Box<PlaySnapshot>),
+    // 玩家筹码变更（对局结算后）
+    PlayerChipsChanged {
+        player_client_id: u64,
+        chips: u32,
+    },
+    // 对局进入下一轮
+    PlayRoundChanged {
+        play_id: u64,
+        round: Round,
+        board_cards: Vec<Card>,
+    },
+    // 玩家行动（含盲注），携带行动后的状态
+    PlayerActed {
+        play_id: u64,
+        player_name: String,
+        action: RoundAction,
+        // 本轮下注总额
+        bet: u32,
+        // 剩余筹码
+        chips: u32,
+        pot: u32,
+    },
+    // 玩家下前注，计入底池但不计入本轮下注
+    AntePosted {
+        play_id: u64,
+        player_name: String,
+        chips: u32,
+        pot: u32,
+    },
+    // 回到牌桌的玩家补交错过的盲注
+    MissedBlindsPosted {
+        play_id: u64,
+        player_name: String,
+        // 本轮下注总额（大盲注活注）
+        bet: u32,
+        chips: u32,
+        pot: u32,
+    },
+    // 轮到下一位玩家行动
+    TurnChanged {
+        play_id: u64,
+        player_name: Option<String>,
+        current_bet: u32,
+        min_raise: u32,
+        raises: u32,
+    },
+    // 所有人全下，开始两次发牌表决
+    RunItTwiceOffered {
+        play_id: u64,
+        player_names: Vec<String>,
+    },
+    // 玩家表决
+    RunItTwiceAnswered {
+        play_id: u64,
+        player_name: String,
+        accept: bool,
+    },
+    // 摊牌（或其他玩家全部弃牌）
+    PlayShowdown {
+        play_id: u64,
+        result: ShowdownResult,
+    },
+    // 对局结束
+    PlayEnded {
+        play_id: u64,
+    },
+    // 锦标赛开始，确定参赛玩家和奖池
+    TournamentStarted {
+        entrants: Vec<String>,
+        prize_pool: u32,
+    },
+    // 盲注变更（锦标赛升级），下一局生效
+    BlindsChanged {
+        level: usize,
+        small_blind: u32,
+        big_blind: u32,
+    },
+    // 锦标赛中玩家被淘汰或获胜，确定名次
+    PlayerFinished(TournamentResult),
+}
+
+// 服务端在客户端连接后发送，告知版本检查结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HandshakeMessage {
+    pub server_version: ProtocolVersion,
+    pub accepted: bool,
+    // 拒绝原因
+    pub reason: Option<String>,
+    // 接受时签发（或沿用）的会话令牌，客户端重新连接该服务端时附带
+    pub session_token: Option<SessionToken>,
 }
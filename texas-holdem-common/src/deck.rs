@@ -0,0 +1,36 @@
+use crate::Card;
+
+// 牌组构建器，按最小点数生成一副牌（A始终保留）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeckBuilder {
+    min_rank: u8,
+}
+
+impl DeckBuilder {
+    // 标准52张牌
+    pub const STANDARD: DeckBuilder = DeckBuilder { min_rank: 2 };
+    // 短牌：去掉2到5，共36张
+    pub const SHORT: DeckBuilder = DeckBuilder { min_rank: 6 };
+
+    // 最小点数（不含A）
+    pub fn min_rank(&self) -> u8 {
+        self.min_rank
+    }
+
+    // 牌的张数
+    pub fn card_count(&self) -> usize {
+        (14 - self.min_rank as usize + 1) * 4
+    }
+
+    // 生成未洗的一副牌
+    pub fn build(&self) -> Vec<Card> {
+        let mut deck = Vec::with_capacity(self.card_count());
+        for i in (1..=13).filter(|i| *i == 1 || *i >= self.min_rank) {
+            deck.push(Card::Spade(i));
+            deck.push(Card::Heart(i));
+            deck.push(Card::Club(i));
+            deck.push(Card::Diamond(i));
+        }
+        deck
+    }
+}
@@ -0,0 +1,123 @@
+use std::{net::SocketAddr, time::Duration};
+
+use bevy_renet::renet::{
+    ChannelConfig, ChunkChannelConfig, ReliableChannelConfig, RenetConnectionConfig,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    channel::RequestError,
+    lobby::{rooms_message_size, RoomFilter, RoomSort, MAX_ROOM_DTO_BYTES},
+    ProtocolVersion, RoomDTO,
+};
+
+// 目录服务的netcode协议id，与游戏服务端区分，连错地址时不会互相收到消息
+pub const DIRECTORY_PROTOCOL_ID: u64 = 1;
+
+// 游戏服务端定期上报状态和房间列表
+pub const SERVER_STATUS_CHANNEL_ID: u8 = 0;
+// 查询所有服务端汇总的房间列表
+pub const DIRECTORY_ROOMS_CHANNEL_ID: u8 = 1;
+// 查询房间所在服务端的地址，或为新房间挑选服务端
+pub const LOCATE_SERVER_CHANNEL_ID: u8 = 2;
+
+// 服务端名称的最大字符数，不短于默认名称（IPv6地址加端口）
+pub const MAX_SERVER_NAME_CHARS: usize = 48;
+// 序列化后目录中单个房间的最大字节数，地址和字段名不超过128字节
+pub const MAX_DIRECTORY_ROOM_BYTES: u64 =
+    MAX_ROOM_DTO_BYTES + 128 + MAX_SERVER_NAME_CHARS as u64 * 4;
+
+pub fn directory_connection_config() -> RenetConnectionConfig {
+    let channels_config = vec![
+        // 房间列表可能超过单个包，分片发送
+        ChannelConfig::Chunk(ChunkChannelConfig {
+            channel_id: SERVER_STATUS_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: DIRECTORY_ROOMS_CHANNEL_ID,
+            // 一页最多MAX_ROOMS_PAGE_SIZE个房间，按名字的最大长度计算
+            packet_budget: rooms_message_size(MAX_DIRECTORY_ROOM_BYTES) + 2000,
+            max_message_size: rooms_message_size(MAX_DIRECTORY_ROOM_BYTES),
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: LOCATE_SERVER_CHANNEL_ID,
+            ..Default::default()
+        }),
+    ];
+
+    RenetConnectionConfig {
+        // 可靠通道的消息不分片，一个包要能容纳一整页房间列表
+        max_packet_size: 32 * 1024,
+        heartbeat_time: Duration::from_millis(100),
+        send_channels_config: channels_config.clone(),
+        receive_channels_config: channels_config,
+        ..Default::default()
+    }
+}
+
+// 游戏服务端的状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerStatus {
+    // 在目录中唯一的名称
+    pub server_name: String,
+    // 客户端连接的地址
+    pub public_addr: SocketAddr,
+    pub protocol_version: ProtocolVersion,
+    // 排空中：不再接受新房间和新玩家，玩家都离开后退出
+    pub draining: bool,
+    pub client_count: u32,
+    pub max_clients: u32,
+    pub rooms: Vec<RoomDTO>,
+    // 目录服务配置的登记密钥，目录不转发给客户端
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl ServerStatus {
+    // 负载，挑选服务端时优先负载最低的
+    pub fn load(&self) -> f32 {
+        self.client_count as f32 / self.max_clients.max(1) as f32
+    }
+}
+
+// 目录中的房间及其所在的服务端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryRoom {
+    pub server_name: String,
+    pub server_addr: SocketAddr,
+    pub room: RoomDTO,
+}
+
+impl AsRef<RoomDTO> for DirectoryRoom {
+    fn as_ref(&self) -> &RoomDTO {
+        &self.room
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryRoomsMessage {
+    pub request_id: u64,
+    // req
+    pub filter: RoomFilter,
+    pub sort: RoomSort,
+    pub page: u32,
+    pub page_size: u32,
+    // resp
+    // 不包括排空中和版本不兼容的服务端上的房间
+    pub rooms: Vec<DirectoryRoom>,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocateServerMessage {
+    pub request_id: u64,
+    // req
+    // 要进入的房间 (服务端名称, 房间id)，为None时挑选负载最低的服务端
+    pub room: Option<(String, u64)>,
+    // resp
+    pub server_name: String,
+    pub server_addr: Option<SocketAddr>,
+    pub error: Option<RequestError>,
+}
@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Card;
+
+// 牌型，按大小排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HandCategory {
+    // 高牌
+    HighCard,
+    // 一对
+    OnePair,
+    // 两对
+    TwoPair,
+    // 三条
+    ThreeOfAKind,
+    // 顺子
+    Straight,
+    // 同花
+    Flush,
+    // 葫芦
+    FullHouse,
+    // 四条
+    FourOfAKind,
+    // 同花顺
+    StraightFlush,
+}
+
+impl HandCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HandCategory::HighCard => "High Card",
+            HandCategory::OnePair => "One Pair",
+            HandCategory::TwoPair => "Two Pair",
+            HandCategory::ThreeOfAKind => "Three of a Kind",
+            HandCategory::Straight => "Straight",
+            HandCategory::Flush => "Flush",
+            HandCategory::FullHouse => "Full House",
+            HandCategory::FourOfAKind => "Four of a Kind",
+            HandCategory::StraightFlush => "Straight Flush",
+        }
+    }
+}
+
+// 牌型大小规则
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandRanking {
+    // 标准规则
+    #[default]
+    Standard,
+    // 短牌规则：同花大于葫芦，A-6-7-8-9为最小的顺子
+    ShortDeck,
+}
+
+impl HandRanking {
+    // 牌型在该规则下的大小
+    pub fn strength(&self, category: HandCategory) -> u8 {
+        match (self, category) {
+            (HandRanking::ShortDeck, HandCategory::Flush) => HandCategory::FullHouse as u8,
+            (HandRanking::ShortDeck, HandCategory::FullHouse) => HandCategory::Flush as u8,
+            _ => category as u8,
+        }
+    }
+
+    // A当作最小牌时组成的顺子，从大到小排列
+    fn wheel(&self) -> [u8; 5] {
+        match self {
+            HandRanking::Standard => [14, 5, 4, 3, 2],
+            HandRanking::ShortDeck => [14, 9, 8, 7, 6],
+        }
+    }
+}
+
+// 五张牌的大小，先比较牌型在规则下的大小，再依次比较点数
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct HandValue {
+    pub strength: u8,
+    pub category: HandCategory,
+    pub ranks: Vec<u8>,
+}
+
+// 计算五张牌的大小
+pub fn evaluate_five(cards: &[Card; 5], ranking: HandRanking) -> HandValue {
+    let mut ranks: Vec<u8> = cards.iter().map(|card| card.rank()).collect();
+    ranks.sort_unstable_by(|a, b| b.cmp(a));
+    let is_flush = cards.iter().all(|card| card.suit() == cards[0].suit());
+    let straight_high = straight_high(&ranks, ranking);
+
+    // 按(张数, 点数)从大到小分组
+    let mut groups: Vec<(u8, u8)> = Vec::new();
+    for rank in ranks.iter() {
+        match groups.iter_mut().find(|(_, r)| r == rank) {
+            Some((count, _)) => *count += 1,
+            None => groups.push((1, *rank)),
+        }
+    }
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+    let grouped_ranks: Vec<u8> = groups.iter().map(|(_, rank)| *rank).collect();
+
+    let (category, ranks) = match (is_flush, straight_high, groups[0].0, groups.len()) {
+        (true, Some(high), _, _) => (HandCategory::StraightFlush, vec![high]),
+        (_, _, 4, _) => (HandCategory::FourOfAKind, grouped_ranks),
+        (_, _, 3, 2) => (HandCategory::FullHouse, grouped_ranks),
+        (true, None, _, _) => (HandCategory::Flush, ranks),
+        (false, Some(high), _, _) => (HandCategory::Straight, vec![high]),
+        (_, _, 3, _) => (HandCategory::ThreeOfAKind, grouped_ranks),
+        (_, _, 2, 3) => (HandCategory::TwoPair, grouped_ranks),
+        (_, _, 2, _) => (HandCategory::OnePair, grouped_ranks),
+        _ => (HandCategory::HighCard, ranks),
+    };
+    HandValue {
+        strength: ranking.strength(category),
+        category,
+        ranks,
+    }
+}
+
+// 从任意张牌（至少5张）中选出最大的五张
+pub fn evaluate_best(cards: &[Card], ranking: HandRanking) -> HandValue {
+    assert!(cards.len() >= 5, "need at least 5 cards to evaluate");
+    let mut best: Option<HandValue> = None;
+    for_each_combination(cards.len(), 5, &mut |indexes| {
+        let five = [
+            cards[indexes[0]],
+            cards[indexes[1]],
+            cards[indexes[2]],
+            cards[indexes[3]],
+            cards[indexes[4]],
+        ];
+        let value = evaluate_five(&five, ranking);
+        if best.as_ref().is_none_or(|best| value > *best) {
+            best = Some(value);
+        }
+    });
+    best.unwrap()
+}
+
+// 奥马哈规则：必须使用两张底牌和三张公共牌
+pub fn evaluate_omaha(
+    hole_cards: &[Card],
+    board_cards: &[Card],
+    ranking: HandRanking,
+) -> HandValue {
+    assert!(
+        hole_cards.len() >= 2 && board_cards.len() >= 3,
+        "need at least 2 hole cards and 3 board cards to evaluate"
+    );
+    let mut best: Option<HandValue> = None;
+    for_each_combination(hole_cards.len(), 2, &mut |hole_indexes| {
+        for_each_combination(board_cards.len(), 3, &mut |board_indexes| {
+            let five = [
+                hole_cards[hole_indexes[0]],
+                hole_cards[hole_indexes[1]],
+                board_cards[board_indexes[0]],
+                board_cards[board_indexes[1]],
+                board_cards[board_indexes[2]],
+            ];
+            let value = evaluate_five(&five, ranking);
+            if best.as_ref().is_none_or(|best| value > *best) {
+                best = Some(value);
+            }
+        });
+    });
+    best.unwrap()
+}
+
+// 顺子的最大点数（A-2-3-4-5记为5，短牌A-6-7-8-9记为9），ranks需从大到小排列
+fn straight_high(ranks: &[u8], ranking: HandRanking) -> Option<u8> {
+    let is_consecutive = ranks.windows(2).all(|pair| pair[0] == pair[1] + 1);
+    let wheel = ranking.wheel();
+    if is_consecutive {
+        Some(ranks[0])
+    } else if ranks == wheel {
+        Some(wheel[1])
+    } else {
+        None
+    }
+}
+
+// 枚举从n个元素中取k个的所有组合
+pub(crate) fn for_each_combination(n: usize, k: usize, f: &mut impl FnMut(&[usize])) {
+    fn walk(
+        start: usize,
+        n: usize,
+        k: usize,
+        chosen: &mut Vec<usize>,
+        f: &mut impl FnMut(&[usize]),
+    ) {
+        if chosen.len() == k {
+            f(chosen);
+            return;
+        }
+        for i in start..n {
+            chosen.push(i);
+            walk(i + 1, n, k, chosen, f);
+            chosen.pop();
+        }
+    }
+    walk(0, n, k, &mut Vec::with_capacity(k), f);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_deck_flush_beats_full_house() {
+        let flush = [
+            Card::Heart(2),
+            Card::Heart(7),
+            Card::Heart(9),
+            Card::Heart(11),
+            Card::Heart(13),
+        ];
+        let full_house = [
+            Card::Spade(10),
+            Card::Heart(10),
+            Card::Club(10),
+            Card::Spade(6),
+            Card::Diamond(6),
+        ];
+        assert!(
+            evaluate_five(&full_house, HandRanking::Standard)
+                > evaluate_five(&flush, HandRanking::Standard)
+        );
+        assert!(
+            evaluate_five(&flush, HandRanking::ShortDeck)
+                > evaluate_five(&full_house, HandRanking::ShortDeck)
+        );
+    }
+
+    #[test]
+    fn short_deck_wheel_is_the_lowest_straight() {
+        let wheel = [
+            Card::Spade(1),
+            Card::Heart(9),
+            Card::Club(8),
+            Card::Diamond(7),
+            Card::Spade(6),
+        ];
+        let value = evaluate_five(&wheel, HandRanking::ShortDeck);
+        assert_eq!(value.category, HandCategory::Straight);
+        assert_eq!(value.ranks, vec![9]);
+        // 标准规则下不是顺子
+        assert_eq!(
+            evaluate_five(&wheel, HandRanking::Standard).category,
+            HandCategory::HighCard
+        );
+        let six_high = [
+            Card::Spade(10),
+            Card::Heart(9),
+            Card::Club(8),
+            Card::Diamond(7),
+            Card::Spade(6),
+        ];
+        assert!(evaluate_five(&six_high, HandRanking::ShortDeck) > value);
+    }
+
+    #[test]
+    fn standard_wheel_is_five_high() {
+        let wheel = [
+            Card::Spade(1),
+            Card::Heart(2),
+            Card::Club(3),
+            Card::Diamond(4),
+            Card::Spade(5),
+        ];
+        let value = evaluate_five(&wheel, HandRanking::Standard);
+        assert_eq!(value.category, HandCategory::Straight);
+        assert_eq!(value.ranks, vec![5]);
+    }
+
+    #[test]
+    fn evaluate_best_picks_the_best_five_of_seven() {
+        let cards = [
+            Card::Spade(1),
+            Card::Heart(1),
+            Card::Club(13),
+            Card::Diamond(13),
+            Card::Spade(13),
+            Card::Heart(4),
+            Card::Club(2),
+        ];
+        let value = evaluate_best(&cards, HandRanking::Standard);
+        assert_eq!(value.category, HandCategory::FullHouse);
+        assert_eq!(value.ranks, vec![13, 14]);
+    }
+
+    #[test]
+    fn omaha_uses_exactly_two_hole_cards_and_three_board_cards() {
+        // 公共牌有四张红桃，手里只有一张红桃，不能组成同花
+        let hole_cards = [
+            Card::Heart(1),
+            Card::Spade(13),
+            Card::Club(7),
+            Card::Diamond(2),
+        ];
+        let board_cards = [
+            Card::Heart(3),
+            Card::Heart(8),
+            Card::Heart(10),
+            Card::Heart(12),
+            Card::Spade(4),
+        ];
+        let mut cards = hole_cards.to_vec();
+        cards.extend(board_cards);
+        assert_eq!(
+            evaluate_best(&cards, HandRanking::Standard).category,
+            HandCategory::Flush
+        );
+        let value = evaluate_omaha(&hole_cards, &board_cards, HandRanking::Standard);
+        assert_eq!(value.category, HandCategory::HighCard);
+        assert_eq!(value.ranks, vec![14, 13, 12, 10, 8]);
+    }
+}
@@ -1,24 +1,116 @@
-use std::{default, time::Duration};
+use std::time::Duration;
 
+use betting::{BettingStructure, RakeConfig};
 use bevy_renet::renet::{
-    ChannelConfig, ReliableChannelConfig, RenetConnectionConfig, UnreliableChannelConfig,
+    ChannelConfig, ReliableChannelConfig, RenetConnectionConfig, NETCODE_USER_DATA_BYTES,
 };
 use channel::{
-    BROADCAST_PLAY_INFO_CHANNEL_ID, BROADCAST_ROOM_INFO_CHANNEL_ID, CREATE_ROOM_CHANNEL_ID,
-    ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID,
-    SWITCH_PLAYER_ROLE_CHANNEL_ID,
+    BANK_CHANNEL_ID, CHAT_CHANNEL_ID, CREATE_ROOM_CHANNEL_ID, ENTER_ROOT_CHANNEL_ID,
+    GET_ROOMS_CHANNEL_ID, HANDSHAKE_CHANNEL_ID, PLAYER_ACTION_CHANNEL_ID, QUICK_SEAT_CHANNEL_ID,
+    ROOM_ADMIN_CHANNEL_ID, ROOM_SYNC_CHANNEL_ID, RUN_IT_TWICE_CHANNEL_ID,
+    SET_ROOM_STATE_CHANNEL_ID, SIT_OUT_CHANNEL_ID, SWITCH_PLAYER_ROLE_CHANNEL_ID,
 };
+use lobby::{rooms_message_size, MAX_ROOM_DTO_BYTES};
 use serde::{Deserialize, Serialize};
+use sync::ROOM_SYNC_MAX_MESSAGE_SIZE;
+use variant::GameVariant;
 
+pub mod betting;
 pub mod channel;
+pub mod deck;
+pub mod directory;
+pub mod hand;
+pub mod lobby;
+pub mod spectator;
+pub mod sync;
+pub mod tournament;
 pub mod util;
+pub mod variant;
 
+// netcode层的协议id，不匹配时连接会被静默丢弃，所以保持不变，版本兼容性由握手检查
 pub const PROTOCOL_ID: u64 = 0;
 
+// 当前协议版本，消息有任何改动都提高次版本号
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion {
+    major: 0,
+    minor: 26,
+};
+// 服务端兼容的最低客户端次版本号（主版本号必须一致）
+// 旧客户端无法解析的改动（包括新增消息或错误类型的变体）都要同时提高
+pub const MIN_COMPATIBLE_MINOR_VERSION: u16 = 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    // 编码到renet连接的user_data中
+    pub fn to_user_data(&self) -> [u8; NETCODE_USER_DATA_BYTES] {
+        let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+        user_data[0..2].copy_from_slice(&self.major.to_le_bytes());
+        user_data[2..4].copy_from_slice(&self.minor.to_le_bytes());
+        user_data
+    }
+
+    pub fn from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Self {
+        ProtocolVersion {
+            major: u16::from_le_bytes([user_data[0], user_data[1]]),
+            minor: u16::from_le_bytes([user_data[2], user_data[3]]),
+        }
+    }
+
+    // 检查客户端版本是否与当前（服务端）版本兼容，不兼容时返回原因
+    pub fn check_compatible(&self, client_version: &ProtocolVersion) -> Result<(), String> {
+        if client_version.major != self.major {
+            return Err(format!(
+                "Incompatible protocol version: client {}, server {}",
+                client_version, self
+            ));
+        }
+        if client_version.minor < MIN_COMPATIBLE_MINOR_VERSION || client_version.minor > self.minor
+        {
+            return Err(format!(
+                "Incompatible protocol version: client {}, server supports {}.{}-{}.{}",
+                client_version, self.major, MIN_COMPATIBLE_MINOR_VERSION, self.major, self.minor
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// 服务端签发的会话令牌，重新连接时附带在user_data中，凭令牌恢复服务端分配的用户身份
+// 连接id由客户端任意选择，不能作为身份；Unsecure连接不加密，令牌可被同一网络上的人截获
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionToken(pub [u8; 16]);
+
+impl SessionToken {
+    // 放在协议版本之后，全为0表示没有令牌
+    pub fn write_user_data(&self, user_data: &mut [u8; NETCODE_USER_DATA_BYTES]) {
+        user_data[4..20].copy_from_slice(&self.0);
+    }
+
+    pub fn from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<Self> {
+        let mut token = [0u8; 16];
+        token.copy_from_slice(&user_data[4..20]);
+        (token != [0u8; 16]).then_some(SessionToken(token))
+    }
+}
+
 pub fn connection_config() -> RenetConnectionConfig {
     let channels_config = vec![
         ChannelConfig::Reliable(ReliableChannelConfig {
             channel_id: GET_ROOMS_CHANNEL_ID,
+            // 一页最多MAX_ROOMS_PAGE_SIZE个房间，按名字的最大长度计算
+            packet_budget: rooms_message_size(MAX_ROOM_DTO_BYTES) + 2000,
+            max_message_size: rooms_message_size(MAX_ROOM_DTO_BYTES),
             ..Default::default()
         }),
         ChannelConfig::Reliable(ReliableChannelConfig {
@@ -33,22 +125,56 @@ pub fn connection_config() -> RenetConnectionConfig {
             channel_id: SWITCH_PLAYER_ROLE_CHANNEL_ID,
             ..Default::default()
         }),
-        ChannelConfig::Unreliable(UnreliableChannelConfig {
-            channel_id: BROADCAST_ROOM_INFO_CHANNEL_ID,
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: ROOM_SYNC_CHANNEL_ID,
+            // 增量必须按序应用
+            ordered: true,
+            // 快照包含房间内所有玩家，超过上限时拆分为多个分片
+            packet_budget: 8000,
+            max_message_size: ROOM_SYNC_MAX_MESSAGE_SIZE,
             ..Default::default()
         }),
         ChannelConfig::Reliable(ReliableChannelConfig {
             channel_id: SET_ROOM_STATE_CHANNEL_ID,
             ..Default::default()
         }),
-        ChannelConfig::Unreliable(UnreliableChannelConfig {
-            channel_id: BROADCAST_PLAY_INFO_CHANNEL_ID,
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: HANDSHAKE_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: PLAYER_ACTION_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: RUN_IT_TWICE_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: SIT_OUT_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: BANK_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: CHAT_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: ROOM_ADMIN_CHANNEL_ID,
+            ..Default::default()
+        }),
+        ChannelConfig::Reliable(ReliableChannelConfig {
+            channel_id: QUICK_SEAT_CHANNEL_ID,
             ..Default::default()
         }),
     ];
 
     RenetConnectionConfig {
-        max_packet_size: 16 * 1024,
+        // 可靠通道的消息不分片，一个包要能容纳一整页房间列表
+        max_packet_size: 32 * 1024,
         sent_packets_buffer_size: 256,
         received_packets_buffer_size: 256,
         reassembly_buffer_size: 256,
@@ -61,7 +187,7 @@ pub fn connection_config() -> RenetConnectionConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomDTO {
     // 房间id
     pub room_id: u64,
@@ -71,8 +197,39 @@ pub struct RoomDTO {
     pub room_state: RoomState,
     // 房主名称
     pub owner_name: String,
-    // 房间人数
+    // 玩法
+    pub variant: GameVariant,
+    // 下注结构
+    pub betting: BettingStructure,
+    // 抽水设置和累计抽水
+    pub rake: RakeConfig,
+    pub rake_collected: u64,
+    // 盲注
+    pub small_blind: u32,
+    pub big_blind: u32,
+    // 房间人数，包括旁观者
     pub player_count: u32,
+    // 座位数和入座人数
+    pub seats: u32,
+    pub participant_count: u32,
+    // 房主锁定后不允许新玩家进入
+    pub locked: bool,
+    // 需要密码或邀请码才能进入
+    pub is_private: bool,
+    // 锦标赛座位数，现金桌为None
+    pub tournament_seats: Option<u32>,
+}
+
+impl RoomDTO {
+    pub fn free_seats(&self) -> u32 {
+        self.seats.saturating_sub(self.participant_count)
+    }
+}
+
+impl AsRef<RoomDTO> for RoomDTO {
+    fn as_ref(&self) -> &RoomDTO {
+        self
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -84,6 +241,12 @@ pub enum RoomState {
     Playing,
     // 暂停中
     Paused,
+    // 锦标赛报名中
+    Registering,
+    // 锦标赛进行中
+    Running,
+    // 锦标赛已结束
+    Finished,
 }
 
 impl RoomState {
@@ -92,8 +255,16 @@ impl RoomState {
             RoomState::Waiting => "Waiting",
             RoomState::Playing => "Playing",
             RoomState::Paused => "Paused",
+            RoomState::Registering => "Registering",
+            RoomState::Running => "Running",
+            RoomState::Finished => "Finished",
         }
     }
+
+    // 是否在进行对局（现金桌游戏中或锦标赛进行中）
+    pub fn is_in_play(&self) -> bool {
+        matches!(self, RoomState::Playing | RoomState::Running)
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -148,10 +319,28 @@ impl PlayerRole {
 
 #[derive(Debug, Serialize, Clone, Deserialize)]
 pub struct Player {
+    // 连接id由客户端选择，只用于区分房间内的玩家，房主和禁止进入按服务端分配的用户校验
     pub player_client_id: u64,
     pub player_name: String,
     pub player_role: PlayerRole,
     pub chips: u32,
+    // 参与者暂时离开，保留座位和筹码但不发牌
+    pub sitting_out: bool,
+    pub missed_blinds: MissedBlinds,
+}
+
+// 坐出期间错过的盲注，回到牌桌时补交（大盲注为活注，小盲注为死钱）
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissedBlinds {
+    // 错过大盲注的次数，每圈一次
+    pub big_blinds: u32,
+    pub small_blind: bool,
+}
+
+impl MissedBlinds {
+    pub fn is_owed(&self) -> bool {
+        self.big_blinds > 0 || self.small_blind
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -166,15 +355,47 @@ pub enum Card {
     Diamond(u8),
 }
 impl Card {
-    pub fn pool() -> Vec<Card> {
-        let mut pool = Vec::with_capacity(52);
-        for i in 1..=13 {
-            pool.push(Card::Spade(i));
-            pool.push(Card::Heart(i));
-            pool.push(Card::Club(i));
-            pool.push(Card::Diamond(i));
+    // 点数，A记为14
+    pub fn rank(&self) -> u8 {
+        let number = match self {
+            Card::Spade(number)
+            | Card::Heart(number)
+            | Card::Club(number)
+            | Card::Diamond(number) => *number,
+        };
+        if number == 1 {
+            14
+        } else {
+            number
+        }
+    }
+
+    // 花色序号
+    pub fn suit(&self) -> u8 {
+        match self {
+            Card::Spade(_) => 0,
+            Card::Heart(_) => 1,
+            Card::Club(_) => 2,
+            Card::Diamond(_) => 3,
         }
-        pool
+    }
+
+    pub fn name(&self) -> String {
+        let rank = match self.rank() {
+            14 => "A".to_string(),
+            13 => "K".to_string(),
+            12 => "Q".to_string(),
+            11 => "J".to_string(),
+            10 => "T".to_string(),
+            n => n.to_string(),
+        };
+        let suit = match self {
+            Card::Spade(_) => "s",
+            Card::Heart(_) => "h",
+            Card::Club(_) => "c",
+            Card::Diamond(_) => "d",
+        };
+        format!("{}{}", rank, suit)
     }
 }
 
@@ -194,6 +415,19 @@ pub enum RoundAction {
     AllIn,
 }
 
+impl RoundAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoundAction::Check => "Check",
+            RoundAction::Bet => "Bet",
+            RoundAction::Call => "Call",
+            RoundAction::Raise => "Raise",
+            RoundAction::Fold => "Fold",
+            RoundAction::AllIn => "AllIn",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Position {
     // 庄家
@@ -205,3 +439,57 @@ pub enum Position {
     // 其他
     Other,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(major: u16, minor: u16) -> ProtocolVersion {
+        ProtocolVersion { major, minor }
+    }
+
+    #[test]
+    fn check_compatible_accepts_only_the_supported_range() {
+        let server = PROTOCOL_VERSION;
+        // 低于最低兼容版本
+        assert!(server
+            .check_compatible(&client(server.major, MIN_COMPATIBLE_MINOR_VERSION - 1))
+            .is_err());
+        // 范围内
+        for minor in MIN_COMPATIBLE_MINOR_VERSION..=server.minor {
+            assert_eq!(
+                server.check_compatible(&client(server.major, minor)),
+                Ok(())
+            );
+        }
+        // 高于服务端版本
+        assert!(server
+            .check_compatible(&client(server.major, server.minor + 1))
+            .is_err());
+        // 主版本号不一致
+        assert!(server
+            .check_compatible(&client(server.major + 1, server.minor))
+            .is_err());
+    }
+
+    #[test]
+    fn protocol_version_round_trips_through_user_data() {
+        let version = client(3, 17);
+        assert_eq!(
+            ProtocolVersion::from_user_data(&version.to_user_data()),
+            version
+        );
+    }
+
+    #[test]
+    fn session_token_follows_the_version_in_user_data() {
+        let version = client(3, 17);
+        let mut user_data = version.to_user_data();
+        // 旧客户端不附带令牌
+        assert_eq!(SessionToken::from_user_data(&user_data), None);
+        let token = SessionToken([7; 16]);
+        token.write_user_data(&mut user_data);
+        assert_eq!(SessionToken::from_user_data(&user_data), Some(token));
+        assert_eq!(ProtocolVersion::from_user_data(&user_data), version);
+    }
+}
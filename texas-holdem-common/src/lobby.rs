@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{variant::GameVariant, RoomDTO, RoomState};
+
+// 每页最多的房间数
+pub const MAX_ROOMS_PAGE_SIZE: u32 = 20;
+
+// 房间名和玩家名的最大字符数，限制房间列表和快照的大小
+pub const MAX_ROOM_NAME_CHARS: usize = 32;
+pub const MAX_PLAYER_NAME_CHARS: usize = 24;
+// 搜索词的最大字符数，更长的搜索词不可能匹配任何房间名
+pub const MAX_SEARCH_CHARS: usize = MAX_ROOM_NAME_CHARS;
+
+// 序列化后单个房间的最大字节数：名字中每个字符最多4字节（UTF-8编码或转义的引号），其余字段不超过512字节
+pub const MAX_ROOM_DTO_BYTES: u64 = 512 + (MAX_ROOM_NAME_CHARS + MAX_PLAYER_NAME_CHARS) as u64 * 4;
+// 房间列表响应中房间以外的字段，回传的搜索词可能包含控制字符，转义后每个字符最多6字节
+pub const MAX_ROOMS_QUERY_BYTES: u64 = 512 + MAX_SEARCH_CHARS as u64 * 6;
+
+// 一整页房间列表响应的大小上限，用于设置通道的消息大小上限
+pub const fn rooms_message_size(max_room_bytes: u64) -> u64 {
+    MAX_ROOMS_PAGE_SIZE as u64 * max_room_bytes + MAX_ROOMS_QUERY_BYTES
+}
+
+// 名字不能是空白、超过最大字符数或包含控制字符
+pub fn is_valid_name(name: &str, max_chars: usize) -> bool {
+    !name.trim().is_empty()
+        && name.chars().count() <= max_chars
+        && !name.chars().any(char::is_control)
+}
+
+// 服务端生成的名字截断到最大字符数
+pub fn truncate_name(name: &str, max_chars: usize) -> String {
+    name.chars().take(max_chars).collect()
+}
+
+// 大厅房间列表的筛选条件，默认不筛选
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomFilter {
+    // 按房间名或房主名搜索，不区分大小写
+    pub search: String,
+    pub variant: Option<GameVariant>,
+    // 大盲注范围
+    pub min_big_blind: Option<u32>,
+    pub max_big_blind: Option<u32>,
+    pub room_state: Option<RoomState>,
+    // 至少有几个空座位
+    pub min_free_seats: u32,
+    // 隐藏没有空座位或已锁定的房间
+    pub hide_full: bool,
+    // 隐藏需要密码的房间
+    pub hide_private: bool,
+}
+
+impl RoomFilter {
+    // 截断过长的搜索词，响应中回传的查询条件不超过上限
+    pub fn truncate_search(&mut self) {
+        if self.search.chars().count() > MAX_SEARCH_CHARS {
+            self.search = truncate_name(&self.search, MAX_SEARCH_CHARS);
+        }
+    }
+
+    pub fn matches(&self, room: &RoomDTO) -> bool {
+        let search = self.search.trim().to_lowercase();
+        if !search.is_empty()
+            && !room.room_name.to_lowercase().contains(&search)
+            && !room.owner_name.to_lowercase().contains(&search)
+        {
+            return false;
+        }
+        if self.variant.is_some_and(|variant| variant != room.variant)
+            || self.min_big_blind.is_some_and(|min| room.big_blind < min)
+            || self.max_big_blind.is_some_and(|max| room.big_blind > max)
+            || self
+                .room_state
+                .is_some_and(|state| state != room.room_state)
+        {
+            return false;
+        }
+        if room.free_seats() < self.min_free_seats
+            || (self.hide_full && (room.free_seats() == 0 || room.locked))
+            || (self.hide_private && room.is_private)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomSortKey {
+    // 按创建顺序
+    #[default]
+    Created,
+    Name,
+    Stakes,
+    Players,
+    FreeSeats,
+}
+
+impl RoomSortKey {
+    pub const ALL: [RoomSortKey; 5] = [
+        RoomSortKey::Created,
+        RoomSortKey::Name,
+        RoomSortKey::Stakes,
+        RoomSortKey::Players,
+        RoomSortKey::FreeSeats,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoomSortKey::Created => "Created",
+            RoomSortKey::Name => "Name",
+            RoomSortKey::Stakes => "Stakes",
+            RoomSortKey::Players => "Players",
+            RoomSortKey::FreeSeats => "Free Seats",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomSort {
+    pub key: RoomSortKey,
+    pub descending: bool,
+}
+
+impl RoomSort {
+    // 排序键相同时按创建顺序，保证翻页时顺序稳定
+    pub fn compare(&self, a: &RoomDTO, b: &RoomDTO) -> Ordering {
+        let ordering = match self.key {
+            RoomSortKey::Created => Ordering::Equal,
+            RoomSortKey::Name => a.room_name.to_lowercase().cmp(&b.room_name.to_lowercase()),
+            RoomSortKey::Stakes => (a.big_blind, a.small_blind).cmp(&(b.big_blind, b.small_blind)),
+            RoomSortKey::Players => a.player_count.cmp(&b.player_count),
+            RoomSortKey::FreeSeats => a.free_seats().cmp(&b.free_seats()),
+        }
+        .then(a.room_id.cmp(&b.room_id));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+// 筛选、排序后取出第page页（从0开始），返回该页和符合条件的总数
+pub fn paginate<T: AsRef<RoomDTO>>(
+    mut rooms: Vec<T>,
+    filter: &RoomFilter,
+    sort: RoomSort,
+    page: u32,
+    page_size: u32,
+) -> (Vec<T>, u32) {
+    rooms.retain(|room| filter.matches(room.as_ref()));
+    // 键相同时由compare按room_id决定顺序；目录汇总时不同服务端的room_id可能相同，
+    // 此时稳定排序保持传入的顺序（按服务端名称）
+    rooms.sort_by(|a, b| sort.compare(a.as_ref(), b.as_ref()));
+    let total = rooms.len() as u32;
+    let page_size = page_size.clamp(1, MAX_ROOMS_PAGE_SIZE) as usize;
+    let rooms = rooms
+        .into_iter()
+        .skip(page as usize * page_size)
+        .take(page_size)
+        .collect();
+    (rooms, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        betting::{BettingStructure, RakeConfig},
+        channel::GetRoomsMessage,
+        directory::{
+            DirectoryRoom, DirectoryRoomsMessage, MAX_DIRECTORY_ROOM_BYTES, MAX_SERVER_NAME_CHARS,
+        },
+    };
+
+    // 每个字段都取序列化后最长的值，名字使用4字节的字符
+    fn largest_room() -> RoomDTO {
+        RoomDTO {
+            room_id: u64::MAX,
+            room_name: "𝄞".repeat(MAX_ROOM_NAME_CHARS),
+            room_state: RoomState::Registering,
+            owner_name: "𝄞".repeat(MAX_PLAYER_NAME_CHARS),
+            variant: GameVariant::ShortDeck,
+            betting: BettingStructure::FixedLimit,
+            rake: RakeConfig {
+                percent: u32::MAX,
+                cap: u32::MAX,
+            },
+            rake_collected: u64::MAX,
+            small_blind: u32::MAX,
+            big_blind: u32::MAX,
+            player_count: u32::MAX,
+            seats: u32::MAX,
+            participant_count: u32::MAX,
+            locked: true,
+            is_private: true,
+            tournament_seats: Some(u32::MAX),
+        }
+    }
+
+    // 回传的筛选条件，搜索词由控制字符组成
+    fn largest_filter() -> RoomFilter {
+        RoomFilter {
+            search: "\u{1}".repeat(MAX_SEARCH_CHARS),
+            variant: Some(GameVariant::ShortDeck),
+            min_big_blind: Some(u32::MAX),
+            max_big_blind: Some(u32::MAX),
+            room_state: Some(RoomState::Registering),
+            min_free_seats: u32::MAX,
+            hide_full: true,
+            hide_private: true,
+        }
+    }
+
+    #[test]
+    fn full_rooms_page_fits_the_message_size() {
+        let room = largest_room();
+        assert!(serde_json::to_vec(&room).unwrap().len() as u64 <= MAX_ROOM_DTO_BYTES);
+        let message = GetRoomsMessage {
+            request_id: u64::MAX,
+            filter: largest_filter(),
+            sort: RoomSort {
+                key: RoomSortKey::FreeSeats,
+                descending: true,
+            },
+            page: u32::MAX,
+            page_size: u32::MAX,
+            rooms: vec![room; MAX_ROOMS_PAGE_SIZE as usize],
+            total: u32::MAX,
+        };
+        let size = serde_json::to_vec(&message).unwrap().len() as u64;
+        assert!(size <= rooms_message_size(MAX_ROOM_DTO_BYTES));
+    }
+
+    #[test]
+    fn full_directory_page_fits_the_message_size() {
+        let room = DirectoryRoom {
+            server_name: "𝄞".repeat(MAX_SERVER_NAME_CHARS),
+            server_addr: "[ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff]:65535"
+                .parse()
+                .unwrap(),
+            room: largest_room(),
+        };
+        assert!(serde_json::to_vec(&room).unwrap().len() as u64 <= MAX_DIRECTORY_ROOM_BYTES);
+        let message = DirectoryRoomsMessage {
+            request_id: u64::MAX,
+            filter: largest_filter(),
+            sort: RoomSort {
+                key: RoomSortKey::FreeSeats,
+                descending: true,
+            },
+            page: u32::MAX,
+            page_size: u32::MAX,
+            rooms: vec![room; MAX_ROOMS_PAGE_SIZE as usize],
+            total: u32::MAX,
+        };
+        let size = serde_json::to_vec(&message).unwrap().len() as u64;
+        assert!(size <= rooms_message_size(MAX_DIRECTORY_ROOM_BYTES));
+    }
+
+    #[test]
+    fn overlong_search_is_truncated() {
+        let mut filter = RoomFilter {
+            search: "x".repeat(MAX_SEARCH_CHARS + 10),
+            ..Default::default()
+        };
+        filter.truncate_search();
+        assert_eq!(filter.search.chars().count(), MAX_SEARCH_CHARS);
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+// 旁观延迟的上限（秒）
+pub const MAX_SPECTATOR_DELAY_SECS: u32 = 300;
+
+// 旁观者看到对局的延迟，防止旁观者向参与者通风报信
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectatorDelay {
+    #[default]
+    Off,
+    // 延迟若干秒
+    Seconds(u32),
+    // 一局结束后才能看到这一局
+    Hand,
+}
+
+impl SpectatorDelay {
+    pub fn is_enabled(&self) -> bool {
+        *self != SpectatorDelay::Off
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SpectatorDelay::Off => "No delay".to_string(),
+            SpectatorDelay::Seconds(secs) => format!("{}s delay", secs),
+            SpectatorDelay::Hand => "One hand delay".to_string(),
+        }
+    }
+}
+
+// 房间的旁观设置
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorConfig {
+    // 旁观者人数上限，0为不限
+    pub max_spectators: u32,
+    pub delay: SpectatorDelay,
+    // 对参与者隐藏旁观者的名字
+    pub hide_names: bool,
+}
+
+impl SpectatorConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.delay {
+            SpectatorDelay::Seconds(0) => Err("spectator delay must be positive".to_string()),
+            SpectatorDelay::Seconds(secs) if secs > MAX_SPECTATOR_DELAY_SECS => Err(format!(
+                "spectator delay must not exceed {}s",
+                MAX_SPECTATOR_DELAY_SECS
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // 已有spectator_count个旁观者时是否还能再加入
+    pub fn is_full(&self, spectator_count: usize) -> bool {
+        self.max_spectators > 0 && spectator_count >= self.max_spectators as usize
+    }
+}
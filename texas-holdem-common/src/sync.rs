@@ -0,0 +1,481 @@
+use crate::{
+    betting::{BettingStructure, ForcedBets, RakeConfig},
+    channel::{PlaySnapshot, RoomDelta, RoomSnapshot, RoomSyncMessage, RunItTwiceVote},
+    spectator::SpectatorConfig,
+    tournament::TournamentSnapshot,
+    variant::GameVariant,
+    Card, Player, PlayerRole, RoomState, Round, RoundAction,
+};
+
+// 客户端视角的房间状态，由快照和增量维护
+#[derive(Debug, Default, Clone)]
+pub struct RoomView {
+    pub room_id: u64,
+    // 已应用的最新增量序号，None表示等待快照
+    pub seq: Option<u64>,
+    pub room_name: String,
+    pub room_state: RoomState,
+    pub owner_client_id: Option<u64>,
+    pub locked: bool,
+    pub banned_names: Vec<String>,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub run_it_twice: bool,
+    pub forced_bets: ForcedBets,
+    pub rake: RakeConfig,
+    pub spectators: SpectatorConfig,
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub players: Vec<Player>,
+    pub play: Option<PlaySnapshot>,
+    pub tournament: Option<TournamentSnapshot>,
+    // 自己在当前对局中的底牌
+    pub hole_cards: Vec<Card>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncResult {
+    // 已应用
+    Applied,
+    // 与当前房间无关或已过期
+    Ignored,
+    // 增量序号出现缺口，需要重新请求快照
+    Gap,
+}
+
+impl RoomView {
+    pub fn new(room_id: u64) -> Self {
+        RoomView {
+            room_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn contains_player(&self, player_name: &str) -> bool {
+        self.players
+            .iter()
+            .any(|player| player.player_name == player_name)
+    }
+
+    // 按当前状态生成快照，等待快照时序号为0
+    pub fn snapshot(&self) -> RoomSnapshot {
+        RoomSnapshot {
+            room_id: self.room_id,
+            seq: self.seq.unwrap_or(0),
+            room_name: self.room_name.clone(),
+            room_state: self.room_state,
+            owner_client_id: self.owner_client_id,
+            locked: self.locked,
+            banned_names: self.banned_names.clone(),
+            variant: self.variant,
+            betting: self.betting,
+            run_it_twice: self.run_it_twice,
+            forced_bets: self.forced_bets,
+            rake: self.rake,
+            spectators: self.spectators,
+            small_blind: self.small_blind,
+            big_blind: self.big_blind,
+            players: self.players.clone(),
+            play: self.play.clone(),
+            tournament: self.tournament.clone(),
+            hole_cards: self.hole_cards.clone(),
+        }
+    }
+
+    pub fn apply(&mut self, message: RoomSyncMessage) -> SyncResult {
+        match message {
+            RoomSyncMessage::Snapshot(snapshot) => {
+                if snapshot.room_id != self.room_id {
+                    return SyncResult::Ignored;
+                }
+                self.seq = Some(snapshot.seq);
+                self.room_name = snapshot.room_name;
+                self.room_state = snapshot.room_state;
+                self.owner_client_id = snapshot.owner_client_id;
+                self.locked = snapshot.locked;
+                self.banned_names = snapshot.banned_names;
+                self.variant = snapshot.variant;
+                self.betting = snapshot.betting;
+                self.run_it_twice = snapshot.run_it_twice;
+                self.forced_bets = snapshot.forced_bets;
+                self.rake = snapshot.rake;
+                self.spectators = snapshot.spectators;
+                self.small_blind = snapshot.small_blind;
+                self.big_blind = snapshot.big_blind;
+                self.players = snapshot.players;
+                self.play = snapshot.play;
+                self.tournament = snapshot.tournament;
+                self.hole_cards = snapshot.hole_cards;
+                SyncResult::Applied
+            }
+            RoomSyncMessage::Delta(delta) => {
+                if delta.room_id != self.room_id {
+                    return SyncResult::Ignored;
+                }
+                // 尚未收到快照，忽略增量
+                let Some(last_seq) = self.seq else {
+                    return SyncResult::Ignored;
+                };
+                if delta.seq <= last_seq {
+                    return SyncResult::Ignored;
+                }
+                if delta.seq != last_seq + 1 {
+                    self.seq = None;
+                    return SyncResult::Gap;
+                }
+                self.seq = Some(delta.seq);
+                self.apply_delta(delta.delta);
+                SyncResult::Applied
+            }
+            RoomSyncMessage::HoleCards {
+                room_id,
+                play_id,
+                cards,
+            } => {
+                if room_id != self.room_id
+                    || self.play.as_ref().map(|play| play.play_id) != Some(play_id)
+                {
+                    return SyncResult::Ignored;
+                }
+                self.hole_cards = cards;
+                SyncResult::Applied
+            }
+            RoomSyncMessage::TableChanged { room_id } => {
+                // 等待新桌的快照
+                *self = RoomView::new(room_id);
+                SyncResult::Applied
+            }
+            // 聊天和被请出房间不属于房间状态，由客户端单独处理；分片由RoomSyncParts拼接
+            RoomSyncMessage::RequestSnapshot { .. }
+            | RoomSyncMessage::Chat(_)
+            | RoomSyncMessage::Kicked { .. }
+            | RoomSyncMessage::Part { .. } => SyncResult::Ignored,
+        }
+    }
+
+    fn apply_delta(&mut self, delta: RoomDelta) {
+        match delta {
+            // 已在房间内的玩家原地更新
+            RoomDelta::PlayerJoined(player) => match self.player_mut(player.player_client_id) {
+                Some(existing) => *existing = player,
+                None => self.players.push(player),
+            },
+            RoomDelta::PlayerLeft { player_client_id } => {
+                self.players
+                    .retain(|player| player.player_client_id != player_client_id);
+            }
+            RoomDelta::PlayerRoleChanged {
+                player_client_id,
+                player_role,
+            } => {
+                if let Some(player) = self.player_mut(player_client_id) {
+                    player.player_role = player_role;
+                    // 离开座位时清除坐出状态
+                    if player_role == PlayerRole::Spectator {
+                        player.sitting_out = false;
+                        player.missed_blinds = Default::default();
+                    }
+                }
+            }
+            RoomDelta::PlayerSeatChanged {
+                player_client_id,
+                sitting_out,
+                missed_blinds,
+            } => {
+                if let Some(player) = self.player_mut(player_client_id) {
+                    player.sitting_out = sitting_out;
+                    player.missed_blinds = missed_blinds;
+                }
+            }
+            RoomDelta::PlayerChipsChanged {
+                player_client_id,
+                chips,
+            } => {
+                if let Some(player) = self.player_mut(player_client_id) {
+                    player.chips = chips;
+                }
+            }
+            RoomDelta::RoomStateChanged(room_state) => {
+                self.room_state = room_state;
+            }
+            RoomDelta::RoomAccessChanged {
+                locked,
+                banned_names,
+            } => {
+                self.locked = locked;
+                self.banned_names = banned_names;
+            }
+            RoomDelta::PlayStarted(play) => {
+                self.play = Some(*play);
+                self.hole_cards.clear();
+            }
+            RoomDelta::PlayRoundChanged {
+                play_id,
+                round,
+                board_cards,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    play.round = round;
+                    play.board_cards = board_cards;
+                    play.bets.clear();
+                    play.current_bet = 0;
+                    play.raises = 0;
+                }
+            }
+            RoomDelta::PlayerActed {
+                play_id,
+                player_name,
+                action,
+                bet,
+                chips,
+                pot,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    if action == RoundAction::Fold {
+                        play.folded_names.push(player_name.clone());
+                    }
+                    if let Some(participant) = play
+                        .participants
+                        .iter_mut()
+                        .find(|participant| participant.player_name == player_name)
+                    {
+                        participant.chips = chips;
+                    }
+                    play.bets.insert(player_name, bet);
+                    play.pot = pot;
+                }
+            }
+            RoomDelta::AntePosted {
+                play_id,
+                player_name,
+                chips,
+                pot,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    if let Some(participant) = play
+                        .participants
+                        .iter_mut()
+                        .find(|participant| participant.player_name == player_name)
+                    {
+                        participant.chips = chips;
+                    }
+                    play.pot = pot;
+                }
+            }
+            RoomDelta::MissedBlindsPosted {
+                play_id,
+                player_name,
+                bet,
+                chips,
+                pot,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    if let Some(participant) = play
+                        .participants
+                        .iter_mut()
+                        .find(|participant| participant.player_name == player_name)
+                    {
+                        participant.chips = chips;
+                    }
+                    play.bets.insert(player_name, bet);
+                    play.pot = pot;
+                }
+            }
+            RoomDelta::TurnChanged {
+                play_id,
+                player_name,
+                current_bet,
+                min_raise,
+                raises,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    play.current_player_name = player_name;
+                    play.current_bet = current_bet;
+                    play.min_raise = min_raise;
+                    play.raises = raises;
+                }
+            }
+            RoomDelta::RunItTwiceOffered {
+                play_id,
+                player_names,
+            } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    play.run_it_twice_vote = Some(RunItTwiceVote::new(player_names));
+                }
+            }
+            RoomDelta::RunItTwiceAnswered {
+                play_id,
+                player_name,
+                accept,
+            } => {
+                if let Some(vote) = self
+                    .play_mut(play_id)
+                    .and_then(|play| play.run_it_twice_vote.as_mut())
+                {
+                    vote.answers.insert(player_name, accept);
+                }
+            }
+            RoomDelta::PlayShowdown { play_id, result } => {
+                if let Some(play) = self.play_mut(play_id) {
+                    play.round = Round::Showdown;
+                    play.current_player_name = None;
+                    for participant in play.participants.iter_mut() {
+                        if let Some(won) = result.winnings.get(&participant.player_name) {
+                            participant.chips += won;
+                        }
+                    }
+                    play.pot = 0;
+                    play.showdown = Some(result);
+                }
+            }
+            RoomDelta::PlayEnded { play_id } => {
+                if self.play.as_ref().map(|play| play.play_id) == Some(play_id) {
+                    self.play = None;
+                    self.hole_cards.clear();
+                }
+            }
+            RoomDelta::TournamentStarted {
+                entrants,
+                prize_pool,
+            } => {
+                if let Some(tournament) = self.tournament.as_mut() {
+                    tournament.entrants = entrants;
+                    tournament.prize_pool = prize_pool;
+                }
+            }
+            RoomDelta::BlindsChanged {
+                level,
+                small_blind,
+                big_blind,
+            } => {
+                self.small_blind = small_blind;
+                self.big_blind = big_blind;
+                if let Some(tournament) = self.tournament.as_mut() {
+                    tournament.level = level;
+                }
+            }
+            RoomDelta::PlayerFinished(result) => {
+                if let Some(tournament) = self.tournament.as_mut() {
+                    tournament.results.push(result);
+                }
+            }
+        }
+    }
+
+    fn player_mut(&mut self, player_client_id: u64) -> Option<&mut Player> {
+        self.players
+            .iter_mut()
+            .find(|player| player.player_client_id == player_client_id)
+    }
+
+    fn play_mut(&mut self, play_id: u64) -> Option<&mut PlaySnapshot> {
+        self.play.as_mut().filter(|play| play.play_id == play_id)
+    }
+}
+
+// 房间同步通道的单条消息大小上限，超过时发送方会断开连接
+pub const ROOM_SYNC_MAX_MESSAGE_SIZE: u64 = 6000;
+// 每个分片的数据字节数，JSON转义后最多变为两倍，并为分片的其他字段留出余量
+const ROOM_SYNC_PART_SIZE: usize = (ROOM_SYNC_MAX_MESSAGE_SIZE as usize - 100) / 2;
+
+// 序列化房间同步消息，超过大小上限时拆分为多个分片，按顺序发送
+pub fn encode_room_sync(message: &RoomSyncMessage) -> Vec<Vec<u8>> {
+    let json = serde_json::to_string(message).unwrap();
+    if json.len() <= ROOM_SYNC_MAX_MESSAGE_SIZE as usize {
+        return vec![json.into_bytes()];
+    }
+    let mut parts = Vec::new();
+    let mut rest = json.as_str();
+    while !rest.is_empty() {
+        // 在字符边界拆分
+        let mut end = rest.len().min(ROOM_SYNC_PART_SIZE);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (data, tail) = rest.split_at(end);
+        rest = tail;
+        let part = RoomSyncMessage::Part {
+            index: parts.len() as u32,
+            last: rest.is_empty(),
+            data: data.to_string(),
+        };
+        parts.push(serde_json::to_vec(&part).unwrap());
+    }
+    parts
+}
+
+// 接收端拼接分片
+#[derive(Debug, Default)]
+pub struct RoomSyncParts {
+    data: String,
+    next_index: u32,
+}
+
+impl RoomSyncParts {
+    // 解析收到的消息，分片未收齐时返回None
+    pub fn decode(&mut self, message: &[u8]) -> Option<RoomSyncMessage> {
+        match serde_json::from_slice::<RoomSyncMessage>(message).ok()? {
+            RoomSyncMessage::Part { index, last, data } => {
+                // 通道可靠有序，序号不连续时丢弃已拼接的部分
+                if index != self.next_index {
+                    self.data.clear();
+                    self.next_index = 0;
+                    if index != 0 {
+                        return None;
+                    }
+                }
+                self.data.push_str(&data);
+                self.next_index += 1;
+                if !last {
+                    return None;
+                }
+                self.next_index = 0;
+                serde_json::from_str(&std::mem::take(&mut self.data)).ok()
+            }
+            message => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{ChatLine, ChatScope};
+
+    #[test]
+    fn oversized_messages_are_split_and_reassembled() {
+        // 多字节字符的内容，拆分点不能落在字符中间
+        let text = "牌".repeat(ROOM_SYNC_MAX_MESSAGE_SIZE as usize);
+        let message = RoomSyncMessage::Chat(ChatLine {
+            room_id: 1,
+            sender_name: Some("\"quoted\"".to_string()),
+            scope: ChatScope::Table,
+            text: text.clone(),
+            timestamp: 0,
+        });
+        let parts = encode_room_sync(&message);
+        assert!(parts.len() > 1);
+        assert!(parts
+            .iter()
+            .all(|part| part.len() <= ROOM_SYNC_MAX_MESSAGE_SIZE as usize));
+
+        let mut assembler = RoomSyncParts::default();
+        let (last, rest) = parts.split_last().unwrap();
+        assert!(rest.iter().all(|part| assembler.decode(part).is_none()));
+        match assembler.decode(last) {
+            Some(RoomSyncMessage::Chat(line)) => assert_eq!(line.text, text),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn small_messages_are_sent_whole() {
+        let message = RoomSyncMessage::Kicked { room_id: 1 };
+        let parts = encode_room_sync(&message);
+        assert_eq!(parts.len(), 1);
+        assert!(matches!(
+            RoomSyncParts::default().decode(&parts[0]),
+            Some(RoomSyncMessage::Kicked { room_id: 1 })
+        ));
+    }
+}
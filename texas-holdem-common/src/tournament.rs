@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+// 锦标赛每桌最多座位数
+pub const MAX_TOURNAMENT_SEATS: u32 = 10;
+// 多桌锦标赛最多桌数
+pub const MAX_TOURNAMENT_TABLES: u32 = 20;
+
+// 盲注级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlindLevel {
+    pub small_blind: u32,
+    pub big_blind: u32,
+}
+
+// 盲注升级方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LevelUp {
+    // 每打完指定局数升级
+    Hands(u32),
+    // 每经过指定秒数升级，在下一局开始时生效
+    Seconds(u32),
+}
+
+// 锦标赛（坐满即玩）设置，由创建房间的玩家指定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentConfig {
+    // 每桌座位数
+    pub seats: u32,
+    // 桌数，大于1时为多桌锦标赛，报名人数达到 seats * tables 后自动开始
+    pub tables: u32,
+    // 起始筹码
+    pub starting_stack: u32,
+    // 报名费，奖池 = 报名费 * 参赛人数
+    pub buy_in: u32,
+    // 盲注结构，最后一级之后不再升级
+    pub blind_levels: Vec<BlindLevel>,
+    pub level_up: LevelUp,
+    // 按名次分配奖池的百分比，第一名在前
+    pub payouts: Vec<u32>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig {
+            seats: 6,
+            tables: 1,
+            starting_stack: 1500,
+            buy_in: 100,
+            blind_levels: [
+                (10, 20),
+                (15, 30),
+                (25, 50),
+                (50, 100),
+                (75, 150),
+                (100, 200),
+                (150, 300),
+                (200, 400),
+                (300, 600),
+                (500, 1000),
+            ]
+            .into_iter()
+            .map(|(small_blind, big_blind)| BlindLevel {
+                small_blind,
+                big_blind,
+            })
+            .collect(),
+            level_up: LevelUp::Hands(10),
+            payouts: vec![65, 35],
+        }
+    }
+}
+
+impl TournamentConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.seats < 2 || self.seats > MAX_TOURNAMENT_SEATS {
+            return Err(format!(
+                "seats must be between 2 and {}",
+                MAX_TOURNAMENT_SEATS
+            ));
+        }
+        if self.tables < 1 || self.tables > MAX_TOURNAMENT_TABLES {
+            return Err(format!(
+                "tables must be between 1 and {}",
+                MAX_TOURNAMENT_TABLES
+            ));
+        }
+        let Some(first_level) = self.blind_levels.first() else {
+            return Err("blind_levels must not be empty".to_string());
+        };
+        if self
+            .blind_levels
+            .iter()
+            .any(|level| level.small_blind == 0 || level.big_blind < level.small_blind)
+        {
+            return Err("invalid blind level".to_string());
+        }
+        if self.starting_stack < first_level.big_blind {
+            return Err("starting_stack must cover the first big blind".to_string());
+        }
+        if matches!(self.level_up, LevelUp::Hands(0) | LevelUp::Seconds(0)) {
+            return Err("level_up must be greater than 0".to_string());
+        }
+        if self.payouts.is_empty()
+            || self.payouts.len() > self.capacity() as usize
+            || self.payouts.iter().sum::<u32>() != 100
+        {
+            return Err("payouts must sum to 100 and not exceed seats * tables".to_string());
+        }
+        Ok(())
+    }
+
+    // 报名人数上限
+    pub fn capacity(&self) -> u32 {
+        self.seats * self.tables
+    }
+
+    pub fn blind_level(&self, level: usize) -> BlindLevel {
+        self.blind_levels[level.min(self.blind_levels.len() - 1)]
+    }
+
+    // 指定名次（从1开始）的奖金，整除余数归第一名
+    pub fn prize(&self, place: u32, prize_pool: u32) -> u32 {
+        let Some(percent) = self.payouts.get(place as usize - 1) else {
+            return 0;
+        };
+        let prize = prize_pool * percent / 100;
+        if place == 1 {
+            let paid: u32 = self
+                .payouts
+                .iter()
+                .map(|percent| prize_pool * percent / 100)
+                .sum();
+            prize + prize_pool - paid
+        } else {
+            prize
+        }
+    }
+}
+
+// 玩家的最终名次
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentResult {
+    pub player_name: String,
+    // 名次，从1开始
+    pub place: u32,
+    pub prize: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentSnapshot {
+    pub tournament_id: u64,
+    pub config: TournamentConfig,
+    // 当前盲注级别（从0开始）
+    pub level: usize,
+    // 参赛玩家，开始后确定
+    pub entrants: Vec<String>,
+    pub prize_pool: u32,
+    // 已确定的名次，按淘汰顺序
+    pub results: Vec<TournamentResult>,
+}
@@ -5,7 +5,19 @@ pub fn timestamp() -> u64 {
     let since_the_epoch = start
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards");
-    let ms = since_the_epoch.as_secs() as u64 * 1000u64
-        + (since_the_epoch.subsec_nanos() as f64 / 1_000_000.0) as u64;
-    ms
+    since_the_epoch.as_secs() * 1000u64
+        + (since_the_epoch.subsec_nanos() as f64 / 1_000_000.0) as u64
+}
+
+// 单调递增的id分配器，从1开始（0保留为无效id）
+#[derive(Debug, Default)]
+pub struct IdAllocator {
+    last_id: u64,
+}
+
+impl IdAllocator {
+    pub fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
 }
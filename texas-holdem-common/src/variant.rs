@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    betting::BettingStructure,
+    deck::DeckBuilder,
+    hand::{evaluate_best, evaluate_omaha, HandRanking, HandValue},
+    Card,
+};
+
+// 房间的游戏玩法
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameVariant {
+    // 德州扑克
+    #[default]
+    Holdem,
+    // 奥马哈
+    Omaha,
+    // 短牌德州（6+）
+    ShortDeck,
+}
+
+impl GameVariant {
+    pub const ALL: [GameVariant; 3] = [
+        GameVariant::Holdem,
+        GameVariant::Omaha,
+        GameVariant::ShortDeck,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameVariant::Holdem => "Hold'em",
+            GameVariant::Omaha => "Omaha",
+            GameVariant::ShortDeck => "Short Deck (6+)",
+        }
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            GameVariant::Holdem => "HE",
+            GameVariant::Omaha => "O",
+            GameVariant::ShortDeck => "6+",
+        }
+    }
+
+    // 创建房间时默认的下注结构
+    pub fn default_betting(&self) -> BettingStructure {
+        match self {
+            GameVariant::Holdem | GameVariant::ShortDeck => BettingStructure::NoLimit,
+            GameVariant::Omaha => BettingStructure::PotLimit,
+        }
+    }
+
+    // 每人底牌张数
+    pub fn hole_card_count(&self) -> usize {
+        match self {
+            GameVariant::Holdem | GameVariant::ShortDeck => 2,
+            GameVariant::Omaha => 4,
+        }
+    }
+
+    // 使用的牌组
+    pub fn deck(&self) -> DeckBuilder {
+        match self {
+            GameVariant::Holdem | GameVariant::Omaha => DeckBuilder::STANDARD,
+            GameVariant::ShortDeck => DeckBuilder::SHORT,
+        }
+    }
+
+    // 牌型大小规则
+    pub fn hand_ranking(&self) -> HandRanking {
+        match self {
+            GameVariant::Holdem | GameVariant::Omaha => HandRanking::Standard,
+            GameVariant::ShortDeck => HandRanking::ShortDeck,
+        }
+    }
+
+    // 计算牌力：德州任选五张，奥马哈必须使用两张底牌和三张公共牌
+    pub fn evaluate(&self, hole_cards: &[Card], board_cards: &[Card]) -> HandValue {
+        match self {
+            GameVariant::Holdem | GameVariant::ShortDeck => {
+                let mut cards = board_cards.to_vec();
+                cards.extend_from_slice(hole_cards);
+                evaluate_best(&cards, self.hand_ranking())
+            }
+            GameVariant::Omaha => evaluate_omaha(hole_cards, board_cards, self.hand_ranking()),
+        }
+    }
+}
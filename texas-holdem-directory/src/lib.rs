@@ -0,0 +1,295 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::{
+    renet::{RenetServer, ServerAuthentication, ServerConfig, ServerEvent},
+    RenetServerPlugin,
+};
+use texas_holdem_common::{
+    channel::RequestError,
+    directory::{
+        directory_connection_config, DirectoryRoom, DirectoryRoomsMessage, LocateServerMessage,
+        ServerStatus, DIRECTORY_PROTOCOL_ID, DIRECTORY_ROOMS_CHANNEL_ID, LOCATE_SERVER_CHANNEL_ID,
+        MAX_SERVER_NAME_CHARS, SERVER_STATUS_CHANNEL_ID,
+    },
+    lobby::{is_valid_name, paginate},
+    ProtocolVersion,
+};
+
+pub fn new_renet_server(bind_addr: SocketAddr, max_clients: usize) -> io::Result<RenetServer> {
+    let socket = UdpSocket::bind(bind_addr)?;
+    let public_addr = socket.local_addr()?;
+    let server_config = ServerConfig::new(
+        max_clients,
+        DIRECTORY_PROTOCOL_ID,
+        public_addr,
+        ServerAuthentication::Unsecure,
+    );
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    RenetServer::new(
+        current_time,
+        server_config,
+        directory_connection_config(),
+        socket,
+    )
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct DirectorySettings {
+    // 超过该时间（秒）未上报状态的服务端从目录中移除
+    pub server_timeout: f32,
+    // 设置后只接受上报了相同密钥的服务端
+    pub registration_secret: Option<String>,
+}
+
+impl Default for DirectorySettings {
+    fn default() -> Self {
+        DirectorySettings {
+            server_timeout: 5.0,
+            registration_secret: None,
+        }
+    }
+}
+
+// 已登记的游戏服务端
+#[derive(Debug)]
+struct RegisteredServer {
+    client_id: u64,
+    status: ServerStatus,
+    last_seen: f64,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct ServerDirectory {
+    // 按名称排序，汇总房间列表时顺序稳定
+    servers: BTreeMap<String, RegisteredServer>,
+    // 连接到目录的客户端（玩家和游戏服务端）的协议版本
+    client_versions: HashMap<u64, ProtocolVersion>,
+}
+
+impl ServerDirectory {
+    pub fn servers(&self) -> impl Iterator<Item = &ServerStatus> {
+        self.servers.values().map(|server| &server.status)
+    }
+
+    pub fn get(&self, server_name: &str) -> Option<&ServerStatus> {
+        self.servers.get(server_name).map(|server| &server.status)
+    }
+
+    // 客户端可以连接的服务端：未在排空且协议版本兼容
+    fn available(&self, client_id: u64) -> impl Iterator<Item = &ServerStatus> {
+        let client_version = self.client_versions.get(&client_id).copied();
+        self.servers().filter(move |status| {
+            !status.draining
+                && client_version.is_some_and(|client_version| {
+                    status
+                        .protocol_version
+                        .check_compatible(&client_version)
+                        .is_ok()
+                })
+        })
+    }
+
+    fn locate(
+        &self,
+        client_id: u64,
+        room: Option<&(String, u64)>,
+    ) -> Result<&ServerStatus, RequestError> {
+        let Some((server_name, room_id)) = room else {
+            // 新房间放到负载最低的服务端
+            return self
+                .available(client_id)
+                .min_by(|a, b| a.load().total_cmp(&b.load()))
+                .ok_or(RequestError::ServerNotFound);
+        };
+        let status = self
+            .available(client_id)
+            .find(|status| &status.server_name == server_name);
+        match status {
+            Some(status) if status.rooms.iter().any(|room| room.room_id == *room_id) => Ok(status),
+            Some(_) => Err(RequestError::RoomNotFound),
+            None if self.get(server_name).is_some_and(|status| status.draining) => {
+                Err(RequestError::ServerDraining)
+            }
+            None => Err(RequestError::ServerNotFound),
+        }
+    }
+}
+
+// 目录服务逻辑，需要先插入RenetServer资源
+pub struct DirectoryPlugin;
+
+impl Plugin for DirectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RenetServerPlugin::default())
+            .init_resource::<DirectorySettings>()
+            .insert_resource(ServerDirectory::default())
+            .add_systems((
+                handle_events_system,
+                handle_server_status,
+                expire_servers,
+                handle_directory_rooms,
+                handle_locate_server,
+            ));
+    }
+}
+
+pub fn handle_events_system(
+    mut server_events: EventReader<ServerEvent>,
+    mut directory: ResMut<ServerDirectory>,
+) {
+    for event in server_events.iter() {
+        match event {
+            ServerEvent::ClientConnected(id, user_data) => {
+                info!("Client {} connected", id);
+                directory
+                    .client_versions
+                    .insert(*id, ProtocolVersion::from_user_data(user_data));
+            }
+            ServerEvent::ClientDisconnected(id) => {
+                info!("Client {} disconnected", id);
+                directory.client_versions.remove(id);
+                directory.servers.retain(|server_name, server| {
+                    if server.client_id == *id {
+                        info!("Server {} unregistered", server_name);
+                    }
+                    server.client_id != *id
+                });
+            }
+        }
+    }
+}
+
+// 游戏服务端定期上报的状态；同名的服务端仍在线时拒绝其他连接的上报，
+// 重启后的服务端等旧的登记断开或超时移除后才能登记
+pub fn handle_server_status(
+    mut server: ResMut<RenetServer>,
+    settings: Res<DirectorySettings>,
+    mut directory: ResMut<ServerDirectory>,
+    time: Res<Time>,
+) {
+    for client_id in server.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, SERVER_STATUS_CHANNEL_ID) {
+            if let Ok(mut status) = serde_json::from_slice::<ServerStatus>(&message) {
+                // 名称过长会使房间列表超过消息大小上限
+                if !is_valid_name(&status.server_name, MAX_SERVER_NAME_CHARS) {
+                    warn!("Rejected invalid server name {:?}", status.server_name);
+                    continue;
+                }
+                if settings.registration_secret.is_some()
+                    && status.secret != settings.registration_secret
+                {
+                    warn!(
+                        "Rejected server {} from client {}: wrong registration secret",
+                        status.server_name, client_id
+                    );
+                    continue;
+                }
+                let registered_by = directory
+                    .servers
+                    .get(&status.server_name)
+                    .map(|server| server.client_id);
+                if registered_by.is_some_and(|registered_by| registered_by != client_id) {
+                    warn!(
+                        "Rejected server {} from client {}: name registered by another client",
+                        status.server_name, client_id
+                    );
+                    continue;
+                }
+                status.secret = None;
+                if registered_by.is_none() {
+                    info!(
+                        "Server {} registered at {}",
+                        status.server_name, status.public_addr
+                    );
+                }
+                directory.servers.insert(
+                    status.server_name.clone(),
+                    RegisteredServer {
+                        client_id,
+                        status,
+                        last_seen: time.elapsed_seconds_f64(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+pub fn expire_servers(
+    settings: Res<DirectorySettings>,
+    mut directory: ResMut<ServerDirectory>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    directory.servers.retain(|server_name, server| {
+        let alive = now - server.last_seen <= settings.server_timeout as f64;
+        if !alive {
+            warn!("Server {} timed out", server_name);
+        }
+        alive
+    });
+}
+
+// 汇总各服务端的房间，筛选、排序后分页返回
+pub fn handle_directory_rooms(mut server: ResMut<RenetServer>, directory: Res<ServerDirectory>) {
+    for client_id in server.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, DIRECTORY_ROOMS_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<DirectoryRoomsMessage>(&message) {
+                info!("Received directory rooms message: {:?}", message);
+                message.filter.truncate_search();
+                let rooms = directory
+                    .available(client_id)
+                    .flat_map(|status| {
+                        status.rooms.iter().map(|room| DirectoryRoom {
+                            server_name: status.server_name.clone(),
+                            server_addr: status.public_addr,
+                            room: room.clone(),
+                        })
+                    })
+                    .collect();
+                (message.rooms, message.total) = paginate(
+                    rooms,
+                    &message.filter,
+                    message.sort,
+                    message.page,
+                    message.page_size,
+                );
+                server.send_message(
+                    client_id,
+                    DIRECTORY_ROOMS_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+pub fn handle_locate_server(mut server: ResMut<RenetServer>, directory: Res<ServerDirectory>) {
+    for client_id in server.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, LOCATE_SERVER_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<LocateServerMessage>(&message) {
+                info!("Received locate server message: {:?}", message);
+                match directory.locate(client_id, message.room.as_ref()) {
+                    Ok(status) => {
+                        message.server_name = status.server_name.clone();
+                        message.server_addr = Some(status.public_addr);
+                    }
+                    Err(error) => message.error = Some(error),
+                }
+                server.send_message(
+                    client_id,
+                    LOCATE_SERVER_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::{net::SocketAddr, process, time::Duration};
+
+use bevy::{
+    app::ScheduleRunnerSettings,
+    log::{Level, LogPlugin},
+    prelude::*,
+};
+use clap::Parser;
+use texas_holdem_directory::{new_renet_server, DirectoryPlugin, DirectorySettings};
+
+// 主循环间隔，避免空转占满CPU
+const TICK_INTERVAL: Duration = Duration::from_millis(16);
+
+// 命令行参数
+#[derive(Debug, Parser)]
+#[command(version, about = "Texas Holdem room directory")]
+struct Cli {
+    /// 监听地址，游戏服务端和客户端都连接该地址
+    #[arg(long, default_value = "127.0.0.1:4000")]
+    bind_addr: SocketAddr,
+    /// 最大连接数（游戏服务端和客户端）
+    #[arg(long, default_value_t = 256)]
+    max_clients: usize,
+    /// 超过该时间（秒）未上报状态的服务端从目录中移除
+    #[arg(long, default_value_t = 5.0)]
+    server_timeout: f32,
+    /// 游戏服务端登记时必须上报的密钥（服务端配置 directory.secret），不设置时任何连接都可以登记
+    #[arg(long)]
+    registration_secret: Option<String>,
+    /// 日志级别（trace/debug/info/warn/error）
+    #[arg(long, default_value = "info")]
+    log_level: Level,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let server = match new_renet_server(cli.bind_addr, cli.max_clients) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", cli.bind_addr, e);
+            process::exit(1);
+        }
+    };
+
+    App::new()
+        .insert_resource(ScheduleRunnerSettings::run_loop(TICK_INTERVAL))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin {
+            level: cli.log_level,
+            ..default()
+        })
+        .insert_resource(server)
+        .insert_resource(DirectorySettings {
+            server_timeout: cli.server_timeout,
+            registration_secret: cli.registration_secret,
+        })
+        .add_plugin(DirectoryPlugin)
+        .run();
+}
@@ -0,0 +1,146 @@
+use std::{collections::VecDeque, net::IpAddr};
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::config::AccessSettings;
+
+// 邀请码长度
+const INVITE_CODE_LEN: usize = 10;
+
+// 加盐的房间密码摘要（PBKDF2-HMAC-SHA256），服务端不保存明文密码
+#[derive(Debug, Clone)]
+pub struct PasswordHash {
+    salt: [u8; 16],
+    // 创建时的迭代次数，修改设置不影响已有的密码
+    iterations: u32,
+    digest: [u8; 32],
+}
+
+impl PasswordHash {
+    // 空密码表示不需要密码
+    pub fn new(password: &str, iterations: u32) -> Option<Self> {
+        if password.is_empty() {
+            return None;
+        }
+        let salt: [u8; 16] = rand::thread_rng().gen();
+        Some(PasswordHash {
+            salt,
+            iterations,
+            digest: pbkdf2_sha256(password.as_bytes(), &salt, iterations),
+        })
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        // 逐字节比较全部摘要，耗时与密码是否部分匹配无关
+        pbkdf2_sha256(password.as_bytes(), &self.salt, self.iterations)
+            .iter()
+            .zip(self.digest.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+// PBKDF2-HMAC-SHA256（RFC 8018），输出一个32字节的块
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    // HMAC的密钥超过块长度时先做摘要
+    let mut key = [0u8; 64];
+    if password.len() > key.len() {
+        key[..32].copy_from_slice(&Sha256::digest(password));
+    } else {
+        key[..password.len()].copy_from_slice(password);
+    }
+    // 内外两层的密钥块只计算一次，每次迭代从这里复制状态
+    let mut inner = Sha256::new();
+    inner.update(key.map(|byte| byte ^ 0x36));
+    let mut outer = Sha256::new();
+    outer.update(key.map(|byte| byte ^ 0x5c));
+    let hmac = |message: &[&[u8]]| -> [u8; 32] {
+        let mut inner = inner.clone();
+        for part in message {
+            inner.update(part);
+        }
+        let mut outer = outer.clone();
+        outer.update(inner.finalize());
+        outer.finalize().into()
+    };
+    let mut block = hmac(&[salt, &1u32.to_be_bytes()]);
+    let mut digest = block;
+    for _ in 1..iterations {
+        block = hmac(&[&block]);
+        for (byte, next) in digest.iter_mut().zip(block.iter()) {
+            *byte ^= next;
+        }
+    }
+    digest
+}
+
+pub fn new_invite_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(INVITE_CODE_LEN)
+        .map(char::from)
+        .collect()
+}
+
+// 每个地址最近输错密码或邀请码的时间（秒），按地址计数，重新连接换连接id不能绕过限制
+#[derive(Debug, Default, Resource)]
+pub struct FailedAttempts(HashMap<Option<IpAddr>, VecDeque<f64>>);
+
+impl FailedAttempts {
+    // 超过次数限制时在窗口内拒绝该地址的尝试
+    pub fn is_blocked(
+        &mut self,
+        settings: &AccessSettings,
+        addr: Option<IpAddr>,
+        now: f64,
+    ) -> bool {
+        if settings.max_failed_attempts == 0 {
+            return false;
+        }
+        let Some(failed_at) = self.0.get_mut(&addr) else {
+            return false;
+        };
+        while failed_at
+            .front()
+            .is_some_and(|time| now - *time >= settings.attempt_window_secs)
+        {
+            failed_at.pop_front();
+        }
+        failed_at.len() >= settings.max_failed_attempts
+    }
+
+    pub fn record(&mut self, addr: Option<IpAddr>, now: f64) {
+        self.0.entry(addr).or_default().push_back(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn pbkdf2_matches_known_vectors() {
+        assert_eq!(
+            hex(&pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(&pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+
+    #[test]
+    fn password_hash_verifies_only_the_password() {
+        let hash = PasswordHash::new("secret", 10).unwrap();
+        assert!(hash.verify("secret"));
+        assert!(!hash.verify("secreT"));
+        assert!(PasswordHash::new("", 10).is_none());
+    }
+}
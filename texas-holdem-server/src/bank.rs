@@ -0,0 +1,739 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{self, File},
+    io::{self, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use texas_holdem_common::{
+    channel::{BankMessage, BankRequest, RequestError, RoomDelta, BANK_CHANNEL_ID},
+    PlayerRole, SessionToken,
+};
+
+use crate::{
+    config::ServerSettings,
+    ledger::{self, Ledger, LedgerAccount, LedgerTransaction, TransactionKind},
+    network::Sessions,
+    play::PlayList,
+    room::{Room, RoomDeltaEvent, RoomList},
+};
+
+// 持久化目录下的账户文件
+const BANK_FILE: &str = "bank.json";
+
+// 玩家账户，按服务端分配的用户区分，玩家名只用于桌上记账
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Account {
+    // 会话令牌的摘要，服务端重启后凭令牌找回账户
+    pub token_digest: String,
+    // 可用余额
+    pub balance: u64,
+    // 各现金桌上的筹码 room_id -> 筹码，每局结束时结算
+    pub tables: BTreeMap<u64, TableStack>,
+}
+
+// 现金桌上的筹码，桌上按入座时的玩家名记账
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TableStack {
+    pub player_name: String,
+    pub chips: u64,
+}
+
+impl Account {
+    pub fn total(&self) -> u64 {
+        self.balance + self.tables.values().map(|stack| stack.chips).sum::<u64>()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct BankState {
+    // 已提交的事务数
+    seq: u64,
+    accounts: BTreeMap<u64, Account>,
+    // 各现金桌累计抽水 room_id -> 筹码
+    #[serde(default)]
+    rake: BTreeMap<u64, u64>,
+}
+
+impl BankState {
+    // 在该桌上以该玩家名持有筹码的账户
+    fn seat_account(&self, room_id: u64, player_name: &str) -> Option<u64> {
+        self.accounts
+            .iter()
+            .find(|(_, account)| {
+                account
+                    .tables
+                    .get(&room_id)
+                    .is_some_and(|stack| stack.player_name == player_name)
+            })
+            .map(|(account_id, _)| *account_id)
+    }
+}
+
+fn token_digest(token: &SessionToken) -> String {
+    Sha256::digest(token.0)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// 服务端保管的玩家资金，每次变更作为一个事务整体写入磁盘后才生效
+#[derive(Debug, Resource)]
+pub struct Bank {
+    state: BankState,
+    // 未设置持久化目录时只保存在内存中
+    path: Option<PathBuf>,
+    // 每个事务的资金变动
+    ledger: Ledger,
+    initial_bankroll: u64,
+    max_new_accounts: usize,
+    new_account_window_secs: f64,
+    // 各地址最近开户的时间（秒），不持久化
+    opened_at: HashMap<Option<IpAddr>, VecDeque<f64>>,
+}
+
+impl Bank {
+    // 读取账户文件，上次退出时留在桌上的筹码退回余额（对局中的筹码以上一局结算为准）
+    pub fn load(settings: &ServerSettings) -> Result<Self, String> {
+        let mut bank = Bank::read(settings)?;
+        bank.ledger.discard_uncommitted(bank.state.seq)?;
+        if bank
+            .state
+            .accounts
+            .values()
+            .any(|account| !account.tables.is_empty())
+        {
+            bank.transact(|state, transactions| {
+                for (account_id, account) in state.accounts.iter_mut() {
+                    for (room_id, stack) in std::mem::take(&mut account.tables) {
+                        if stack.chips > 0 {
+                            info!(
+                                "Returned {} table chips in room {} to {} ({})",
+                                stack.chips, room_id, stack.player_name, account_id
+                            );
+                            transactions.push(cash_out_transaction(
+                                *account_id,
+                                room_id,
+                                &stack.player_name,
+                                stack.chips,
+                            ));
+                            account.balance += stack.chips;
+                        }
+                    }
+                }
+                Ok(())
+            })
+            .map_err(|_| "Failed to recover bank".to_string())?;
+        }
+        Ok(bank)
+    }
+
+    // 只读取账户文件和账本，不做任何修改，用于对账
+    pub fn read(settings: &ServerSettings) -> Result<Self, String> {
+        let persistence_path = settings.persistence_path.as_deref();
+        let path = persistence_path.map(|persistence_path| persistence_path.join(BANK_FILE));
+        let state = match path.as_ref() {
+            Some(path) if path.exists() => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read bank {}: {}", path.display(), e))?;
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("Failed to parse bank {}: {}", path.display(), e))?
+            }
+            _ => BankState::default(),
+        };
+        Ok(Bank {
+            state,
+            path,
+            ledger: Ledger::new(persistence_path),
+            initial_bankroll: settings.bank.initial_bankroll,
+            max_new_accounts: settings.bank.max_new_accounts,
+            new_account_window_secs: settings.bank.new_account_window_secs,
+            opened_at: HashMap::new(),
+        })
+    }
+
+    pub fn account(&self, account_id: u64) -> Option<&Account> {
+        self.state.accounts.get(&account_id)
+    }
+
+    // 凭会话令牌找回账户
+    pub fn account_of_token(&self, token: &SessionToken) -> Option<u64> {
+        let digest = token_digest(token);
+        self.state
+            .accounts
+            .iter()
+            .find(|(_, account)| account.token_digest == digest)
+            .map(|(account_id, _)| *account_id)
+    }
+
+    // 在该桌上以该玩家名持有筹码的账户
+    pub fn seat_account(&self, room_id: u64, player_name: &str) -> Option<u64> {
+        self.state.seat_account(room_id, player_name)
+    }
+
+    // 所有账户的资金总额（余额加桌上筹码）
+    pub fn total(&self) -> u64 {
+        self.state.accounts.values().map(Account::total).sum()
+    }
+
+    // 按账本重新计算每个科目，与账户逐一核对，返回所有不一致之处
+    pub fn reconcile(&self) -> Result<Vec<String>, String> {
+        let records = self.ledger.records(self.state.seq)?;
+        let mut balances = ledger::balances(&records);
+        let mut mismatches = Vec::new();
+        let mut check = |account: LedgerAccount, expected: u64| {
+            let actual = balances.remove(&account).unwrap_or(0);
+            if actual != expected as i64 {
+                mismatches.push(format!(
+                    "{:?}: ledger {} != bank {}",
+                    account, actual, expected
+                ));
+            }
+        };
+        for (account_id, account) in self.state.accounts.iter() {
+            check(LedgerAccount::Bankroll(*account_id), account.balance);
+            for (room_id, stack) in account.tables.iter() {
+                check(
+                    LedgerAccount::Stack {
+                        room_id: *room_id,
+                        player_name: stack.player_name.clone(),
+                    },
+                    stack.chips,
+                );
+            }
+        }
+        for (room_id, rake) in self.state.rake.iter() {
+            check(LedgerAccount::Rake { room_id: *room_id }, *rake);
+        }
+        // 发放的资金等于所有账户的总额加上抽水
+        let issued = -balances.remove(&LedgerAccount::House).unwrap_or(0);
+        let expected = self.total() + self.state.rake.values().sum::<u64>();
+        if issued != expected as i64 {
+            mismatches.push(format!(
+                "House: issued {} != bank total {}",
+                issued, expected
+            ));
+        }
+        // 其余科目（已兑现的筹码、已分配的底池）必须为0
+        for (account, balance) in balances {
+            if balance != 0 {
+                mismatches.push(format!("{:?}: ledger {} != bank 0", account, balance));
+            }
+        }
+        Ok(mismatches)
+    }
+
+    // 现金桌累计抽水
+    pub fn rake_of(&self, room_id: u64) -> u64 {
+        self.state.rake.get(&room_id).copied().unwrap_or(0)
+    }
+
+    // 按房间和时间段（毫秒，按UTC对齐）汇总抽水 (room_id, 时间段开始) -> 筹码
+    pub fn rake_report(&self, period: u64) -> Result<BTreeMap<(u64, u64), u64>, String> {
+        let mut report = BTreeMap::new();
+        for record in self.ledger.records(self.state.seq)? {
+            if let LedgerAccount::Rake { room_id } = record.transaction.to {
+                let period_start = record.timestamp / period * period;
+                *report.entry((room_id, period_start)).or_insert(0) += record.transaction.amount;
+            }
+        }
+        Ok(report)
+    }
+
+    // 已提交的账本分录数
+    pub fn ledger_len(&self) -> Result<usize, String> {
+        Ok(self.ledger.records(self.state.seq)?.len())
+    }
+
+    // 在副本上执行事务，先写账本再写账户文件，都成功后才替换当前状态，失败时不产生任何变更
+    fn transact<T>(
+        &mut self,
+        f: impl FnOnce(&mut BankState, &mut Vec<LedgerTransaction>) -> Result<T, RequestError>,
+    ) -> Result<T, RequestError> {
+        let mut state = self.state.clone();
+        let mut transactions = Vec::new();
+        let result = f(&mut state, &mut transactions)?;
+        state.seq += 1;
+        let ledger_len = match self.ledger.append(state.seq, &transactions) {
+            Ok(ledger_len) => ledger_len,
+            Err(e) => {
+                error!("Failed to write ledger: {}", e);
+                return Err(RequestError::BankUnavailable);
+            }
+        };
+        if let Some(path) = self.path.as_ref() {
+            if let Err(e) = write_atomically(path, &state) {
+                error!("Failed to write bank {}: {}", path.display(), e);
+                self.ledger.rollback(ledger_len);
+                return Err(RequestError::BankUnavailable);
+            }
+        }
+        self.state = state;
+        Ok(result)
+    }
+
+    // 用户首次进入房间时开户并发放初始资金，同一地址在时间窗口内开户的数量有限
+    pub fn open(
+        &mut self,
+        account_id: u64,
+        token: &SessionToken,
+        addr: Option<IpAddr>,
+        now: f64,
+    ) -> Result<(), RequestError> {
+        if self.state.accounts.contains_key(&account_id) {
+            return Ok(());
+        }
+        let opened_at = self.opened_at.entry(addr).or_default();
+        while opened_at
+            .front()
+            .is_some_and(|time| now - *time >= self.new_account_window_secs)
+        {
+            opened_at.pop_front();
+        }
+        if self.max_new_accounts > 0 && opened_at.len() >= self.max_new_accounts {
+            return Err(RequestError::TooManyAccounts);
+        }
+        let initial_bankroll = self.initial_bankroll;
+        self.transact(|state, transactions| {
+            transactions.push(LedgerTransaction {
+                kind: TransactionKind::Issue,
+                room_id: None,
+                play_id: None,
+                player_name: None,
+                from: LedgerAccount::House,
+                to: LedgerAccount::Bankroll(account_id),
+                amount: initial_bankroll,
+            });
+            state.accounts.insert(
+                account_id,
+                Account {
+                    token_digest: token_digest(token),
+                    balance: initial_bankroll,
+                    tables: BTreeMap::new(),
+                },
+            );
+            Ok(())
+        })?;
+        self.opened_at.entry(addr).or_default().push_back(now);
+        Ok(())
+    }
+
+    // 从余额转入桌上筹码，返回剩余余额
+    pub fn buy_in(
+        &mut self,
+        account_id: u64,
+        room_id: u64,
+        player_name: &str,
+        amount: u32,
+    ) -> Result<u64, RequestError> {
+        self.transact(|state, transactions| {
+            // 同名玩家离开后筹码在对局结束时才兑现，此前不能有其他账户以该名字入座
+            if state
+                .seat_account(room_id, player_name)
+                .is_some_and(|seat_account| seat_account != account_id)
+            {
+                return Err(RequestError::NameTaken);
+            }
+            let account = state
+                .accounts
+                .get_mut(&account_id)
+                .ok_or(RequestError::InsufficientFunds)?;
+            if account.balance < amount as u64 {
+                return Err(RequestError::InsufficientFunds);
+            }
+            let stack = account.tables.entry(room_id).or_insert_with(|| TableStack {
+                player_name: player_name.to_string(),
+                chips: 0,
+            });
+            // 以其他名字离开的筹码尚未兑现
+            if stack.player_name != player_name {
+                return Err(RequestError::HandInProgress);
+            }
+            transactions.push(LedgerTransaction {
+                kind: TransactionKind::BuyIn,
+                room_id: Some(room_id),
+                play_id: None,
+                player_name: Some(player_name.to_string()),
+                from: LedgerAccount::Bankroll(account_id),
+                to: LedgerAccount::Stack {
+                    room_id,
+                    player_name: player_name.to_string(),
+                },
+                amount: amount as u64,
+            });
+            stack.chips += amount as u64;
+            account.balance -= amount as u64;
+            Ok(account.balance)
+        })
+    }
+
+    // 对局结束后结算一张桌上的所有玩家：从上一次结算的筹码出发按本局的分录推算，必须与对局结果一致且底池分配完毕
+    pub fn settle(
+        &mut self,
+        room_id: u64,
+        stacks: &[(String, u32)],
+        play_transactions: &[LedgerTransaction],
+    ) -> Result<(), RequestError> {
+        self.transact(|state, transactions| {
+            let mut balances = BTreeMap::new();
+            let mut account_ids = Vec::new();
+            for (player_name, _) in stacks.iter() {
+                let account_id = state
+                    .seat_account(room_id, player_name)
+                    .ok_or(RequestError::InvalidAction)?;
+                let chips = state.accounts[&account_id].tables[&room_id].chips;
+                balances.insert(
+                    LedgerAccount::Stack {
+                        room_id,
+                        player_name: player_name.clone(),
+                    },
+                    chips as i64,
+                );
+                account_ids.push(account_id);
+            }
+            for transaction in play_transactions.iter() {
+                ledger::apply(&mut balances, transaction);
+            }
+            for ((player_name, chips), account_id) in stacks.iter().zip(account_ids) {
+                let account = LedgerAccount::Stack {
+                    room_id,
+                    player_name: player_name.clone(),
+                };
+                if balances.remove(&account) != Some(*chips as i64) {
+                    error!(
+                        "Unbalanced settlement in room {}: {} ends with {}",
+                        room_id, player_name, chips
+                    );
+                    return Err(RequestError::InvalidAction);
+                }
+                state
+                    .accounts
+                    .get_mut(&account_id)
+                    .unwrap()
+                    .tables
+                    .get_mut(&room_id)
+                    .unwrap()
+                    .chips = *chips as u64;
+            }
+            // 抽水转入本桌的抽水科目
+            if let Some(rake) = balances.remove(&LedgerAccount::Rake { room_id }) {
+                if rake < 0 {
+                    return Err(RequestError::InvalidAction);
+                }
+                *state.rake.entry(room_id).or_insert(0) += rake as u64;
+            }
+            // 只允许剩下已分配完的底池
+            if let Some((account, balance)) = balances.iter().find(|(account, balance)| {
+                !matches!(account, LedgerAccount::Pot { .. }) || **balance != 0
+            }) {
+                error!(
+                    "Unbalanced settlement in room {}: {:?} ends with {}",
+                    room_id, account, balance
+                );
+                return Err(RequestError::InvalidAction);
+            }
+            transactions.extend_from_slice(play_transactions);
+            Ok(())
+        })
+    }
+
+    // 桌上筹码全部退回余额，返回退回的数量
+    pub fn cash_out(&mut self, player_name: &str, room_id: u64) -> Result<u64, RequestError> {
+        self.transact(|state, transactions| {
+            let Some(account_id) = state.seat_account(room_id, player_name) else {
+                return Ok(0);
+            };
+            let account = state.accounts.get_mut(&account_id).unwrap();
+            let chips = account
+                .tables
+                .remove(&room_id)
+                .map_or(0, |stack| stack.chips);
+            if chips > 0 {
+                transactions.push(cash_out_transaction(
+                    account_id,
+                    room_id,
+                    player_name,
+                    chips,
+                ));
+                account.balance += chips;
+            }
+            Ok(chips)
+        })
+    }
+}
+
+fn cash_out_transaction(
+    account_id: u64,
+    room_id: u64,
+    player_name: &str,
+    chips: u64,
+) -> LedgerTransaction {
+    LedgerTransaction {
+        kind: TransactionKind::CashOut,
+        room_id: Some(room_id),
+        play_id: None,
+        player_name: Some(player_name.to_string()),
+        from: LedgerAccount::Stack {
+            room_id,
+            player_name: player_name.to_string(),
+        },
+        to: LedgerAccount::Bankroll(account_id),
+        amount: chips,
+    }
+}
+
+// 先写临时文件并刷盘，再原子替换，崩溃时文件要么是旧状态要么是新状态
+fn write_atomically(path: &Path, state: &BankState) -> io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&serde_json::to_vec_pretty(state).unwrap())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    if let Some(dir) = path.parent() {
+        // 目录刷盘保证重命名持久化，部分平台不支持打开目录
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// 进入房间时为会话的用户开户，已开户时不做任何事
+pub fn open_account(
+    bank: &mut Bank,
+    sessions: &Sessions,
+    client_id: u64,
+    now: f64,
+) -> Result<(), RequestError> {
+    let session = sessions.get(client_id).ok_or(RequestError::InvalidAction)?;
+    bank.open(
+        session.user_id,
+        &session.token,
+        session.addr.map(|addr| addr.ip()),
+        now,
+    )
+}
+
+// 玩家是否在进行中的对局里，此时不能买入或兑现
+pub fn is_in_hand(play_list: &PlayList, room_id: u64, client_id: u64) -> bool {
+    play_list.plays.iter().any(|play| {
+        play.room_id == room_id
+            && play
+                .participants
+                .iter()
+                .any(|participant| participant.player_client_id == client_id)
+    })
+}
+
+// 现金桌上入座时买入默认数量，余额不足时买入全部余额
+pub fn sit_down(
+    bank: &mut Bank,
+    settings: &ServerSettings,
+    sessions: &Sessions,
+    room: &mut Room,
+    client_id: u64,
+) -> Result<Option<RoomDelta>, RequestError> {
+    if room.tournament_id.is_some() {
+        return Ok(None);
+    }
+    let account_id = sessions.user_id(client_id).ok_or(RequestError::NotInRoom)?;
+    let room_id = room.room_id;
+    let Some(player) = room
+        .players
+        .iter_mut()
+        .find(|player| player.player_client_id == client_id)
+    else {
+        return Err(RequestError::NotInRoom);
+    };
+    if player.chips > 0 {
+        return Ok(None);
+    }
+    let balance = bank
+        .account(account_id)
+        .map_or(0, |account| account.balance);
+    let amount = (settings.stakes.starting_chips as u64).min(balance) as u32;
+    if amount < settings.stakes.min_buy_in {
+        return Err(RequestError::InsufficientFunds);
+    }
+    bank.buy_in(account_id, room_id, &player.player_name, amount)?;
+    player.chips = amount;
+    Ok(Some(RoomDelta::PlayerChipsChanged {
+        player_client_id: client_id,
+        chips: player.chips,
+    }))
+}
+
+// 玩家离开座位或房间时兑现桌上筹码，在对局中的玩家在对局结束时兑现
+pub fn leave_table(
+    bank: &mut Bank,
+    play_list: &PlayList,
+    room: &mut Room,
+    client_id: u64,
+) -> Option<RoomDelta> {
+    if room.tournament_id.is_some() || is_in_hand(play_list, room.room_id, client_id) {
+        return None;
+    }
+    let room_id = room.room_id;
+    let player = room
+        .players
+        .iter_mut()
+        .find(|player| player.player_client_id == client_id)?;
+    match bank.cash_out(&player.player_name, room_id) {
+        Ok(chips) => {
+            info!(
+                "{} cashed out {} from room {}",
+                player.player_name, chips, room_id
+            );
+            player.chips = 0;
+            Some(RoomDelta::PlayerChipsChanged {
+                player_client_id: player.player_client_id,
+                chips: 0,
+            })
+        }
+        Err(error) => {
+            error!("Failed to cash out {}: {:?}", player.player_name, error);
+            None
+        }
+    }
+}
+
+pub fn handle_bank(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    mut room_list: ResMut<RoomList>,
+    play_list: Res<PlayList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, BANK_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<BankMessage>(&message) {
+                info!("Received bank message: {:?}", message);
+                let account_id = sessions.user_id(client_id).unwrap();
+                let result = room_list
+                    .room_of_client(client_id)
+                    .filter(|room| room.room_id == message.room_id)
+                    .map(|room| room.room_id)
+                    .ok_or(RequestError::NotInRoom)
+                    .and_then(|room_id| {
+                        let room = room_list.get_mut(room_id).unwrap();
+                        match message.request {
+                            BankRequest::Balance => Ok(None),
+                            BankRequest::BuyIn(amount) => buy_in(
+                                &mut bank, &settings, &play_list, room, client_id, account_id,
+                                amount,
+                            )
+                            .map(Some),
+                        }
+                    });
+                match result {
+                    Ok(delta) => {
+                        if let Some(delta) = delta {
+                            room_delta_ew.send(RoomDeltaEvent {
+                                room_id: message.room_id,
+                                delta,
+                            });
+                        }
+                    }
+                    Err(error) => message.error = Some(error),
+                }
+                message.balance = bank
+                    .account(account_id)
+                    .map_or(0, |account| account.balance);
+                message.min_buy_in = settings.stakes.min_buy_in;
+                message.max_buy_in = settings.stakes.max_buy_in;
+                server.send_message(
+                    client_id,
+                    BANK_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+// 两局之间补充筹码（筹码为0时即重新买入），补充后不超过最大买入
+fn buy_in(
+    bank: &mut Bank,
+    settings: &ServerSettings,
+    play_list: &PlayList,
+    room: &mut Room,
+    client_id: u64,
+    account_id: u64,
+    amount: u32,
+) -> Result<RoomDelta, RequestError> {
+    // 锦标赛筹码不能买入
+    if room.tournament_id.is_some() {
+        return Err(RequestError::InvalidAction);
+    }
+    if is_in_hand(play_list, room.room_id, client_id) {
+        return Err(RequestError::HandInProgress);
+    }
+    let room_id = room.room_id;
+    let player = room
+        .players
+        .iter_mut()
+        .find(|player| player.player_client_id == client_id)
+        .ok_or(RequestError::NotInRoom)?;
+    if player.player_role != PlayerRole::Participant {
+        return Err(RequestError::NotSeated);
+    }
+    let stack = player
+        .chips
+        .checked_add(amount)
+        .ok_or(RequestError::InvalidBuyIn)?;
+    if amount == 0 || stack < settings.stakes.min_buy_in || stack > settings.stakes.max_buy_in {
+        return Err(RequestError::InvalidBuyIn);
+    }
+    bank.buy_in(account_id, room_id, &player.player_name, amount)?;
+    player.chips = stack;
+    Ok(RoomDelta::PlayerChipsChanged {
+        player_client_id: client_id,
+        chips: stack,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use texas_holdem_common::{MissedBlinds, Player};
+
+    use super::*;
+    use crate::quick_seat::test_room;
+
+    #[test]
+    fn buy_in_rejects_stack_overflow() {
+        let settings = ServerSettings::default();
+        let mut bank = Bank::load(&settings).unwrap();
+        bank.open(7, &SessionToken([1; 16]), None, 0.0).unwrap();
+        let mut room = test_room(1);
+        room.players.push(Player {
+            player_client_id: 1,
+            player_name: "Alice".to_string(),
+            player_role: PlayerRole::Participant,
+            chips: u32::MAX,
+            sitting_out: false,
+            missed_blinds: MissedBlinds::default(),
+        });
+        // 赢下的筹码可以超过最大买入，补充时不能溢出
+        let result = buy_in(
+            &mut bank,
+            &settings,
+            &PlayList::default(),
+            &mut room,
+            1,
+            7,
+            1,
+        );
+        assert_eq!(result.err(), Some(RequestError::InvalidBuyIn));
+        assert_eq!(
+            bank.account(7).unwrap().balance,
+            settings.bank.initial_bankroll
+        );
+    }
+}
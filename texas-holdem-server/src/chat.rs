@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::RenetServer;
+use texas_holdem_common::{
+    channel::{ChatLine, ChatMessage, ChatScope, RequestError, RoomSyncMessage, CHAT_CHANNEL_ID},
+    sync::encode_room_sync,
+    util::timestamp,
+    Player, PlayerRole,
+};
+
+use crate::{
+    bank::Bank,
+    config::{ChatSettings, ServerSettings},
+    network::Sessions,
+    play::PlayList,
+    room::{kick_player, send_room_sync, Room, RoomDeltaEvent, RoomList},
+    spectator::hidden_player,
+};
+
+// 每个用户（会话）最近发送聊天消息的时间（秒），断开时移除
+#[derive(Debug, Default, Resource)]
+pub struct ChatRateLimiter(HashMap<u64, VecDeque<f64>>);
+
+impl ChatRateLimiter {
+    // 记录一次发送，超过频率限制时返回false
+    fn check(&mut self, settings: &ChatSettings, user_id: u64, now: f64) -> bool {
+        if settings.rate_limit_messages == 0 {
+            return true;
+        }
+        let sent_at = self.0.entry(user_id).or_default();
+        while sent_at
+            .front()
+            .is_some_and(|time| now - *time >= settings.rate_limit_secs)
+        {
+            sent_at.pop_front();
+        }
+        if sent_at.len() >= settings.rate_limit_messages {
+            return false;
+        }
+        sent_at.push_back(now);
+        true
+    }
+
+    pub fn is_tracked(&self, user_id: u64) -> bool {
+        self.0.contains_key(&user_id)
+    }
+
+    pub fn remove(&mut self, user_id: u64) {
+        self.0.remove(&user_id);
+    }
+}
+
+// 房主命令
+enum ChatCommand {
+    Mute(String),
+    Unmute(String),
+    Kick(String),
+}
+
+impl ChatCommand {
+    fn parse(text: &str) -> Option<Result<Self, RequestError>> {
+        let text = text.strip_prefix('/')?;
+        let (command, player_name) = text.split_once(' ').unwrap_or((text, ""));
+        let player_name = player_name.trim().to_string();
+        if player_name.is_empty() {
+            return Some(Err(RequestError::InvalidChatMessage));
+        }
+        Some(match command {
+            "mute" => Ok(ChatCommand::Mute(player_name)),
+            "unmute" => Ok(ChatCommand::Unmute(player_name)),
+            "kick" => Ok(ChatCommand::Kick(player_name)),
+            _ => Err(RequestError::InvalidChatMessage),
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_chat(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    mut play_list: ResMut<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    mut rate_limiter: ResMut<ChatRateLimiter>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, CHAT_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<ChatMessage>(&message) {
+                info!("Received chat message: {:?}", message);
+                let text = message.text.trim();
+                let sender = room_list.get(message.room_id).and_then(|room| {
+                    room.players
+                        .iter()
+                        .find(|player| player.player_client_id == client_id)
+                        .map(|player| (room.is_owner(&sessions, client_id), player.clone()))
+                });
+                let result = match sender {
+                    None if room_list.get(message.room_id).is_none() => {
+                        Err(RequestError::RoomNotFound)
+                    }
+                    None => Err(RequestError::NotInRoom),
+                    Some(_)
+                        if text.is_empty() || text.chars().count() > settings.chat.max_length =>
+                    {
+                        Err(RequestError::InvalidChatMessage)
+                    }
+                    Some(_)
+                        if !sessions.user_id(client_id).is_some_and(|user_id| {
+                            rate_limiter.check(&settings.chat, user_id, time.elapsed_seconds_f64())
+                        }) =>
+                    {
+                        Err(RequestError::RateLimited)
+                    }
+                    Some((is_owner, sender)) => match ChatCommand::parse(text) {
+                        Some(_) if !is_owner => Err(RequestError::NotRoomOwner),
+                        Some(Err(error)) => Err(error),
+                        Some(Ok(command)) => run_command(
+                            &mut server,
+                            &sessions,
+                            &mut room_list,
+                            &mut bank,
+                            &mut play_list,
+                            &mut room_delta_ew,
+                            message.room_id,
+                            command,
+                        ),
+                        None => relay(
+                            &mut server,
+                            &settings.chat,
+                            room_list.get(message.room_id).unwrap(),
+                            &sender,
+                            text,
+                        ),
+                    },
+                };
+                message.error = result.err();
+                server.send_message(
+                    client_id,
+                    CHAT_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+fn relay(
+    server: &mut RenetServer,
+    settings: &ChatSettings,
+    room: &Room,
+    sender: &Player,
+    text: &str,
+) -> Result<(), RequestError> {
+    if room.muted_names.contains(&sender.player_name) {
+        return Err(RequestError::Muted);
+    }
+    // 参与者的消息所有人可见，旁观者的消息只有旁观者可见
+    let scope = match sender.player_role {
+        PlayerRole::Participant => ChatScope::Table,
+        PlayerRole::Spectator => ChatScope::Spectators,
+    };
+    let text = filter_words(text, &settings.banned_words);
+    send_line(server, room, Some(sender.player_name.clone()), scope, text);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_command(
+    server: &mut RenetServer,
+    sessions: &Sessions,
+    room_list: &mut RoomList,
+    bank: &mut Bank,
+    play_list: &mut PlayList,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+    room_id: u64,
+    command: ChatCommand,
+) -> Result<(), RequestError> {
+    let room = room_list.get_mut(room_id).unwrap();
+    let (ChatCommand::Mute(player_name)
+    | ChatCommand::Unmute(player_name)
+    | ChatCommand::Kick(player_name)) = &command;
+    let Some(target) = room
+        .players
+        .iter()
+        .find(|player| &player.player_name == player_name)
+        .cloned()
+    else {
+        return Err(RequestError::PlayerNotFound);
+    };
+    if room.is_owner(sessions, target.player_client_id) {
+        return Err(RequestError::InvalidAction);
+    }
+    let action = match command {
+        ChatCommand::Mute(_) => {
+            room.muted_names.insert(target.player_name.clone());
+            "muted"
+        }
+        ChatCommand::Unmute(_) => {
+            room.muted_names.remove(&target.player_name);
+            "unmuted"
+        }
+        ChatCommand::Kick(_) => {
+            kick_player(
+                server,
+                sessions,
+                room_list,
+                bank,
+                play_list,
+                room_delta_ew,
+                room_id,
+                target.player_client_id,
+            )?;
+            "kicked"
+        }
+    };
+    info!("Room {}: {} was {}", room_id, target.player_name, action);
+    // 隐藏旁观者名字时参与者看到的是旁观者的代称
+    let room = room_list.get(room_id).unwrap();
+    let hidden_name = hidden_player(sessions, &target).player_name;
+    for player in room.players.iter() {
+        let target_name =
+            if room.spectators.hide_names && player.player_role == PlayerRole::Participant {
+                &hidden_name
+            } else {
+                &target.player_name
+            };
+        let message = chat_line(
+            room,
+            None,
+            ChatScope::Table,
+            format!("{} was {}", target_name, action),
+        );
+        send_room_sync(server, player.player_client_id, &message);
+    }
+    Ok(())
+}
+
+fn chat_line(
+    room: &Room,
+    sender_name: Option<String>,
+    scope: ChatScope,
+    text: String,
+) -> Vec<Vec<u8>> {
+    encode_room_sync(&RoomSyncMessage::Chat(ChatLine {
+        room_id: room.room_id,
+        sender_name,
+        scope,
+        text,
+        timestamp: timestamp(),
+    }))
+}
+
+// 发送给该频道可见的房间内玩家
+fn send_line(
+    server: &mut RenetServer,
+    room: &Room,
+    sender_name: Option<String>,
+    scope: ChatScope,
+    text: String,
+) {
+    let message = chat_line(room, sender_name, scope, text);
+    for player in room.players.iter() {
+        if scope == ChatScope::Table || player.player_role == PlayerRole::Spectator {
+            send_room_sync(server, player.player_client_id, &message);
+        }
+    }
+}
+
+// 将屏蔽词替换为等长的*，不区分大小写
+fn filter_words(text: &str, banned_words: &[String]) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    for word in banned_words.iter() {
+        let word: Vec<char> = word.chars().flat_map(char::to_lowercase).collect();
+        if word.is_empty() {
+            continue;
+        }
+        let mut start = 0;
+        while start + word.len() <= chars.len() {
+            let matched = chars[start..start + word.len()]
+                .iter()
+                .zip(word.iter())
+                .all(|(c, w)| c.to_lowercase().eq(std::iter::once(*w)));
+            if matched {
+                chars[start..start + word.len()].fill('*');
+                start += word.len();
+            } else {
+                start += 1;
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
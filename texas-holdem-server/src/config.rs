@@ -0,0 +1,487 @@
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use texas_holdem_common::{
+    betting::BettingStructure, directory::MAX_SERVER_NAME_CHARS, lobby::is_valid_name,
+    tournament::MAX_TOURNAMENT_SEATS, variant::GameVariant,
+};
+
+// 未指定--config时尝试读取的配置文件
+const DEFAULT_CONFIG_PATH: &str = "texas-holdem-server.toml";
+
+// 命令行参数，优先级高于配置文件
+#[derive(Debug, Parser)]
+#[command(version, about = "Texas Holdem server")]
+pub struct Cli {
+    /// 配置文件路径
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// 监听地址
+    #[arg(long)]
+    pub bind_addr: Option<SocketAddr>,
+    /// 对外公布的地址（客户端连接的地址）
+    #[arg(long)]
+    pub public_addr: Option<SocketAddr>,
+    /// 最大客户端数
+    #[arg(long)]
+    pub max_clients: Option<usize>,
+    /// 新房间默认小盲注
+    #[arg(long)]
+    pub small_blind: Option<u32>,
+    /// 新房间默认大盲注
+    #[arg(long)]
+    pub big_blind: Option<u32>,
+    /// 现金桌入座时默认买入的筹码
+    #[arg(long)]
+    pub starting_chips: Option<u32>,
+    /// 新账户的初始资金
+    #[arg(long)]
+    pub initial_bankroll: Option<u64>,
+    /// 坐出超过该圈数后自动离开座位，0表示不限
+    #[arg(long)]
+    pub max_sit_out_orbits: Option<u32>,
+    /// 房间完整快照的重发间隔（秒），0表示不重发
+    #[arg(long)]
+    pub resync_interval: Option<f32>,
+    /// 数据持久化目录
+    #[arg(long)]
+    pub persistence_path: Option<PathBuf>,
+    /// 日志级别（trace/debug/info/warn/error）
+    #[arg(long)]
+    pub log_level: Option<String>,
+    /// 目录服务地址，设置后定期登记本服务端和房间列表
+    #[arg(long)]
+    pub directory_addr: Option<SocketAddr>,
+    /// 在目录中的名称，默认为对外公布的地址
+    #[arg(long)]
+    pub server_name: Option<String>,
+    /// 收到退出信号后等待玩家离开的最长时间（秒），0表示一直等待
+    #[arg(long)]
+    pub drain_timeout: Option<f32>,
+    /// 按账本核对持久化目录下的账户余额后退出
+    #[arg(long)]
+    pub reconcile: bool,
+    /// 按房间和时间段汇总持久化目录下账本中的抽水后退出
+    #[arg(long, value_enum)]
+    pub rake_report: Option<ReportPeriod>,
+}
+
+// 抽水报表的时间段（UTC）
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportPeriod {
+    Hour,
+    Day,
+}
+
+impl ReportPeriod {
+    pub fn millis(&self) -> u64 {
+        match self {
+            ReportPeriod::Hour => 3_600_000,
+            ReportPeriod::Day => 86_400_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Resource)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub network: NetworkSettings,
+    pub stakes: StakesSettings,
+    pub seat: SeatSettings,
+    pub bank: BankSettings,
+    pub sync: SyncSettings,
+    pub chat: ChatSettings,
+    pub access: AccessSettings,
+    pub quick_seat: QuickSeatSettings,
+    pub directory: DirectorySettings,
+    pub persistence_path: Option<PathBuf>,
+    pub log_level: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub bind_addr: SocketAddr,
+    // 未设置时使用实际监听的地址（bind_addr端口为0时由系统分配）
+    pub public_addr: Option<SocketAddr>,
+    pub max_clients: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StakesSettings {
+    pub small_blind: u32,
+    pub big_blind: u32,
+    // 现金桌入座时默认买入的筹码
+    pub starting_chips: u32,
+    // 买入或补充后桌上筹码的范围
+    pub min_buy_in: u32,
+    pub max_buy_in: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BankSettings {
+    // 新账户的初始资金
+    pub initial_bankroll: u64,
+    // 每个地址在new_account_window_secs秒内最多开户的数量，0表示不限
+    pub max_new_accounts: usize,
+    pub new_account_window_secs: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SeatSettings {
+    // 坐出超过该圈数（错过大盲注的次数）后自动离开座位，0表示不限
+    pub max_sit_out_orbits: u32,
+    // 现金桌的座位数
+    pub table_seats: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    // 房间完整快照的重发间隔（秒），0表示不重发，客户端发现增量缺失时会自行请求快照
+    pub resync_interval: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    // 单条消息的最大字符数
+    pub max_length: usize,
+    // 每个客户端在rate_limit_secs秒内最多发送的消息数，0表示不限
+    pub rate_limit_messages: usize,
+    pub rate_limit_secs: f64,
+    // 替换为*的词，不区分大小写
+    pub banned_words: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccessSettings {
+    // 邀请码的有效期（秒）
+    pub invite_valid_secs: u32,
+    // 每个地址在attempt_window_secs秒内最多输错密码或邀请码的次数，0表示不限
+    pub max_failed_attempts: usize,
+    pub attempt_window_secs: f64,
+    // 房间密码摘要（PBKDF2-HMAC-SHA256）的迭代次数，越大越难离线穷举，但创建和进入房间越慢
+    pub password_hash_iterations: u32,
+    // 断开后会话令牌的有效期（秒），期间凭令牌重新连接恢复身份
+    pub session_ttl_secs: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuickSeatSettings {
+    // 快速入座找不到空座位时按模板新开现金桌，为空时不新开
+    pub templates: Vec<TableTemplate>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableTemplate {
+    pub variant: GameVariant,
+    // 未设置时使用该玩法常用的下注结构
+    #[serde(default)]
+    pub betting: Option<BettingStructure>,
+    pub small_blind: u32,
+    pub big_blind: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DirectorySettings {
+    // 目录服务地址，未设置时不登记
+    pub addr: Option<SocketAddr>,
+    // 在目录中唯一的名称，未设置时使用对外公布的地址
+    pub server_name: Option<String>,
+    // 上报状态的间隔（秒），应小于目录服务的超时时间
+    pub report_interval: f32,
+    // 收到退出信号后等待玩家离开的最长时间（秒），0表示一直等待
+    pub drain_timeout: f32,
+    // 目录服务的登记密钥
+    pub secret: Option<String>,
+}
+
+impl TableTemplate {
+    pub fn betting(&self) -> BettingStructure {
+        self.betting
+            .unwrap_or_else(|| self.variant.default_betting())
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            network: NetworkSettings::default(),
+            stakes: StakesSettings::default(),
+            seat: SeatSettings::default(),
+            bank: BankSettings::default(),
+            sync: SyncSettings::default(),
+            chat: ChatSettings::default(),
+            access: AccessSettings::default(),
+            quick_seat: QuickSeatSettings::default(),
+            directory: DirectorySettings::default(),
+            persistence_path: None,
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            bind_addr: "127.0.0.1:5000".parse().unwrap(),
+            public_addr: None,
+            max_clients: 64,
+        }
+    }
+}
+
+impl Default for StakesSettings {
+    fn default() -> Self {
+        StakesSettings {
+            small_blind: 1,
+            big_blind: 2,
+            starting_chips: 1000,
+            min_buy_in: 40,
+            max_buy_in: 1000,
+        }
+    }
+}
+
+impl Default for BankSettings {
+    fn default() -> Self {
+        BankSettings {
+            initial_bankroll: 10000,
+            max_new_accounts: 3,
+            new_account_window_secs: 3600.0,
+        }
+    }
+}
+
+impl Default for SeatSettings {
+    fn default() -> Self {
+        SeatSettings {
+            max_sit_out_orbits: 3,
+            table_seats: 9,
+        }
+    }
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        SyncSettings {
+            resync_interval: 0.0,
+        }
+    }
+}
+
+impl Default for AccessSettings {
+    fn default() -> Self {
+        AccessSettings {
+            invite_valid_secs: 3600,
+            max_failed_attempts: 5,
+            attempt_window_secs: 60.0,
+            password_hash_iterations: 100_000,
+            session_ttl_secs: 86400,
+        }
+    }
+}
+
+impl Default for QuickSeatSettings {
+    fn default() -> Self {
+        QuickSeatSettings {
+            templates: GameVariant::ALL
+                .into_iter()
+                .map(|variant| TableTemplate {
+                    variant,
+                    betting: None,
+                    small_blind: 1,
+                    big_blind: 2,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for DirectorySettings {
+    fn default() -> Self {
+        DirectorySettings {
+            addr: None,
+            server_name: None,
+            report_interval: 1.0,
+            drain_timeout: 0.0,
+            secret: None,
+        }
+    }
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        ChatSettings {
+            max_length: 200,
+            rate_limit_messages: 5,
+            rate_limit_secs: 10.0,
+            banned_words: Vec::new(),
+        }
+    }
+}
+
+impl ServerSettings {
+    // 读取配置文件并应用命令行参数
+    pub fn load(cli: Cli) -> Result<Self, String> {
+        let mut settings = match cli.config.as_deref() {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => ServerSettings::default(),
+        };
+
+        if let Some(bind_addr) = cli.bind_addr {
+            settings.network.bind_addr = bind_addr;
+        }
+        if let Some(public_addr) = cli.public_addr {
+            settings.network.public_addr = Some(public_addr);
+        }
+        if let Some(max_clients) = cli.max_clients {
+            settings.network.max_clients = max_clients;
+        }
+        if let Some(small_blind) = cli.small_blind {
+            settings.stakes.small_blind = small_blind;
+        }
+        if let Some(big_blind) = cli.big_blind {
+            settings.stakes.big_blind = big_blind;
+        }
+        if let Some(starting_chips) = cli.starting_chips {
+            settings.stakes.starting_chips = starting_chips;
+        }
+        if let Some(initial_bankroll) = cli.initial_bankroll {
+            settings.bank.initial_bankroll = initial_bankroll;
+        }
+        if let Some(max_sit_out_orbits) = cli.max_sit_out_orbits {
+            settings.seat.max_sit_out_orbits = max_sit_out_orbits;
+        }
+        if let Some(resync_interval) = cli.resync_interval {
+            settings.sync.resync_interval = resync_interval;
+        }
+        if let Some(persistence_path) = cli.persistence_path {
+            settings.persistence_path = Some(persistence_path);
+        }
+        if let Some(log_level) = cli.log_level {
+            settings.log_level = log_level;
+        }
+        if let Some(directory_addr) = cli.directory_addr {
+            settings.directory.addr = Some(directory_addr);
+        }
+        if let Some(server_name) = cli.server_name {
+            settings.directory.server_name = Some(server_name);
+        }
+        if let Some(drain_timeout) = cli.drain_timeout {
+            settings.directory.drain_timeout = drain_timeout;
+        }
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.network.max_clients == 0 {
+            return Err("max_clients must be greater than 0".to_string());
+        }
+        if self.stakes.small_blind == 0 || self.stakes.big_blind < self.stakes.small_blind {
+            return Err(format!(
+                "Invalid stakes {}/{}",
+                self.stakes.small_blind, self.stakes.big_blind
+            ));
+        }
+        if self.stakes.min_buy_in < self.stakes.big_blind {
+            return Err(format!(
+                "min_buy_in {} must not be less than big_blind {}",
+                self.stakes.min_buy_in, self.stakes.big_blind
+            ));
+        }
+        if self.stakes.starting_chips < self.stakes.min_buy_in
+            || self.stakes.starting_chips > self.stakes.max_buy_in
+        {
+            return Err(format!(
+                "starting_chips {} must be between min_buy_in {} and max_buy_in {}",
+                self.stakes.starting_chips, self.stakes.min_buy_in, self.stakes.max_buy_in
+            ));
+        }
+        if self.seat.table_seats < 3 || self.seat.table_seats > MAX_TOURNAMENT_SEATS {
+            return Err(format!(
+                "table_seats must be between 3 and {}",
+                MAX_TOURNAMENT_SEATS
+            ));
+        }
+        if self.chat.max_length == 0 {
+            return Err("chat max_length must be greater than 0".to_string());
+        }
+        if self.access.invite_valid_secs == 0 {
+            return Err("invite_valid_secs must be greater than 0".to_string());
+        }
+        if self.access.password_hash_iterations == 0 {
+            return Err("password_hash_iterations must be greater than 0".to_string());
+        }
+        if self.access.session_ttl_secs == 0 {
+            return Err("session_ttl_secs must be greater than 0".to_string());
+        }
+        // 入座买入按全局设置，模板的大盲注不能超过最低买入
+        for template in self.quick_seat.templates.iter() {
+            if template.small_blind == 0
+                || template.big_blind < template.small_blind
+                || template.big_blind > self.stakes.min_buy_in
+            {
+                return Err(format!(
+                    "Invalid quick seat template {:?} {}/{}",
+                    template.variant, template.small_blind, template.big_blind
+                ));
+            }
+        }
+        if self.directory.report_interval <= 0.0 || self.directory.drain_timeout < 0.0 {
+            return Err("Invalid directory report_interval or drain_timeout".to_string());
+        }
+        if self
+            .directory
+            .server_name
+            .as_ref()
+            .is_some_and(|name| !is_valid_name(name, MAX_SERVER_NAME_CHARS))
+        {
+            return Err(format!(
+                "server_name must be 1 to {} characters without control characters",
+                MAX_SERVER_NAME_CHARS
+            ));
+        }
+        self.log_level()?;
+        Ok(())
+    }
+
+    // 在目录中的名称
+    pub fn server_name(&self, public_addr: SocketAddr) -> String {
+        self.directory
+            .server_name
+            .clone()
+            .unwrap_or_else(|| public_addr.to_string())
+    }
+
+    pub fn log_level(&self) -> Result<bevy::log::Level, String> {
+        self.log_level
+            .parse()
+            .map_err(|_| format!("Invalid log level: {}", self.log_level))
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_renet::renet::{ClientAuthentication, RenetClient, RenetServer};
+use texas_holdem_common::{
+    channel::{QuickSeatMessage, RequestError, QUICK_SEAT_CHANNEL_ID},
+    directory::{
+        directory_connection_config, ServerStatus, DIRECTORY_PROTOCOL_ID, SERVER_STATUS_CHANNEL_ID,
+    },
+    PROTOCOL_VERSION,
+};
+
+use crate::{
+    bank::Bank, config::ServerSettings, lobby::room_dto, room::RoomList, tournament::TournamentList,
+};
+
+// 连接目录服务的客户端，与RenetServer在同一个App中，需要手动更新
+#[derive(Resource, Deref, DerefMut)]
+pub struct DirectoryClient(pub RenetClient);
+
+pub fn new_directory_client(directory_addr: SocketAddr) -> io::Result<DirectoryClient> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let authentication = ClientAuthentication::Unsecure {
+        client_id: rand::random(),
+        protocol_id: DIRECTORY_PROTOCOL_ID,
+        server_addr: directory_addr,
+        user_data: Some(PROTOCOL_VERSION.to_user_data()),
+    };
+    let client = RenetClient::new(
+        current_time,
+        socket,
+        directory_connection_config(),
+        authentication,
+    )
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(DirectoryClient(client))
+}
+
+// 退出信号，由信号处理线程置位
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ShutdownSignal(pub Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    // 第一次收到SIGINT/SIGTERM时开始排空，再次收到时立即退出
+    pub fn install_handler(&self) -> Result<(), ctrlc::Error> {
+        let signal = self.clone();
+        ctrlc::set_handler(move || {
+            if signal.is_triggered() {
+                process::exit(1);
+            }
+            eprintln!("Draining, press Ctrl-C again to exit immediately");
+            signal.trigger();
+        })
+    }
+}
+
+// 排空：不再接受新房间和新玩家，房间内的玩家都离开后退出
+#[derive(Debug, Default, Resource)]
+pub struct Drain {
+    pub started_at: Option<f64>,
+}
+
+impl Drain {
+    pub fn is_draining(&self) -> bool {
+        self.started_at.is_some()
+    }
+}
+
+pub fn update_directory_client(
+    client: Option<ResMut<DirectoryClient>>,
+    settings: Res<ServerSettings>,
+    drain: Res<Drain>,
+    time: Res<Time>,
+) {
+    let Some(mut client) = client else {
+        return;
+    };
+    if let Err(e) = client.update(time.delta()) {
+        warn!("Directory connection error: {}", e);
+    }
+    // 目录服务重启后重新连接，排空中不再登记
+    if client.disconnected().is_some() && !drain.is_draining() {
+        if let Some(directory_addr) = settings.directory.addr {
+            match new_directory_client(directory_addr) {
+                Ok(new_client) => *client = new_client,
+                Err(e) => warn!("Failed to reconnect directory {}: {}", directory_addr, e),
+            }
+        }
+    }
+}
+
+pub fn send_directory_packets(client: Option<ResMut<DirectoryClient>>) {
+    if let Some(mut client) = client {
+        if let Err(e) = client.send_packets() {
+            warn!("Failed to send packets to directory: {}", e);
+        }
+    }
+}
+
+// 定期上报状态和房间列表，开始排空时立即上报
+#[allow(clippy::too_many_arguments)]
+pub fn report_to_directory(
+    client: Option<ResMut<DirectoryClient>>,
+    server: Res<RenetServer>,
+    settings: Res<ServerSettings>,
+    bank: Res<Bank>,
+    room_list: Res<RoomList>,
+    tournament_list: Res<TournamentList>,
+    drain: Res<Drain>,
+    time: Res<Time>,
+    mut last_report: Local<Option<f64>>,
+) {
+    let Some(mut client) = client else {
+        return;
+    };
+    let now = time.elapsed_seconds_f64();
+    let due =
+        last_report.is_none_or(|last| now - last >= settings.directory.report_interval as f64);
+    if !client.is_connected()
+        || !(due || drain.is_changed())
+        || !client.can_send_message(SERVER_STATUS_CHANNEL_ID)
+    {
+        return;
+    }
+    let public_addr = server.addr();
+    let status = ServerStatus {
+        server_name: settings.server_name(public_addr),
+        public_addr,
+        protocol_version: PROTOCOL_VERSION,
+        draining: drain.is_draining(),
+        client_count: server.connected_clients() as u32,
+        max_clients: server.max_clients() as u32,
+        rooms: room_list
+            .iter()
+            .map(|room| room_dto(room, &settings, &bank, &tournament_list))
+            .collect(),
+        secret: settings.directory.secret.clone(),
+    };
+    client.send_message(
+        SERVER_STATUS_CHANNEL_ID,
+        serde_json::to_vec(&status).unwrap(),
+    );
+    *last_report = Some(now);
+}
+
+// 收到退出信号后开始排空，通知等候队列中的玩家
+pub fn begin_drain(
+    mut server: ResMut<RenetServer>,
+    signal: Res<ShutdownSignal>,
+    mut drain: ResMut<Drain>,
+    mut room_list: ResMut<RoomList>,
+    time: Res<Time>,
+) {
+    if drain.is_draining() || !signal.is_triggered() {
+        return;
+    }
+    info!("Draining: no new rooms or players accepted");
+    drain.started_at = Some(time.elapsed_seconds_f64());
+    for room in room_list.iter_mut() {
+        for waiting in room.waiting_list.drain(..) {
+            let message = QuickSeatMessage {
+                request_id: waiting.request_id,
+                player_name: waiting.player_name,
+                variant: room.variant,
+                min_big_blind: None,
+                max_big_blind: None,
+                cancel: false,
+                room_id: room.room_id,
+                seated: false,
+                waiting_position: 0,
+                error: Some(RequestError::ServerDraining),
+            };
+            server.send_message(
+                waiting.client_id,
+                QUICK_SEAT_CHANNEL_ID,
+                serde_json::to_vec(&message).unwrap(),
+            );
+        }
+    }
+}
+
+// 所有房间都没有玩家或等待超时后退出
+pub fn exit_when_drained(
+    settings: Res<ServerSettings>,
+    drain: Res<Drain>,
+    room_list: Res<RoomList>,
+    client: Option<ResMut<DirectoryClient>>,
+    time: Res<Time>,
+    mut app_exit_ew: EventWriter<AppExit>,
+) {
+    let Some(started_at) = drain.started_at else {
+        return;
+    };
+    let drain_timeout = settings.directory.drain_timeout;
+    let timed_out =
+        drain_timeout > 0.0 && time.elapsed_seconds_f64() - started_at >= drain_timeout as f64;
+    let empty = room_list.iter().all(|room| room.players.is_empty());
+    if !empty && !timed_out {
+        return;
+    }
+    if timed_out && !empty {
+        warn!("Drain timed out with players still in rooms");
+    }
+    info!("Drained, exiting");
+    // 立即通知目录服务移除该服务端
+    if let Some(mut client) = client {
+        client.disconnect();
+    }
+    app_exit_ew.send(AppExit);
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use texas_holdem_common::{channel::ShownHand, util::timestamp, variant::GameVariant, Card};
+
+use crate::play::Play;
+
+// 持久化目录下的牌局记录文件，每行一局
+const HISTORY_FILE: &str = "hands.jsonl";
+
+// 一局结束时的记录，发两次时两组公共牌都记录下来
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandRecord {
+    pub timestamp: u64,
+    pub room_id: u64,
+    pub play_id: u64,
+    pub variant: GameVariant,
+    pub dealer_name: Option<String>,
+    pub board: Vec<Card>,
+    pub second_board: Option<Vec<Card>>,
+    // 其他玩家都弃牌时不亮牌
+    pub shown_hands: Vec<ShownHand>,
+    pub winnings: HashMap<String, u32>,
+    pub rake: u32,
+    // 本局结束后各玩家的筹码
+    pub stacks: Vec<(String, u32)>,
+}
+
+impl HandRecord {
+    pub fn new(play: &Play) -> Self {
+        let (second_board, shown_hands, winnings, rake) = match play.showdown.clone() {
+            Some(showdown) => (
+                showdown.second_board,
+                showdown.shown_hands,
+                showdown.winnings,
+                showdown.rake,
+            ),
+            None => (None, Vec::new(), HashMap::new(), 0),
+        };
+        HandRecord {
+            timestamp: timestamp(),
+            room_id: play.room_id,
+            play_id: play.play_id,
+            variant: play.variant,
+            dealer_name: play.dealer_name.clone(),
+            board: play.board_cards(),
+            second_board,
+            shown_hands,
+            winnings,
+            rake,
+            stacks: play
+                .participants
+                .iter()
+                .map(|participant| (participant.player_name.clone(), participant.chips))
+                .collect(),
+        }
+    }
+}
+
+// 只追加的牌局记录
+#[derive(Debug, Resource)]
+pub struct HandHistory {
+    path: Option<PathBuf>,
+    // 未设置持久化目录时保存在内存中
+    records: Vec<HandRecord>,
+}
+
+impl HandHistory {
+    pub fn new(persistence_path: Option<&Path>) -> Self {
+        HandHistory {
+            path: persistence_path.map(|persistence_path| persistence_path.join(HISTORY_FILE)),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn records(&self) -> Result<Vec<HandRecord>, String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(self.records.clone());
+        };
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read(path)
+            .map_err(|e| format!("Failed to read hand history {}: {}", path.display(), e))?;
+        content
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_slice(line)
+                    .map_err(|e| format!("Failed to parse hand history {}: {}", path.display(), e))
+            })
+            .collect()
+    }
+
+    pub fn append(&mut self, record: HandRecord) -> io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            self.records.push(record);
+            return Ok(());
+        };
+        let mut content = serde_json::to_vec(&record).unwrap();
+        content.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(&content)
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use texas_holdem_common::util::timestamp;
+
+// 持久化目录下的账本文件，每行一条分录
+const LEDGER_FILE: &str = "ledger.jsonl";
+
+// 记账科目
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LedgerAccount {
+    // 发放初始资金的来源，余额为所有科目之和的相反数
+    House,
+    // 玩家账户余额，按账户id区分
+    Bankroll(u64),
+    // 玩家在现金桌上的筹码
+    Stack { room_id: u64, player_name: String },
+    // 一局的底池，对局结束时必须为0
+    Pot { room_id: u64, play_id: u64 },
+    // 现金桌累计抽水
+    Rake { room_id: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionKind {
+    // 开户发放初始资金
+    Issue,
+    BuyIn,
+    CashOut,
+    // 大小盲注、补交的盲注和跨注
+    Blind,
+    Ante,
+    Bet,
+    // 赢得底池
+    Award,
+    // 从底池抽水
+    Rake,
+}
+
+// 一笔复式记账分录，从from转出的数量等于转入to的数量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerTransaction {
+    pub kind: TransactionKind,
+    pub room_id: Option<u64>,
+    pub play_id: Option<u64>,
+    pub player_name: Option<String>,
+    pub from: LedgerAccount,
+    pub to: LedgerAccount,
+    pub amount: u64,
+}
+
+// 账本中的一行，seq为所属的账户事务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerRecord {
+    pub seq: u64,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub transaction: LedgerTransaction,
+}
+
+// 只追加的账本，与账户文件同属一个事务：先写账本再写账户文件，账户文件的seq之后的分录视为未提交
+#[derive(Debug)]
+pub struct Ledger {
+    path: Option<PathBuf>,
+    // 未设置持久化目录时保存在内存中
+    records: Vec<LedgerRecord>,
+}
+
+impl Ledger {
+    pub fn new(persistence_path: Option<&Path>) -> Self {
+        Ledger {
+            path: persistence_path.map(|persistence_path| persistence_path.join(LEDGER_FILE)),
+            records: Vec::new(),
+        }
+    }
+
+    // 读取已提交的分录，末尾写了一半的行视为未提交
+    pub fn records(&self, committed_seq: u64) -> Result<Vec<LedgerRecord>, String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(self.records.clone());
+        };
+        let (records, _) = read_committed(path, committed_seq)?;
+        Ok(records)
+    }
+
+    // 删除未提交的分录，避免之后的事务复用同一个seq
+    pub fn discard_uncommitted(&mut self, committed_seq: u64) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let (_, committed_len) = read_committed(path, committed_seq)?;
+        if fs::metadata(path).map_or(0, |metadata| metadata.len()) > committed_len {
+            truncate(path, committed_len)
+                .map_err(|e| format!("Failed to truncate ledger {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    // 追加一个事务的所有分录并刷盘，返回追加前的文件长度用于回滚
+    pub fn append(&mut self, seq: u64, transactions: &[LedgerTransaction]) -> io::Result<u64> {
+        let timestamp = timestamp();
+        let records = transactions.iter().map(|transaction| LedgerRecord {
+            seq,
+            timestamp,
+            transaction: transaction.clone(),
+        });
+        let Some(path) = self.path.as_ref() else {
+            let len = self.records.len() as u64;
+            self.records.extend(records);
+            return Ok(len);
+        };
+        let mut content = Vec::new();
+        for record in records {
+            content.extend(serde_json::to_vec(&record).unwrap());
+            content.push(b'\n');
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        if let Err(e) = file.write_all(&content).and_then(|_| file.sync_all()) {
+            let _ = file.set_len(len);
+            return Err(e);
+        }
+        Ok(len)
+    }
+
+    // 账户文件写入失败时撤销刚追加的分录
+    pub fn rollback(&mut self, len: u64) {
+        match self.path.as_ref() {
+            Some(path) => {
+                let _ = truncate(path, len);
+            }
+            None => self.records.truncate(len as usize),
+        }
+    }
+}
+
+// 按分录计算各科目的余额
+pub fn balances(records: &[LedgerRecord]) -> BTreeMap<LedgerAccount, i64> {
+    let mut balances = BTreeMap::new();
+    for record in records.iter() {
+        apply(&mut balances, &record.transaction);
+    }
+    balances
+}
+
+pub fn apply(balances: &mut BTreeMap<LedgerAccount, i64>, transaction: &LedgerTransaction) {
+    *balances.entry(transaction.from.clone()).or_insert(0) -= transaction.amount as i64;
+    *balances.entry(transaction.to.clone()).or_insert(0) += transaction.amount as i64;
+}
+
+// 返回已提交的分录和它们在文件中占用的长度
+fn read_committed(path: &Path, committed_seq: u64) -> Result<(Vec<LedgerRecord>, u64), String> {
+    if !path.exists() {
+        return Ok((Vec::new(), 0));
+    }
+    let content =
+        fs::read(path).map_err(|e| format!("Failed to read ledger {}: {}", path.display(), e))?;
+    let mut records = Vec::new();
+    let mut committed_len = 0;
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+        // 没有换行符的最后一行是写了一半的分录
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        let record: LedgerRecord = serde_json::from_slice(line)
+            .map_err(|e| format!("Failed to parse ledger {}: {}", path.display(), e))?;
+        if record.seq > committed_seq {
+            break;
+        }
+        records.push(record);
+        committed_len += line.len() as u64;
+    }
+    Ok((records, committed_len))
+}
+
+fn truncate(path: &Path, len: u64) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.set_len(len)?;
+    file.sync_all()
+}
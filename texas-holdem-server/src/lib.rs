@@ -0,0 +1,123 @@
+use std::{io, net::UdpSocket, time::SystemTime};
+
+use access::FailedAttempts;
+use bank::handle_bank;
+use bevy::prelude::*;
+use bevy_renet::{
+    renet::{RenetServer, ServerAuthentication, ServerConfig},
+    RenetServerPlugin,
+};
+use chat::{handle_chat, ChatRateLimiter};
+use config::ServerSettings;
+use directory::{
+    begin_drain, exit_when_drained, report_to_directory, send_directory_packets,
+    update_directory_client, Drain, ShutdownSignal,
+};
+use lobby::{handle_create_room, handle_enter_room, handle_get_rooms};
+use network::{disconnect_rejected_clients, handle_events_system, PendingDisconnects, Sessions};
+use play::{
+    handle_player_action, handle_run_it_twice, process_play_round_end, process_play_round_start,
+    process_play_turn_timeout, start_new_play, PlayList,
+};
+use quick_seat::{handle_quick_seat, seat_waiting_players};
+use room::{
+    handle_room_admin, handle_room_sync_request, handle_set_room_state, handle_sit_out,
+    handle_switch_player_role, resync_rooms, sync_room_state, HoleCardsEvent, RoomDeltaEvent,
+    RoomList, RoomSnapshotEvent, TableChangedEvent,
+};
+use texas_holdem_common::{connection_config, PROTOCOL_ID};
+use tournament::{
+    advance_tournament_levels, balance_tournament_tables, start_full_tournaments, TournamentList,
+};
+
+pub mod access;
+pub mod bank;
+pub mod chat;
+pub mod config;
+pub mod directory;
+pub mod history;
+pub mod ledger;
+pub mod lobby;
+pub mod network;
+pub mod play;
+pub mod quick_seat;
+pub mod room;
+pub mod spectator;
+pub mod tournament;
+
+pub fn new_renet_server(settings: &ServerSettings) -> io::Result<RenetServer> {
+    let socket = UdpSocket::bind(settings.network.bind_addr)?;
+    let public_addr = match settings.network.public_addr {
+        Some(public_addr) => public_addr,
+        None => socket.local_addr()?,
+    };
+    let server_config = ServerConfig::new(
+        settings.network.max_clients,
+        PROTOCOL_ID,
+        public_addr,
+        ServerAuthentication::Unsecure,
+    );
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    RenetServer::new(current_time, server_config, connection_config(), socket)
+}
+
+// 服务端逻辑，需要先插入ServerSettings、RenetServer、Bank和HandHistory资源，登记到目录时还需插入DirectoryClient
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RenetServerPlugin::default())
+            .add_event::<RoomDeltaEvent>()
+            .add_event::<RoomSnapshotEvent>()
+            .add_event::<HoleCardsEvent>()
+            .add_event::<TableChangedEvent>()
+            .insert_resource(RoomList::default())
+            .insert_resource(PlayList::default())
+            .insert_resource(TournamentList::default())
+            .insert_resource(PendingDisconnects::default())
+            .insert_resource(Sessions::default())
+            .insert_resource(ChatRateLimiter::default())
+            .insert_resource(FailedAttempts::default())
+            .insert_resource(Drain::default())
+            .init_resource::<ShutdownSignal>()
+            // 目录客户端与RenetServer在同一个App中，由RenetClientPlugin之外的系统更新
+            .add_system(update_directory_client.in_base_set(CoreSet::PreUpdate))
+            .add_system(send_directory_packets.in_base_set(CoreSet::PostUpdate))
+            .add_systems((
+                begin_drain,
+                report_to_directory.after(begin_drain),
+                exit_when_drained.after(begin_drain),
+            ))
+            .add_systems((
+                handle_get_rooms,
+                handle_create_room,
+                handle_enter_room,
+                handle_switch_player_role,
+                handle_sit_out,
+                handle_bank,
+                handle_chat,
+                handle_set_room_state,
+                handle_room_admin,
+                handle_room_sync_request,
+                sync_room_state,
+                resync_rooms,
+                handle_events_system,
+                disconnect_rejected_clients,
+            ))
+            .add_systems((handle_quick_seat, seat_waiting_players))
+            .add_systems((
+                start_full_tournaments,
+                advance_tournament_levels,
+                // 先调整各桌人数再开局
+                balance_tournament_tables.before(start_new_play),
+                start_new_play,
+                process_play_round_start,
+                handle_player_action,
+                handle_run_it_twice,
+                process_play_turn_timeout,
+                process_play_round_end,
+            ));
+    }
+}
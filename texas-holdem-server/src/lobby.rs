@@ -1,32 +1,56 @@
-use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_renet::renet::RenetServer;
 use texas_holdem_common::{
     channel::{
-        CreateRoomMessage, EnterRoomMessage, GetRoomsMessage, CREATE_ROOM_CHANNEL_ID,
-        ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID,
+        CreateRoomMessage, EnterRoomMessage, GetRoomsMessage, RequestError, RoomDelta,
+        CREATE_ROOM_CHANNEL_ID, ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID,
     },
-    util::timestamp,
-    Player, PlayerRole, RoomDTO, RoomState,
+    lobby::{is_valid_name, paginate, MAX_PLAYER_NAME_CHARS, MAX_ROOM_NAME_CHARS},
+    tournament::BlindLevel,
+    MissedBlinds, Player, PlayerRole, RoomDTO, RoomState,
 };
 
-use crate::room::{Room, RoomList};
+use crate::{
+    access::{FailedAttempts, PasswordHash},
+    bank::{self, Bank},
+    config::ServerSettings,
+    directory::Drain,
+    network::Sessions,
+    play::PlayList,
+    room::{Room, RoomDeltaEvent, RoomList, RoomSnapshotEvent},
+    tournament::TournamentList,
+};
 
-pub fn handle_get_rooms(mut server: ResMut<RenetServer>, room_list: Res<RoomList>) {
-    for client_id in server.clients_id().into_iter() {
+pub fn handle_get_rooms(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    bank: Res<Bank>,
+    room_list: Res<RoomList>,
+    tournament_list: Res<TournamentList>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, GET_ROOMS_CHANNEL_ID) {
             if let Ok(mut message) = serde_json::from_slice::<GetRoomsMessage>(&message) {
                 info!("Received get rooms message: {:?}", message);
-                message.rooms = room_list
-                    .0
+                message.filter.truncate_search();
+                let rooms = room_list
                     .iter()
-                    .map(|room| RoomDTO {
-                        room_id: room.room_id,
-                        room_name: room.room_name.clone(),
-                        room_state: room.room_state,
-                        owner_name: room.owner_name.clone(),
-                        player_count: room.players.len() as u32,
-                    })
+                    .map(|room| room_dto(room, &settings, &bank, &tournament_list))
                     .collect();
+                // 分页返回，避免响应超过单个包的大小
+                (message.rooms, message.total) = paginate(
+                    rooms,
+                    &message.filter,
+                    message.sort,
+                    message.page,
+                    message.page_size,
+                );
                 server.send_message(
                     client_id,
                     GET_ROOMS_CHANNEL_ID,
@@ -37,25 +61,162 @@ pub fn handle_get_rooms(mut server: ResMut<RenetServer>, room_list: Res<RoomList
     }
 }
 
-pub fn handle_create_room(mut server: ResMut<RenetServer>, mut room_list: ResMut<RoomList>) {
-    for client_id in server.clients_id().into_iter() {
+pub fn room_dto(
+    room: &Room,
+    settings: &ServerSettings,
+    bank: &Bank,
+    tournament_list: &TournamentList,
+) -> RoomDTO {
+    let tournament = tournament_list.of_room(room);
+    // 报名中显示报名上限，开始后显示每桌座位数
+    let tournament_seats = tournament.map(|tournament| {
+        if room.room_state == RoomState::Registering {
+            tournament.config.capacity()
+        } else {
+            tournament.config.seats
+        }
+    });
+    RoomDTO {
+        room_id: room.room_id,
+        room_name: room.room_name.clone(),
+        room_state: room.room_state,
+        owner_name: room.owner_name.clone(),
+        variant: room.variant,
+        betting: room.betting,
+        rake: room.rake,
+        rake_collected: bank.rake_of(room.room_id),
+        small_blind: room.small_blind,
+        big_blind: room.big_blind,
+        player_count: room.players.len() as u32,
+        seats: tournament_seats.unwrap_or(settings.seat.table_seats),
+        participant_count: room.participant_count() as u32,
+        locked: room.locked,
+        is_private: room.is_private(),
+        tournament_seats,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_create_room(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    play_list: Res<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+    drain: Res<Drain>,
+    time: Res<Time>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, CREATE_ROOM_CHANNEL_ID) {
             if let Ok(mut message) = serde_json::from_slice::<CreateRoomMessage>(&message) {
-                info!("Received create room message: {:?}", message);
-                message.room_id = timestamp();
-                room_list.0.push(Room {
+                // 不记录密码
+                info!(
+                    "Received create room message: {} from {}",
+                    message.room_name, message.player_name
+                );
+                if drain.is_draining() {
+                    message.error = Some(RequestError::ServerDraining);
+                } else if tournament_list.is_playing(&room_list, client_id) {
+                    // 参赛玩家不能离开锦标赛的牌桌
+                    message.error = Some(RequestError::RegistrationClosed);
+                } else if !is_valid_name(&message.room_name, MAX_ROOM_NAME_CHARS)
+                    || !is_valid_name(&message.player_name, MAX_PLAYER_NAME_CHARS)
+                {
+                    message.error = Some(RequestError::InvalidName);
+                } else if let Some(Err(error)) =
+                    message.tournament.as_ref().map(|config| config.validate())
+                {
+                    warn!("Invalid tournament config: {}", error);
+                    message.error = Some(RequestError::InvalidTournament);
+                } else if let Err(error) = message.rake.validate() {
+                    warn!("Invalid rake config: {}", error);
+                    message.error = Some(RequestError::InvalidRake);
+                } else if message.tournament.is_some() && message.rake.is_enabled() {
+                    // 锦标赛的奖池不抽水
+                    message.error = Some(RequestError::InvalidRake);
+                } else if let Err(error) = message.spectators.validate() {
+                    warn!("Invalid spectator config: {}", error);
+                    message.error = Some(RequestError::InvalidSpectatorSettings);
+                } else if let Err(error) =
+                    bank::open_account(&mut bank, &sessions, client_id, time.elapsed_seconds_f64())
+                {
+                    message.error = Some(error);
+                }
+                if message.error.is_some() {
+                    server.send_message(
+                        client_id,
+                        CREATE_ROOM_CHANNEL_ID,
+                        serde_json::to_vec(&message).unwrap(),
+                    );
+                    continue;
+                }
+                message.room_id = room_list.next_room_id();
+                // 锦标赛房间先报名，使用第一级盲注
+                let (room_state, blind_level) = match &message.tournament {
+                    Some(config) => (RoomState::Registering, config.blind_level(0)),
+                    None => (
+                        RoomState::Waiting,
+                        BlindLevel {
+                            small_blind: settings.stakes.small_blind,
+                            big_blind: settings.stakes.big_blind,
+                        },
+                    ),
+                };
+                room_list.insert(Room {
                     room_id: message.room_id,
                     room_name: message.room_name.clone(),
-                    room_password: message.room_password.clone(),
-                    room_state: RoomState::Waiting,
+                    password: PasswordHash::new(
+                        &message.room_password,
+                        settings.access.password_hash_iterations,
+                    ),
+                    invites: HashMap::new(),
+                    room_state,
+                    variant: message.variant,
+                    betting: message.betting,
+                    run_it_twice: message.run_it_twice,
+                    forced_bets: message.forced_bets,
+                    rake: message.rake,
+                    spectators: message.spectators,
+                    spectator_feed: None,
                     owner_name: message.player_name.clone(),
-                    players: vec![Player {
-                        player_client_id: client_id,
-                        player_name: message.player_name.clone(),
-                        player_role: PlayerRole::Spectator,
-                        chips: 0,
-                    }],
+                    owner_user_id: sessions.user_id(client_id),
+                    locked: false,
+                    bans: HashMap::new(),
+                    small_blind: blind_level.small_blind,
+                    big_blind: blind_level.big_blind,
+                    players: Vec::new(),
                     last_dealer_name: None,
+                    tournament_id: message
+                        .tournament
+                        .clone()
+                        .map(|config| tournament_list.create(config, message.room_id)),
+                    sync_seq: 0,
+                    muted_names: HashSet::new(),
+                    waiting_list: VecDeque::new(),
+                });
+                let player = Player {
+                    player_client_id: client_id,
+                    player_name: message.player_name.clone(),
+                    player_role: PlayerRole::Spectator,
+                    chips: 0,
+                    sitting_out: false,
+                    missed_blinds: MissedBlinds::default(),
+                };
+                leave_room(
+                    &mut room_list,
+                    &mut bank,
+                    &play_list,
+                    client_id,
+                    &mut room_delta_ew,
+                );
+                room_list.add_player(message.room_id, player);
+                room_snapshot_ew.send(RoomSnapshotEvent {
+                    room_id: message.room_id,
+                    client_id,
                 });
                 server.send_message(
                     client_id,
@@ -67,40 +228,140 @@ pub fn handle_create_room(mut server: ResMut<RenetServer>, mut room_list: ResMut
     }
 }
 
-pub fn handle_enter_room(mut server: ResMut<RenetServer>, mut room_list: ResMut<RoomList>) {
-    for client_id in server.clients_id().into_iter() {
+#[allow(clippy::too_many_arguments)]
+pub fn handle_enter_room(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    play_list: Res<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    tournament_list: Res<TournamentList>,
+    mut failed_attempts: ResMut<FailedAttempts>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+    drain: Res<Drain>,
+    time: Res<Time>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, ENTER_ROOT_CHANNEL_ID) {
             if let Ok(mut message) = serde_json::from_slice::<EnterRoomMessage>(&message) {
-                info!("Received enter room message: {:?}", message);
-                if let Some(room) = room_list
-                    .0
-                    .iter_mut()
-                    .find(|room| room.room_id == message.room_id)
-                {
-                    // 同一房间内不允许重名
-                    if room.room_password == message.room_password
-                        && !room.contains_player(&message.player_name)
-                    {
-                        room.players.push(Player {
+                // 不记录密码和邀请码
+                info!(
+                    "Received enter room message: room {} from {}",
+                    message.room_id, message.player_name
+                );
+                let now = time.elapsed_seconds_f64();
+                let addr = sessions
+                    .get(client_id)
+                    .and_then(|session| session.addr)
+                    .map(|addr| addr.ip());
+                let by_invite = !message.invite_code.is_empty();
+                // 邀请码代替密码
+                let room = if drain.is_draining() {
+                    Err(RequestError::ServerDraining)
+                } else if tournament_list.is_playing(&room_list, client_id) {
+                    Err(RequestError::RegistrationClosed)
+                } else if !is_valid_name(&message.player_name, MAX_PLAYER_NAME_CHARS) {
+                    Err(RequestError::InvalidName)
+                } else if failed_attempts.is_blocked(&settings.access, addr, now) {
+                    Err(RequestError::TooManyAttempts)
+                } else if by_invite {
+                    room_list
+                        .iter()
+                        .find(|room| room.has_invite(&message.invite_code, now))
+                        .ok_or(RequestError::InvalidInvite)
+                } else {
+                    room_list
+                        .get(message.room_id)
+                        .ok_or(RequestError::RoomNotFound)
+                };
+                let result = room.and_then(|room| {
+                    if room.is_banned(&sessions, &message.player_name, client_id) {
+                        Err(RequestError::Banned)
+                    } else if room.locked {
+                        Err(RequestError::RoomLocked)
+                    } else if !by_invite && !room.check_password(&message.room_password) {
+                        Err(RequestError::WrongPassword)
+                    } else if room.spectators.is_full(room.spectator_count()) {
+                        // 进入房间的玩家先旁观
+                        Err(RequestError::SpectatorsFull)
+                    } else if room.contains_player(&message.player_name) {
+                        // 同一房间内不允许重名
+                        Err(RequestError::NameTaken)
+                    } else {
+                        Ok(room.room_id)
+                    }
+                });
+                let result = result.and_then(|room_id| {
+                    bank::open_account(&mut bank, &sessions, client_id, now).map(|_| room_id)
+                });
+                match result {
+                    Ok(room_id) => {
+                        message.room_id = room_id;
+                        // 旁观者不持有筹码，入座时从账户买入
+                        let player = Player {
                             player_client_id: client_id,
                             player_name: message.player_name.clone(),
                             player_role: PlayerRole::Spectator,
-                            // TODO 断线重连
                             chips: 0,
+                            sitting_out: false,
+                            missed_blinds: MissedBlinds::default(),
+                        };
+                        leave_room(
+                            &mut room_list,
+                            &mut bank,
+                            &play_list,
+                            client_id,
+                            &mut room_delta_ew,
+                        );
+                        room_list.add_player(room_id, player.clone());
+                        room_delta_ew.send(RoomDeltaEvent {
+                            room_id,
+                            delta: RoomDelta::PlayerJoined(player),
                         });
-                        message.success = true;
-                    } else {
-                        message.success = false;
+                        room_snapshot_ew.send(RoomSnapshotEvent { room_id, client_id });
+                    }
+                    Err(error) => {
+                        if matches!(
+                            error,
+                            RequestError::WrongPassword | RequestError::InvalidInvite
+                        ) {
+                            warn!("Client {} failed to enter room: {:?}", client_id, error);
+                            failed_attempts.record(addr, now);
+                        }
+                        message.error = Some(error);
                     }
-                    server.send_message(
-                        client_id,
-                        ENTER_ROOT_CHANNEL_ID,
-                        serde_json::to_vec(&message).unwrap(),
-                    );
-                } else {
-                    error!("Room not found when enter room")
                 }
+                server.send_message(
+                    client_id,
+                    ENTER_ROOT_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
             }
         }
     }
 }
+
+// 离开当前所在的房间，兑现桌上筹码
+pub fn leave_room(
+    room_list: &mut RoomList,
+    bank: &mut Bank,
+    play_list: &PlayList,
+    client_id: u64,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+) {
+    let Some(room_id) = room_list.room_of_client(client_id).map(|room| room.room_id) else {
+        return;
+    };
+    if let Some(room) = room_list.get_mut(room_id) {
+        bank::leave_table(bank, play_list, room, client_id);
+    }
+    room_list.remove_player(client_id);
+    room_delta_ew.send(RoomDeltaEvent {
+        room_id,
+        delta: RoomDelta::PlayerLeft {
+            player_client_id: client_id,
+        },
+    });
+}
@@ -1,52 +1,162 @@
-use std::{net::UdpSocket, time::SystemTime};
+use std::{fs, process};
 
 use bevy::{log::LogPlugin, prelude::*};
-use bevy_renet::{
-    renet::{RenetServer, ServerAuthentication, ServerConfig},
-    RenetServerPlugin,
+use clap::Parser;
+use texas_holdem_server::{
+    bank::Bank,
+    config::{Cli, ReportPeriod, ServerSettings},
+    directory::{new_directory_client, ShutdownSignal},
+    history::HandHistory,
+    new_renet_server, ServerPlugin,
 };
-use lobby::{handle_create_room, handle_enter_room, handle_get_rooms};
-use play::{broadcast_play_info, process_play_round_start, start_new_play, PlayList};
-use room::{broadcast_room_info, handle_set_room_state, handle_switch_player_role};
-use texas_holdem_common::{connection_config, PROTOCOL_ID};
 
-use crate::{network::handle_events_system, room::RoomList};
+fn main() {
+    let cli = Cli::parse();
+    let reconcile = cli.reconcile;
+    let rake_report = cli.rake_report;
+    let settings = match ServerSettings::load(cli) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    if reconcile {
+        process::exit(run_reconcile(&settings));
+    }
+    if let Some(period) = rake_report {
+        process::exit(run_rake_report(&settings, period));
+    }
+    // 数据持久化目录
+    if let Some(persistence_path) = settings.persistence_path.as_ref() {
+        if let Err(e) = fs::create_dir_all(persistence_path) {
+            eprintln!(
+                "Failed to create persistence path {}: {}",
+                persistence_path.display(),
+                e
+            );
+            process::exit(1);
+        }
+    }
+    let bank = match Bank::load(&settings) {
+        Ok(bank) => bank,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let server = match new_renet_server(&settings) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", settings.network.bind_addr, e);
+            process::exit(1);
+        }
+    };
 
-mod lobby;
-mod network;
-mod play;
-mod room;
+    // 收到退出信号后排空再退出
+    let shutdown_signal = ShutdownSignal::default();
+    if let Err(e) = shutdown_signal.install_handler() {
+        eprintln!("Failed to install signal handler: {}", e);
+        process::exit(1);
+    }
 
-fn new_renet_server() -> RenetServer {
-    let server_addr = "127.0.0.1:5000".parse().unwrap();
-    let socket = UdpSocket::bind(server_addr).unwrap();
-    let server_config =
-        ServerConfig::new(64, PROTOCOL_ID, server_addr, ServerAuthentication::Unsecure);
-    let current_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap();
-    RenetServer::new(current_time, server_config, connection_config(), socket).unwrap()
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin {
+            level: settings.log_level().unwrap(),
+            ..default()
+        })
+        .insert_resource(shutdown_signal);
+    if let Some(directory_addr) = settings.directory.addr {
+        match new_directory_client(directory_addr) {
+            Ok(client) => {
+                app.insert_resource(client);
+            }
+            Err(e) => {
+                eprintln!("Failed to connect directory {}: {}", directory_addr, e);
+                process::exit(1);
+            }
+        }
+    }
+    app.insert_resource(server)
+        .insert_resource(bank)
+        .insert_resource(HandHistory::new(settings.persistence_path.as_deref()))
+        .insert_resource(settings)
+        .add_plugin(ServerPlugin)
+        .run();
 }
 
-fn main() {
-    App::new()
-        .add_plugins(MinimalPlugins)
-        .add_plugin(LogPlugin::default())
-        .add_plugin(RenetServerPlugin::default())
-        .insert_resource(new_renet_server())
-        .insert_resource(RoomList(Vec::new()))
-        .insert_resource(PlayList(Vec::new()))
-        .add_systems((
-            handle_get_rooms,
-            handle_create_room,
-            handle_enter_room,
-            handle_switch_player_role,
-            broadcast_room_info,
-            handle_set_room_state,
-            handle_events_system,
-            broadcast_play_info,
-            start_new_play,
-            process_play_round_start,
-        ))
-        .run();
+// 对账，账户余额全部能由账本推算出时返回0
+fn run_reconcile(settings: &ServerSettings) -> i32 {
+    if settings.persistence_path.is_none() {
+        eprintln!("Reconcile requires persistence_path");
+        return 1;
+    }
+    let result = Bank::read(settings).and_then(|bank| {
+        let mismatches = bank.reconcile()?;
+        Ok((bank, mismatches))
+    });
+    match result {
+        Ok((bank, mismatches)) if mismatches.is_empty() => {
+            println!(
+                "Reconciled: total {}, {} ledger transactions",
+                bank.total(),
+                bank.ledger_len().unwrap_or(0)
+            );
+            0
+        }
+        Ok((_, mismatches)) => {
+            for mismatch in mismatches {
+                eprintln!("{}", mismatch);
+            }
+            1
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// 按房间和时间段输出抽水
+fn run_rake_report(settings: &ServerSettings, period: ReportPeriod) -> i32 {
+    if settings.persistence_path.is_none() {
+        eprintln!("Rake report requires persistence_path");
+        return 1;
+    }
+    match Bank::read(settings).and_then(|bank| bank.rake_report(period.millis())) {
+        Ok(report) => {
+            println!("room\tperiod (UTC)\trake");
+            for ((room_id, period_start), rake) in report.iter() {
+                println!("{}\t{}\t{}", room_id, format_utc(*period_start), rake);
+            }
+            println!("total\t\t{}", report.values().sum::<u64>());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// 毫秒时间戳格式化为 YYYY-MM-DD HH:MM（UTC）
+fn format_utc(timestamp: u64) -> String {
+    let secs = timestamp / 1000;
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = (secs % 86_400 / 3600, secs % 3600 / 60);
+    // 公历日期换算 http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
 }
@@ -1,28 +1,257 @@
-use bevy::prelude::*;
+use std::net::SocketAddr;
+
+use bevy::{prelude::*, utils::HashMap};
 use bevy_renet::renet::{RenetServer, ServerEvent};
 use texas_holdem_common::{
-    channel::{
-        BroadcastRoomInfoMessage, CreateRoomMessage, EnterRoomMessage, GetRoomsMessage,
-        SetRoomStateMessage, SwitchPlayerRoleMessage, BROADCAST_ROOM_INFO_CHANNEL_ID,
-        CREATE_ROOM_CHANNEL_ID, ENTER_ROOT_CHANNEL_ID, GET_ROOMS_CHANNEL_ID,
-        SET_ROOM_STATE_CHANNEL_ID, SWITCH_PLAYER_ROLE_CHANNEL_ID,
-    },
-    util::timestamp,
-    Player, PlayerRole, RoomDTO, RoomState,
+    channel::{HandshakeMessage, HANDSHAKE_CHANNEL_ID},
+    PlayerRole, ProtocolVersion, SessionToken, PROTOCOL_VERSION,
+};
+
+use crate::{
+    bank::Bank,
+    chat::ChatRateLimiter,
+    config::ServerSettings,
+    lobby::leave_room,
+    play::PlayList,
+    room::{fold_out_player, RoomDeltaEvent, RoomList},
 };
 
-use crate::room::{Room, RoomList};
+// 版本不兼容、等待断开的客户端（留出时间把拒绝原因发送给客户端）
+#[derive(Debug, Default, Resource)]
+pub struct PendingDisconnects(pub HashMap<u64, f32>);
+
+// 通过握手的客户端会话，请求处理只处理会话中的客户端
+#[derive(Debug)]
+pub struct Session {
+    pub addr: Option<SocketAddr>,
+    // 服务端分配的用户，房主、禁止进入和账户按用户校验
+    pub user_id: u64,
+    pub token: SessionToken,
+}
+
+// 令牌对应的用户，断开后保留一段时间供重新连接
+#[derive(Debug)]
+struct TokenEntry {
+    user_id: u64,
+    // 最后一个连接断开的时间（秒），连接中为None
+    disconnected_at: Option<f64>,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct Sessions {
+    clients: HashMap<u64, Session>,
+    tokens: HashMap<SessionToken, TokenEntry>,
+}
+
+impl Sessions {
+    pub fn clients_id(&self) -> Vec<u64> {
+        self.clients.keys().copied().collect()
+    }
+
+    pub fn get(&self, client_id: u64) -> Option<&Session> {
+        self.clients.get(&client_id)
+    }
+
+    pub fn user_id(&self, client_id: u64) -> Option<u64> {
+        self.get(client_id).map(|session| session.user_id)
+    }
+
+    // 用户当前的连接
+    pub fn client_of(&self, user_id: u64) -> Option<u64> {
+        self.clients
+            .iter()
+            .find(|(_, session)| session.user_id == user_id)
+            .map(|(client_id, _)| *client_id)
+    }
 
-pub fn handle_events_system(mut server_events: EventReader<ServerEvent>) {
+    // 凭令牌恢复用户，令牌已过期但有账户（account_id）时恢复为账户的用户，
+    // 没有令牌或令牌未知时分配新用户，返回客户端保存的令牌
+    // 同一用户只保留最新的连接，返回被取代的连接
+    pub fn login(
+        &mut self,
+        client_id: u64,
+        addr: Option<SocketAddr>,
+        token: Option<SessionToken>,
+        account_id: Option<u64>,
+    ) -> (SessionToken, Vec<u64>) {
+        let token = match token {
+            Some(token) if self.tokens.contains_key(&token) => token,
+            _ => {
+                let (token, user_id) = match token.zip(account_id) {
+                    Some((token, account_id)) => (token, account_id),
+                    None => (SessionToken(rand::random()), rand::random()),
+                };
+                self.tokens.insert(
+                    token,
+                    TokenEntry {
+                        user_id,
+                        disconnected_at: None,
+                    },
+                );
+                token
+            }
+        };
+        let entry = self.tokens.get_mut(&token).unwrap();
+        entry.disconnected_at = None;
+        let user_id = entry.user_id;
+        let replaced: Vec<u64> = self
+            .clients
+            .iter()
+            .filter(|(_, session)| session.user_id == user_id)
+            .map(|(client_id, _)| *client_id)
+            .collect();
+        for client_id in replaced.iter() {
+            self.clients.remove(client_id);
+        }
+        self.clients.insert(
+            client_id,
+            Session {
+                addr,
+                user_id,
+                token,
+            },
+        );
+        (token, replaced)
+    }
+
+    pub fn logout(&mut self, client_id: u64, now: f64) {
+        let Some(session) = self.clients.remove(&client_id) else {
+            return;
+        };
+        if self.client_of(session.user_id).is_none() {
+            for entry in self.tokens.values_mut() {
+                if entry.user_id == session.user_id {
+                    entry.disconnected_at = Some(now);
+                }
+            }
+        }
+    }
+
+    // 移除断开超过ttl秒的令牌
+    pub fn expire(&mut self, ttl: f64, now: f64) {
+        self.tokens.retain(|_, entry| {
+            entry
+                .disconnected_at
+                .is_none_or(|disconnected_at| now - disconnected_at < ttl)
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_events_system(
+    mut server: ResMut<RenetServer>,
+    mut server_events: EventReader<ServerEvent>,
+    settings: Res<ServerSettings>,
+    mut pending_disconnects: ResMut<PendingDisconnects>,
+    mut sessions: ResMut<Sessions>,
+    mut room_list: ResMut<RoomList>,
+    mut bank: ResMut<Bank>,
+    mut play_list: ResMut<PlayList>,
+    mut chat_rate_limiter: ResMut<ChatRateLimiter>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
     for event in server_events.iter() {
         match event {
             ServerEvent::ClientConnected(id, user_data) => {
                 println!("Client {} connected", id);
+                let client_version = ProtocolVersion::from_user_data(user_data);
+                let message = match PROTOCOL_VERSION.check_compatible(&client_version) {
+                    Ok(()) => {
+                        sessions.expire(settings.access.session_ttl_secs as f64, now);
+                        let addr = server.client_addr(*id);
+                        let token = SessionToken::from_user_data(user_data);
+                        let account_id = token.and_then(|token| bank.account_of_token(&token));
+                        let (token, replaced) = sessions.login(*id, addr, token, account_id);
+                        for client_id in replaced {
+                            info!("Client {} replaced by {}", client_id, id);
+                            server.disconnect(client_id);
+                        }
+                        HandshakeMessage {
+                            server_version: PROTOCOL_VERSION,
+                            accepted: true,
+                            reason: None,
+                            session_token: Some(token),
+                        }
+                    }
+                    Err(reason) => {
+                        warn!("Reject client {}: {}", id, reason);
+                        pending_disconnects.0.insert(*id, 1.0);
+                        HandshakeMessage {
+                            server_version: PROTOCOL_VERSION,
+                            accepted: false,
+                            reason: Some(reason),
+                            session_token: None,
+                        }
+                    }
+                };
+                server.send_message(
+                    *id,
+                    HANDSHAKE_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
             }
             ServerEvent::ClientDisconnected(id) => {
                 // TODO 房主断开需要进行异常处理
                 println!("Client {} disconnected", id);
+                pending_disconnects.0.remove(id);
+                if let Some(user_id) = sessions.user_id(*id) {
+                    chat_rate_limiter.remove(user_id);
+                }
+                sessions.logout(*id, now);
+                for room in room_list.iter_mut() {
+                    room.waiting_list.retain(|waiting| waiting.client_id != *id);
+                }
+                // 离开所在的房间，对局中立即弃牌；锦标赛的参赛玩家留在桌上
+                let Some(room) = room_list.room_of_client(*id) else {
+                    continue;
+                };
+                let room_id = room.room_id;
+                let Some(player) = room
+                    .players
+                    .iter()
+                    .find(|player| player.player_client_id == *id)
+                    .cloned()
+                else {
+                    continue;
+                };
+                if room.tournament_id.is_some() && player.player_role == PlayerRole::Participant {
+                    continue;
+                }
+                fold_out_player(
+                    &mut play_list,
+                    &mut room_delta_ew,
+                    room_id,
+                    *id,
+                    &player.player_name,
+                );
+                leave_room(
+                    &mut room_list,
+                    &mut bank,
+                    &play_list,
+                    *id,
+                    &mut room_delta_ew,
+                );
             }
         }
     }
 }
+
+pub fn disconnect_rejected_clients(
+    mut server: ResMut<RenetServer>,
+    mut pending_disconnects: ResMut<PendingDisconnects>,
+    time: Res<Time>,
+) {
+    let mut expired = Vec::new();
+    for (client_id, cd) in pending_disconnects.0.iter_mut() {
+        *cd -= time.delta_seconds();
+        if *cd < 0.0 {
+            expired.push(*client_id);
+        }
+    }
+    for client_id in expired {
+        pending_disconnects.0.remove(&client_id);
+        server.disconnect(client_id);
+    }
+}
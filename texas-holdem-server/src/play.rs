@@ -1,12 +1,36 @@
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_renet::renet::RenetServer;
+use rand::seq::SliceRandom;
 use texas_holdem_common::{
-    channel::{BroadcastPlayInfoMessage, BROADCAST_PLAY_INFO_CHANNEL_ID},
-    util::timestamp,
-    Card, Player, PlayerRole, RoomState, Round,
+    betting::{AnteKind, BetState, BettingStructure, ForcedBets, RakeConfig},
+    channel::{
+        PlaySnapshot, PlayerActionMessage, RequestError, RoomDelta, RunItTwiceMessage,
+        RunItTwiceVote, ShowdownResult, ShownHand, PLAYER_ACTION_CHANNEL_ID,
+        RUN_IT_TWICE_CHANNEL_ID,
+    },
+    hand::HandValue,
+    util::IdAllocator,
+    variant::GameVariant,
+    Card, MissedBlinds, Player, PlayerRole, Round, RoundAction,
+};
+
+use crate::{
+    bank::{self, Bank},
+    config::ServerSettings,
+    history::{HandHistory, HandRecord},
+    ledger::{LedgerAccount, LedgerTransaction, TransactionKind},
+    network::Sessions,
+    room::{HoleCardsEvent, Room, RoomDeltaEvent, RoomList},
+    tournament::{self, TournamentList},
 };
 
-use crate::room::RoomList;
+// 行动超时时间（秒），超时自动过牌或弃牌
+const TURN_TIMEOUT_SECS: f32 = 30.0;
+// 摊牌后展示结果的时间（秒）
+const SHOWDOWN_DELAY_SECS: f32 = 3.0;
 
 // 一场对局
 #[derive(Debug)]
@@ -18,149 +42,1433 @@ pub struct Play {
     pub dealer_name: Option<String>,
     pub small_blind_name: Option<String>,
     pub big_blind_name: Option<String>,
-    pub hole_cards: HashMap<String, [Card; 2]>,
+    // 枪口位跨注的玩家
+    pub straddle_name: Option<String>,
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub forced_bets: ForcedBets,
+    // 房间允许发两次
+    pub run_it_twice: bool,
+    pub rake: RakeConfig,
+    // 两次发牌表决，以及表决时已发出的公共牌张数
+    pub run_it_twice_vote: Option<RunItTwiceVote>,
+    pub run_it_twice_from: usize,
+    pub hole_cards: HashMap<String, Vec<Card>>,
     pub flop_cards: Option<[Card; 3]>,
     pub turn_card: Option<Card>,
     pub river_card: Option<Card>,
     pub card_pool: Vec<Card>,
     pub pot: u32,
+    // 本轮下注
+    pub bets: HashMap<String, u32>,
+    // 本局累计下注，用于计算边池
+    pub total_bets: HashMap<String, u32>,
+    // 不属于任何玩家的死钱（大盲注前注），并入主池
+    pub dead_money: u32,
+    pub folded_names: HashSet<String>,
+    // 本轮已行动的玩家（有人加注后清空）
+    pub acted_names: HashSet<String>,
+    pub current_player_name: Option<String>,
+    // 本轮最高下注
+    pub current_bet: u32,
+    // 最小加注额
+    pub min_raise: u32,
+    // 本轮已下注和加注的次数（翻牌前大盲注算一次）
+    pub raises: u32,
+    // 当前玩家剩余行动时间
+    pub turn_cd: f32,
+    pub showdown: Option<ShowdownResult>,
+    // 摊牌后剩余展示时间
+    pub showdown_cd: f32,
+    // 本局的筹码变动，现金桌在对局结束时随结算一起记账
+    pub ledger: Vec<LedgerTransaction>,
 }
 
 #[derive(Debug, Default, Resource)]
-pub struct PlayList(pub Vec<Play>);
+pub struct PlayList {
+    pub plays: Vec<Play>,
+    pub play_ids: IdAllocator,
+}
 
-pub fn broadcast_play_info(
-    mut server: ResMut<RenetServer>,
-    play_list: Res<PlayList>,
-    room_list: Res<RoomList>,
-    mut refresh_cd: Local<f32>,
-    time: Res<Time>,
-) {
-    *refresh_cd -= time.delta_seconds();
-    if *refresh_cd < 0.0 {
-        for play in play_list.0.iter() {
-            let message = BroadcastPlayInfoMessage {
-                timestamp: timestamp(),
-                room_id: play.room_id,
-                play_id: play.play_id,
-                round: play.round,
-                participants: play.participants.clone(),
+impl Play {
+    pub fn new(play_id: u64, room: &Room) -> Self {
+        let mut card_pool = room.variant.deck().build();
+        card_pool.shuffle(&mut rand::thread_rng());
+        let mut play = Play {
+            play_id,
+            room_id: room.room_id,
+            round: Round::Start,
+            participants: room.dealt_in_players().cloned().collect(),
+            dealer_name: None,
+            small_blind_name: None,
+            big_blind_name: None,
+            straddle_name: None,
+            small_blind: room.small_blind,
+            big_blind: room.big_blind,
+            variant: room.variant,
+            betting: room.betting,
+            forced_bets: room.forced_bets,
+            run_it_twice: room.run_it_twice,
+            rake: room.rake,
+            run_it_twice_vote: None,
+            run_it_twice_from: 0,
+            hole_cards: HashMap::new(),
+            flop_cards: None,
+            turn_card: None,
+            river_card: None,
+            card_pool,
+            pot: 0,
+            bets: HashMap::new(),
+            total_bets: HashMap::new(),
+            dead_money: 0,
+            folded_names: HashSet::new(),
+            acted_names: HashSet::new(),
+            current_player_name: None,
+            current_bet: 0,
+            min_raise: room.big_blind,
+            raises: 0,
+            turn_cd: TURN_TIMEOUT_SECS,
+            showdown: None,
+            showdown_cd: SHOWDOWN_DELAY_SECS,
+            ledger: Vec::new(),
+        };
+        if play.participants.len() >= 2 {
+            play.assign_positions(room.last_dealer_name.as_ref());
+        }
+        play
+    }
+
+    // 确定庄家以及大盲注和小盲注位置
+    fn assign_positions(&mut self, last_dealer_name: Option<&String>) {
+        let len = self.participants.len();
+        let dealer_index = last_dealer_name
+            .and_then(|last_dealer_name| self.index_of(last_dealer_name))
+            .map_or(0, |last_dealer_index| (last_dealer_index + 1) % len);
+        // 单挑时庄家下小盲注
+        let small_blind_index = if len == 2 {
+            dealer_index
+        } else {
+            (dealer_index + 1) % len
+        };
+        self.dealer_name = Some(self.participants[dealer_index].player_name.clone());
+        self.small_blind_name = Some(self.participants[small_blind_index].player_name.clone());
+        self.big_blind_name = Some(
+            self.participants[(small_blind_index + 1) % len]
+                .player_name
+                .clone(),
+        );
+        // 大盲注之后的玩家跨注，筹码不足时不跨注
+        if self.forced_bets.straddle && len >= 3 {
+            let straddle_player = &self.participants[(small_blind_index + 2) % len];
+            let ante = match self.forced_bets.ante_kind {
+                AnteKind::PerPlayer => self.forced_bets.ante,
+                _ => 0,
             };
-            // 向房间内的所有玩家广播对局信息
-            if let Some(room) = room_list.0.iter().find(|room| room.room_id == play.room_id) {
-                for player in room.players.iter() {
-                    server.send_message(
-                        player.player_client_id,
-                        BROADCAST_PLAY_INFO_CHANNEL_ID,
-                        serde_json::to_vec(&message).unwrap(),
-                    );
+            if straddle_player.chips > self.big_blind * 2 + ante {
+                self.straddle_name = Some(straddle_player.player_name.clone());
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> PlaySnapshot {
+        PlaySnapshot {
+            play_id: self.play_id,
+            round: self.round,
+            participants: self.participants.clone(),
+            dealer_name: self.dealer_name.clone(),
+            small_blind_name: self.small_blind_name.clone(),
+            big_blind_name: self.big_blind_name.clone(),
+            straddle_name: self.straddle_name.clone(),
+            big_blind: self.big_blind,
+            board_cards: self.board_cards(),
+            pot: self.pot,
+            bets: self.bets.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            folded_names: self.folded_names.iter().cloned().collect(),
+            current_player_name: self.current_player_name.clone(),
+            current_bet: self.current_bet,
+            min_raise: self.min_raise,
+            raises: self.raises,
+            run_it_twice_vote: self.run_it_twice_vote.clone(),
+            showdown: self.showdown.clone(),
+        }
+    }
+
+    pub fn board_cards(&self) -> Vec<Card> {
+        let mut board_cards = Vec::with_capacity(5);
+        if let Some(flop_cards) = self.flop_cards {
+            board_cards.extend_from_slice(&flop_cards);
+        }
+        board_cards.extend(self.turn_card);
+        board_cards.extend(self.river_card);
+        board_cards
+    }
+
+    fn index_of(&self, player_name: &str) -> Option<usize> {
+        self.participants
+            .iter()
+            .position(|player| player.player_name == player_name)
+    }
+
+    fn chips_of(&self, player_name: &str) -> u32 {
+        self.index_of(player_name)
+            .map_or(0, |index| self.participants[index].chips)
+    }
+
+    fn bet_of(&self, player_name: &str) -> u32 {
+        self.bets.get(player_name).copied().unwrap_or(0)
+    }
+
+    // 未弃牌且还有筹码，可以继续行动
+    fn can_act(&self, player_name: &str) -> bool {
+        !self.folded_names.contains(player_name) && self.chips_of(player_name) > 0
+    }
+
+    fn in_hand_names(&self) -> Vec<String> {
+        self.participants
+            .iter()
+            .filter(|player| !self.folded_names.contains(&player.player_name))
+            .map(|player| player.player_name.clone())
+            .collect()
+    }
+
+    // 从指定玩家之后按座位顺序找到第一个满足条件的玩家
+    fn next_player_after(
+        &self,
+        player_name: &str,
+        predicate: impl Fn(&Player) -> bool,
+    ) -> Option<String> {
+        let start = self.index_of(player_name)?;
+        let len = self.participants.len();
+        (1..=len)
+            .map(|offset| &self.participants[(start + offset) % len])
+            .find(|player| predicate(player))
+            .map(|player| player.player_name.clone())
+    }
+
+    // 记录筹码变动，赢得底池时从底池转入筹码，其余从筹码转入底池
+    fn record(&mut self, kind: TransactionKind, player_name: &str, amount: u32) {
+        if amount == 0 {
+            return;
+        }
+        let stack = LedgerAccount::Stack {
+            room_id: self.room_id,
+            player_name: player_name.to_string(),
+        };
+        let pot = LedgerAccount::Pot {
+            room_id: self.room_id,
+            play_id: self.play_id,
+        };
+        let (from, to) = match kind {
+            TransactionKind::Award => (pot, stack),
+            _ => (stack, pot),
+        };
+        self.ledger.push(LedgerTransaction {
+            kind,
+            room_id: Some(self.room_id),
+            play_id: Some(self.play_id),
+            player_name: Some(player_name.to_string()),
+            from,
+            to,
+            amount: amount as u64,
+        });
+    }
+
+    // 下注，筹码不足时全下，返回实际下注额
+    fn put_chips(&mut self, kind: TransactionKind, player_name: &str, amount: u32) -> u32 {
+        let Some(index) = self.index_of(player_name) else {
+            return 0;
+        };
+        let amount = amount.min(self.participants[index].chips);
+        self.participants[index].chips -= amount;
+        self.record(kind, player_name, amount);
+        *self.bets.entry(player_name.to_string()).or_insert(0) += amount;
+        *self.total_bets.entry(player_name.to_string()).or_insert(0) += amount;
+        self.pot += amount;
+        amount
+    }
+
+    // 下死钱，并入主池但不计入玩家下注
+    fn post_dead(&mut self, kind: TransactionKind, player_name: &str, amount: u32) {
+        if let Some(index) = self.index_of(player_name) {
+            let amount = amount.min(self.participants[index].chips);
+            self.participants[index].chips -= amount;
+            self.record(kind, player_name, amount);
+            self.pot += amount;
+            self.dead_money += amount;
+        }
+    }
+
+    // 下前注，计入底池但不计入本轮下注
+    fn post_ante(&mut self, player_name: &str, amount: u32) -> RoomDelta {
+        match self.forced_bets.ante_kind {
+            AnteKind::BigBlind => self.post_dead(TransactionKind::Ante, player_name, amount),
+            _ => {
+                if let Some(index) = self.index_of(player_name) {
+                    let amount = amount.min(self.participants[index].chips);
+                    self.participants[index].chips -= amount;
+                    self.record(TransactionKind::Ante, player_name, amount);
+                    self.pot += amount;
+                    *self.total_bets.entry(player_name.to_string()).or_insert(0) += amount;
+                }
+            }
+        }
+        RoomDelta::AntePosted {
+            play_id: self.play_id,
+            player_name: player_name.to_string(),
+            chips: self.chips_of(player_name),
+            pot: self.pot,
+        }
+    }
+
+    fn acted_delta(&self, player_name: &str, action: RoundAction) -> RoomDelta {
+        RoomDelta::PlayerActed {
+            play_id: self.play_id,
+            player_name: player_name.to_string(),
+            action,
+            bet: self.bet_of(player_name),
+            chips: self.chips_of(player_name),
+            pot: self.pot,
+        }
+    }
+
+    fn turn_delta(&self) -> RoomDelta {
+        RoomDelta::TurnChanged {
+            play_id: self.play_id,
+            player_name: self.current_player_name.clone(),
+            current_bet: self.current_bet,
+            min_raise: self.min_raise,
+            raises: self.raises,
+        }
+    }
+
+    fn deal(&mut self) -> Card {
+        self.card_pool.pop().unwrap()
+    }
+
+    // 下大小盲注、发底牌，进入翻牌前下注
+    pub fn start_preflop(&mut self) -> Vec<RoomDelta> {
+        let mut deltas = Vec::new();
+        self.round = Round::Preflop;
+        deltas.push(RoomDelta::PlayRoundChanged {
+            play_id: self.play_id,
+            round: self.round,
+            board_cards: Vec::new(),
+        });
+
+        // 每人前注在盲注之前下
+        if self.forced_bets.has_ante() && self.forced_bets.ante_kind == AnteKind::PerPlayer {
+            let player_names: Vec<String> = self
+                .participants
+                .iter()
+                .map(|player| player.player_name.clone())
+                .collect();
+            for player_name in player_names.iter() {
+                deltas.push(self.post_ante(player_name, self.forced_bets.ante));
+            }
+        }
+
+        // 大小盲注（大盲注为最小下注金额、小盲注为最小下注金额一半）
+        let small_blind_name = self.small_blind_name.clone().unwrap();
+        let big_blind_name = self.big_blind_name.clone().unwrap();
+        self.put_chips(TransactionKind::Blind, &small_blind_name, self.small_blind);
+        deltas.push(self.acted_delta(&small_blind_name, RoundAction::Bet));
+        self.put_chips(TransactionKind::Blind, &big_blind_name, self.big_blind);
+        deltas.push(self.acted_delta(&big_blind_name, RoundAction::Bet));
+        self.current_bet = self.big_blind;
+        self.min_raise = self.big_blind;
+        self.raises = 1;
+
+        // 大盲注前注在大盲注之后下
+        if self.forced_bets.has_ante() && self.forced_bets.ante_kind == AnteKind::BigBlind {
+            deltas.push(self.post_ante(&big_blind_name, self.forced_bets.ante));
+        }
+
+        // 回到牌桌的玩家补交错过的盲注，本局轮到大小盲注时不用补交
+        let returning: Vec<(String, MissedBlinds)> = self
+            .participants
+            .iter()
+            .filter(|player| {
+                player.missed_blinds.is_owed()
+                    && Some(&player.player_name) != self.small_blind_name.as_ref()
+                    && player.player_name != big_blind_name
+            })
+            .map(|player| (player.player_name.clone(), player.missed_blinds))
+            .collect();
+        for (player_name, missed_blinds) in returning {
+            if missed_blinds.big_blinds > 0 {
+                self.put_chips(TransactionKind::Blind, &player_name, self.big_blind);
+            }
+            if missed_blinds.small_blind {
+                self.post_dead(TransactionKind::Blind, &player_name, self.small_blind);
+            }
+            deltas.push(RoomDelta::MissedBlindsPosted {
+                play_id: self.play_id,
+                player_name: player_name.clone(),
+                bet: self.bet_of(&player_name),
+                chips: self.chips_of(&player_name),
+                pot: self.pot,
+            });
+        }
+
+        // 跨注视为一次加注，最小再加注额为跨注额
+        let mut last_blind_name = big_blind_name;
+        if let Some(straddle_name) = self.straddle_name.clone() {
+            let straddle = self.big_blind * 2;
+            self.put_chips(
+                TransactionKind::Blind,
+                &straddle_name,
+                straddle.saturating_sub(self.bet_of(&straddle_name)),
+            );
+            deltas.push(self.acted_delta(&straddle_name, RoundAction::Raise));
+            self.current_bet = straddle;
+            self.min_raise = straddle;
+            self.raises = 2;
+            last_blind_name = straddle_name;
+        }
+
+        // 发手牌
+        for index in 0..self.participants.len() {
+            let cards = (0..self.variant.hole_card_count())
+                .map(|_| self.deal())
+                .collect();
+            self.hole_cards
+                .insert(self.participants[index].player_name.clone(), cards);
+        }
+
+        // 大盲注（或跨注）之后的玩家先行动
+        let first = self.next_player_after(&last_blind_name, |player| {
+            !self.folded_names.contains(&player.player_name) && player.chips > 0
+        });
+        deltas.extend(self.set_turn_or_advance(first));
+        deltas
+    }
+
+    // 处理玩家行动
+    pub fn act(
+        &mut self,
+        player_name: &str,
+        action: RoundAction,
+        amount: u32,
+    ) -> Result<Vec<RoomDelta>, RequestError> {
+        if self.current_player_name.as_deref() != Some(player_name) {
+            return Err(RequestError::NotYourTurn);
+        }
+        let state = self.bet_state(player_name);
+        let to_call = state.to_call();
+        match action {
+            RoundAction::Check => {
+                if to_call > 0 {
+                    return Err(RequestError::InvalidAction);
+                }
+            }
+            RoundAction::Call => {
+                if to_call == 0 {
+                    return Err(RequestError::InvalidAction);
                 }
+                self.put_chips(TransactionKind::Bet, player_name, to_call);
+            }
+            RoundAction::Bet | RoundAction::Raise => {
+                let is_bet = action == RoundAction::Bet;
+                if is_bet != (self.current_bet == 0)
+                    || !self
+                        .betting
+                        .raise_range(&state)
+                        .is_some_and(|(min, max)| (min..=max).contains(&amount))
+                {
+                    return Err(RequestError::InvalidAction);
+                }
+                self.put_chips(TransactionKind::Bet, player_name, amount - state.bet);
+                self.min_raise = amount - self.current_bet;
+                self.current_bet = amount;
+                self.raises += 1;
+                self.acted_names.clear();
+            }
+            RoundAction::AllIn => {
+                // 限注时全下不能超过最大加注额
+                if !self.betting.allows_all_in(&state) {
+                    return Err(RequestError::InvalidAction);
+                }
+                self.put_chips(TransactionKind::Bet, player_name, state.chips);
+                let total = state.all_in_to();
+                if total > self.current_bet {
+                    // 不足最小加注额的全下不重新开放加注
+                    if total - self.current_bet >= self.min_raise {
+                        self.min_raise = total - self.current_bet;
+                        self.raises += 1;
+                        self.acted_names.clear();
+                    }
+                    self.current_bet = total;
+                }
+            }
+            RoundAction::Fold => {
+                self.folded_names.insert(player_name.to_string());
+            }
+        }
+        self.acted_names.insert(player_name.to_string());
+
+        let mut deltas = vec![self.acted_delta(player_name, action)];
+        deltas.extend(self.next_turn(player_name));
+        Ok(deltas)
+    }
+
+    // 玩家当前的下注状态，用于按下注结构校验加注额
+    fn bet_state(&self, player_name: &str) -> BetState {
+        BetState {
+            round: self.round,
+            big_blind: self.big_blind,
+            pot: self.pot,
+            current_bet: self.current_bet,
+            min_raise: self.min_raise,
+            raises: self.raises,
+            bet: self.bet_of(player_name),
+            chips: self.chips_of(player_name),
+        }
+    }
+
+    // 超时自动行动：能过牌则过牌，否则弃牌；两次发牌表决超时视为拒绝
+    pub fn act_on_timeout(&mut self) -> Vec<RoomDelta> {
+        let waiting_name = self.run_it_twice_vote.as_ref().and_then(|vote| {
+            vote.player_names
+                .iter()
+                .find(|player_name| vote.is_waiting_for(player_name))
+                .cloned()
+        });
+        if let Some(player_name) = waiting_name {
+            return self
+                .answer_run_it_twice(&player_name, false)
+                .unwrap_or_default();
+        }
+        let Some(player_name) = self.current_player_name.clone() else {
+            return Vec::new();
+        };
+        let action = if self.bet_of(&player_name) >= self.current_bet {
+            RoundAction::Check
+        } else {
+            RoundAction::Fold
+        };
+        self.act(&player_name, action, 0).unwrap_or_default()
+    }
+
+    // 玩家被请出房间时弃牌，不必等到轮到该玩家行动
+    pub fn fold_out(&mut self, player_name: &str) -> Vec<RoomDelta> {
+        if self.showdown.is_some() || !self.in_hand_names().iter().any(|name| name == player_name) {
+            return Vec::new();
+        }
+        if self.current_player_name.as_deref() == Some(player_name) {
+            return self
+                .act(player_name, RoundAction::Fold, 0)
+                .unwrap_or_default();
+        }
+        self.folded_names.insert(player_name.to_string());
+        let mut deltas = vec![self.acted_delta(player_name, RoundAction::Fold)];
+        let in_hand_names = self.in_hand_names();
+        if in_hand_names.len() == 1 {
+            deltas.extend(self.award_uncontested(&in_hand_names[0]));
+        } else if self
+            .run_it_twice_vote
+            .as_ref()
+            .is_some_and(|vote| vote.is_waiting_for(player_name))
+        {
+            // 弃牌的玩家视为拒绝发两次
+            deltas.extend(
+                self.answer_run_it_twice(player_name, false)
+                    .unwrap_or_default(),
+            );
+        }
+        deltas
+    }
+
+    fn next_turn(&mut self, last_player_name: &str) -> Vec<RoomDelta> {
+        let in_hand_names = self.in_hand_names();
+        if in_hand_names.len() == 1 {
+            // 其他玩家全部弃牌
+            return self.award_uncontested(&in_hand_names[0]);
+        }
+        let next = self.next_player_after(last_player_name, |player| {
+            self.can_act(&player.player_name)
+                && (!self.acted_names.contains(&player.player_name)
+                    || self.bet_of(&player.player_name) < self.current_bet)
+        });
+        self.set_turn_or_advance(next)
+    }
+
+    fn set_turn_or_advance(&mut self, next: Option<String>) -> Vec<RoomDelta> {
+        // 只剩一个可行动的玩家且已跟平时，无需再下注
+        let can_act_count = self
+            .participants
+            .iter()
+            .filter(|player| self.can_act(&player.player_name))
+            .count();
+        let next = next
+            .filter(|player_name| can_act_count > 1 || self.bet_of(player_name) < self.current_bet);
+        match next {
+            Some(player_name) => {
+                self.current_player_name = Some(player_name);
+                self.turn_cd = TURN_TIMEOUT_SECS;
+                vec![self.turn_delta()]
+            }
+            // 全下后还有公共牌未发时，询问是否发两次
+            None if self.run_it_twice
+                && can_act_count <= 1
+                && self.run_it_twice_vote.is_none()
+                && matches!(self.round, Round::Preflop | Round::Flop | Round::Turn)
+                && self.in_hand_names().len() >= 2 =>
+            {
+                let player_names = self.in_hand_names();
+                self.run_it_twice_vote = Some(RunItTwiceVote::new(player_names.clone()));
+                self.run_it_twice_from = self.board_cards().len();
+                self.current_player_name = None;
+                self.turn_cd = TURN_TIMEOUT_SECS;
+                vec![RoomDelta::RunItTwiceOffered {
+                    play_id: self.play_id,
+                    player_names,
+                }]
             }
+            None => self.advance_round(),
         }
-        // 1秒广播一次
-        *refresh_cd = 1.0;
+    }
+
+    // 是否正在等待两次发牌表决
+    pub fn is_voting(&self) -> bool {
+        self.run_it_twice_vote
+            .as_ref()
+            .is_some_and(|vote| vote.is_pending())
+    }
+
+    // 玩家表决是否发两次，表决结束后发完剩余公共牌
+    pub fn answer_run_it_twice(
+        &mut self,
+        player_name: &str,
+        accept: bool,
+    ) -> Result<Vec<RoomDelta>, RequestError> {
+        let Some(vote) = self
+            .run_it_twice_vote
+            .as_mut()
+            .filter(|vote| vote.is_waiting_for(player_name))
+        else {
+            return Err(RequestError::InvalidAction);
+        };
+        vote.answers.insert(player_name.to_string(), accept);
+        let mut deltas = vec![RoomDelta::RunItTwiceAnswered {
+            play_id: self.play_id,
+            player_name: player_name.to_string(),
+            accept,
+        }];
+        if !vote.is_pending() {
+            deltas.extend(self.advance_round());
+        }
+        Ok(deltas)
+    }
+
+    // 本轮下注结束，发下一轮公共牌或摊牌
+    fn advance_round(&mut self) -> Vec<RoomDelta> {
+        self.bets.clear();
+        self.acted_names.clear();
+        self.current_bet = 0;
+        self.min_raise = self.big_blind;
+        self.raises = 0;
+        self.current_player_name = None;
+
+        self.round = match self.round {
+            Round::Preflop => {
+                self.flop_cards = Some([self.deal(), self.deal(), self.deal()]);
+                Round::Flop
+            }
+            Round::Flop => {
+                self.turn_card = Some(self.deal());
+                Round::Turn
+            }
+            Round::Turn => {
+                self.river_card = Some(self.deal());
+                Round::River
+            }
+            _ => return self.showdown(),
+        };
+        let mut deltas = vec![RoomDelta::PlayRoundChanged {
+            play_id: self.play_id,
+            round: self.round,
+            board_cards: self.board_cards(),
+        }];
+        // 翻牌后从庄家之后第一个未弃牌的玩家开始
+        let dealer_name = self.dealer_name.clone().unwrap();
+        let first = self.next_player_after(&dealer_name, |player| {
+            !self.folded_names.contains(&player.player_name) && player.chips > 0
+        });
+        deltas.extend(self.set_turn_or_advance(first));
+        deltas
+    }
+
+    // 抽水，不翻牌不抽水；没有人跟的下注不计入，按比例计算且不超过每局上限
+    fn collect_rake(&mut self) -> u32 {
+        if !self.rake.is_enabled() || self.flop_cards.is_none() {
+            return 0;
+        }
+        let mut totals: Vec<u32> = self.total_bets.values().copied().collect();
+        totals.sort_unstable_by(|a, b| b.cmp(a));
+        let uncalled = totals.first().copied().unwrap_or(0) - totals.get(1).copied().unwrap_or(0);
+        let rake = self.rake.rake(self.pot - uncalled);
+        if rake > 0 {
+            self.ledger.push(LedgerTransaction {
+                kind: TransactionKind::Rake,
+                room_id: Some(self.room_id),
+                play_id: Some(self.play_id),
+                player_name: None,
+                from: LedgerAccount::Pot {
+                    room_id: self.room_id,
+                    play_id: self.play_id,
+                },
+                to: LedgerAccount::Rake {
+                    room_id: self.room_id,
+                },
+                amount: rake as u64,
+            });
+        }
+        rake
+    }
+
+    fn award_uncontested(&mut self, winner_name: &str) -> Vec<RoomDelta> {
+        let rake = self.collect_rake();
+        let mut winnings = std::collections::HashMap::new();
+        winnings.insert(winner_name.to_string(), self.pot - rake);
+        self.finish(ShowdownResult {
+            shown_hands: Vec::new(),
+            winnings,
+            second_board: None,
+            rake,
+        })
+    }
+
+    // 摊牌，按边池分配筹码；发两次时每个底池按两组公共牌各分一半
+    fn showdown(&mut self) -> Vec<RoomDelta> {
+        let board_cards = self.board_cards();
+        let second_board = if self
+            .run_it_twice_vote
+            .as_ref()
+            .is_some_and(|vote| vote.is_accepted())
+        {
+            // 从同一副牌中为表决后的公共牌再发一组
+            let mut second_board = board_cards[..self.run_it_twice_from].to_vec();
+            while second_board.len() < board_cards.len() {
+                second_board.push(self.deal());
+            }
+            Some(second_board)
+        } else {
+            None
+        };
+        let mut boards = vec![board_cards];
+        boards.extend(second_board.clone());
+
+        let in_hand_names = self.in_hand_names();
+        let board_values: Vec<HashMap<String, HandValue>> = boards
+            .iter()
+            .map(|board| {
+                in_hand_names
+                    .iter()
+                    .map(|player_name| {
+                        let hole_cards = &self.hole_cards[player_name];
+                        (
+                            player_name.clone(),
+                            self.variant.evaluate(hole_cards, board),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let shown_hands = in_hand_names
+            .iter()
+            .map(|player_name| ShownHand {
+                player_name: player_name.clone(),
+                cards: self.hole_cards[player_name].clone(),
+                category: board_values[0][player_name].category,
+                second_category: board_values
+                    .get(1)
+                    .map(|hand_values| hand_values[player_name].category),
+            })
+            .collect();
+
+        // 抽水依次从主池和边池中扣除
+        let rake = self.collect_rake();
+        let mut remaining_rake = rake;
+        let mut winnings = std::collections::HashMap::new();
+        for (amount, eligible_names) in self.side_pots() {
+            let taken = remaining_rake.min(amount);
+            remaining_rake -= taken;
+            let amount = amount - taken;
+            // 多余的筹码归第一组公共牌
+            let half = amount / board_values.len() as u32;
+            for (index, hand_values) in board_values.iter().enumerate() {
+                let board_amount = if index == 0 {
+                    amount - half * (board_values.len() as u32 - 1)
+                } else {
+                    half
+                };
+                self.award_pot(board_amount, &eligible_names, hand_values, &mut winnings);
+            }
+        }
+        self.finish(ShowdownResult {
+            shown_hands,
+            winnings,
+            second_board,
+            rake,
+        })
+    }
+
+    // 将底池分给有资格的玩家中牌力最大者
+    fn award_pot(
+        &self,
+        amount: u32,
+        eligible_names: &[String],
+        hand_values: &HashMap<String, HandValue>,
+        winnings: &mut std::collections::HashMap<String, u32>,
+    ) {
+        let best = eligible_names
+            .iter()
+            .filter_map(|player_name| hand_values.get(player_name))
+            .max();
+        let Some(best) = best else {
+            return;
+        };
+        let winner_names = self.seat_order_from_dealer(
+            eligible_names
+                .iter()
+                .filter(|player_name| hand_values.get(*player_name) == Some(best))
+                .cloned()
+                .collect(),
+        );
+        // 平分底池，多余的筹码给庄家之后最近的赢家
+        let share = amount / winner_names.len() as u32;
+        let remainder = amount % winner_names.len() as u32;
+        for (i, winner_name) in winner_names.iter().enumerate() {
+            let won = share + if i == 0 { remainder } else { 0 };
+            *winnings.entry(winner_name.clone()).or_insert(0) += won;
+        }
+    }
+
+    // 按累计下注拆分主池和边池，返回 (金额, 有资格赢取的玩家)
+    fn side_pots(&self) -> Vec<(u32, Vec<String>)> {
+        let mut levels: Vec<u32> = self
+            .total_bets
+            .iter()
+            .filter(|(player_name, _)| !self.folded_names.contains(*player_name))
+            .map(|(_, total)| *total)
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+
+        let mut pots = Vec::new();
+        let mut previous_level = 0;
+        for level in levels {
+            let amount: u32 = self
+                .total_bets
+                .values()
+                .map(|total| (*total).min(level).saturating_sub(previous_level))
+                .sum();
+            let eligible_names = self
+                .total_bets
+                .iter()
+                .filter(|(player_name, total)| {
+                    **total >= level && !self.folded_names.contains(*player_name)
+                })
+                .map(|(player_name, _)| player_name.clone())
+                .collect();
+            pots.push((amount, eligible_names));
+            previous_level = level;
+        }
+        // 死钱并入主池，已弃牌玩家超出最高档的下注并入最后一个池
+        let total_in_pots: u32 = pots.iter().map(|(amount, _)| *amount).sum();
+        if let Some((amount, _)) = pots.first_mut() {
+            *amount += self.dead_money;
+        }
+        if let Some((amount, _)) = pots.last_mut() {
+            *amount += self.pot - total_in_pots - self.dead_money;
+        }
+        pots
+    }
+
+    // 按庄家之后的座位顺序排序
+    fn seat_order_from_dealer(&self, mut player_names: Vec<String>) -> Vec<String> {
+        let len = self.participants.len();
+        let dealer_index = self
+            .dealer_name
+            .as_ref()
+            .and_then(|dealer_name| self.index_of(dealer_name))
+            .unwrap_or(0);
+        player_names.sort_by_key(|player_name| {
+            let index = self.index_of(player_name).unwrap_or(0);
+            (index + len - dealer_index - 1) % len
+        });
+        player_names
+    }
+
+    fn finish(&mut self, result: ShowdownResult) -> Vec<RoomDelta> {
+        for (player_name, amount) in result.winnings.iter() {
+            if let Some(index) = self.index_of(player_name) {
+                self.participants[index].chips += amount;
+                self.record(TransactionKind::Award, player_name, *amount);
+            }
+        }
+        self.pot = 0;
+        self.current_player_name = None;
+        self.round = Round::Showdown;
+        self.showdown_cd = SHOWDOWN_DELAY_SECS;
+        self.showdown = Some(result.clone());
+        vec![RoomDelta::PlayShowdown {
+            play_id: self.play_id,
+            result,
+        }]
     }
 }
 
-pub fn start_new_play(room_list: Res<RoomList>, mut play_list: ResMut<PlayList>) {
-    for room in room_list.0.iter() {
+pub fn start_new_play(
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    mut room_list: ResMut<RoomList>,
+    tournament_list: Res<TournamentList>,
+    mut play_list: ResMut<PlayList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+) {
+    for room in room_list.iter_mut() {
         // 如果房间正在游戏中，且对局列表中没有该房间的对局，则创建新的对局
-        if room.room_state == RoomState::Playing
-            && play_list
-                .0
+        // 锦标赛等待调整各桌人数时暂停开局；有筹码的参与者人数足够才分配对局id并洗牌
+        if room.room_state.is_in_play()
+            && !tournament_list.is_held(room)
+            && !play_list
+                .plays
                 .iter()
-                .find(|play| play.room_id == room.room_id)
-                .is_none()
+                .any(|play| play.room_id == room.room_id)
+            && room.dealt_in_players().count() >= room.min_participants()
         {
-            let play = Play {
-                play_id: timestamp(),
-                room_id: room.room_id,
-                round: Round::Start,
-                participants: room
-                    .players
-                    .iter()
-                    .filter(|player| player.player_role == PlayerRole::Participant)
-                    .cloned()
-                    .collect(),
-                dealer_name: None,
-                small_blind_name: None,
-                big_blind_name: None,
-                hole_cards: HashMap::new(),
-                flop_cards: None,
-                turn_card: None,
-                river_card: None,
-                card_pool: Card::pool(),
-                pot: 0,
-            };
-            // 参与者人数大于等于3人才开始游戏
-            // TODO 筹码检查
-            if play.participants.len() >= 3 {
-                play_list.0.push(play);
+            let play = Play::new(play_list.play_ids.next_id(), room);
+            room_delta_ew.send(RoomDeltaEvent {
+                room_id: play.room_id,
+                delta: RoomDelta::PlayStarted(Box::new(play.snapshot())),
+            });
+            let deltas = update_seats(
+                room,
+                &play,
+                &mut bank,
+                &play_list,
+                settings.seat.max_sit_out_orbits,
+            );
+            room_delta_ew.send_batch(deltas.into_iter().map(|delta| RoomDeltaEvent {
+                room_id: play.room_id,
+                delta,
+            }));
+            play_list.plays.push(play);
+        }
+    }
+}
+
+// 新对局开始时更新座位：坐出的玩家被大小盲注跳过时记为错过盲注，
+// 错过大盲注超过圈数上限时离开座位并兑现筹码；被发牌的玩家已在本局补交错过的盲注
+fn update_seats(
+    room: &mut Room,
+    play: &Play,
+    bank: &mut Bank,
+    play_list: &PlayList,
+    max_sit_out_orbits: u32,
+) -> Vec<RoomDelta> {
+    let (Some(dealer_name), Some(small_blind_name), Some(big_blind_name)) = (
+        play.dealer_name.as_ref(),
+        play.small_blind_name.as_ref(),
+        play.big_blind_name.as_ref(),
+    ) else {
+        return Vec::new();
+    };
+    let seats: Vec<usize> = (0..room.players.len())
+        .filter(|index| room.players[*index].player_role == PlayerRole::Participant)
+        .collect();
+    let seat_of = |player_name: &String| {
+        seats
+            .iter()
+            .position(|index| &room.players[*index].player_name == player_name)
+    };
+    // 按座位顺序在from和to之间（不含两端）的玩家
+    let between = |from: Option<usize>, to: Option<usize>| -> Vec<usize> {
+        let (Some(from), Some(to)) = (from, to) else {
+            return Vec::new();
+        };
+        // 单挑时庄家即小盲注，中间没有座位
+        if from == to {
+            return Vec::new();
+        }
+        (1..seats.len())
+            .map(|offset| (from + offset) % seats.len())
+            .take_while(|seat| *seat != to)
+            .map(|seat| seats[seat])
+            .collect()
+    };
+    let missed_big_blind = between(seat_of(small_blind_name), seat_of(big_blind_name));
+    let missed_small_blind = between(seat_of(dealer_name), seat_of(small_blind_name));
+
+    let mut deltas = Vec::new();
+    let mut removed_ids = Vec::new();
+    for (index, player) in room.players.iter_mut().enumerate() {
+        let dealt_in = play
+            .participants
+            .iter()
+            .any(|participant| participant.player_client_id == player.player_client_id);
+        let previous = player.missed_blinds;
+        if dealt_in {
+            player.missed_blinds = MissedBlinds::default();
+        } else if player.sitting_out {
+            if missed_big_blind.contains(&index) {
+                player.missed_blinds.big_blinds += 1;
             }
+            if missed_small_blind.contains(&index) {
+                player.missed_blinds.small_blind = true;
+            }
+            if max_sit_out_orbits > 0 && player.missed_blinds.big_blinds >= max_sit_out_orbits {
+                info!(
+                    "Player {} sat out too long, leaving seat",
+                    player.player_name
+                );
+                player.player_role = PlayerRole::Spectator;
+                player.sitting_out = false;
+                player.missed_blinds = MissedBlinds::default();
+                deltas.push(RoomDelta::PlayerRoleChanged {
+                    player_client_id: player.player_client_id,
+                    player_role: PlayerRole::Spectator,
+                });
+                removed_ids.push(player.player_client_id);
+                continue;
+            }
+        }
+        if player.missed_blinds != previous {
+            deltas.push(RoomDelta::PlayerSeatChanged {
+                player_client_id: player.player_client_id,
+                sitting_out: player.sitting_out,
+                missed_blinds: player.missed_blinds,
+            });
         }
     }
+    for client_id in removed_ids {
+        deltas.extend(bank::leave_table(bank, play_list, room, client_id));
+    }
+    deltas
 }
 
-pub fn process_play_round_start(mut play_list: ResMut<PlayList>, room_list: Res<RoomList>) {
-    for play in play_list.0.iter_mut() {
+pub fn process_play_round_start(
+    mut play_list: ResMut<PlayList>,
+    room_list: Res<RoomList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut hole_cards_ew: EventWriter<HoleCardsEvent>,
+) {
+    for play in play_list.plays.iter_mut() {
         if play.round != Round::Start {
             continue;
         }
-        if let Some(room) = room_list.0.iter().find(|room| room.room_id == play.room_id) {
-            if room.room_state != RoomState::Playing {
+        if let Some(room) = room_list.get(play.room_id) {
+            if !room.room_state.is_in_play() {
                 continue;
             }
-            // 确定庄家以及大盲注和小盲注位置
-            if let Some(last_dealer_name) = room.last_dealer_name.as_ref() {
-                if let Some(last_dealer_index) = play
+            let deltas = play.start_preflop();
+            room_delta_ew.send_batch(deltas.into_iter().map(|delta| RoomDeltaEvent {
+                room_id: play.room_id,
+                delta,
+            }));
+            for participant in play.participants.iter() {
+                hole_cards_ew.send(HoleCardsEvent {
+                    room_id: play.room_id,
+                    play_id: play.play_id,
+                    client_id: participant.player_client_id,
+                    cards: play.hole_cards[&participant.player_name].clone(),
+                });
+            }
+        }
+    }
+}
+
+pub fn handle_player_action(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    room_list: Res<RoomList>,
+    mut play_list: ResMut<PlayList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, PLAYER_ACTION_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<PlayerActionMessage>(&message) {
+                info!("Received player action message: {:?}", message);
+                let result = find_client_play(
+                    &room_list,
+                    &mut play_list,
+                    client_id,
+                    message.room_id,
+                    message.play_id,
+                )
+                .and_then(|(play, player_name)| {
+                    play.act(&player_name, message.action, message.amount)
+                        .map(|deltas| (play.room_id, deltas))
+                });
+                match result {
+                    Ok((room_id, deltas)) => {
+                        room_delta_ew.send_batch(
+                            deltas
+                                .into_iter()
+                                .map(|delta| RoomDeltaEvent { room_id, delta }),
+                        );
+                    }
+                    Err(error) => message.error = Some(error),
+                }
+                server.send_message(
+                    client_id,
+                    PLAYER_ACTION_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+pub fn handle_run_it_twice(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    room_list: Res<RoomList>,
+    mut play_list: ResMut<PlayList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, RUN_IT_TWICE_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<RunItTwiceMessage>(&message) {
+                info!("Received run it twice message: {:?}", message);
+                let result = find_client_play(
+                    &room_list,
+                    &mut play_list,
+                    client_id,
+                    message.room_id,
+                    message.play_id,
+                )
+                .and_then(|(play, player_name)| {
+                    play.answer_run_it_twice(&player_name, message.accept)
+                        .map(|deltas| (play.room_id, deltas))
+                });
+                match result {
+                    Ok((room_id, deltas)) => {
+                        room_delta_ew.send_batch(
+                            deltas
+                                .into_iter()
+                                .map(|delta| RoomDeltaEvent { room_id, delta }),
+                        );
+                    }
+                    Err(error) => message.error = Some(error),
+                }
+                server.send_message(
+                    client_id,
+                    RUN_IT_TWICE_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+// 查找客户端所在房间中进行中的对局，返回对局和客户端的玩家名称
+fn find_client_play<'a>(
+    room_list: &RoomList,
+    play_list: &'a mut PlayList,
+    client_id: u64,
+    room_id: u64,
+    play_id: u64,
+) -> Result<(&'a mut Play, String), RequestError> {
+    let room = room_list
+        .room_of_client(client_id)
+        .filter(|room| room.room_id == room_id)
+        .ok_or(RequestError::NotInRoom)?;
+    if !room.room_state.is_in_play() {
+        return Err(RequestError::InvalidAction);
+    }
+    let play = play_list
+        .plays
+        .iter_mut()
+        .find(|play| play.room_id == room.room_id && play.play_id == play_id)
+        .ok_or(RequestError::NoActivePlay)?;
+    let player_name = play
+        .participants
+        .iter()
+        .find(|player| player.player_client_id == client_id)
+        .map(|player| player.player_name.clone())
+        .ok_or(RequestError::NotYourTurn)?;
+    Ok((play, player_name))
+}
+
+pub fn process_play_turn_timeout(
+    mut play_list: ResMut<PlayList>,
+    room_list: Res<RoomList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    for play in play_list.plays.iter_mut() {
+        if play.current_player_name.is_none() && !play.is_voting() {
+            continue;
+        }
+        if room_list
+            .get(play.room_id)
+            .is_none_or(|room| !room.room_state.is_in_play())
+        {
+            continue;
+        }
+        play.turn_cd -= time.delta_seconds();
+        if play.turn_cd < 0.0 {
+            info!(
+                "Player {:?} timed out in play {}",
+                play.current_player_name, play.play_id
+            );
+            let deltas = play.act_on_timeout();
+            room_delta_ew.send_batch(deltas.into_iter().map(|delta| RoomDeltaEvent {
+                room_id: play.room_id,
+                delta,
+            }));
+        }
+    }
+}
+
+// 摊牌展示结束后结算筹码、记录庄家位置并移除对局
+pub fn process_play_round_end(
+    mut play_list: ResMut<PlayList>,
+    mut bank: ResMut<Bank>,
+    mut hand_history: ResMut<HandHistory>,
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    for play in play_list.plays.iter_mut() {
+        if play.round == Round::Showdown {
+            play.showdown_cd -= time.delta_seconds();
+            if play.showdown_cd < 0.0 {
+                play.round = Round::End;
+            }
+        }
+    }
+
+    let (ended, plays) = std::mem::take(&mut play_list.plays)
+        .into_iter()
+        .partition(|play| play.round == Round::End);
+    play_list.plays = plays;
+
+    for play in ended {
+        if let Err(e) = hand_history.append(HandRecord::new(&play)) {
+            error!(
+                "Failed to record play {} in room {}: {}",
+                play.play_id, play.room_id, e
+            );
+        }
+        if let Some(room) = room_list.get_mut(play.room_id) {
+            let is_cash_game = room.tournament_id.is_none();
+            // 现金桌结算本局后的筹码，已离座或离开房间的玩家随后兑现；
+            // 结算失败时本局作废，桌上筹码保持账户中记录的数额
+            let settled = !is_cash_game || {
+                let stacks: Vec<(String, u32)> = play
                     .participants
                     .iter()
-                    .position(|player| player.player_name == *last_dealer_name)
+                    .map(|participant| (participant.player_name.clone(), participant.chips))
+                    .collect();
+                match bank.settle(room.room_id, &stacks, &play.ledger) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        error!(
+                            "Failed to settle play {} in room {}, voiding it: {:?}",
+                            play.play_id, room.room_id, error
+                        );
+                        false
+                    }
+                }
+            };
+            let mut left_ids = Vec::new();
+            for participant in play.participants.iter() {
+                match room
+                    .players
+                    .iter_mut()
+                    .find(|player| player.player_client_id == participant.player_client_id)
                 {
-                    play.dealer_name = Some(
-                        play.participants[(last_dealer_index + 1) % play.participants.len()]
-                            .player_name
-                            .clone(),
-                    );
-                    play.small_blind_name = Some(
-                        play.participants[(last_dealer_index + 2) % play.participants.len()]
-                            .player_name
-                            .clone(),
-                    );
-                    play.big_blind_name = Some(
-                        play.participants[(last_dealer_index + 3) % play.participants.len()]
-                            .player_name
-                            .clone(),
-                    );
-                } else {
-                    play.dealer_name = Some(play.participants[0].player_name.clone());
-                    play.small_blind_name = Some(play.participants[1].player_name.clone());
-                    play.big_blind_name = Some(play.participants[2].player_name.clone());
+                    Some(player) => {
+                        if settled {
+                            player.chips = participant.chips;
+                        }
+                        room_delta_ew.send(RoomDeltaEvent {
+                            room_id: room.room_id,
+                            delta: RoomDelta::PlayerChipsChanged {
+                                player_client_id: player.player_client_id,
+                                chips: player.chips,
+                            },
+                        });
+                        if is_cash_game && player.player_role != PlayerRole::Participant {
+                            left_ids.push(player.player_client_id);
+                        }
+                    }
+                    None if is_cash_game => {
+                        if let Err(error) = bank.cash_out(&participant.player_name, room.room_id) {
+                            error!(
+                                "Failed to cash out {}: {:?}",
+                                participant.player_name, error
+                            );
+                        }
+                    }
+                    None => {}
+                }
+            }
+            for client_id in left_ids {
+                if let Some(delta) = bank::leave_table(&mut bank, &play_list, room, client_id) {
+                    room_delta_ew.send(RoomDeltaEvent {
+                        room_id: room.room_id,
+                        delta,
+                    });
                 }
-            } else {
-                play.dealer_name = Some(play.participants[0].player_name.clone());
-                play.small_blind_name = Some(play.participants[1].player_name.clone());
-                play.big_blind_name = Some(play.participants[2].player_name.clone());
             }
-            play.round = Round::Preflop;
+            room.last_dealer_name = play.dealer_name.clone();
         }
+        room_delta_ew.send(RoomDeltaEvent {
+            room_id: play.room_id,
+            delta: RoomDelta::PlayEnded {
+                play_id: play.play_id,
+            },
+        });
+        // 锦标赛淘汰玩家、升级盲注
+        room_delta_ew.send_batch(tournament::on_play_ended(
+            &mut room_list,
+            &mut tournament_list,
+            &play,
+        ));
     }
 }
 
-pub fn process_play_round_preflop(mut play_list: ResMut<PlayList>, room_list: Res<RoomList>) {
-    for play in play_list.0.iter_mut() {
-        if play.round != Round::Preflop {
-            continue;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_seat::test_room;
+
+    fn play_with_bets(total_bets: &[(&str, u32)], folded_names: &[&str]) -> Play {
+        let mut play = Play::new(1, &test_room(1));
+        for (player_name, total) in total_bets {
+            play.total_bets.insert(player_name.to_string(), *total);
+            play.pot += total;
         }
-        if let Some(room) = room_list.0.iter().find(|room| room.room_id == play.room_id) {
-            if room.room_state != RoomState::Playing {
-                continue;
-            }
-            // 大小盲注（大盲注为最小下注金额、小盲注为最小下注金额一半）
-            // 发手牌
-            // 下注
+        for player_name in folded_names {
+            play.folded_names.insert(player_name.to_string());
+        }
+        play
+    }
+
+    fn sorted_pots(play: &Play) -> Vec<(u32, Vec<String>)> {
+        play.side_pots()
+            .into_iter()
+            .map(|(amount, mut eligible_names)| {
+                eligible_names.sort();
+                (amount, eligible_names)
+            })
+            .collect()
+    }
+
+    fn names(player_names: &[&str]) -> Vec<String> {
+        player_names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn side_pots_split_by_all_in_levels() {
+        let play = play_with_bets(&[("a", 100), ("b", 300), ("c", 300)], &[]);
+        assert_eq!(
+            sorted_pots(&play),
+            vec![(300, names(&["a", "b", "c"])), (400, names(&["b", "c"]))]
+        );
+    }
+
+    #[test]
+    fn folded_players_fund_pots_they_cannot_win() {
+        let play = play_with_bets(&[("a", 100), ("b", 200), ("c", 300)], &["b"]);
+        assert_eq!(
+            sorted_pots(&play),
+            vec![(300, names(&["a", "c"])), (300, names(&["c"]))]
+        );
+    }
 
-            play.round = Round::Flop;
+    #[test]
+    fn dead_money_goes_to_the_main_pot() {
+        let mut play = play_with_bets(&[("a", 50), ("b", 80)], &[]);
+        play.dead_money = 10;
+        play.pot += 10;
+        assert_eq!(
+            sorted_pots(&play),
+            vec![(110, names(&["a", "b"])), (30, names(&["b"]))]
+        );
+    }
+
+    #[test]
+    fn short_handed_tables_do_not_allocate_play_ids() {
+        let mut room = test_room(1);
+        room.players.push(Player {
+            player_client_id: 1,
+            player_name: "a".to_string(),
+            player_role: PlayerRole::Participant,
+            chips: 100,
+            sitting_out: false,
+            missed_blinds: MissedBlinds::default(),
+        });
+        let mut room_list = RoomList::default();
+        room_list.insert(room);
+
+        let mut app = App::new();
+        app.add_event::<RoomDeltaEvent>()
+            .insert_resource(ServerSettings::default())
+            .insert_resource(Bank::load(&ServerSettings::default()).unwrap())
+            .insert_resource(room_list)
+            .insert_resource(TournamentList::default())
+            .insert_resource(PlayList::default())
+            .add_system(start_new_play);
+        app.update();
+        app.update();
+
+        let mut play_list = app.world.resource_mut::<PlayList>();
+        assert!(play_list.plays.is_empty());
+        assert_eq!(play_list.play_ids.next_id(), 1);
+    }
+
+    #[test]
+    fn failed_settlement_keeps_table_chips() {
+        let mut room = test_room(1);
+        for (client_id, player_name) in [(1, "a"), (2, "b")] {
+            room.players.push(Player {
+                player_client_id: client_id,
+                player_name: player_name.to_string(),
+                player_role: PlayerRole::Participant,
+                chips: 100,
+                sitting_out: false,
+                missed_blinds: MissedBlinds::default(),
+            });
         }
+        // 账户中没有这两位玩家的桌上筹码，结算失败
+        let mut play = Play::new(1, &room);
+        play.participants[0].chips = 150;
+        play.participants[1].chips = 50;
+        play.round = Round::End;
+        let mut room_list = RoomList::default();
+        room_list.insert(room);
+        let mut play_list = PlayList::default();
+        play_list.plays.push(play);
+
+        let mut app = App::new();
+        app.add_event::<RoomDeltaEvent>()
+            .init_resource::<Time>()
+            .insert_resource(Bank::load(&ServerSettings::default()).unwrap())
+            .insert_resource(HandHistory::new(None))
+            .insert_resource(room_list)
+            .insert_resource(TournamentList::default())
+            .insert_resource(play_list)
+            .add_system(process_play_round_end);
+        app.update();
+
+        assert!(app.world.resource::<PlayList>().plays.is_empty());
+        let chips: Vec<u32> = app
+            .world
+            .resource::<RoomList>()
+            .get(1)
+            .unwrap()
+            .players
+            .iter()
+            .map(|player| player.chips)
+            .collect();
+        assert_eq!(chips, vec![100, 100]);
     }
 }
@@ -0,0 +1,381 @@
+use std::{cmp::Reverse, collections::VecDeque};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::RenetServer;
+use texas_holdem_common::{
+    betting::{ForcedBets, RakeConfig},
+    channel::{QuickSeatMessage, RequestError, RoomDelta, QUICK_SEAT_CHANNEL_ID},
+    lobby::{is_valid_name, truncate_name, MAX_PLAYER_NAME_CHARS, MAX_ROOM_NAME_CHARS},
+    spectator::SpectatorConfig,
+    MissedBlinds, Player, PlayerRole, RoomState,
+};
+
+use crate::{
+    bank::{self, Bank},
+    config::{ServerSettings, TableTemplate},
+    directory::Drain,
+    lobby::leave_room,
+    network::Sessions,
+    play::PlayList,
+    room::{Room, RoomDeltaEvent, RoomList, RoomSnapshotEvent},
+    tournament::TournamentList,
+};
+
+// 服务端按模板开的牌桌没有房主连接，只显示该名称
+const SERVER_OWNER_NAME: &str = "Quick Seat";
+
+// 在满桌等候队列中的玩家
+#[derive(Debug, Clone)]
+pub struct WaitingPlayer {
+    pub client_id: u64,
+    pub player_name: String,
+    // 排到时用原请求id通知客户端
+    pub request_id: u64,
+}
+
+// 快速入座的玩家只进入公开、未锁定且没有禁止自己的现金桌
+fn accepts_quick_seat(
+    room: &Room,
+    sessions: &Sessions,
+    message: &QuickSeatMessage,
+    client_id: u64,
+) -> bool {
+    room.tournament_id.is_none()
+        && !room.is_private()
+        && !room.locked
+        && !room.is_banned(sessions, &message.player_name, client_id)
+        && !room.contains_player(&message.player_name)
+        && room.room_state != RoomState::Finished
+        && room.variant == message.variant
+        && in_range(message, room.big_blind)
+}
+
+fn has_free_seat(room: &Room, settings: &ServerSettings) -> bool {
+    room.participant_count() < settings.seat.table_seats as usize
+}
+
+fn in_range(message: &QuickSeatMessage, big_blind: u32) -> bool {
+    message.min_big_blind.is_none_or(|min| big_blind >= min)
+        && message.max_big_blind.is_none_or(|max| big_blind <= max)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_quick_seat(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    play_list: Res<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    tournament_list: Res<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+    drain: Res<Drain>,
+    time: Res<Time>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, QUICK_SEAT_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<QuickSeatMessage>(&message) {
+                info!("Received quick seat message: {:?}", message);
+                // 同时只在一个等候队列中
+                for room in room_list.iter_mut() {
+                    room.waiting_list
+                        .retain(|waiting| waiting.client_id != client_id);
+                }
+                if !message.cancel && drain.is_draining() {
+                    message.error = Some(RequestError::ServerDraining);
+                } else if !message.cancel && tournament_list.is_playing(&room_list, client_id) {
+                    // 参赛玩家不能离开锦标赛的牌桌
+                    message.error = Some(RequestError::RegistrationClosed);
+                } else if !message.cancel {
+                    if let Err(error) = quick_seat(
+                        &settings,
+                        &sessions,
+                        &mut bank,
+                        &play_list,
+                        &mut room_list,
+                        &mut room_delta_ew,
+                        &mut room_snapshot_ew,
+                        client_id,
+                        &mut message,
+                        time.elapsed_seconds_f64(),
+                    ) {
+                        message.error = Some(error);
+                    }
+                }
+                server.send_message(
+                    client_id,
+                    QUICK_SEAT_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+// 依次尝试：人最多的有空座位的桌、按模板新开一桌、排队人数最少的满桌
+#[allow(clippy::too_many_arguments)]
+fn quick_seat(
+    settings: &ServerSettings,
+    sessions: &Sessions,
+    bank: &mut Bank,
+    play_list: &PlayList,
+    room_list: &mut RoomList,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+    room_snapshot_ew: &mut EventWriter<RoomSnapshotEvent>,
+    client_id: u64,
+    message: &mut QuickSeatMessage,
+    now: f64,
+) -> Result<(), RequestError> {
+    if !is_valid_name(&message.player_name, MAX_PLAYER_NAME_CHARS) {
+        return Err(RequestError::InvalidName);
+    }
+    bank::open_account(bank, sessions, client_id, now)?;
+    let candidates: Vec<&Room> = room_list
+        .iter()
+        .filter(|room| accepts_quick_seat(room, sessions, message, client_id))
+        .collect();
+    // 有排队的玩家时空座位留给队列
+    let free_room_id = candidates
+        .iter()
+        .filter(|room| has_free_seat(room, settings) && room.waiting_list.is_empty())
+        .max_by_key(|room| (room.participant_count(), Reverse(room.room_id)))
+        .map(|room| room.room_id);
+    let full_room_id = candidates
+        .iter()
+        .min_by_key(|room| (room.waiting_list.len(), room.room_id))
+        .map(|room| room.room_id);
+    let template = settings.quick_seat.templates.iter().find(|template| {
+        template.variant == message.variant && in_range(message, template.big_blind)
+    });
+
+    let room_id = match (free_room_id, template, full_room_id) {
+        (Some(room_id), _, _) => room_id,
+        (None, Some(template), _) => {
+            let room_id = room_list.next_room_id();
+            room_list.insert(template_room(room_id, template));
+            info!("Opened room {} for quick seat", room_id);
+            room_id
+        }
+        (None, None, Some(room_id)) => {
+            let room = room_list.get_mut(room_id).unwrap();
+            room.waiting_list.push_back(WaitingPlayer {
+                client_id,
+                player_name: message.player_name.clone(),
+                request_id: message.request_id,
+            });
+            message.room_id = room_id;
+            message.waiting_position = room.waiting_list.len() as u32;
+            return Ok(());
+        }
+        (None, None, None) => return Err(RequestError::NoTableAvailable),
+    };
+    seat_player(
+        settings,
+        sessions,
+        bank,
+        play_list,
+        room_list,
+        room_delta_ew,
+        room_snapshot_ew,
+        room_id,
+        client_id,
+        &message.player_name,
+    )?;
+    message.room_id = room_id;
+    message.seated = true;
+    Ok(())
+}
+
+// 按模板开一张现金桌，直接进入游戏状态，人数够了自动开局
+fn template_room(room_id: u64, template: &TableTemplate) -> Room {
+    Room {
+        room_id,
+        room_name: truncate_name(
+            &format!(
+                "{} {}/{} #{}",
+                template.variant.name(),
+                template.small_blind,
+                template.big_blind,
+                room_id
+            ),
+            MAX_ROOM_NAME_CHARS,
+        ),
+        password: None,
+        invites: HashMap::new(),
+        room_state: RoomState::Playing,
+        variant: template.variant,
+        betting: template.betting(),
+        run_it_twice: false,
+        forced_bets: ForcedBets::default(),
+        rake: RakeConfig::default(),
+        spectators: SpectatorConfig::default(),
+        spectator_feed: None,
+        owner_name: SERVER_OWNER_NAME.to_string(),
+        owner_user_id: None,
+        locked: false,
+        bans: HashMap::new(),
+        small_blind: template.small_blind,
+        big_blind: template.big_blind,
+        players: Vec::new(),
+        last_dealer_name: None,
+        tournament_id: None,
+        sync_seq: 0,
+        muted_names: HashSet::new(),
+        waiting_list: VecDeque::new(),
+    }
+}
+
+// 单元测试用的1/2现金桌
+#[cfg(test)]
+pub(crate) fn test_room(room_id: u64) -> Room {
+    let template = TableTemplate {
+        variant: texas_holdem_common::variant::GameVariant::default(),
+        betting: None,
+        small_blind: 1,
+        big_blind: 2,
+    };
+    template_room(room_id, &template)
+}
+
+// 离开当前房间后进入room_id并买入坐下，买入失败时不留在房间内
+#[allow(clippy::too_many_arguments)]
+fn seat_player(
+    settings: &ServerSettings,
+    sessions: &Sessions,
+    bank: &mut Bank,
+    play_list: &PlayList,
+    room_list: &mut RoomList,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+    room_snapshot_ew: &mut EventWriter<RoomSnapshotEvent>,
+    room_id: u64,
+    client_id: u64,
+    player_name: &str,
+) -> Result<(), RequestError> {
+    leave_room(room_list, bank, play_list, client_id, room_delta_ew);
+    room_list.add_player(
+        room_id,
+        Player {
+            player_client_id: client_id,
+            player_name: player_name.to_string(),
+            player_role: PlayerRole::Spectator,
+            chips: 0,
+            sitting_out: false,
+            missed_blinds: MissedBlinds::default(),
+        },
+    );
+    let room = room_list
+        .get_mut(room_id)
+        .ok_or(RequestError::RoomNotFound)?;
+    if let Err(error) = bank::sit_down(bank, settings, sessions, room, client_id) {
+        room_list.remove_player(client_id);
+        return Err(error);
+    }
+    let player = room
+        .players
+        .iter_mut()
+        .find(|player| player.player_client_id == client_id)
+        .unwrap();
+    player.player_role = PlayerRole::Participant;
+    room_delta_ew.send(RoomDeltaEvent {
+        room_id,
+        delta: RoomDelta::PlayerJoined(player.clone()),
+    });
+    room_snapshot_ew.send(RoomSnapshotEvent { room_id, client_id });
+    Ok(())
+}
+
+// 牌桌空出座位时按顺序让排队的玩家入座，已断开的玩家移出队列，已进入其他房间的玩家取消排队
+#[allow(clippy::too_many_arguments)]
+pub fn seat_waiting_players(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    play_list: Res<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+) {
+    let room_ids: Vec<u64> = room_list
+        .iter()
+        .filter(|room| !room.waiting_list.is_empty())
+        .map(|room| room.room_id)
+        .collect();
+    for room_id in room_ids {
+        while let Some(waiting) = room_list
+            .get_mut(room_id)
+            .filter(|room| !room.locked && has_free_seat(room, &settings))
+            .and_then(|room| room.waiting_list.pop_front())
+        {
+            let client_id = waiting.client_id;
+            if sessions.get(client_id).is_none() {
+                continue;
+            }
+            if room_list.room_of_client(client_id).is_some() {
+                info!(
+                    "Cancelled quick seat of {} in another room",
+                    waiting.player_name
+                );
+                let room = room_list.get(room_id).unwrap();
+                notify_waiting(&mut server, waiting, room, true, Ok(()));
+                continue;
+            }
+            let room = room_list.get(room_id).unwrap();
+            let result = if room.is_banned(&sessions, &waiting.player_name, client_id) {
+                Err(RequestError::Banned)
+            } else if room.contains_player(&waiting.player_name) {
+                Err(RequestError::NameTaken)
+            } else {
+                seat_player(
+                    &settings,
+                    &sessions,
+                    &mut bank,
+                    &play_list,
+                    &mut room_list,
+                    &mut room_delta_ew,
+                    &mut room_snapshot_ew,
+                    room_id,
+                    client_id,
+                    &waiting.player_name,
+                )
+            };
+            info!(
+                "Seated {} from waiting list of room {}: {:?}",
+                waiting.player_name, room_id, result
+            );
+            let room = room_list.get(room_id).unwrap();
+            notify_waiting(&mut server, waiting, room, false, result);
+        }
+    }
+}
+
+// 用排队时的请求id通知结果：入座、取消排队或失败
+fn notify_waiting(
+    server: &mut RenetServer,
+    waiting: WaitingPlayer,
+    room: &Room,
+    cancel: bool,
+    result: Result<(), RequestError>,
+) {
+    let message = QuickSeatMessage {
+        request_id: waiting.request_id,
+        player_name: waiting.player_name,
+        variant: room.variant,
+        min_big_blind: None,
+        max_big_blind: None,
+        cancel,
+        room_id: room.room_id,
+        seated: !cancel && result.is_ok(),
+        waiting_position: 0,
+        error: result.err(),
+    };
+    server.send_message(
+        waiting.client_id,
+        QUICK_SEAT_CHANNEL_ID,
+        serde_json::to_vec(&message).unwrap(),
+    );
+}
@@ -1,25 +1,184 @@
-use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_renet::renet::RenetServer;
 use texas_holdem_common::{
+    betting::{BettingStructure, ForcedBets, RakeConfig},
     channel::{
-        BroadcastRoomInfoMessage, SetRoomStateMessage, SwitchPlayerRoleMessage,
-        BROADCAST_ROOM_INFO_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID, SWITCH_PLAYER_ROLE_CHANNEL_ID,
+        InviteCode, RequestError, RoomAdminCommand, RoomAdminMessage, RoomDelta, RoomDeltaMessage,
+        RoomSnapshot, RoomSyncMessage, SetRoomStateMessage, SitOutMessage, SwitchPlayerRoleMessage,
+        ROOM_ADMIN_CHANNEL_ID, ROOM_SYNC_CHANNEL_ID, SET_ROOM_STATE_CHANNEL_ID, SIT_OUT_CHANNEL_ID,
+        SWITCH_PLAYER_ROLE_CHANNEL_ID,
     },
-    util::timestamp,
-    Player, PlayerRole, RoomState,
+    lobby::{truncate_name, MAX_ROOM_NAME_CHARS},
+    spectator::SpectatorConfig,
+    sync::encode_room_sync,
+    util::IdAllocator,
+    variant::GameVariant,
+    Card, Player, PlayerRole, RoomState,
+};
+
+use crate::{
+    access::{new_invite_code, PasswordHash},
+    bank::{self, Bank},
+    config::ServerSettings,
+    lobby::leave_room,
+    network::Sessions,
+    play::PlayList,
+    quick_seat::WaitingPlayer,
+    spectator::{hide_spectators, hide_spectators_in_delta, SpectatorFeed},
+    tournament::{self, TournamentList},
 };
 
 #[derive(Debug)]
 pub struct Room {
     pub room_id: u64,
     pub room_name: String,
-    pub room_password: String,
+    // 房间密码的摘要，None为不需要密码
+    pub password: Option<PasswordHash>,
+    // 邀请码 -> 过期时间（秒）
+    pub invites: HashMap<String, f64>,
     pub room_state: RoomState,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    // 全下后允许剩余公共牌发两次
+    pub run_it_twice: bool,
+    // 前注和跨注
+    pub forced_bets: ForcedBets,
+    // 现金桌抽水
+    pub rake: RakeConfig,
+    // 旁观人数上限、延迟和隐藏名字
+    pub spectators: SpectatorConfig,
+    // 开启旁观延迟时旁观者看到的房间状态，首次同步时创建
+    pub spectator_feed: Option<SpectatorFeed>,
     pub owner_name: String,
+    // 创建房间的用户，房主命令按用户校验；服务端按模板开的牌桌没有房主
+    pub owner_user_id: Option<u64>,
+    // 锁定后不允许新玩家进入
+    pub locked: bool,
+    // 被禁止进入的玩家名 -> 被禁止时的用户（已断开的锦标赛玩家没有会话，只按名字禁止）
+    pub bans: HashMap<String, Option<u64>>,
+    // 盲注
+    pub small_blind: u32,
+    pub big_blind: u32,
     pub players: Vec<Player>,
     pub last_dealer_name: Option<String>,
+    // 所属的锦标赛，现金桌为None
+    pub tournament_id: Option<u64>,
+    // 最近一次广播的增量序号
+    pub sync_seq: u64,
+    // 被房主禁言的玩家
+    pub muted_names: HashSet<String>,
+    // 快速入座时满桌的等候队列
+    pub waiting_list: VecDeque<WaitingPlayer>,
 }
 impl Room {
+    // 开始一局所需的最少参与者人数，锦标赛需要打到单挑
+    pub fn min_participants(&self) -> usize {
+        if self.tournament_id.is_some() {
+            2
+        } else {
+            3
+        }
+    }
+
+    // 下一局会被发牌的玩家：没有坐出且有筹码的参与者
+    pub fn dealt_in_players(&self) -> impl Iterator<Item = &Player> {
+        self.players.iter().filter(|player| {
+            player.player_role == PlayerRole::Participant && !player.sitting_out && player.chips > 0
+        })
+    }
+
+    // 以该房间为模板创建锦标赛的另一张桌子
+    pub fn new_table(&self, room_id: u64, table_number: usize) -> Room {
+        let suffix = format!(" #{}", table_number);
+        let base_name = truncate_name(&self.room_name, MAX_ROOM_NAME_CHARS - suffix.len());
+        Room {
+            room_id,
+            room_name: base_name + &suffix,
+            password: self.password.clone(),
+            invites: HashMap::new(),
+            room_state: RoomState::Running,
+            variant: self.variant,
+            betting: self.betting,
+            run_it_twice: self.run_it_twice,
+            forced_bets: self.forced_bets,
+            rake: self.rake,
+            spectators: self.spectators,
+            spectator_feed: None,
+            owner_name: self.owner_name.clone(),
+            owner_user_id: self.owner_user_id,
+            locked: self.locked,
+            bans: self.bans.clone(),
+            small_blind: self.small_blind,
+            big_blind: self.big_blind,
+            players: Vec::new(),
+            last_dealer_name: None,
+            tournament_id: self.tournament_id,
+            sync_seq: 0,
+            muted_names: HashSet::new(),
+            waiting_list: VecDeque::new(),
+        }
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.password.is_some()
+    }
+
+    pub fn check_password(&self, password: &str) -> bool {
+        self.password
+            .as_ref()
+            .is_none_or(|hash| hash.verify(password))
+    }
+
+    pub fn has_invite(&self, code: &str, now: f64) -> bool {
+        self.invites
+            .get(code)
+            .is_some_and(|expires_at| *expires_at > now)
+    }
+
+    // 按服务端分配的用户校验，客户端选择的连接id可以被冒用
+    pub fn is_owner(&self, sessions: &Sessions, client_id: u64) -> bool {
+        self.owner_user_id.is_some() && self.owner_user_id == sessions.user_id(client_id)
+    }
+
+    // 按玩家名或用户禁止进入
+    pub fn is_banned(&self, sessions: &Sessions, player_name: &str, client_id: u64) -> bool {
+        let user_id = sessions.user_id(client_id);
+        self.bans.contains_key(player_name)
+            || (user_id.is_some() && self.bans.values().any(|banned| *banned == user_id))
+    }
+
+    pub fn banned_names(&self) -> Vec<String> {
+        let mut banned_names: Vec<String> = self.bans.keys().cloned().collect();
+        banned_names.sort_unstable();
+        banned_names
+    }
+
+    fn access_delta(&self) -> RoomDelta {
+        RoomDelta::RoomAccessChanged {
+            locked: self.locked,
+            banned_names: self.banned_names(),
+        }
+    }
+
+    pub fn participant_count(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|player| player.player_role == PlayerRole::Participant)
+            .count()
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|player| player.player_role == PlayerRole::Spectator)
+            .count()
+    }
+
     pub fn contains_player(&self, player_name: &str) -> bool {
         self.players
             .iter()
@@ -28,92 +187,777 @@ impl Room {
 }
 
 // 房间列表
-#[derive(Debug, Resource)]
-pub struct RoomList(pub Vec<Room>);
+#[derive(Debug, Default, Resource)]
+pub struct RoomList {
+    // room_id -> 房间
+    rooms: HashMap<u64, Room>,
+    // client_id -> 所在房间id
+    client_rooms: HashMap<u64, u64>,
+    room_ids: IdAllocator,
+}
+
+impl RoomList {
+    pub fn next_room_id(&mut self) -> u64 {
+        self.room_ids.next_id()
+    }
+
+    // 添加房间，房间内的玩家需通过add_player加入以维护反向索引
+    pub fn insert(&mut self, room: Room) {
+        self.rooms.insert(room.room_id, room);
+    }
+
+    pub fn get(&self, room_id: u64) -> Option<&Room> {
+        self.rooms.get(&room_id)
+    }
+
+    pub fn get_mut(&mut self, room_id: u64) -> Option<&mut Room> {
+        self.rooms.get_mut(&room_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Room> {
+        self.rooms.values_mut()
+    }
+
+    // 客户端所在的房间
+    pub fn room_of_client(&self, client_id: u64) -> Option<&Room> {
+        self.client_rooms
+            .get(&client_id)
+            .and_then(|room_id| self.rooms.get(room_id))
+    }
 
-pub fn handle_switch_player_role(mut server: ResMut<RenetServer>, mut room_list: ResMut<RoomList>) {
-    for client_id in server.clients_id().into_iter() {
+    // 玩家进入房间，一个客户端同时只能在一个房间内，返回其离开的旧房间id
+    pub fn add_player(&mut self, room_id: u64, player: Player) -> Option<u64> {
+        let client_id = player.player_client_id;
+        let previous_room_id = self.remove_player(client_id).map(|(room_id, _)| room_id);
+        if let Some(room) = self.rooms.get_mut(&room_id) {
+            room.players.push(player);
+            self.client_rooms.insert(client_id, room_id);
+        }
+        previous_room_id
+    }
+
+    // 玩家离开所在房间，返回 (房间id, 玩家)
+    pub fn remove_player(&mut self, client_id: u64) -> Option<(u64, Player)> {
+        let room_id = self.client_rooms.remove(&client_id)?;
+        let room = self.rooms.get_mut(&room_id)?;
+        let index = room
+            .players
+            .iter()
+            .position(|player| player.player_client_id == client_id)?;
+        Some((room_id, room.players.remove(index)))
+    }
+}
+
+// 房间状态变更，由sync_room_state分配序号后广播给房间内所有玩家
+#[derive(Debug)]
+pub struct RoomDeltaEvent {
+    pub room_id: u64,
+    pub delta: RoomDelta,
+}
+
+// 向某个客户端发送房间完整快照
+#[derive(Debug)]
+pub struct RoomSnapshotEvent {
+    pub room_id: u64,
+    pub client_id: u64,
+}
+
+// 多桌锦标赛中玩家被移到另一张桌，通知客户端后发送新桌的快照
+#[derive(Debug)]
+pub struct TableChangedEvent {
+    pub room_id: u64,
+    pub client_id: u64,
+}
+
+// 一次操作需要广播的增量和换桌
+#[derive(Debug, Default)]
+pub struct RoomUpdate {
+    pub deltas: Vec<RoomDeltaEvent>,
+    pub table_changes: Vec<TableChangedEvent>,
+}
+
+impl RoomUpdate {
+    pub fn push(&mut self, room_id: u64, delta: RoomDelta) {
+        self.deltas.push(RoomDeltaEvent { room_id, delta });
+    }
+
+    // 向多个房间广播同一增量
+    pub fn broadcast(&mut self, room_ids: &[u64], delta: RoomDelta) {
+        for room_id in room_ids {
+            self.push(*room_id, delta.clone());
+        }
+    }
+
+    pub fn send(
+        self,
+        room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+        table_changed_ew: &mut EventWriter<TableChangedEvent>,
+    ) {
+        room_delta_ew.send_batch(self.deltas);
+        table_changed_ew.send_batch(self.table_changes);
+    }
+}
+
+// 向对局参与者私发底牌
+#[derive(Debug)]
+pub struct HoleCardsEvent {
+    pub room_id: u64,
+    pub play_id: u64,
+    pub client_id: u64,
+    pub cards: Vec<Card>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_switch_player_role(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    play_list: Res<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    tournament_list: Res<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, SWITCH_PLAYER_ROLE_CHANNEL_ID) {
             if let Ok(mut message) = serde_json::from_slice::<SwitchPlayerRoleMessage>(&message) {
                 info!("Received switch player role message: {:?}", message);
-                if let Some(room) = room_list
-                    .0
-                    .iter_mut()
-                    .find(|room| room.room_id == message.room_id)
-                {
-                    room.players
+                if let Some(room) = room_list.get_mut(message.room_id) {
+                    let participant_count = room.participant_count();
+                    // 锦标赛只能在报名阶段切换角色，且参与者不超过报名上限；现金桌不超过座位数
+                    let role_error = match tournament_list.of_room(room) {
+                        Some(_) if room.room_state != RoomState::Registering => {
+                            Some(RequestError::RegistrationClosed)
+                        }
+                        Some(tournament)
+                            if message.target_player_role == PlayerRole::Participant
+                                && participant_count >= tournament.config.capacity() as usize =>
+                        {
+                            Some(RequestError::RoomFull)
+                        }
+                        None if message.target_player_role == PlayerRole::Participant
+                            && participant_count >= settings.seat.table_seats as usize =>
+                        {
+                            Some(RequestError::RoomFull)
+                        }
+                        _ => None,
+                    };
+                    // 旁观人数已满时不能离开座位
+                    let role_error = role_error.or_else(|| {
+                        (message.target_player_role == PlayerRole::Spectator
+                            && room.spectators.is_full(room.spectator_count()))
+                        .then_some(RequestError::SpectatorsFull)
+                    });
+                    // 现金桌入座时从账户买入，离座时兑现
+                    let bank_result = match message.target_player_role {
+                        _ if role_error.is_some() => Ok(None),
+                        PlayerRole::Participant => {
+                            bank::sit_down(&mut bank, &settings, &sessions, room, client_id)
+                        }
+                        PlayerRole::Spectator => {
+                            Ok(bank::leave_table(&mut bank, &play_list, room, client_id))
+                        }
+                    };
+                    if let Some(error) = role_error.or(bank_result.as_ref().err().copied()) {
+                        message.error = Some(error);
+                    } else if let Some(player) = room
+                        .players
                         .iter_mut()
                         .find(|player| player.player_client_id == client_id)
-                        .unwrap()
-                        .player_role = message.target_player_role;
-                    message.success = true;
-                    server.send_message(
-                        client_id,
-                        SWITCH_PLAYER_ROLE_CHANNEL_ID,
-                        serde_json::to_vec(&message).unwrap(),
-                    );
+                    {
+                        player.player_role = message.target_player_role;
+                        // 离开座位时清除坐出状态和错过的盲注
+                        if message.target_player_role == PlayerRole::Spectator {
+                            player.sitting_out = false;
+                            player.missed_blinds = Default::default();
+                        }
+                        room_delta_ew.send(RoomDeltaEvent {
+                            room_id: room.room_id,
+                            delta: RoomDelta::PlayerRoleChanged {
+                                player_client_id: client_id,
+                                player_role: message.target_player_role,
+                            },
+                        });
+                        if let Ok(Some(delta)) = bank_result {
+                            room_delta_ew.send(RoomDeltaEvent {
+                                room_id: room.room_id,
+                                delta,
+                            });
+                        }
+                    } else {
+                        message.error = Some(RequestError::NotInRoom);
+                    }
                 } else {
-                    error!("Room not found when switch player role")
+                    error!("Room not found when switch player role");
+                    message.error = Some(RequestError::RoomNotFound);
                 }
+                server.send_message(
+                    client_id,
+                    SWITCH_PLAYER_ROLE_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
             }
         }
     }
 }
 
-pub fn broadcast_room_info(
+pub fn handle_sit_out(
     mut server: ResMut<RenetServer>,
-    room_list: Res<RoomList>,
-    mut refresh_cd: Local<f32>,
-    time: Res<Time>,
+    sessions: Res<Sessions>,
+    mut room_list: ResMut<RoomList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
 ) {
-    *refresh_cd -= time.delta_seconds();
-    if *refresh_cd < 0.0 {
-        for room in room_list.0.iter() {
-            let message = BroadcastRoomInfoMessage {
-                timestamp: timestamp(),
-                room_id: room.room_id,
-                room_name: room.room_name.clone(),
-                room_state: room.room_state,
-                players: room.players.clone(),
-            };
-            for player in room.players.iter() {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, SIT_OUT_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<SitOutMessage>(&message) {
+                info!("Received sit out message: {:?}", message);
+                if let Some(room) = room_list.get_mut(message.room_id) {
+                    let room_id = room.room_id;
+                    // 锦标赛中不能坐出，离开的玩家照常下盲注
+                    let is_tournament = room.tournament_id.is_some();
+                    match room
+                        .players
+                        .iter_mut()
+                        .find(|player| player.player_client_id == client_id)
+                    {
+                        None => message.error = Some(RequestError::NotInRoom),
+                        Some(player) if player.player_role != PlayerRole::Participant => {
+                            message.error = Some(RequestError::NotSeated)
+                        }
+                        Some(_) if is_tournament => {
+                            message.error = Some(RequestError::InvalidAction)
+                        }
+                        // 坐出从下一局开始生效，错过的盲注在回到牌桌后的下一局补交
+                        Some(player) => {
+                            player.sitting_out = message.sitting_out;
+                            room_delta_ew.send(RoomDeltaEvent {
+                                room_id,
+                                delta: RoomDelta::PlayerSeatChanged {
+                                    player_client_id: client_id,
+                                    sitting_out: player.sitting_out,
+                                    missed_blinds: player.missed_blinds,
+                                },
+                            });
+                        }
+                    }
+                } else {
+                    error!("Room not found when sit out");
+                    message.error = Some(RequestError::RoomNotFound);
+                }
                 server.send_message(
-                    player.player_client_id,
-                    BROADCAST_ROOM_INFO_CHANNEL_ID,
+                    client_id,
+                    SIT_OUT_CHANNEL_ID,
                     serde_json::to_vec(&message).unwrap(),
                 );
             }
         }
-        // 5秒广播一次
-        *refresh_cd = 5.0;
     }
 }
 
-pub fn handle_set_room_state(mut server: ResMut<RenetServer>, mut room_list: ResMut<RoomList>) {
-    for client_id in server.clients_id().into_iter() {
+pub fn handle_set_room_state(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut table_changed_ew: EventWriter<TableChangedEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, SET_ROOM_STATE_CHANNEL_ID) {
             if let Ok(mut message) = serde_json::from_slice::<SetRoomStateMessage>(&message) {
                 info!("Received set room state message: {:?}", message);
-                if let Some(room) = room_list
-                    .0
-                    .iter_mut()
-                    .find(|room| room.room_id == message.room_id)
-                {
-                    if room.owner_name == message.player_name {
-                        room.room_state = message.target_room_state;
-                        message.success = true;
+                if let Some(room) = room_list.get(message.room_id) {
+                    if room.is_owner(&sessions, client_id) {
+                        match tournament::change_room_state(
+                            &mut room_list,
+                            &mut tournament_list,
+                            message.room_id,
+                            message.target_room_state,
+                        ) {
+                            Ok(update) => update.send(&mut room_delta_ew, &mut table_changed_ew),
+                            Err(error) => message.error = Some(error),
+                        }
                     } else {
-                        message.success = false;
+                        message.error = Some(RequestError::NotRoomOwner);
                     }
-                    server.send_message(
-                        client_id,
-                        SET_ROOM_STATE_CHANNEL_ID,
-                        serde_json::to_vec(&message).unwrap(),
-                    );
                 } else {
-                    error!("Room not found when set room state")
+                    error!("Room not found when set room state");
+                    message.error = Some(RequestError::RoomNotFound);
+                }
+                server.send_message(
+                    client_id,
+                    SET_ROOM_STATE_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_room_admin(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    settings: Res<ServerSettings>,
+    mut bank: ResMut<Bank>,
+    mut play_list: ResMut<PlayList>,
+    mut room_list: ResMut<RoomList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, ROOM_ADMIN_CHANNEL_ID) {
+            if let Ok(mut message) = serde_json::from_slice::<RoomAdminMessage>(&message) {
+                // 不记录新密码
+                match &message.command {
+                    RoomAdminCommand::ChangePassword(_) => info!(
+                        "Received room admin message: change password of room {}",
+                        message.room_id
+                    ),
+                    command => info!(
+                        "Received room admin message: {:?} in room {}",
+                        command, message.room_id
+                    ),
+                }
+                let room_id = message.room_id;
+                let result = match room_list.get(room_id) {
+                    None => Err(RequestError::RoomNotFound),
+                    // 只认创建房间的用户，不信任消息中的玩家名
+                    Some(room) if !room.is_owner(&sessions, client_id) => {
+                        Err(RequestError::NotRoomOwner)
+                    }
+                    Some(_) => match message.command.clone() {
+                        RoomAdminCommand::Kick { player_client_id } => kick_player(
+                            &mut server,
+                            &sessions,
+                            &mut room_list,
+                            &mut bank,
+                            &mut play_list,
+                            &mut room_delta_ew,
+                            room_id,
+                            player_client_id,
+                        )
+                        .map(|_| ()),
+                        RoomAdminCommand::Ban { player_client_id } => kick_player(
+                            &mut server,
+                            &sessions,
+                            &mut room_list,
+                            &mut bank,
+                            &mut play_list,
+                            &mut room_delta_ew,
+                            room_id,
+                            player_client_id,
+                        )
+                        .map(|player| {
+                            let room = room_list.get_mut(room_id).unwrap();
+                            let user_id = sessions.user_id(player_client_id);
+                            room.bans.insert(player.player_name, user_id);
+                            room_delta_ew.send(RoomDeltaEvent {
+                                room_id,
+                                delta: room.access_delta(),
+                            });
+                        }),
+                        RoomAdminCommand::Unban { player_name } => {
+                            let room = room_list.get_mut(room_id).unwrap();
+                            match room.bans.remove(&player_name) {
+                                Some(_) => {
+                                    room_delta_ew.send(RoomDeltaEvent {
+                                        room_id,
+                                        delta: room.access_delta(),
+                                    });
+                                    Ok(())
+                                }
+                                None => Err(RequestError::PlayerNotFound),
+                            }
+                        }
+                        RoomAdminCommand::Lock(locked) => {
+                            let room = room_list.get_mut(room_id).unwrap();
+                            room.locked = locked;
+                            room_delta_ew.send(RoomDeltaEvent {
+                                room_id,
+                                delta: room.access_delta(),
+                            });
+                            Ok(())
+                        }
+                        // 只影响之后进入的玩家
+                        RoomAdminCommand::ChangePassword(room_password) => {
+                            room_list.get_mut(room_id).unwrap().password = PasswordHash::new(
+                                &room_password,
+                                settings.access.password_hash_iterations,
+                            );
+                            Ok(())
+                        }
+                        RoomAdminCommand::CreateInvite => {
+                            let room = room_list.get_mut(room_id).unwrap();
+                            let now = time.elapsed_seconds_f64();
+                            room.invites.retain(|_, expires_at| *expires_at > now);
+                            let valid_secs = settings.access.invite_valid_secs;
+                            let code = new_invite_code();
+                            room.invites.insert(code.clone(), now + valid_secs as f64);
+                            message.invite = Some(InviteCode { code, valid_secs });
+                            Ok(())
+                        }
+                        RoomAdminCommand::RevokeInvites => {
+                            room_list.get_mut(room_id).unwrap().invites.clear();
+                            Ok(())
+                        }
+                    },
+                };
+                message.error = result.err();
+                server.send_message(
+                    client_id,
+                    ROOM_ADMIN_CHANNEL_ID,
+                    serde_json::to_vec(&message).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+// 请出房间内的玩家：对局中立即弃牌，筹码在对局结束后兑回账户
+#[allow(clippy::too_many_arguments)]
+pub fn kick_player(
+    server: &mut RenetServer,
+    sessions: &Sessions,
+    room_list: &mut RoomList,
+    bank: &mut Bank,
+    play_list: &mut PlayList,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+    room_id: u64,
+    player_client_id: u64,
+) -> Result<Player, RequestError> {
+    let room = room_list.get(room_id).ok_or(RequestError::RoomNotFound)?;
+    let player = room
+        .players
+        .iter()
+        .find(|player| player.player_client_id == player_client_id)
+        .cloned()
+        .ok_or(RequestError::PlayerNotFound)?;
+    // 不能请出自己，锦标赛的参赛玩家不能被请出
+    if room.is_owner(sessions, player_client_id)
+        || (room.tournament_id.is_some() && player.player_role == PlayerRole::Participant)
+    {
+        return Err(RequestError::InvalidAction);
+    }
+    fold_out_player(
+        play_list,
+        room_delta_ew,
+        room_id,
+        player_client_id,
+        &player.player_name,
+    );
+    send_room_sync(
+        server,
+        player_client_id,
+        &encode_room_sync(&RoomSyncMessage::Kicked { room_id }),
+    );
+    leave_room(room_list, bank, play_list, player_client_id, room_delta_ew);
+    info!("{} was kicked from room {}", player.player_name, room_id);
+    Ok(player)
+}
+
+// 对局中的玩家立即弃牌
+pub fn fold_out_player(
+    play_list: &mut PlayList,
+    room_delta_ew: &mut EventWriter<RoomDeltaEvent>,
+    room_id: u64,
+    player_client_id: u64,
+    player_name: &str,
+) {
+    if let Some(play) = play_list.plays.iter_mut().find(|play| {
+        play.room_id == room_id
+            && play
+                .participants
+                .iter()
+                .any(|participant| participant.player_client_id == player_client_id)
+    }) {
+        let deltas = play.fold_out(player_name);
+        room_delta_ew.send_batch(
+            deltas
+                .into_iter()
+                .map(|delta| RoomDeltaEvent { room_id, delta }),
+        );
+    }
+}
+
+pub fn handle_room_sync_request(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    room_list: Res<RoomList>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+) {
+    for client_id in sessions.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, ROOM_SYNC_CHANNEL_ID) {
+            if let Ok(RoomSyncMessage::RequestSnapshot { room_id }) =
+                serde_json::from_slice::<RoomSyncMessage>(&message)
+            {
+                info!("Received room snapshot request: {}", room_id);
+                // 只给房间内的玩家发送快照
+                if room_list
+                    .room_of_client(client_id)
+                    .is_some_and(|room| room.room_id == room_id)
+                {
+                    room_snapshot_ew.send(RoomSnapshotEvent { room_id, client_id });
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sync_room_state(
+    mut server: ResMut<RenetServer>,
+    sessions: Res<Sessions>,
+    mut room_list: ResMut<RoomList>,
+    play_list: Res<PlayList>,
+    tournament_list: Res<TournamentList>,
+    mut room_delta_er: EventReader<RoomDeltaEvent>,
+    mut hole_cards_er: EventReader<HoleCardsEvent>,
+    mut table_changed_er: EventReader<TableChangedEvent>,
+    mut room_snapshot_er: EventReader<RoomSnapshotEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    // 开启旁观延迟的房间从当前状态开始记录旁观者看到的状态
+    for room in room_list.iter_mut() {
+        if room.spectators.delay.is_enabled() && room.spectator_feed.is_none() {
+            let snapshot = room_snapshot(room, &sessions, &play_list, &tournament_list, None);
+            room.spectator_feed = Some(SpectatorFeed::new(snapshot));
+        }
+    }
+
+    // 先广播增量，再发送底牌和快照，保证客户端收到底牌时已有对应的对局
+    // 切换角色的玩家改看另一份状态，需要重新发送快照
+    let mut snapshot_events = Vec::new();
+    for event in room_delta_er.iter() {
+        if let Some(room) = room_list.get_mut(event.room_id) {
+            room.sync_seq += 1;
+            let message = |delta: RoomDelta| {
+                encode_room_sync(&RoomSyncMessage::Delta(RoomDeltaMessage {
+                    room_id: room.room_id,
+                    seq: room.sync_seq,
+                    delta,
+                }))
+            };
+            let live_message = message(event.delta.clone());
+            let participant_message = if room.spectators.hide_names {
+                message(hide_spectators_in_delta(&sessions, room, &event.delta))
+            } else {
+                live_message.clone()
+            };
+            let delayed = room.spectators.delay.is_enabled();
+            for player in room.players.iter() {
+                match player.player_role {
+                    PlayerRole::Participant => {
+                        send_room_sync(&mut server, player.player_client_id, &participant_message)
+                    }
+                    PlayerRole::Spectator if !delayed => {
+                        send_room_sync(&mut server, player.player_client_id, &live_message)
+                    }
+                    PlayerRole::Spectator => {}
                 }
             }
+            if let RoomDelta::PlayerRoleChanged {
+                player_client_id, ..
+            } = event.delta
+            {
+                if delayed || room.spectators.hide_names {
+                    snapshot_events.push(RoomSnapshotEvent {
+                        room_id: room.room_id,
+                        client_id: player_client_id,
+                    });
+                }
+            }
+            if let Some(feed) = room.spectator_feed.as_mut() {
+                if let Some(message) = feed.push(now, event.delta.clone()) {
+                    send_to_spectators(&mut server, room, message);
+                }
+            }
+        }
+    }
+    for room in room_list.iter_mut() {
+        let delay = room.spectators.delay;
+        let Some(feed) = room.spectator_feed.as_mut() else {
+            continue;
+        };
+        for message in feed.release(delay, now) {
+            send_to_spectators(&mut server, room, message);
+        }
+    }
+
+    for event in hole_cards_er.iter() {
+        let message = RoomSyncMessage::HoleCards {
+            room_id: event.room_id,
+            play_id: event.play_id,
+            cards: event.cards.clone(),
+        };
+        send_room_sync(&mut server, event.client_id, &encode_room_sync(&message));
+    }
+
+    // 换桌的客户端先切换房间再接收新桌快照
+    for event in table_changed_er.iter() {
+        let message = RoomSyncMessage::TableChanged {
+            room_id: event.room_id,
+        };
+        send_room_sync(&mut server, event.client_id, &encode_room_sync(&message));
+        snapshot_events.push(RoomSnapshotEvent {
+            room_id: event.room_id,
+            client_id: event.client_id,
+        });
+    }
+    snapshot_events.extend(room_snapshot_er.iter().map(|event| RoomSnapshotEvent {
+        room_id: event.room_id,
+        client_id: event.client_id,
+    }));
+
+    for event in snapshot_events.iter() {
+        let Some(room) = room_list.get(event.room_id) else {
+            continue;
+        };
+        let Some(player) = room
+            .players
+            .iter()
+            .find(|player| player.player_client_id == event.client_id)
+        else {
+            continue;
+        };
+        // 旁观者看延迟的状态，参与者看隐藏旁观者名字后的状态
+        let snapshot = match (player.player_role, room.spectator_feed.as_ref()) {
+            (PlayerRole::Spectator, Some(feed)) => feed.snapshot(),
+            (PlayerRole::Spectator, None) => {
+                room_snapshot(room, &sessions, &play_list, &tournament_list, None)
+            }
+            (PlayerRole::Participant, _) => {
+                let mut snapshot = room_snapshot(
+                    room,
+                    &sessions,
+                    &play_list,
+                    &tournament_list,
+                    Some(event.client_id),
+                );
+                if room.spectators.hide_names {
+                    hide_spectators(&sessions, &mut snapshot);
+                }
+                snapshot
+            }
+        };
+        // 快照随房间人数增长，超过消息大小上限时拆分发送
+        send_room_sync(
+            &mut server,
+            event.client_id,
+            &encode_room_sync(&RoomSyncMessage::Snapshot(Box::new(snapshot))),
+        );
+    }
+}
+
+// 按顺序发送encode_room_sync生成的消息或分片
+pub fn send_room_sync(server: &mut RenetServer, client_id: u64, parts: &[Vec<u8>]) {
+    for part in parts {
+        server.send_message(client_id, ROOM_SYNC_CHANNEL_ID, part.clone());
+    }
+}
+
+fn send_to_spectators(server: &mut RenetServer, room: &Room, message: RoomDeltaMessage) {
+    let message = encode_room_sync(&RoomSyncMessage::Delta(message));
+    for player in room.players.iter() {
+        if player.player_role == PlayerRole::Spectator {
+            send_room_sync(server, player.player_client_id, &message);
+        }
+    }
+}
+
+// 房间当前的完整状态，只包含client_id自己的底牌
+fn room_snapshot(
+    room: &Room,
+    sessions: &Sessions,
+    play_list: &PlayList,
+    tournament_list: &TournamentList,
+    client_id: Option<u64>,
+) -> RoomSnapshot {
+    let play = play_list
+        .plays
+        .iter()
+        .find(|play| play.room_id == room.room_id);
+    let hole_cards = play
+        .zip(client_id)
+        .and_then(|(play, client_id)| {
+            play.participants
+                .iter()
+                .find(|player| player.player_client_id == client_id)
+                .and_then(|player| play.hole_cards.get(&player.player_name))
+        })
+        .map(|cards| cards.to_vec())
+        .unwrap_or_default();
+    RoomSnapshot {
+        room_id: room.room_id,
+        seq: room.sync_seq,
+        room_name: room.room_name.clone(),
+        room_state: room.room_state,
+        // 只向客户端公开房主当前的连接
+        owner_client_id: room
+            .owner_user_id
+            .and_then(|user_id| sessions.client_of(user_id)),
+        locked: room.locked,
+        banned_names: room.banned_names(),
+        variant: room.variant,
+        betting: room.betting,
+        run_it_twice: room.run_it_twice,
+        forced_bets: room.forced_bets,
+        rake: room.rake,
+        spectators: room.spectators,
+        small_blind: room.small_blind,
+        big_blind: room.big_blind,
+        players: room.players.clone(),
+        play: play.map(|play| play.snapshot()),
+        tournament: tournament_list
+            .of_room(room)
+            .map(|tournament| tournament.snapshot()),
+        hole_cards,
+    }
+}
+
+// 定期向房间内所有玩家重发完整快照，作为增量同步的兜底
+pub fn resync_rooms(
+    settings: Res<ServerSettings>,
+    room_list: Res<RoomList>,
+    mut room_snapshot_ew: EventWriter<RoomSnapshotEvent>,
+    mut refresh_cd: Local<f32>,
+    time: Res<Time>,
+) {
+    if settings.sync.resync_interval <= 0.0 {
+        return;
+    }
+    *refresh_cd -= time.delta_seconds();
+    if *refresh_cd < 0.0 {
+        for room in room_list.iter() {
+            for player in room.players.iter() {
+                room_snapshot_ew.send(RoomSnapshotEvent {
+                    room_id: room.room_id,
+                    client_id: player.player_client_id,
+                });
+            }
         }
+        *refresh_cd = settings.sync.resync_interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_seat::test_room;
+
+    #[test]
+    fn server_tables_have_no_owner() {
+        let room = test_room(1);
+        // 任何已登录的连接（包括id为0的连接）都不能以房主身份操作
+        let mut sessions = Sessions::default();
+        sessions.login(0, Some("127.0.0.1:5000".parse().unwrap()), None, None);
+        assert!(!room.is_owner(&sessions, 0));
+        assert!(room.owner_user_id.is_none());
+        assert!(room.new_table(2, 2).owner_user_id.is_none());
     }
 }
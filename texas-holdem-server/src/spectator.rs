@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use texas_holdem_common::{
+    channel::{RoomDelta, RoomDeltaMessage, RoomSnapshot, RoomSyncMessage},
+    spectator::SpectatorDelay,
+    sync::RoomView,
+    Player, PlayerRole,
+};
+
+use crate::{network::Sessions, room::Room};
+
+// 开启旁观延迟的房间中旁观者看到的房间状态，序号独立于参与者
+#[derive(Debug)]
+pub struct SpectatorFeed {
+    view: RoomView,
+    // 尚未公开的对局增量及其产生时间（秒）
+    pending: VecDeque<(f64, RoomDelta)>,
+    // 最新的增量是否处于一局之中
+    in_hand: bool,
+}
+
+impl SpectatorFeed {
+    pub fn new(snapshot: RoomSnapshot) -> Self {
+        let in_hand = snapshot.play.is_some();
+        let mut view = RoomView::new(snapshot.room_id);
+        view.apply(RoomSyncMessage::Snapshot(Box::new(snapshot)));
+        SpectatorFeed {
+            view,
+            pending: VecDeque::new(),
+            in_hand,
+        }
+    }
+
+    // 对局相关的增量延迟公开，其余立即公开
+    pub fn push(&mut self, now: f64, delta: RoomDelta) -> Option<RoomDeltaMessage> {
+        if !is_delayed(&delta) {
+            return Some(self.publish(delta));
+        }
+        match delta {
+            RoomDelta::PlayStarted(_) => self.in_hand = true,
+            RoomDelta::PlayEnded { .. } => self.in_hand = false,
+            _ => {}
+        }
+        self.pending.push_back((now, delta));
+        None
+    }
+
+    // 公开已到期的增量
+    pub fn release(&mut self, delay: SpectatorDelay, now: f64) -> Vec<RoomDeltaMessage> {
+        let count = match delay {
+            SpectatorDelay::Off => self.pending.len(),
+            SpectatorDelay::Seconds(secs) => self
+                .pending
+                .iter()
+                .take_while(|(time, _)| now - *time >= secs as f64)
+                .count(),
+            // 正在进行的一局开始之前的增量都可以公开
+            SpectatorDelay::Hand if self.in_hand => self
+                .pending
+                .iter()
+                .rposition(|(_, delta)| matches!(delta, RoomDelta::PlayStarted(_)))
+                .unwrap_or(0),
+            SpectatorDelay::Hand => self.pending.len(),
+        };
+        self.pending
+            .drain(..count)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(_, delta)| self.publish(delta))
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> RoomSnapshot {
+        self.view.snapshot()
+    }
+
+    fn publish(&mut self, delta: RoomDelta) -> RoomDeltaMessage {
+        let message = RoomDeltaMessage {
+            room_id: self.view.room_id,
+            seq: self.view.seq.unwrap_or(0) + 1,
+            delta,
+        };
+        self.view.apply(RoomSyncMessage::Delta(message.clone()));
+        message
+    }
+}
+
+fn is_delayed(delta: &RoomDelta) -> bool {
+    matches!(
+        delta,
+        RoomDelta::PlayStarted(_)
+            | RoomDelta::PlayerChipsChanged { .. }
+            | RoomDelta::PlayRoundChanged { .. }
+            | RoomDelta::PlayerActed { .. }
+            | RoomDelta::AntePosted { .. }
+            | RoomDelta::MissedBlindsPosted { .. }
+            | RoomDelta::TurnChanged { .. }
+            | RoomDelta::RunItTwiceOffered { .. }
+            | RoomDelta::RunItTwiceAnswered { .. }
+            | RoomDelta::PlayShowdown { .. }
+            | RoomDelta::PlayEnded { .. }
+            | RoomDelta::PlayerFinished(_)
+    )
+}
+
+// 对参与者隐藏旁观者的名字，代称按服务端分配的用户编号，客户端无法选择
+pub fn hidden_player(sessions: &Sessions, player: &Player) -> Player {
+    if player.player_role != PlayerRole::Spectator {
+        return player.clone();
+    }
+    let player_name = match sessions.user_id(player.player_client_id) {
+        Some(user_id) => format!("Spectator {}", user_id),
+        None => "Spectator".to_string(),
+    };
+    Player {
+        player_name,
+        ..player.clone()
+    }
+}
+
+pub fn hide_spectators(sessions: &Sessions, snapshot: &mut RoomSnapshot) {
+    for player in snapshot.players.iter_mut() {
+        *player = hidden_player(sessions, player);
+    }
+}
+
+// 参与者收到的增量：旁观者进入时隐藏名字，切换角色时按新角色重发玩家信息
+pub fn hide_spectators_in_delta(sessions: &Sessions, room: &Room, delta: &RoomDelta) -> RoomDelta {
+    match delta {
+        RoomDelta::PlayerJoined(player) => RoomDelta::PlayerJoined(hidden_player(sessions, player)),
+        RoomDelta::PlayerRoleChanged {
+            player_client_id,
+            player_role,
+        } => match room
+            .players
+            .iter()
+            .find(|player| player.player_client_id == *player_client_id)
+        {
+            Some(player) => RoomDelta::PlayerJoined(hidden_player(
+                sessions,
+                &Player {
+                    player_role: *player_role,
+                    ..player.clone()
+                },
+            )),
+            None => delta.clone(),
+        },
+        _ => delta.clone(),
+    }
+}
@@ -0,0 +1,553 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::seq::SliceRandom;
+use texas_holdem_common::{
+    channel::{RequestError, RoomDelta},
+    tournament::{LevelUp, TournamentConfig, TournamentResult, TournamentSnapshot},
+    util::IdAllocator,
+    PlayerRole, RoomState,
+};
+
+use crate::{
+    play::{Play, PlayList},
+    room::{Room, RoomDeltaEvent, RoomList, RoomUpdate, TableChangedEvent},
+};
+
+// 锦标赛进度，多桌锦标赛跨越多个房间
+#[derive(Debug)]
+pub struct Tournament {
+    pub tournament_id: u64,
+    pub config: TournamentConfig,
+    // 报名所在的房间，开始后作为第一张桌
+    pub room_id: u64,
+    // 仍在进行的桌
+    pub tables: Vec<u64>,
+    // 用过的所有桌（包括已拆的），名次等结果广播给所有桌上的旁观者
+    pub rooms: Vec<u64>,
+    // 等待调整人数、暂停开局的桌
+    pub held_tables: HashSet<u64>,
+    // 当前盲注级别
+    pub level: usize,
+    // 各桌在当前级别已打完的局数
+    pub table_hands: HashMap<u64, u32>,
+    // 当前级别已经过的时间（秒）
+    pub level_elapsed: f32,
+    pub entrants: Vec<String>,
+    pub prize_pool: u32,
+    // 已确定的名次，按淘汰顺序
+    pub results: Vec<TournamentResult>,
+}
+
+impl Tournament {
+    pub fn snapshot(&self) -> TournamentSnapshot {
+        TournamentSnapshot {
+            tournament_id: self.tournament_id,
+            config: self.config.clone(),
+            level: self.level,
+            entrants: self.entrants.clone(),
+            prize_pool: self.prize_pool,
+            results: self.results.clone(),
+        }
+    }
+
+    // 已开始且尚未决出冠军
+    pub fn is_running(&self) -> bool {
+        !self.entrants.is_empty() && self.remaining() > 0
+    }
+
+    // 尚未被淘汰的人数
+    pub fn remaining(&self) -> usize {
+        self.entrants.len() - self.results.len()
+    }
+
+    fn is_finished(&self, player_name: &str) -> bool {
+        self.results
+            .iter()
+            .any(|result| result.player_name == player_name)
+    }
+}
+
+// 锦标赛列表
+#[derive(Debug, Default, Resource)]
+pub struct TournamentList {
+    tournaments: HashMap<u64, Tournament>,
+    tournament_ids: IdAllocator,
+}
+
+impl TournamentList {
+    // 在报名房间创建锦标赛，返回锦标赛id
+    pub fn create(&mut self, config: TournamentConfig, room_id: u64) -> u64 {
+        let tournament_id = self.tournament_ids.next_id();
+        self.tournaments.insert(
+            tournament_id,
+            Tournament {
+                tournament_id,
+                config,
+                room_id,
+                tables: Vec::new(),
+                rooms: Vec::new(),
+                held_tables: HashSet::new(),
+                level: 0,
+                table_hands: HashMap::new(),
+                level_elapsed: 0.0,
+                entrants: Vec::new(),
+                prize_pool: 0,
+                results: Vec::new(),
+            },
+        );
+        tournament_id
+    }
+
+    pub fn get(&self, tournament_id: u64) -> Option<&Tournament> {
+        self.tournaments.get(&tournament_id)
+    }
+
+    pub fn get_mut(&mut self, tournament_id: u64) -> Option<&mut Tournament> {
+        self.tournaments.get_mut(&tournament_id)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tournament> {
+        self.tournaments.values_mut()
+    }
+
+    // 房间所属的锦标赛
+    pub fn of_room(&self, room: &Room) -> Option<&Tournament> {
+        room.tournament_id
+            .and_then(|tournament_id| self.get(tournament_id))
+    }
+
+    // 客户端是否是进行中的锦标赛尚未被淘汰的参赛玩家，离开牌桌会使锦标赛无法结束
+    pub fn is_playing(&self, room_list: &RoomList, client_id: u64) -> bool {
+        room_list.room_of_client(client_id).is_some_and(|room| {
+            self.of_room(room).is_some_and(Tournament::is_running)
+                && room.players.iter().any(|player| {
+                    player.player_client_id == client_id
+                        && player.player_role == PlayerRole::Participant
+                })
+        })
+    }
+
+    // 房间是否在等待调整人数，此时不开始新的对局
+    pub fn is_held(&self, room: &Room) -> bool {
+        self.of_room(room)
+            .is_some_and(|tournament| tournament.held_tables.contains(&room.room_id))
+    }
+}
+
+fn participant_count(room: &Room) -> usize {
+    room.players
+        .iter()
+        .filter(|player| player.player_role == PlayerRole::Participant)
+        .count()
+}
+
+fn participant_ids(room: &Room) -> Vec<u64> {
+    room.players
+        .iter()
+        .filter(|player| player.player_role == PlayerRole::Participant)
+        .map(|player| player.player_client_id)
+        .collect()
+}
+
+// 把玩家移到另一张桌，客户端随后收到新桌的快照
+fn move_player(room_list: &mut RoomList, client_id: u64, to_room_id: u64, update: &mut RoomUpdate) {
+    let Some((from_room_id, player)) = room_list.remove_player(client_id) else {
+        return;
+    };
+    info!(
+        "Move {} from table {} to table {}",
+        player.player_name, from_room_id, to_room_id
+    );
+    update.push(
+        from_room_id,
+        RoomDelta::PlayerLeft {
+            player_client_id: client_id,
+        },
+    );
+    room_list.add_player(to_room_id, player.clone());
+    update.push(to_room_id, RoomDelta::PlayerJoined(player));
+    update.table_changes.push(TableChangedEvent {
+        room_id: to_room_id,
+        client_id,
+    });
+}
+
+// 报名截止：确定参赛玩家、奖池并发放起始筹码，人数超过一桌时开新桌并随机分配座位
+pub fn start_tournament(room_list: &mut RoomList, tournament: &mut Tournament) -> RoomUpdate {
+    let mut update = RoomUpdate::default();
+    let Some(room) = room_list.get_mut(tournament.room_id) else {
+        return update;
+    };
+    tournament.entrants.clear();
+    let mut entrant_ids = Vec::new();
+    for player in room.players.iter_mut() {
+        if player.player_role == PlayerRole::Participant {
+            player.chips = tournament.config.starting_stack;
+            tournament.entrants.push(player.player_name.clone());
+            entrant_ids.push(player.player_client_id);
+            update.push(
+                room.room_id,
+                RoomDelta::PlayerChipsChanged {
+                    player_client_id: player.player_client_id,
+                    chips: player.chips,
+                },
+            );
+        }
+    }
+    tournament.prize_pool = tournament.config.buy_in * tournament.entrants.len() as u32;
+    room.last_dealer_name = None;
+    room.room_state = RoomState::Running;
+    update.push(room.room_id, RoomDelta::RoomStateChanged(room.room_state));
+
+    let table_count = entrant_ids
+        .len()
+        .div_ceil(tournament.config.seats as usize)
+        .max(1);
+    tournament.tables = vec![tournament.room_id];
+    for index in 1..table_count {
+        let room_id = room_list.next_room_id();
+        let table = room_list
+            .get(tournament.room_id)
+            .unwrap()
+            .new_table(room_id, index + 1);
+        room_list.insert(table);
+        tournament.tables.push(room_id);
+    }
+    tournament.rooms = tournament.tables.clone();
+    if table_count > 1 {
+        entrant_ids.shuffle(&mut rand::thread_rng());
+        for (index, client_id) in entrant_ids.into_iter().enumerate() {
+            let table = tournament.tables[index % table_count];
+            if table != tournament.room_id {
+                move_player(room_list, client_id, table, &mut update);
+            }
+        }
+    }
+    update.broadcast(
+        &tournament.tables,
+        RoomDelta::TournamentStarted {
+            entrants: tournament.entrants.clone(),
+            prize_pool: tournament.prize_pool,
+        },
+    );
+    info!(
+        "Tournament {} started with {} entrants at {} tables",
+        tournament.tournament_id,
+        tournament.entrants.len(),
+        table_count
+    );
+    update
+}
+
+// 所有桌同时升到下一个盲注级别，已是最后一级时不变
+fn level_up(room_list: &mut RoomList, tournament: &mut Tournament, update: &mut RoomUpdate) {
+    if tournament.level + 1 >= tournament.config.blind_levels.len() {
+        return;
+    }
+    tournament.level += 1;
+    tournament.table_hands.clear();
+    tournament.level_elapsed = 0.0;
+    let blind_level = tournament.config.blind_level(tournament.level);
+    for room_id in tournament.tables.iter() {
+        if let Some(room) = room_list.get_mut(*room_id) {
+            room.small_blind = blind_level.small_blind;
+            room.big_blind = blind_level.big_blind;
+        }
+    }
+    update.broadcast(
+        &tournament.tables,
+        RoomDelta::BlindsChanged {
+            level: tournament.level,
+            small_blind: blind_level.small_blind,
+            big_blind: blind_level.big_blind,
+        },
+    );
+}
+
+// 对局结算后：按局数升级盲注，淘汰没有筹码的玩家，只剩一人时结束锦标赛
+pub fn on_play_ended(
+    room_list: &mut RoomList,
+    tournament_list: &mut TournamentList,
+    play: &Play,
+) -> Vec<RoomDeltaEvent> {
+    let mut update = RoomUpdate::default();
+    let Some(tournament) = room_list
+        .get(play.room_id)
+        .and_then(|room| room.tournament_id)
+        .and_then(|tournament_id| tournament_list.get_mut(tournament_id))
+        .filter(|tournament| tournament.is_running())
+    else {
+        return update.deltas;
+    };
+
+    // 以打得最快的桌为准
+    let hands = tournament.table_hands.entry(play.room_id).or_insert(0);
+    *hands += 1;
+    if let LevelUp::Hands(level_hands) = tournament.config.level_up {
+        if *hands >= level_hands {
+            level_up(room_list, tournament, &mut update);
+        }
+    }
+
+    let Some(room) = room_list.get_mut(play.room_id) else {
+        return update.deltas;
+    };
+    // 本局参与者中没有筹码或已离开房间的玩家被淘汰，本局投入少的名次靠后
+    let mut busted_names: Vec<String> = play
+        .participants
+        .iter()
+        .map(|participant| participant.player_name.clone())
+        .filter(|player_name| {
+            !tournament.is_finished(player_name)
+                && !room.players.iter().any(|player| {
+                    &player.player_name == player_name
+                        && player.player_role == PlayerRole::Participant
+                        && player.chips > 0
+                })
+        })
+        .collect();
+    busted_names.sort_by_key(|player_name| play.total_bets.get(player_name).copied().unwrap_or(0));
+    let mut place = tournament.remaining() as u32;
+    let mut finished = Vec::new();
+    for player_name in busted_names {
+        finished.push(TournamentResult {
+            prize: tournament.config.prize(place, tournament.prize_pool),
+            player_name,
+            place,
+        });
+        place -= 1;
+    }
+    if place == 1 {
+        if let Some(player_name) = tournament.entrants.iter().find(|player_name| {
+            !tournament.is_finished(player_name)
+                && !finished
+                    .iter()
+                    .any(|result: &TournamentResult| &result.player_name == *player_name)
+        }) {
+            finished.push(TournamentResult {
+                player_name: player_name.clone(),
+                place: 1,
+                prize: tournament.config.prize(1, tournament.prize_pool),
+            });
+        }
+    }
+
+    for result in finished {
+        info!(
+            "{} finished #{} in tournament {}",
+            result.player_name, result.place, tournament.tournament_id
+        );
+        tournament.results.push(result.clone());
+        // 被淘汰的玩家转为旁观者
+        if let Some(player) = room
+            .players
+            .iter_mut()
+            .find(|player| player.player_name == result.player_name)
+            .filter(|_| result.place > 1)
+        {
+            player.player_role = PlayerRole::Spectator;
+            update.push(
+                room.room_id,
+                RoomDelta::PlayerRoleChanged {
+                    player_client_id: player.player_client_id,
+                    player_role: player.player_role,
+                },
+            );
+        }
+        update.broadcast(&tournament.rooms, RoomDelta::PlayerFinished(result));
+    }
+    if !tournament.is_running() {
+        for room_id in tournament.tables.iter() {
+            if let Some(room) = room_list.get_mut(*room_id) {
+                room.room_state = RoomState::Finished;
+                update.push(room.room_id, RoomDelta::RoomStateChanged(room.room_state));
+            }
+        }
+    }
+    update.deltas
+}
+
+// 拆掉多余的桌或从人数最多的桌移一人到人数最少的桌，源桌对局结束前暂停其开局
+fn balance_tables(
+    room_list: &mut RoomList,
+    play_list: &PlayList,
+    tournament: &mut Tournament,
+) -> RoomUpdate {
+    let mut update = RoomUpdate::default();
+    tournament.held_tables.clear();
+    let counts: Vec<(u64, usize)> = tournament
+        .tables
+        .iter()
+        .map(|room_id| {
+            (
+                *room_id,
+                room_list.get(*room_id).map_or(0, participant_count),
+            )
+        })
+        .collect();
+    let Some((min_room_id, min_count)) = counts.iter().min_by_key(|(_, count)| *count).copied()
+    else {
+        return update;
+    };
+    let Some((max_room_id, max_count)) = counts.iter().max_by_key(|(_, count)| *count).copied()
+    else {
+        return update;
+    };
+    let alive: usize = counts.iter().map(|(_, count)| count).sum();
+    let needed = alive.div_ceil(tournament.config.seats as usize).max(1);
+    let in_play = |room_id: u64| play_list.plays.iter().any(|play| play.room_id == room_id);
+
+    if tournament.tables.len() > needed {
+        // 拆掉人数最少的桌，玩家依次坐到人数最少的桌
+        if in_play(min_room_id) {
+            tournament.held_tables.insert(min_room_id);
+            return update;
+        }
+        tournament.tables.retain(|room_id| *room_id != min_room_id);
+        let client_ids = room_list
+            .get(min_room_id)
+            .map_or(Vec::new(), participant_ids);
+        for client_id in client_ids {
+            let to_room_id = tournament
+                .tables
+                .iter()
+                .copied()
+                .min_by_key(|room_id| room_list.get(*room_id).map_or(0, participant_count))
+                .unwrap();
+            move_player(room_list, client_id, to_room_id, &mut update);
+        }
+        if let Some(room) = room_list.get_mut(min_room_id) {
+            room.room_state = RoomState::Finished;
+            update.push(room.room_id, RoomDelta::RoomStateChanged(room.room_state));
+        }
+        info!(
+            "Tournament {} broke table {}, {} tables left",
+            tournament.tournament_id,
+            min_room_id,
+            tournament.tables.len()
+        );
+    } else if max_count >= min_count + 2 {
+        if in_play(max_room_id) {
+            tournament.held_tables.insert(max_room_id);
+            return update;
+        }
+        if let Some(client_id) = room_list
+            .get(max_room_id)
+            .and_then(|room| participant_ids(room).pop())
+        {
+            move_player(room_list, client_id, min_room_id, &mut update);
+        }
+    }
+    update
+}
+
+// 报名人数达到上限时自动开始
+pub fn start_full_tournaments(
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut table_changed_ew: EventWriter<TableChangedEvent>,
+) {
+    for tournament in tournament_list.iter_mut() {
+        let is_full = room_list.get(tournament.room_id).is_some_and(|room| {
+            room.room_state == RoomState::Registering
+                && participant_count(room) >= tournament.config.capacity() as usize
+        });
+        if is_full {
+            start_tournament(&mut room_list, tournament)
+                .send(&mut room_delta_ew, &mut table_changed_ew);
+        }
+    }
+}
+
+// 多桌锦标赛调整各桌人数
+pub fn balance_tournament_tables(
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    play_list: Res<PlayList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    mut table_changed_ew: EventWriter<TableChangedEvent>,
+) {
+    for tournament in tournament_list.iter_mut() {
+        if tournament.tables.len() > 1 && tournament.is_running() {
+            balance_tables(&mut room_list, &play_list, tournament)
+                .send(&mut room_delta_ew, &mut table_changed_ew);
+        }
+    }
+}
+
+// 按时间升级盲注，暂停时不计时
+pub fn advance_tournament_levels(
+    mut room_list: ResMut<RoomList>,
+    mut tournament_list: ResMut<TournamentList>,
+    mut room_delta_ew: EventWriter<RoomDeltaEvent>,
+    time: Res<Time>,
+) {
+    for tournament in tournament_list.iter_mut() {
+        let LevelUp::Seconds(seconds) = tournament.config.level_up else {
+            continue;
+        };
+        let is_running = tournament.tables.iter().any(|room_id| {
+            room_list
+                .get(*room_id)
+                .is_some_and(|room| room.room_state == RoomState::Running)
+        });
+        if !is_running {
+            continue;
+        }
+        tournament.level_elapsed += time.delta_seconds();
+        if tournament.level_elapsed >= seconds as f32 {
+            let mut update = RoomUpdate::default();
+            level_up(&mut room_list, tournament, &mut update);
+            room_delta_ew.send_batch(update.deltas);
+        }
+    }
+}
+
+// 检查房主设置的房间状态，锦标赛的暂停和继续作用于所有桌
+pub fn change_room_state(
+    room_list: &mut RoomList,
+    tournament_list: &mut TournamentList,
+    room_id: u64,
+    target_room_state: RoomState,
+) -> Result<RoomUpdate, RequestError> {
+    let mut update = RoomUpdate::default();
+    let room = room_list
+        .get_mut(room_id)
+        .ok_or(RequestError::RoomNotFound)?;
+    let Some(tournament) = room
+        .tournament_id
+        .and_then(|tournament_id| tournament_list.get_mut(tournament_id))
+    else {
+        // 现金桌不能使用锦标赛状态
+        return match target_room_state {
+            RoomState::Waiting | RoomState::Playing | RoomState::Paused => {
+                room.room_state = target_room_state;
+                update.push(room_id, RoomDelta::RoomStateChanged(room.room_state));
+                Ok(update)
+            }
+            _ => Err(RequestError::InvalidRoomState),
+        };
+    };
+    match (room.room_state, target_room_state) {
+        // 未坐满时房主提前开始
+        (RoomState::Registering, RoomState::Running) => {
+            if participant_count(room) < 2 {
+                return Err(RequestError::InvalidRoomState);
+            }
+            Ok(start_tournament(room_list, tournament))
+        }
+        (RoomState::Running, RoomState::Paused) | (RoomState::Paused, RoomState::Running) => {
+            for room_id in tournament.tables.iter() {
+                if let Some(room) = room_list.get_mut(*room_id) {
+                    room.room_state = target_room_state;
+                    update.push(room.room_id, RoomDelta::RoomStateChanged(room.room_state));
+                }
+            }
+            Ok(update)
+        }
+        _ => Err(RequestError::InvalidRoomState),
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use texas_holdem_common::{channel::RequestError, SessionToken};
+use texas_holdem_server::{
+    bank::Bank,
+    config::ServerSettings,
+    ledger::{LedgerAccount, LedgerTransaction, TransactionKind},
+};
+
+// 每个测试使用独立的持久化目录
+fn temp_settings(name: &str) -> ServerSettings {
+    let path =
+        std::env::temp_dir().join(format!("texas-holdem-bank-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    ServerSettings {
+        persistence_path: Some(path),
+        ..Default::default()
+    }
+}
+
+fn persistence_file(settings: &ServerSettings, file_name: &str) -> PathBuf {
+    settings.persistence_path.as_ref().unwrap().join(file_name)
+}
+
+fn hand_transaction(kind: TransactionKind, player_name: &str, amount: u64) -> LedgerTransaction {
+    let stack = LedgerAccount::Stack {
+        room_id: 1,
+        player_name: player_name.to_string(),
+    };
+    let pot = LedgerAccount::Pot {
+        room_id: 1,
+        play_id: 1,
+    };
+    let (from, to) = match kind {
+        TransactionKind::Award => (pot, stack),
+        _ => (stack, pot),
+    };
+    LedgerTransaction {
+        kind,
+        room_id: Some(1),
+        play_id: Some(1),
+        player_name: Some(player_name.to_string()),
+        from,
+        to,
+        amount,
+    }
+}
+
+// 服务端分配给Alice和Bob的账户
+const ALICE: u64 = 1;
+const BOB: u64 = 2;
+
+// Alice和Bob各买入100，Alice赢了Bob 30筹码
+fn play_one_hand(bank: &mut Bank) {
+    bank.open(ALICE, &SessionToken([1; 16]), None, 0.0).unwrap();
+    bank.open(BOB, &SessionToken([2; 16]), None, 0.0).unwrap();
+    bank.buy_in(ALICE, 1, "Alice", 100).unwrap();
+    bank.buy_in(BOB, 1, "Bob", 100).unwrap();
+    let hand = [
+        hand_transaction(TransactionKind::Blind, "Alice", 30),
+        hand_transaction(TransactionKind::Bet, "Bob", 30),
+        hand_transaction(TransactionKind::Award, "Alice", 60),
+    ];
+    bank.settle(
+        1,
+        &[("Alice".to_string(), 130), ("Bob".to_string(), 70)],
+        &hand,
+    )
+    .unwrap();
+    // 筹码与分录推算的结果不一致时拒绝
+    assert!(bank
+        .settle(
+            1,
+            &[("Alice".to_string(), 200), ("Bob".to_string(), 70)],
+            &hand
+        )
+        .is_err());
+}
+
+#[test]
+fn bank_persists_and_returns_table_chips_after_restart() {
+    let settings = temp_settings("restart");
+    let initial_bankroll = settings.bank.initial_bankroll;
+
+    let mut bank = Bank::load(&settings).unwrap();
+    play_one_hand(&mut bank);
+    assert!(bank.reconcile().unwrap().is_empty());
+    drop(bank);
+
+    // 重启后桌上筹码按最后一次结算退回余额
+    let bank = Bank::load(&settings).unwrap();
+    let alice = bank.account(ALICE).unwrap();
+    assert_eq!(alice.balance, initial_bankroll + 30);
+    assert!(alice.tables.is_empty());
+    assert_eq!(bank.account(BOB).unwrap().balance, initial_bankroll - 30);
+    // 凭会话令牌找回账户
+    assert_eq!(bank.account_of_token(&SessionToken([1; 16])), Some(ALICE));
+    assert_eq!(bank.account_of_token(&SessionToken([3; 16])), None);
+    assert_eq!(bank.total(), initial_bankroll * 2);
+    // 开户2笔、买入2笔、本局3笔、重启兑现2笔
+    assert!(bank.reconcile().unwrap().is_empty());
+    assert_eq!(bank.ledger_len().unwrap(), 9);
+
+    fs::remove_dir_all(settings.persistence_path.unwrap()).unwrap();
+}
+
+#[test]
+fn reconcile_ignores_uncommitted_ledger_and_detects_tampering() {
+    let settings = temp_settings("reconcile");
+    let mut bank = Bank::load(&settings).unwrap();
+    play_one_hand(&mut bank);
+    drop(bank);
+
+    // 模拟写完账本、写账户文件前崩溃：末尾多出未提交的分录和写了一半的行
+    let ledger_path = persistence_file(&settings, "ledger.jsonl");
+    let committed = fs::read(&ledger_path).unwrap();
+    let last_line = committed[..committed.len() - 1]
+        .rsplit(|byte| *byte == b'\n')
+        .next()
+        .unwrap();
+    let uncommitted = String::from_utf8(last_line.to_vec())
+        .unwrap()
+        .replace("\"seq\":5", "\"seq\":6");
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&ledger_path)
+        .unwrap();
+    writeln!(file, "{}", uncommitted).unwrap();
+    write!(file, "{{\"seq\":6,").unwrap();
+    drop(file);
+    assert!(Bank::read(&settings)
+        .unwrap()
+        .reconcile()
+        .unwrap()
+        .is_empty());
+    let bank = Bank::load(&settings).unwrap();
+    assert!(bank.reconcile().unwrap().is_empty());
+    drop(bank);
+
+    // 直接修改账户文件的余额无法由账本推算出
+    let bank_path = persistence_file(&settings, "bank.json");
+    let content = fs::read_to_string(&bank_path).unwrap();
+    let initial_bankroll = settings.bank.initial_bankroll;
+    fs::write(
+        &bank_path,
+        content.replacen(
+            &format!("\"balance\": {}", initial_bankroll + 30),
+            &format!("\"balance\": {}", initial_bankroll + 1030),
+            1,
+        ),
+    )
+    .unwrap();
+    let mismatches = Bank::read(&settings).unwrap().reconcile().unwrap();
+    assert!(mismatches
+        .iter()
+        .any(|mismatch| mismatch.contains(&format!("Bankroll({})", ALICE))));
+    assert!(mismatches
+        .iter()
+        .any(|mismatch| mismatch.starts_with("House")));
+
+    fs::remove_dir_all(settings.persistence_path.unwrap()).unwrap();
+}
+
+#[test]
+fn accounts_are_opened_per_user_and_limited_per_address() {
+    let mut settings = ServerSettings::default();
+    settings.bank.max_new_accounts = 2;
+    settings.bank.new_account_window_secs = 60.0;
+    let mut bank = Bank::load(&settings).unwrap();
+    let addr = Some("10.0.0.1".parse().unwrap());
+
+    // 已开户的用户再次进入房间不重复发放资金，也不计入开户次数
+    bank.open(1, &SessionToken([1; 16]), addr, 0.0).unwrap();
+    bank.open(1, &SessionToken([1; 16]), addr, 1.0).unwrap();
+    bank.open(2, &SessionToken([2; 16]), addr, 2.0).unwrap();
+    assert_eq!(bank.total(), settings.bank.initial_bankroll * 2);
+    assert_eq!(
+        bank.open(3, &SessionToken([3; 16]), addr, 3.0),
+        Err(RequestError::TooManyAccounts)
+    );
+    bank.open(
+        3,
+        &SessionToken([3; 16]),
+        Some("10.0.0.2".parse().unwrap()),
+        3.0,
+    )
+    .unwrap();
+    bank.open(4, &SessionToken([4; 16]), addr, 61.0).unwrap();
+    assert_eq!(bank.total(), settings.bank.initial_bankroll * 4);
+
+    // 桌上的玩家名属于入座的账户，其他账户不能以同名买入
+    bank.buy_in(1, 1, "Alice", 100).unwrap();
+    assert_eq!(bank.seat_account(1, "Alice"), Some(1));
+    assert_eq!(
+        bank.buy_in(2, 1, "Alice", 100),
+        Err(RequestError::NameTaken)
+    );
+    assert_eq!(bank.cash_out("Alice", 1), Ok(100));
+    bank.buy_in(2, 1, "Alice", 100).unwrap();
+    assert_eq!(bank.seat_account(1, "Alice"), Some(2));
+    assert!(bank.reconcile().unwrap().is_empty());
+}
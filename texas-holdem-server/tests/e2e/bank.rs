@@ -0,0 +1,211 @@
+use texas_holdem_client::{
+    lobby::PlayerName,
+    request::RequestKind,
+    room::{BankEvent, SwitchPlayerRoleEvent},
+};
+use texas_holdem_common::{
+    betting::RakeConfig, channel::BankRequest, PlayerRole, Round, RoundAction,
+};
+use texas_holdem_server::config::ServerSettings;
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        call_until_round_ends, chips_of, current_player_index, fold_until_next_play, setup_room,
+        setup_room_with, start_play, switch_to_participant, try_action, try_enter_room,
+        ROOM_PASSWORD,
+    },
+};
+
+// 发送买入请求并等待服务端响应
+fn buy_in(harness: &mut TestHarness, index: usize, amount: u32) {
+    harness.send_client_event(
+        index,
+        BankEvent {
+            request: BankRequest::BuyIn(amount),
+        },
+    );
+    harness.tick();
+    harness.run_until("bank response", |harness| {
+        !harness.is_request_pending(index, RequestKind::Bank)
+    });
+}
+
+#[test]
+fn bank_buys_in_tops_up_and_cashes_out_between_hands() {
+    let mut harness = TestHarness::new();
+    {
+        let mut settings = harness.server_settings_mut();
+        settings.stakes.starting_chips = 100;
+        settings.stakes.max_buy_in = 200;
+    }
+    let initial_bankroll = ServerSettings::default().bank.initial_bankroll;
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol", "Dave"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2, 3]);
+
+    // 入座时从账户买入默认数量，客户端收到最新余额
+    for index in 0..4 {
+        assert_eq!(chips_of(&harness, room_id, index), 100);
+    }
+    harness.run_until("bankroll synced", |harness| {
+        (0..4).all(|index| harness.bankroll(index).balance == Some(initial_bankroll - 100))
+    });
+
+    // 两局之间补充筹码，不能超过最大买入
+    buy_in(&mut harness, 0, 50);
+    assert_eq!(chips_of(&harness, room_id, 0), 150);
+    assert_eq!(harness.account(0).unwrap().balance, initial_bankroll - 150);
+    buy_in(&mut harness, 0, 51);
+    assert_eq!(chips_of(&harness, room_id, 0), 150);
+
+    // 手牌进行中不能买入
+    let play_id = start_play(&mut harness, room_id);
+    buy_in(&mut harness, 1, 50);
+    assert_eq!(chips_of(&harness, room_id, 1), 100);
+    assert_eq!(harness.account(1).unwrap().balance, initial_bankroll - 100);
+
+    // 对局中离开座位，对局结束后筹码兑回账户
+    harness.send_client_event(
+        2,
+        SwitchPlayerRoleEvent {
+            room_id,
+            target_player_role: PlayerRole::Spectator,
+        },
+    );
+    harness.run_until("Carol becomes spectator", |harness| {
+        harness.current_room_info(2).my_role == PlayerRole::Spectator
+    });
+    let play_id = fold_until_next_play(&mut harness, room_id, play_id);
+    harness.run_until("Carol cashed out", |harness| {
+        harness.account(2).unwrap().tables.is_empty()
+    });
+    assert_eq!(chips_of(&harness, room_id, 2), 0);
+    assert_eq!(harness.bank().total(), initial_bankroll * 4);
+
+    // 每局结束后账户中的桌上筹码与房间一致
+    fold_until_next_play(&mut harness, room_id, play_id);
+    for index in [0, 1, 3] {
+        let account = harness.account(index).unwrap();
+        assert_eq!(
+            account.total(),
+            account.balance + chips_of(&harness, room_id, index) as u64
+        );
+    }
+    assert_eq!(harness.bank().total(), initial_bankroll * 4);
+    // 所有余额和桌上筹码都能由账本推算出
+    assert_eq!(harness.bank().reconcile().unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn bankroll_follows_the_session_not_the_player_name() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().stakes.starting_chips = 100;
+    let initial_bankroll = ServerSettings::default().bank.initial_bankroll;
+    let room_id = setup_room(&mut harness, &["Alice", "Bob"]);
+    switch_to_participant(&mut harness, room_id, &[1]);
+    let bob_account = harness.user_id(1).unwrap();
+
+    // Bob断开后筹码兑回账户，其他人用Bob的名字进入时开新账户，不能动用Bob的资金
+    harness.disconnect_client(1);
+    assert!(harness
+        .bank()
+        .account(bob_account)
+        .unwrap()
+        .tables
+        .is_empty());
+    let impostor = harness.add_client("Bob");
+    assert!(try_enter_room(
+        &mut harness,
+        impostor,
+        room_id,
+        ROOM_PASSWORD
+    ));
+    switch_to_participant(&mut harness, room_id, &[impostor]);
+    assert_ne!(harness.user_id(impostor), Some(bob_account));
+    assert_eq!(
+        harness.account(impostor).unwrap().balance,
+        initial_bankroll - 100
+    );
+    assert_eq!(
+        harness.bank().account(bob_account).unwrap().balance,
+        initial_bankroll
+    );
+
+    // Bob凭令牌重新连接，换了名字仍使用原来的账户
+    harness.reconnect_client(1);
+    harness.clients[1].world.resource_mut::<PlayerName>().0 = "Robert".to_string();
+    assert_eq!(harness.user_id(1), Some(bob_account));
+    assert!(try_enter_room(&mut harness, 1, room_id, ROOM_PASSWORD));
+    switch_to_participant(&mut harness, room_id, &[1]);
+    assert_eq!(harness.account(1).unwrap().balance, initial_bankroll - 100);
+    assert_eq!(harness.bank().total(), initial_bankroll * 3);
+    assert_eq!(harness.bank().reconcile().unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn rake_is_capped_and_skipped_without_a_flop() {
+    let mut harness = TestHarness::new();
+    let rake = RakeConfig {
+        percent: 10,
+        cap: 3,
+    };
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_rake(owner, rake),
+    );
+    assert!((0..3).all(|index| harness.current_room_info(index).view.rake == rake));
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let starting_chips = ServerSettings::default().stakes.starting_chips;
+    let room_chips = |harness: &TestHarness| {
+        harness
+            .room_list()
+            .get(room_id)
+            .unwrap()
+            .players
+            .iter()
+            .map(|player| player.chips)
+            .sum::<u32>()
+    };
+
+    // 不翻牌不抽水
+    let play_id = start_play(&mut harness, room_id);
+    let play_id = fold_until_next_play(&mut harness, room_id, play_id);
+    assert_eq!(harness.bank().rake_of(room_id), 0);
+    assert_eq!(room_chips(&harness), starting_chips * 3);
+
+    // 翻牌前加注到40，三人跟到摊牌，底池120抽水10%为12，按上限抽3
+    let (index, _, _) = current_player_index(&harness).unwrap();
+    assert!(try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::Raise,
+        40
+    ));
+    while harness.play_list().plays[0].round != Round::Showdown {
+        call_until_round_ends(&mut harness, play_id, room_id);
+    }
+    let showdown = harness.play_list().plays[0].showdown.clone().unwrap();
+    assert_eq!(showdown.rake, 3);
+    assert_eq!(showdown.winnings.values().sum::<u32>(), 117);
+    harness.run_until("clients see rake", |harness| {
+        (0..3).all(|index| {
+            harness
+                .current_room_info(index)
+                .view
+                .play
+                .as_ref()
+                .and_then(|play| play.showdown.as_ref())
+                .is_some_and(|showdown| showdown.rake == 3)
+        })
+    });
+
+    // 结算后抽水记入本桌，账本可以推算出所有余额
+    harness.run_until("rake settled", |harness| {
+        harness.bank().rake_of(room_id) == 3
+    });
+    assert_eq!(room_chips(&harness), starting_chips * 3 - 3);
+    assert_eq!(harness.bank().reconcile().unwrap(), Vec::<String>::new());
+}
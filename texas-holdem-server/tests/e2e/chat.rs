@@ -0,0 +1,201 @@
+use texas_holdem_client::{lobby::EnterRoomEvent, request::RequestKind, room::ChatEvent, AppState};
+use texas_holdem_common::spectator::SpectatorConfig;
+use texas_holdem_server::chat::ChatRateLimiter;
+
+use crate::{
+    common::TestHarness,
+    fixtures::{setup_room, setup_room_with, switch_to_participant, ROOM_PASSWORD},
+};
+
+// 发送聊天消息并等待服务端响应
+fn chat(harness: &mut TestHarness, index: usize, text: &str) {
+    harness.send_client_event(
+        index,
+        ChatEvent {
+            text: text.to_string(),
+        },
+    );
+    harness.tick();
+    harness.run_until("chat response", |harness| {
+        !harness.is_request_pending(index, RequestKind::Chat)
+    });
+}
+
+// 客户端收到的聊天记录，系统消息没有发送者
+fn chat_lines(harness: &TestHarness, index: usize) -> Vec<String> {
+    harness
+        .current_room_info(index)
+        .chat
+        .iter()
+        .map(|line| match &line.sender_name {
+            Some(sender_name) => format!("{}: {}", sender_name, line.text),
+            None => line.text.clone(),
+        })
+        .collect()
+}
+
+fn wait_for_chat_line(harness: &mut TestHarness, index: usize, line: &str) {
+    harness.run_until(
+        &format!("client {} receives {:?}", index, line),
+        |harness| chat_lines(harness, index).iter().any(|l| l == line),
+    );
+}
+
+#[test]
+fn chat_is_filtered_rate_limited_and_kept_apart_for_spectators() {
+    let mut harness = TestHarness::new();
+    {
+        let mut settings = harness.server_settings_mut();
+        settings.chat.banned_words = vec!["darn".to_string()];
+        settings.chat.rate_limit_messages = 2;
+    }
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1]);
+
+    // 参与者的消息所有人可见，屏蔽词被替换
+    chat(&mut harness, 0, "  DARN good hand ");
+    for index in 0..3 {
+        wait_for_chat_line(&mut harness, index, "Alice: **** good hand");
+    }
+
+    // 旁观者的消息只发给旁观者
+    chat(&mut harness, 2, "Bob has aces");
+    wait_for_chat_line(&mut harness, 2, "Carol: Bob has aces");
+    chat(&mut harness, 1, "gl");
+    wait_for_chat_line(&mut harness, 0, "Bob: gl");
+    wait_for_chat_line(&mut harness, 1, "Bob: gl");
+    for index in 0..2 {
+        assert!(!chat_lines(&harness, index)
+            .iter()
+            .any(|line| line.starts_with("Carol")));
+    }
+
+    // 超过频率限制的消息被拒绝，空消息不发送
+    chat(&mut harness, 1, "one");
+    chat(&mut harness, 1, "two");
+    chat(&mut harness, 1, "  ");
+    wait_for_chat_line(&mut harness, 0, "Bob: one");
+    chat(&mut harness, 0, "ping");
+    wait_for_chat_line(&mut harness, 1, "Alice: ping");
+    assert_eq!(
+        chat_lines(&harness, 0),
+        vec![
+            "Alice: **** good hand",
+            "Bob: gl",
+            "Bob: one",
+            "Alice: ping"
+        ]
+    );
+
+    // 频率限制按服务端分配的用户记录，断开后移除
+    let bob_user_id = harness.user_id(1).unwrap();
+    let rate_limiter = |harness: &TestHarness| {
+        harness
+            .server_app(0)
+            .world
+            .resource::<ChatRateLimiter>()
+            .is_tracked(bob_user_id)
+    };
+    assert!(rate_limiter(&harness));
+    harness.disconnect_client(1);
+    assert!(!rate_limiter(&harness));
+}
+
+#[test]
+fn owner_mutes_and_kicks_players_with_chat_commands() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().chat.rate_limit_messages = 0;
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol"]);
+
+    // 只有房主能使用命令
+    chat(&mut harness, 1, "/mute Carol");
+    chat(&mut harness, 0, "/mute Dave");
+    assert!(harness
+        .room_list()
+        .get(room_id)
+        .unwrap()
+        .muted_names
+        .is_empty());
+
+    // 被禁言的玩家发送的消息不会转发
+    chat(&mut harness, 0, "/mute Bob");
+    wait_for_chat_line(&mut harness, 2, "Bob was muted");
+    chat(&mut harness, 1, "hello?");
+    chat(&mut harness, 2, "anyone?");
+    wait_for_chat_line(&mut harness, 1, "Carol: anyone?");
+    assert!(!chat_lines(&harness, 1).contains(&"Bob: hello?".to_string()));
+    chat(&mut harness, 0, "/unmute Bob");
+    chat(&mut harness, 1, "thanks");
+    wait_for_chat_line(&mut harness, 2, "Bob: thanks");
+
+    // 被请出的玩家回到大厅，其他人收到离开的增量
+    let bob_id = harness.client_id(1);
+    chat(&mut harness, 0, "/kick Bob");
+    harness.run_until("Bob back in lobby", |harness| {
+        harness.app_state(1) == AppState::Lobby
+    });
+    wait_for_chat_line(&mut harness, 2, "Bob was kicked");
+    harness.run_until("Bob removed from views", |harness| {
+        [0, 2].iter().all(|index| {
+            !harness
+                .current_room_info(*index)
+                .view
+                .players
+                .iter()
+                .any(|player| player.player_client_id == bob_id)
+        })
+    });
+    assert!(harness.room_list().room_of_client(bob_id).is_none());
+    assert!(harness.current_room_info(1).chat.is_empty());
+
+    // 被请出后可以重新进入
+    harness.send_client_event(
+        1,
+        EnterRoomEvent {
+            room_id,
+            room_password: ROOM_PASSWORD.to_string(),
+        },
+    );
+    harness.run_until("Bob back in room", |harness| {
+        harness.app_state(1) == AppState::Gaming && harness.current_room_info(1).view.seq.is_some()
+    });
+}
+
+#[test]
+fn command_notices_keep_spectator_names_hidden() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().chat.rate_limit_messages = 0;
+    let spectators = SpectatorConfig {
+        hide_names: true,
+        ..Default::default()
+    };
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_spectators(owner, spectators),
+    );
+    switch_to_participant(&mut harness, room_id, &[0, 1]);
+    let carol_id = harness.client_id(2);
+    let hidden_name = harness
+        .current_room_info(1)
+        .view
+        .players
+        .iter()
+        .find(|player| player.player_client_id == carol_id)
+        .unwrap()
+        .player_name
+        .clone();
+    assert_ne!(hidden_name, "Carol");
+
+    // 参与者看到旁观者的代称，旁观者看到真实名字
+    chat(&mut harness, 0, "/mute Carol");
+    wait_for_chat_line(&mut harness, 1, &format!("{} was muted", hidden_name));
+    wait_for_chat_line(&mut harness, 2, "Carol was muted");
+    chat(&mut harness, 0, "/kick Carol");
+    wait_for_chat_line(&mut harness, 1, &format!("{} was kicked", hidden_name));
+    for index in 0..2 {
+        assert!(!chat_lines(&harness, index)
+            .iter()
+            .any(|line| line.contains("Carol")));
+    }
+}
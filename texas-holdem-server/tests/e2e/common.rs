@@ -0,0 +1,534 @@
+// 端到端测试工具：在同一进程内运行服务端和多个无界面客户端，通过本地回环UDP通信
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, SystemTime},
+};
+
+use bevy::utils::Instant;
+
+use bevy::{app::AppExit, prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::renet::{ClientAuthentication, RenetClient, RenetServer};
+use texas_holdem_client::{
+    directory::DirectoryClient,
+    lobby::{
+        self, ConnectDirectoryEvent, ConnectServerEvent, CurrentServer, DirectoryRoomList,
+        NewRoomSettings, PlayerName, QuickSeatState, RoomQuery,
+    },
+    request::{PendingRequests, RequestKind},
+    room::{Bankroll, CurrentRoomInfo},
+    AppState, ClientNetworkPlugin,
+};
+use texas_holdem_common::{
+    betting::{BettingStructure, ForcedBets, RakeConfig},
+    channel::{PlayerActionMessage, PLAYER_ACTION_CHANNEL_ID},
+    connection_config,
+    spectator::SpectatorConfig,
+    tournament::TournamentConfig,
+    variant::GameVariant,
+    RoundAction, PROTOCOL_ID, PROTOCOL_VERSION,
+};
+use texas_holdem_directory::{DirectoryPlugin, DirectorySettings, ServerDirectory};
+use texas_holdem_server::{
+    bank::{Account, Bank},
+    config::ServerSettings,
+    directory::{new_directory_client, ShutdownSignal},
+    history::{HandHistory, HandRecord},
+    network::Sessions,
+    new_renet_server,
+    play::PlayList,
+    room::RoomList,
+    tournament::{Tournament, TournamentList},
+    ServerPlugin,
+};
+
+// 每帧推进的时间，所有App使用相同的虚拟时间，与实际耗时无关
+pub const TICK: Duration = Duration::from_millis(16);
+// run_until的最大帧数
+const MAX_TICKS: usize = 2000;
+
+pub struct TestHarness {
+    pub server: App,
+    // 登记到同一目录的其他服务端，序号从1开始
+    pub other_servers: Vec<App>,
+    pub directory: Option<App>,
+    pub clients: Vec<App>,
+    // 当前虚拟时间（ManualDuration以实际时间为基准，这里用ManualInstant逐帧推进）
+    now: Instant,
+}
+
+impl TestHarness {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        TestHarness {
+            server: new_server_app(now, test_settings()),
+            other_servers: Vec::new(),
+            directory: None,
+            clients: Vec::new(),
+            now,
+        }
+    }
+
+    // 启动目录服务和多个登记到目录的服务端，第一个为server，其余为other_servers
+    pub fn with_directory(server_names: &[&str]) -> Self {
+        let now = Instant::now();
+        let mut directory = App::new();
+        directory
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualInstant(now))
+            .insert_resource(
+                texas_holdem_directory::new_renet_server("127.0.0.1:0".parse().unwrap(), 64)
+                    .unwrap(),
+            )
+            .add_plugin(DirectoryPlugin);
+        let directory_addr = directory.world.resource::<RenetServer>().addr();
+        let mut servers: Vec<App> = server_names
+            .iter()
+            .map(|server_name| new_directory_server_app(now, directory_addr, server_name, None))
+            .collect();
+        let mut harness = TestHarness {
+            server: servers.remove(0),
+            other_servers: servers,
+            directory: Some(directory),
+            clients: Vec::new(),
+            now,
+        };
+        harness.run_until("servers registered", |harness| {
+            harness.server_directory().servers().count() == server_names.len()
+        });
+        harness
+    }
+
+    // 再启动一个连接到目录的服务端，返回服务端序号；不等待登记
+    pub fn add_server(&mut self, server_name: &str, secret: Option<&str>) -> usize {
+        let directory_addr = self
+            .directory
+            .as_ref()
+            .unwrap()
+            .world
+            .resource::<RenetServer>()
+            .addr();
+        self.other_servers.push(new_directory_server_app(
+            self.now,
+            directory_addr,
+            server_name,
+            secret,
+        ));
+        self.other_servers.len()
+    }
+
+    pub fn directory_settings_mut(&mut self) -> Mut<'_, DirectorySettings> {
+        self.directory
+            .as_mut()
+            .unwrap()
+            .world
+            .resource_mut::<DirectorySettings>()
+    }
+
+    pub fn server_app(&self, server_index: usize) -> &App {
+        match server_index {
+            0 => &self.server,
+            _ => &self.other_servers[server_index - 1],
+        }
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server_addr_of(0)
+    }
+
+    pub fn server_addr_of(&self, server_index: usize) -> SocketAddr {
+        self.server_app(server_index)
+            .world
+            .resource::<RenetServer>()
+            .addr()
+    }
+
+    // 添加客户端并等待连接成功，返回客户端序号
+    pub fn add_client(&mut self, player_name: &str) -> usize {
+        self.add_client_to(0, player_name)
+    }
+
+    pub fn add_client_to(&mut self, server_index: usize, player_name: &str) -> usize {
+        let mut client = App::new();
+        client
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now))
+            .add_plugin(ClientNetworkPlugin)
+            .insert_resource(PlayerName(player_name.to_string()));
+        client.world.send_event(ConnectServerEvent {
+            server_addr: self.server_addr_of(server_index).to_string(),
+        });
+        self.clients.push(client);
+        let index = self.clients.len() - 1;
+        self.run_until(&format!("{} connected", player_name), |harness| {
+            harness.clients[index]
+                .world
+                .get_resource::<RenetClient>()
+                .is_some_and(|client| client.is_connected())
+        });
+        index
+    }
+
+    // 添加冒用指定连接id、不带会话令牌的客户端并等待连接成功，返回客户端序号
+    pub fn add_spoofed_client(&mut self, player_name: &str, client_id: u64) -> usize {
+        let server_addr = self.server_addr();
+        let authentication = ClientAuthentication::Unsecure {
+            client_id,
+            protocol_id: PROTOCOL_ID,
+            server_addr,
+            user_data: Some(PROTOCOL_VERSION.to_user_data()),
+        };
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let renet_client =
+            RenetClient::new(current_time, socket, connection_config(), authentication).unwrap();
+        let mut client = App::new();
+        client
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now))
+            .add_plugin(ClientNetworkPlugin)
+            .insert_resource(PlayerName(player_name.to_string()))
+            .insert_resource(renet_client);
+        client.world.resource_mut::<CurrentServer>().server_addr = Some(server_addr);
+        self.clients.push(client);
+        let index = self.clients.len() - 1;
+        self.run_until(&format!("{} connected", player_name), |harness| {
+            harness.clients[index]
+                .world
+                .resource::<RenetClient>()
+                .is_connected()
+        });
+        index
+    }
+
+    // 断开客户端并等待服务端处理断开
+    pub fn disconnect_client(&mut self, index: usize) {
+        let client_id = self.client_id(index);
+        self.clients[index]
+            .world
+            .resource_mut::<RenetClient>()
+            .disconnect();
+        self.run_until("client disconnected", |harness| {
+            !harness
+                .server
+                .world
+                .resource::<RenetServer>()
+                .clients_id()
+                .contains(&client_id)
+                && harness.room_list().room_of_client(client_id).is_none()
+        });
+    }
+
+    // 客户端回到大厅重新连接服务端（带上保存的会话令牌）并等待连接成功
+    pub fn reconnect_client(&mut self, index: usize) {
+        let server_addr = self.server_addr();
+        self.clients[index]
+            .world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Lobby);
+        self.send_client_event(
+            index,
+            ConnectServerEvent {
+                server_addr: server_addr.to_string(),
+            },
+        );
+        self.run_until("client reconnected", |harness| {
+            harness.clients[index]
+                .world
+                .resource::<RenetClient>()
+                .is_connected()
+        });
+    }
+
+    // 推进一帧：先更新目录和服务端，再依次更新客户端
+    pub fn tick(&mut self) {
+        self.now += TICK;
+        let servers = self
+            .directory
+            .iter_mut()
+            .chain([&mut self.server])
+            .chain(self.other_servers.iter_mut());
+        for server in servers {
+            server.insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+            server.update();
+        }
+        for client in self.clients.iter_mut() {
+            client.insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+            client.update();
+        }
+    }
+
+    // 推进直到条件满足，超过最大帧数时失败
+    pub fn run_until(&mut self, description: &str, mut condition: impl FnMut(&mut Self) -> bool) {
+        for _ in 0..MAX_TICKS {
+            self.tick();
+            if condition(self) {
+                return;
+            }
+        }
+        panic!("Timed out waiting for: {}", description);
+    }
+
+    pub fn send_client_event<E: Event>(&mut self, index: usize, event: E) {
+        self.clients[index].world.send_event(event);
+    }
+
+    // 修改服务端设置，在下一帧生效
+    pub fn server_settings_mut(&mut self) -> Mut<'_, ServerSettings> {
+        self.server.world.resource_mut::<ServerSettings>()
+    }
+
+    pub fn bank(&self) -> &Bank {
+        self.server.world.resource::<Bank>()
+    }
+
+    // 服务端分配给客户端当前会话的用户
+    pub fn user_id(&self, index: usize) -> Option<u64> {
+        self.server
+            .world
+            .resource::<Sessions>()
+            .user_id(self.client_id(index))
+    }
+
+    // 客户端当前会话的账户
+    pub fn account(&self, index: usize) -> Option<&Account> {
+        self.bank().account(self.user_id(index)?)
+    }
+
+    pub fn hand_history(&self) -> Vec<HandRecord> {
+        self.server
+            .world
+            .resource::<HandHistory>()
+            .records()
+            .unwrap()
+    }
+
+    pub fn room_list(&self) -> &RoomList {
+        self.server.world.resource::<RoomList>()
+    }
+
+    pub fn play_list(&self) -> &PlayList {
+        self.server.world.resource::<PlayList>()
+    }
+
+    pub fn tournament_of(&self, room_id: u64) -> Option<&Tournament> {
+        let room = self.room_list().get(room_id)?;
+        self.server.world.resource::<TournamentList>().of_room(room)
+    }
+
+    pub fn current_room_info(&self, index: usize) -> &CurrentRoomInfo {
+        self.clients[index].world.resource::<CurrentRoomInfo>()
+    }
+
+    pub fn bankroll(&self, index: usize) -> &Bankroll {
+        self.clients[index].world.resource::<Bankroll>()
+    }
+
+    // 请求是否还在等待服务端响应
+    pub fn is_request_pending(&self, index: usize, kind: RequestKind) -> bool {
+        self.clients[index]
+            .world
+            .resource::<PendingRequests>()
+            .is_pending(kind)
+    }
+
+    pub fn app_state(&self, index: usize) -> AppState {
+        self.clients[index].world.resource::<State<AppState>>().0
+    }
+
+    pub fn client_id(&self, index: usize) -> u64 {
+        self.clients[index]
+            .world
+            .resource::<RenetClient>()
+            .client_id()
+    }
+
+    pub fn room_query_mut(&mut self, index: usize) -> Mut<'_, RoomQuery> {
+        self.clients[index].world.resource_mut::<RoomQuery>()
+    }
+
+    pub fn server_directory(&self) -> &ServerDirectory {
+        self.directory
+            .as_ref()
+            .unwrap()
+            .world
+            .resource::<ServerDirectory>()
+    }
+
+    // 客户端连接目录服务并等待连接成功
+    pub fn connect_directory(&mut self, index: usize) {
+        let directory_addr = self
+            .directory
+            .as_ref()
+            .unwrap()
+            .world
+            .resource::<RenetServer>()
+            .addr();
+        self.send_client_event(
+            index,
+            ConnectDirectoryEvent {
+                directory_addr: directory_addr.to_string(),
+            },
+        );
+        self.run_until("directory connected", |harness| {
+            harness.clients[index]
+                .world
+                .get_resource::<DirectoryClient>()
+                .is_some_and(|client| client.is_connected())
+        });
+    }
+
+    pub fn directory_rooms(&self, index: usize) -> &DirectoryRoomList {
+        self.clients[index].world.resource::<DirectoryRoomList>()
+    }
+
+    pub fn current_server(&self, index: usize) -> &CurrentServer {
+        self.clients[index].world.resource::<CurrentServer>()
+    }
+
+    pub fn room_list_of(&self, server_index: usize) -> &RoomList {
+        self.server_app(server_index).world.resource::<RoomList>()
+    }
+
+    // 与收到SIGTERM相同，服务端开始排空
+    pub fn drain_server(&mut self, server_index: usize) {
+        self.server_app(server_index)
+            .world
+            .resource::<ShutdownSignal>()
+            .trigger();
+    }
+
+    // 服务端是否已发出退出事件
+    pub fn server_exited(&self, server_index: usize) -> bool {
+        !self
+            .server_app(server_index)
+            .world
+            .resource::<Events<AppExit>>()
+            .is_empty()
+    }
+
+    pub fn quick_seat_state(&self, index: usize) -> &QuickSeatState {
+        self.clients[index].world.resource::<QuickSeatState>()
+    }
+
+    // 客户端在大厅看到的房间列表
+    pub fn lobby_rooms(&self, index: usize) -> &lobby::RoomList {
+        self.clients[index].world.resource::<lobby::RoomList>()
+    }
+
+    pub fn set_new_room_settings(&mut self, index: usize, room_name: &str, room_password: &str) {
+        let mut settings = self.clients[index].world.resource_mut::<NewRoomSettings>();
+        settings.room_name = room_name.to_string();
+        settings.room_password = room_password.to_string();
+    }
+
+    // 与界面一致，切换玩法时使用该玩法常用的下注结构
+    pub fn set_new_room_variant(&mut self, index: usize, variant: GameVariant) {
+        let mut settings = self.clients[index].world.resource_mut::<NewRoomSettings>();
+        settings.variant = variant;
+        settings.betting = variant.default_betting();
+    }
+
+    pub fn set_new_room_betting(&mut self, index: usize, betting: BettingStructure) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .betting = betting;
+    }
+
+    pub fn set_new_room_run_it_twice(&mut self, index: usize, run_it_twice: bool) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .run_it_twice = run_it_twice;
+    }
+
+    pub fn set_new_room_forced_bets(&mut self, index: usize, forced_bets: ForcedBets) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .forced_bets = forced_bets;
+    }
+
+    pub fn set_new_room_rake(&mut self, index: usize, rake: RakeConfig) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .rake = rake;
+    }
+
+    pub fn set_new_room_spectators(&mut self, index: usize, spectators: SpectatorConfig) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .spectators = spectators;
+    }
+
+    pub fn set_new_room_tournament(&mut self, index: usize, tournament: TournamentConfig) {
+        self.clients[index]
+            .world
+            .resource_mut::<NewRoomSettings>()
+            .tournament = Some(tournament);
+    }
+
+    // 直接发送行动消息，指定play_id以免受客户端同步进度影响
+    pub fn send_action(&mut self, index: usize, play_id: u64, action: RoundAction, amount: u32) {
+        let room_id = self.current_room_info(index).view.room_id;
+        let message = PlayerActionMessage {
+            request_id: 0,
+            room_id,
+            play_id,
+            action,
+            amount,
+            error: None,
+        };
+        self.clients[index]
+            .world
+            .resource_mut::<RenetClient>()
+            .send_message(
+                PLAYER_ACTION_CHANNEL_ID,
+                serde_json::to_vec(&message).unwrap(),
+            );
+    }
+}
+
+fn test_settings() -> ServerSettings {
+    let mut settings = ServerSettings::default();
+    // 由系统分配端口，测试可并行运行
+    settings.network.bind_addr = "127.0.0.1:0".parse().unwrap();
+    // 所有客户端都从本机连接，不限制开户数量
+    settings.bank.max_new_accounts = 0;
+    // 调试构建中哈希很慢，测试不需要抵抗穷举
+    settings.access.password_hash_iterations = 1000;
+    settings
+}
+
+// 登记到目录的服务端
+fn new_directory_server_app(
+    now: Instant,
+    directory_addr: SocketAddr,
+    server_name: &str,
+    secret: Option<&str>,
+) -> App {
+    let mut settings = test_settings();
+    settings.directory.addr = Some(directory_addr);
+    settings.directory.server_name = Some(server_name.to_string());
+    settings.directory.secret = secret.map(str::to_string);
+    let mut server = new_server_app(now, settings);
+    server.insert_resource(new_directory_client(directory_addr).unwrap());
+    server
+}
+
+fn new_server_app(now: Instant, settings: ServerSettings) -> App {
+    let mut server = App::new();
+    server
+        .add_plugins(MinimalPlugins)
+        .insert_resource(TimeUpdateStrategy::ManualInstant(now))
+        .insert_resource(new_renet_server(&settings).unwrap())
+        .insert_resource(Bank::load(&settings).unwrap())
+        .insert_resource(HandHistory::new(settings.persistence_path.as_deref()))
+        .insert_resource(settings)
+        .add_plugin(ServerPlugin);
+    server
+}
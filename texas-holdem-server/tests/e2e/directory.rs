@@ -0,0 +1,230 @@
+use bevy_renet::renet::RenetClient;
+use texas_holdem_client::{
+    lobby::{CreateRoomEvent, JoinDirectoryRoomEvent},
+    request::RequestKind,
+    AppState,
+};
+
+use texas_holdem_common::{
+    directory::MAX_SERVER_NAME_CHARS,
+    lobby::{MAX_PLAYER_NAME_CHARS, MAX_ROOMS_PAGE_SIZE},
+};
+
+use crate::{
+    common::TestHarness,
+    fixtures::{create_longest_named_rooms, LONGEST_CHAR},
+};
+
+// 覆盖多次上报间隔（默认1秒）、又不到目录超时（默认5秒）的帧数
+const REPORT_TICKS: u32 = 200;
+
+// 在指定的服务端上创建房间，返回房主的客户端序号和房间id
+fn create_room_on(
+    harness: &mut TestHarness,
+    server_index: usize,
+    player_name: &str,
+) -> (usize, u64) {
+    let owner = harness.add_client_to(server_index, player_name);
+    harness.set_new_room_settings(owner, &format!("{}'s room", player_name), "");
+    harness.send_client_event(owner, CreateRoomEvent);
+    harness.run_until("owner in room", |harness| {
+        harness.app_state(owner) == AppState::Gaming
+            && harness.current_room_info(owner).view.seq.is_some()
+    });
+    (owner, harness.current_room_info(owner).view.room_id)
+}
+
+#[test]
+fn directory_aggregates_rooms_locates_servers_and_drains() {
+    let mut harness = TestHarness::with_directory(&["alpha", "beta"]);
+    let (_, alpha_room_id) = create_room_on(&mut harness, 0, "Alice");
+    let (bob, beta_room_id) = create_room_on(&mut harness, 1, "Bob");
+
+    // 连接alpha的客户端通过目录看到两个服务端的房间，按服务端名称排列
+    let carol = harness.add_client("Carol");
+    harness.connect_directory(carol);
+    harness.run_until("directory lists both rooms", |harness| {
+        harness.directory_rooms(carol).total == 2
+    });
+    let rooms: Vec<_> = harness
+        .directory_rooms(carol)
+        .rooms
+        .iter()
+        .map(|entry| (entry.server_name.clone(), entry.room.room_id))
+        .collect();
+    assert_eq!(
+        rooms,
+        vec![
+            ("alpha".to_string(), alpha_room_id),
+            ("beta".to_string(), beta_room_id)
+        ]
+    );
+    assert_eq!(
+        harness.directory_rooms(carol).rooms[1].server_addr,
+        harness.server_addr_of(1)
+    );
+
+    // 进入beta上的房间时切换连接
+    harness.send_client_event(
+        carol,
+        JoinDirectoryRoomEvent {
+            server_name: "beta".to_string(),
+            room_id: beta_room_id,
+            room_password: String::new(),
+        },
+    );
+    harness.run_until("Carol in beta room", |harness| {
+        harness.app_state(carol) == AppState::Gaming
+            && harness.current_room_info(carol).view.seq.is_some()
+    });
+    assert_eq!(harness.current_room_info(carol).view.room_id, beta_room_id);
+    assert_eq!(
+        harness.current_server(carol).server_name.as_deref(),
+        Some("beta")
+    );
+    assert_eq!(
+        harness
+            .room_list_of(1)
+            .get(beta_room_id)
+            .unwrap()
+            .players
+            .len(),
+        2
+    );
+
+    // beta排空：目录隐藏其房间，不再接受新房间
+    harness.drain_server(1);
+    harness.run_until("beta draining in directory", |harness| {
+        harness
+            .server_directory()
+            .get("beta")
+            .is_some_and(|status| status.draining)
+    });
+    let dave = harness.add_client("Dave");
+    harness.connect_directory(dave);
+    harness.run_until("directory lists alpha only", |harness| {
+        harness.directory_rooms(dave).total == 1
+    });
+    assert_eq!(harness.directory_rooms(dave).rooms[0].server_name, "alpha");
+    harness.send_client_event(
+        dave,
+        JoinDirectoryRoomEvent {
+            server_name: "beta".to_string(),
+            room_id: beta_room_id,
+            room_password: String::new(),
+        },
+    );
+    harness.tick();
+    harness.run_until("locate response", |harness| {
+        !harness.is_request_pending(dave, RequestKind::LocateServer)
+    });
+    assert_eq!(
+        harness.current_server(dave).server_addr,
+        Some(harness.server_addr())
+    );
+
+    let erin = harness.add_client_to(1, "Erin");
+    harness.set_new_room_settings(erin, "late", "");
+    harness.send_client_event(erin, CreateRoomEvent);
+    harness.tick();
+    harness.run_until("create room response", |harness| {
+        !harness.is_request_pending(erin, RequestKind::CreateRoom)
+    });
+    assert_eq!(harness.app_state(erin), AppState::Lobby);
+    assert_eq!(harness.room_list_of(1).iter().count(), 1);
+    assert!(!harness.server_exited(1));
+
+    // 房间内的玩家断开后离开房间，beta退出并从目录中移除
+    for index in [bob, carol] {
+        harness.clients[index]
+            .world
+            .resource_mut::<RenetClient>()
+            .disconnect();
+    }
+    harness.run_until("beta exited", |harness| harness.server_exited(1));
+    assert!(harness
+        .room_list_of(1)
+        .iter()
+        .all(|room| room.players.is_empty()));
+    harness.run_until("beta removed from directory", |harness| {
+        harness.server_directory().get("beta").is_none()
+    });
+    assert!(!harness.server_exited(0));
+}
+
+#[test]
+fn full_directory_page_fits_in_one_message() {
+    let server_name = LONGEST_CHAR.repeat(MAX_SERVER_NAME_CHARS);
+    let mut harness = TestHarness::with_directory(&[&server_name]);
+    let owner = harness.add_client(&LONGEST_CHAR.repeat(MAX_PLAYER_NAME_CHARS));
+    create_longest_named_rooms(&mut harness, owner, MAX_ROOMS_PAGE_SIZE);
+    harness.run_until("all rooms reported", |harness| {
+        harness
+            .server_directory()
+            .get(&server_name)
+            .is_some_and(|status| status.rooms.len() as u32 == MAX_ROOMS_PAGE_SIZE)
+    });
+
+    let browser = harness.add_client("Zed");
+    harness.connect_directory(browser);
+    harness.room_query_mut(browser).page_size = MAX_ROOMS_PAGE_SIZE;
+    harness.run_until("directory lists a full page", |harness| {
+        harness.directory_rooms(browser).rooms.len() as u32 == MAX_ROOMS_PAGE_SIZE
+    });
+    assert!(harness
+        .directory_rooms(browser)
+        .rooms
+        .iter()
+        .all(|entry| entry.server_name == server_name));
+}
+
+#[test]
+fn directory_keeps_live_server_name_registration() {
+    let mut harness = TestHarness::with_directory(&["alpha"]);
+    let alpha_addr = harness.server_addr();
+
+    // 另一个连接用同名上报，alpha在线时不能顶替
+    let impostor = harness.add_server("alpha", None);
+    for _ in 0..REPORT_TICKS {
+        harness.tick();
+    }
+    assert_eq!(
+        harness.server_directory().get("alpha").unwrap().public_addr,
+        alpha_addr
+    );
+
+    // alpha退出后移除登记，之后才接受新的服务端
+    harness.drain_server(0);
+    harness.run_until("alpha exited", |harness| harness.server_exited(0));
+    let impostor_addr = harness.server_addr_of(impostor);
+    harness.run_until("name registered by new server", |harness| {
+        harness
+            .server_directory()
+            .get("alpha")
+            .is_some_and(|status| status.public_addr == impostor_addr)
+    });
+}
+
+#[test]
+fn directory_requires_registration_secret() {
+    let mut harness = TestHarness::with_directory(&["alpha"]);
+    harness.directory_settings_mut().registration_secret = Some("letmein".to_string());
+    harness.add_server("beta", None);
+    harness.add_server("gamma", Some("wrong"));
+    harness.add_server("delta", Some("letmein"));
+    harness.run_until("server with secret registered", |harness| {
+        harness.server_directory().get("delta").is_some()
+    });
+    for _ in 0..REPORT_TICKS {
+        harness.tick();
+    }
+    assert!(harness.server_directory().get("beta").is_none());
+    assert!(harness.server_directory().get("gamma").is_none());
+    // 目录不保留密钥
+    assert!(harness
+        .server_directory()
+        .get("delta")
+        .unwrap()
+        .secret
+        .is_none());
+}
@@ -0,0 +1,291 @@
+use bevy::prelude::NextState;
+use texas_holdem_client::{
+    lobby::{CreateRoomEvent, EnterRoomEvent},
+    request::RequestKind,
+    room::{RoomAdminEvent, SetRoomStateEvent, SwitchPlayerRoleEvent},
+    AppState,
+};
+use texas_holdem_common::{
+    channel::RoomAdminCommand, lobby::MAX_ROOM_NAME_CHARS, PlayerRole, RoomState, Round,
+    RoundAction,
+};
+
+use texas_holdem_server::play::Play;
+
+use crate::common::TestHarness;
+
+pub const ROOM_NAME: &str = "e2e";
+pub const ROOM_PASSWORD: &str = "secret";
+// 序列化后最长的字符（UTF-8编码4字节），用于测试名字长度上限
+pub const LONGEST_CHAR: &str = "𝄞";
+
+// 第一个客户端创建房间，其余客户端进入，返回房间id
+pub fn setup_room(harness: &mut TestHarness, player_names: &[&str]) -> u64 {
+    setup_room_with(harness, player_names, |_, _| {})
+}
+
+// 同setup_room，创建前由configure修改房主的新房间设置
+pub fn setup_room_with(
+    harness: &mut TestHarness,
+    player_names: &[&str],
+    configure: impl FnOnce(&mut TestHarness, usize),
+) -> u64 {
+    let (_, room_id) = create_room_with(harness, player_names[0], configure);
+    for player_name in player_names.iter().skip(1) {
+        let index = harness.add_client(player_name);
+        harness.send_client_event(
+            index,
+            EnterRoomEvent {
+                room_id,
+                room_password: ROOM_PASSWORD.to_string(),
+            },
+        );
+        harness.run_until(&format!("{} in room", player_name), |harness| {
+            harness.app_state(index) == AppState::Gaming
+                && harness.current_room_info(index).view.seq.is_some()
+        });
+    }
+    room_id
+}
+
+// 添加房主客户端并创建房间，返回房主的客户端序号和房间id
+pub fn create_room_with(
+    harness: &mut TestHarness,
+    owner_name: &str,
+    configure: impl FnOnce(&mut TestHarness, usize),
+) -> (usize, u64) {
+    let owner = harness.add_client(owner_name);
+    harness.set_new_room_settings(owner, ROOM_NAME, ROOM_PASSWORD);
+    configure(harness, owner);
+    harness.send_client_event(owner, CreateRoomEvent);
+    harness.run_until("owner in room", |harness| {
+        harness.app_state(owner) == AppState::Gaming
+            && harness.current_room_info(owner).view.seq.is_some()
+    });
+    (owner, harness.current_room_info(owner).view.room_id)
+}
+
+pub fn switch_to_participant(harness: &mut TestHarness, room_id: u64, indexes: &[usize]) {
+    for index in indexes.iter().copied() {
+        harness.send_client_event(
+            index,
+            SwitchPlayerRoleEvent {
+                room_id,
+                target_player_role: PlayerRole::Participant,
+            },
+        );
+    }
+    harness.run_until("participants switched", |harness| {
+        indexes
+            .iter()
+            .all(|index| harness.current_room_info(*index).my_role == PlayerRole::Participant)
+    });
+}
+
+pub fn start_play(harness: &mut TestHarness, room_id: u64) -> u64 {
+    harness.send_client_event(
+        0,
+        SetRoomStateEvent {
+            target_room_state: RoomState::Playing,
+        },
+    );
+    harness.run_until("play reaches preflop", |harness| {
+        harness
+            .play_list()
+            .plays
+            .iter()
+            .any(|play| play.room_id == room_id && play.round == Round::Preflop)
+    });
+    harness.play_list().plays[0].play_id
+}
+
+// 所有客户端的房间视图与服务端一致
+pub fn assert_clients_in_sync(harness: &TestHarness, room_id: u64) {
+    let room = harness.room_list().get(room_id).unwrap();
+    for index in 0..harness.clients.len() {
+        let view = &harness.current_room_info(index).view;
+        assert_eq!(view.seq, Some(room.sync_seq), "client {} seq", index);
+        assert_eq!(view.room_state, room.room_state, "client {} state", index);
+        let mut view_players: Vec<_> = view
+            .players
+            .iter()
+            .map(|player| {
+                (
+                    player.player_client_id,
+                    player.player_role,
+                    player.chips,
+                    player.sitting_out,
+                    player.missed_blinds,
+                )
+            })
+            .collect();
+        let mut room_players: Vec<_> = room
+            .players
+            .iter()
+            .map(|player| {
+                (
+                    player.player_client_id,
+                    player.player_role,
+                    player.chips,
+                    player.sitting_out,
+                    player.missed_blinds,
+                )
+            })
+            .collect();
+        view_players.sort_unstable_by_key(|player| player.0);
+        room_players.sort_unstable_by_key(|player| player.0);
+        assert_eq!(view_players, room_players, "client {} players", index);
+    }
+}
+
+// 任意一桌的当前行动玩家对应的客户端序号，返回 (客户端序号, 对局id, 房间id)
+pub fn current_player_index(harness: &TestHarness) -> Option<(usize, u64, u64)> {
+    harness.play_list().plays.iter().find_map(|play| {
+        let current_player_name = play.current_player_name.as_ref()?;
+        let client_id = play
+            .participants
+            .iter()
+            .find(|player| &player.player_name == current_player_name)?
+            .player_client_id;
+        let index =
+            (0..harness.clients.len()).find(|index| harness.client_id(*index) == client_id)?;
+        Some((index, play.play_id, play.room_id))
+    })
+}
+
+// 行动后推进几帧，返回服务端是否接受（增量序号是否增加）
+pub fn try_action(
+    harness: &mut TestHarness,
+    index: usize,
+    play_id: u64,
+    room_id: u64,
+    action: RoundAction,
+    amount: u32,
+) -> bool {
+    let seq = harness.room_list().get(room_id).unwrap().sync_seq;
+    harness.send_action(index, play_id, action, amount);
+    for _ in 0..30 {
+        harness.tick();
+    }
+    harness.room_list().get(room_id).unwrap().sync_seq > seq
+}
+
+// 当前玩家需要跟注时跟注，否则过牌
+pub fn call_or_check(play: &Play) -> RoundAction {
+    let bet = play
+        .current_player_name
+        .as_ref()
+        .and_then(|player_name| play.bets.get(player_name))
+        .copied()
+        .unwrap_or(0);
+    if bet < play.current_bet {
+        RoundAction::Call
+    } else {
+        RoundAction::Check
+    }
+}
+
+// 当前玩家跟注或过牌，直到对局进入下一轮
+pub fn call_until_round_ends(harness: &mut TestHarness, play_id: u64, room_id: u64) {
+    let round = harness.play_list().plays[0].round;
+    while harness.play_list().plays[0].round == round {
+        match current_player_index(harness) {
+            Some((index, _, _)) => {
+                let action = call_or_check(&harness.play_list().plays[0]);
+                assert!(try_action(harness, index, play_id, room_id, action, 0));
+            }
+            None => harness.tick(),
+        }
+    }
+}
+
+// 轮到的玩家依次弃牌直到对局结束，返回下一局的play_id
+pub fn fold_until_next_play(harness: &mut TestHarness, room_id: u64, play_id: u64) -> u64 {
+    while let Some((index, _, _)) = current_player_index(harness) {
+        assert!(try_action(
+            harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::Fold,
+            0
+        ));
+    }
+    harness.run_until("next play reaches preflop", |harness| {
+        harness
+            .play_list()
+            .plays
+            .first()
+            .is_some_and(|play| play.play_id != play_id && play.round == Round::Preflop)
+    });
+    harness.play_list().plays[0].play_id
+}
+
+pub fn chips_of(harness: &TestHarness, room_id: u64, index: usize) -> u32 {
+    let client_id = harness.client_id(index);
+    harness
+        .room_list()
+        .get(room_id)
+        .unwrap()
+        .players
+        .iter()
+        .find(|player| player.player_client_id == client_id)
+        .unwrap()
+        .chips
+}
+
+// 发送房主命令并等待服务端响应
+pub fn room_admin(harness: &mut TestHarness, index: usize, command: RoomAdminCommand) {
+    harness.send_client_event(index, RoomAdminEvent { command });
+    harness.tick();
+    harness.run_until("room admin response", |harness| {
+        !harness.is_request_pending(index, RequestKind::RoomAdmin)
+    });
+}
+
+// 尝试进入房间，返回是否成功
+pub fn try_enter_room(
+    harness: &mut TestHarness,
+    index: usize,
+    room_id: u64,
+    password: &str,
+) -> bool {
+    harness.send_client_event(
+        index,
+        EnterRoomEvent {
+            room_id,
+            room_password: password.to_string(),
+        },
+    );
+    harness.tick();
+    harness.run_until("enter room response", |harness| {
+        !harness.is_request_pending(index, RequestKind::EnterRoom)
+    });
+    harness.run_until("entered room synced", |harness| {
+        harness.app_state(index) == AppState::Lobby
+            || harness.current_room_info(index).view.seq.is_some()
+    });
+    harness.app_state(index) == AppState::Gaming
+}
+
+// 同一个房主依次创建count个最长名字的房间，之前的房间保留在列表中
+pub fn create_longest_named_rooms(harness: &mut TestHarness, owner: usize, count: u32) {
+    for number in 0..count {
+        // 回到大厅再创建下一个房间
+        harness.clients[owner]
+            .world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Lobby);
+        harness.tick();
+        let room_name = format!(
+            "{}{:02}",
+            LONGEST_CHAR.repeat(MAX_ROOM_NAME_CHARS - 2),
+            number
+        );
+        harness.set_new_room_settings(owner, &room_name, "");
+        harness.send_client_event(owner, CreateRoomEvent);
+        harness.run_until("room created", |harness| {
+            harness.room_list().iter().count() as u32 == number + 1
+        });
+    }
+}
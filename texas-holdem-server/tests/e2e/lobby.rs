@@ -0,0 +1,236 @@
+use bevy_renet::renet::RenetClient;
+use texas_holdem_client::{
+    lobby::{CreateRoomEvent, EnterRoomEvent, RoomQuery},
+    request::RequestKind,
+    AppState,
+};
+use texas_holdem_common::{
+    channel::{RoomAdminCommand, RoomSyncMessage},
+    lobby::{
+        RoomSort, RoomSortKey, MAX_PLAYER_NAME_CHARS, MAX_ROOMS_PAGE_SIZE, MAX_ROOM_NAME_CHARS,
+    },
+    sync::ROOM_SYNC_MAX_MESSAGE_SIZE,
+    variant::GameVariant,
+};
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        assert_clients_in_sync, create_longest_named_rooms, room_admin, setup_room,
+        setup_room_with, start_play, switch_to_participant, try_enter_room, LONGEST_CHAR,
+        ROOM_PASSWORD,
+    },
+};
+
+#[test]
+fn wrong_password_is_rejected() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice"]);
+    let index = harness.add_client("Mallory");
+    harness.send_client_event(
+        index,
+        EnterRoomEvent {
+            room_id,
+            room_password: "wrong".to_string(),
+        },
+    );
+    for _ in 0..30 {
+        harness.tick();
+    }
+    assert_eq!(harness.app_state(index), AppState::Lobby);
+    assert_eq!(harness.room_list().get(room_id).unwrap().players.len(), 1);
+}
+
+// 修改查询条件后等待大厅列表刷新，返回当前页的房间名
+fn query_rooms(
+    harness: &mut TestHarness,
+    index: usize,
+    update: impl FnOnce(&mut RoomQuery),
+    expected_total: u32,
+) -> Vec<String> {
+    let mut query = RoomQuery::default();
+    update(&mut query);
+    *harness.room_query_mut(index) = query;
+    harness.tick();
+    harness.run_until("room list refreshed", |harness| {
+        !harness.is_request_pending(index, RequestKind::GetRooms)
+            && harness.lobby_rooms(index).total == expected_total
+    });
+    harness
+        .lobby_rooms(index)
+        .rooms
+        .iter()
+        .map(|room| room.room_name.clone())
+        .collect()
+}
+
+#[test]
+fn lobby_filters_sorts_and_pages_rooms_on_the_server() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().seat.table_seats = 3;
+    // 房间名、玩法、盲注、密码
+    let rooms = [
+        ("Alpha", GameVariant::Holdem, 1, "secret"),
+        ("Bravo", GameVariant::Omaha, 5, ""),
+        ("Charlie", GameVariant::Holdem, 25, ""),
+        ("Delta", GameVariant::Holdem, 5, ""),
+        ("alpha two", GameVariant::ShortDeck, 2, ""),
+    ];
+    for (room_name, variant, small_blind, password) in rooms {
+        {
+            let mut settings = harness.server_settings_mut();
+            settings.stakes.small_blind = small_blind;
+            settings.stakes.big_blind = small_blind * 2;
+        }
+        let owner = harness.add_client(&format!("{} owner", room_name));
+        harness.set_new_room_settings(owner, room_name, password);
+        harness.set_new_room_variant(owner, variant);
+        harness.send_client_event(owner, CreateRoomEvent);
+        harness.run_until("owner in room", |harness| {
+            harness.current_room_info(owner).view.seq.is_some()
+        });
+    }
+    let browser = harness.add_client("Zed");
+
+    // 按创建顺序分页
+    let page_size = |query: &mut RoomQuery| query.page_size = 2;
+    assert_eq!(
+        query_rooms(&mut harness, browser, page_size, 5),
+        ["Alpha", "Bravo"]
+    );
+    assert_eq!(
+        query_rooms(
+            &mut harness,
+            browser,
+            |query| {
+                page_size(query);
+                query.page = 2;
+            },
+            5
+        ),
+        ["alpha two"]
+    );
+
+    // 按玩法、盲注、名字和私人房间筛选
+    assert_eq!(
+        query_rooms(
+            &mut harness,
+            browser,
+            |query| query.filter.variant = Some(GameVariant::Omaha),
+            1
+        ),
+        ["Bravo"]
+    );
+    assert_eq!(
+        query_rooms(
+            &mut harness,
+            browser,
+            |query| {
+                query.filter.min_big_blind = Some(10);
+                query.sort = RoomSort {
+                    key: RoomSortKey::Stakes,
+                    descending: true,
+                };
+            },
+            3
+        ),
+        ["Charlie", "Delta", "Bravo"]
+    );
+    assert_eq!(
+        query_rooms(
+            &mut harness,
+            browser,
+            |query| {
+                query.filter.search = "ALP".to_string();
+                query.filter.hide_private = true;
+            },
+            1
+        ),
+        ["alpha two"]
+    );
+
+    // 入座后空座位减少，锁定的房间视为已满
+    let alpha_room_id = harness.current_room_info(0).view.room_id;
+    switch_to_participant(&mut harness, alpha_room_id, &[0]);
+    room_admin(&mut harness, 2, RoomAdminCommand::Lock(true));
+    assert_eq!(
+        query_rooms(
+            &mut harness,
+            browser,
+            |query| {
+                query.filter.min_free_seats = 3;
+                query.filter.hide_full = true;
+                query.sort.key = RoomSortKey::Name;
+            },
+            3
+        ),
+        ["alpha two", "Bravo", "Delta"]
+    );
+}
+
+#[test]
+fn full_room_list_page_fits_in_one_message() {
+    let mut harness = TestHarness::new();
+    let owner = harness.add_client(&LONGEST_CHAR.repeat(MAX_PLAYER_NAME_CHARS));
+    create_longest_named_rooms(&mut harness, owner, MAX_ROOMS_PAGE_SIZE);
+    let browser = harness.add_client("Zed");
+    let rooms = query_rooms(
+        &mut harness,
+        browser,
+        |query| query.page_size = MAX_ROOMS_PAGE_SIZE,
+        MAX_ROOMS_PAGE_SIZE,
+    );
+    assert_eq!(rooms.len() as u32, MAX_ROOMS_PAGE_SIZE);
+}
+
+#[test]
+fn overlong_names_are_rejected() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice"]);
+    let index = harness.add_client(&"M".repeat(MAX_PLAYER_NAME_CHARS + 1));
+    assert!(!try_enter_room(&mut harness, index, room_id, ROOM_PASSWORD));
+
+    let owner = harness.add_client("Bob");
+    harness.set_new_room_settings(owner, &"R".repeat(MAX_ROOM_NAME_CHARS + 1), "");
+    harness.send_client_event(owner, CreateRoomEvent);
+    harness.tick();
+    harness.run_until("create room response", |harness| {
+        !harness.is_request_pending(owner, RequestKind::CreateRoom)
+    });
+    assert_eq!(harness.app_state(owner), AppState::Lobby);
+    assert_eq!(harness.room_list().iter().count(), 1);
+}
+
+// 满桌加旁观者且名字都是最长的多字节字符，快照超过单条消息的上限时拆分发送
+#[test]
+fn oversized_snapshot_is_split_across_messages() {
+    let mut harness = TestHarness::new();
+    let table_seats = harness.server_settings_mut().seat.table_seats as usize;
+    let names: Vec<String> = (0..table_seats + 20)
+        .map(|number| format!("{}{:02}", "玩".repeat(MAX_PLAYER_NAME_CHARS - 2), number))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let room_name = "房".repeat(MAX_ROOM_NAME_CHARS);
+    let room_id = setup_room_with(&mut harness, &names, |harness, owner| {
+        harness.set_new_room_settings(owner, &room_name, ROOM_PASSWORD)
+    });
+    let seated: Vec<usize> = (0..table_seats).collect();
+    switch_to_participant(&mut harness, room_id, &seated);
+    start_play(&mut harness, room_id);
+    let snapshot = harness.current_room_info(0).view.snapshot();
+    let message = RoomSyncMessage::Snapshot(Box::new(snapshot));
+    assert!(serde_json::to_vec(&message).unwrap().len() > ROOM_SYNC_MAX_MESSAGE_SIZE as usize);
+
+    // 定期重新同步给每个客户端发送完整快照，不能使客户端断开
+    harness.server_settings_mut().sync.resync_interval = 0.1;
+    for _ in 0..30 {
+        harness.tick();
+    }
+    for index in 0..harness.clients.len() {
+        let client = harness.clients[index].world.resource::<RenetClient>();
+        assert!(client.is_connected(), "client {} disconnected", index);
+        assert_eq!(harness.app_state(index), AppState::Gaming);
+        assert_eq!(harness.current_room_info(index).view.room_name, room_name);
+    }
+    assert_clients_in_sync(&harness, room_id);
+}
@@ -0,0 +1,14 @@
+// 端到端测试，按功能分模块，共用common中的测试工具和fixtures中的房间准备步骤
+mod common;
+mod fixtures;
+
+mod bank;
+mod chat;
+mod directory;
+mod lobby;
+mod play;
+mod quick_seat;
+mod room_admin;
+mod spectator;
+mod tournament;
+mod variant;
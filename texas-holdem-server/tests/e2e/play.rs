@@ -0,0 +1,530 @@
+use texas_holdem_client::{
+    lobby::EnterRoomEvent,
+    room::{RunItTwiceEvent, SitOutEvent},
+};
+use texas_holdem_common::{
+    betting::{AnteKind, ForcedBets},
+    variant::GameVariant,
+    MissedBlinds, PlayerRole, Round, RoundAction,
+};
+use texas_holdem_server::config::ServerSettings;
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        assert_clients_in_sync, call_or_check, call_until_round_ends, current_player_index,
+        fold_until_next_play, setup_room, setup_room_with, start_play, switch_to_participant,
+        try_action, ROOM_PASSWORD,
+    },
+};
+
+#[test]
+fn create_enter_switch_and_start_play() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol"]);
+
+    let room = harness.room_list().get(room_id).unwrap();
+    assert_eq!(room.owner_name, "Alice");
+    assert_eq!(room.players.len(), 3);
+    assert!(room
+        .players
+        .iter()
+        .all(|player| player.player_role == PlayerRole::Spectator));
+
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    // 旁观者不能开始游戏，参与者不少于3人时才创建对局
+    assert!(harness.play_list().plays.is_empty());
+    let play_id = start_play(&mut harness, room_id);
+
+    harness.run_until("clients receive hole cards", |harness| {
+        (0..3).all(|index| harness.current_room_info(index).view.hole_cards.len() == 2)
+    });
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.participants.len(), 3);
+    assert_eq!(play.pot, play.small_blind + play.big_blind);
+    for index in 0..3 {
+        let view = &harness.current_room_info(index).view;
+        let view_play = view.play.as_ref().unwrap();
+        assert_eq!(view_play.play_id, play_id);
+        assert_eq!(view_play.round, Round::Preflop);
+        assert_eq!(view_play.pot, play.pot);
+        assert_eq!(view_play.current_player_name, play.current_player_name);
+        // 每个客户端只收到自己的底牌
+        let player_name = &play
+            .participants
+            .iter()
+            .find(|player| player.player_client_id == harness.client_id(index))
+            .unwrap()
+            .player_name;
+        assert_eq!(view.hole_cards, play.hole_cards[player_name].to_vec());
+    }
+    assert_clients_in_sync(&harness, room_id);
+}
+
+#[test]
+fn hand_plays_to_the_end_and_conserves_chips() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+
+    // 每位玩家都跟注或过牌直到摊牌
+    let mut rounds_seen = Vec::new();
+    while let Some(play) = harness
+        .play_list()
+        .plays
+        .iter()
+        .find(|play| play.play_id == play_id)
+    {
+        if !rounds_seen.contains(&play.round) {
+            rounds_seen.push(play.round);
+        }
+        let action = call_or_check(play);
+        match current_player_index(&harness) {
+            Some((index, _, _)) => {
+                harness.send_action(index, play_id, action, 0);
+                let seq = harness.room_list().get(room_id).unwrap().sync_seq;
+                harness.run_until("action applied", |harness| {
+                    harness.room_list().get(room_id).unwrap().sync_seq > seq
+                });
+            }
+            None => harness.tick(),
+        }
+    }
+    assert_eq!(
+        rounds_seen,
+        vec![
+            Round::Preflop,
+            Round::Flop,
+            Round::Turn,
+            Round::River,
+            Round::Showdown
+        ]
+    );
+
+    // 房间仍在游戏中，服务端会立即开始下一局
+    harness.run_until("clients see play ended", |harness| {
+        let sync_seq = harness.room_list().get(room_id).unwrap().sync_seq;
+        (0..3).all(|index| {
+            let view = &harness.current_room_info(index).view;
+            view.seq == Some(sync_seq)
+                && view.play.as_ref().map(|play| play.play_id) != Some(play_id)
+        })
+    });
+    let starting_chips = ServerSettings::default().stakes.starting_chips;
+    let room = harness.room_list().get(room_id).unwrap();
+    let total_chips: u32 = room.players.iter().map(|player| player.chips).sum();
+    assert_eq!(total_chips, starting_chips * 3);
+    assert!(room.last_dealer_name.is_some());
+    assert_clients_in_sync(&harness, room_id);
+}
+
+#[test]
+fn late_spectator_receives_snapshot_of_running_play() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+
+    let index = harness.add_client("Dave");
+    harness.send_client_event(
+        index,
+        EnterRoomEvent {
+            room_id,
+            room_password: ROOM_PASSWORD.to_string(),
+        },
+    );
+    harness.run_until("spectator receives snapshot", |harness| {
+        harness.current_room_info(index).view.play.is_some()
+    });
+    let view = &harness.current_room_info(index).view;
+    assert_eq!(view.play.as_ref().unwrap().play_id, play_id);
+    assert_eq!(view.players.len(), 4);
+    // 旁观者看不到任何底牌
+    assert!(view.hole_cards.is_empty());
+    assert_eq!(
+        harness.current_room_info(index).my_role,
+        PlayerRole::Spectator
+    );
+    assert_clients_in_sync(&harness, room_id);
+}
+
+#[test]
+fn run_it_twice_deals_second_board_after_consent() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_run_it_twice(owner, true),
+    );
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+
+    // 翻牌前所有人全下，等待表决
+    while harness.play_list().plays[0].run_it_twice_vote.is_none() {
+        let (index, _, _) = current_player_index(&harness).unwrap();
+        assert!(try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::AllIn,
+            0
+        ));
+    }
+    assert_eq!(harness.play_list().plays[0].round, Round::Preflop);
+    harness.run_until("clients see the vote", |harness| {
+        (0..3).all(|index| {
+            let view = &harness.current_room_info(index).view;
+            let client_id = harness.client_id(index);
+            let player_name = view
+                .players
+                .iter()
+                .find(|player| player.player_client_id == client_id)
+                .unwrap()
+                .player_name
+                .clone();
+            view.play
+                .as_ref()
+                .and_then(|play| play.run_it_twice_vote.as_ref())
+                .is_some_and(|vote| vote.is_waiting_for(&player_name))
+        })
+    });
+    for index in 0..3 {
+        harness.send_client_event(index, RunItTwiceEvent { accept: true });
+        harness.run_until("answer applied", |harness| {
+            harness.play_list().plays[0]
+                .run_it_twice_vote
+                .as_ref()
+                .unwrap()
+                .answers
+                .len()
+                == index + 1
+        });
+    }
+
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.round, Round::Showdown);
+    let showdown = play.showdown.clone().unwrap();
+    let first_board = play.board_cards();
+    let second_board = showdown.second_board.clone().unwrap();
+    assert_eq!(second_board.len(), 5);
+    let mut dealt_cards = first_board.clone();
+    dealt_cards.extend(second_board.iter());
+    dealt_cards.extend(play.hole_cards.values().flatten());
+    assert!(dealt_cards
+        .iter()
+        .enumerate()
+        .all(|(i, card)| !dealt_cards[i + 1..].contains(card)));
+    assert!(showdown
+        .shown_hands
+        .iter()
+        .all(|shown_hand| shown_hand.second_category.is_some()));
+    let starting_chips = ServerSettings::default().stakes.starting_chips;
+    assert_eq!(showdown.winnings.values().sum::<u32>(), starting_chips * 3);
+
+    // 每组公共牌各分一半底池（无平局时可精确核对）
+    let half = starting_chips * 3 / 2;
+    let mut expected = std::collections::HashMap::new();
+    let mut has_tie = false;
+    for (board, amount) in [
+        (&first_board, starting_chips * 3 - half),
+        (&second_board, half),
+    ] {
+        let values: Vec<_> = showdown
+            .shown_hands
+            .iter()
+            .map(|shown_hand| GameVariant::Holdem.evaluate(&shown_hand.cards, board))
+            .collect();
+        let best = values.iter().max().unwrap();
+        has_tie |= values.iter().filter(|value| *value == best).count() > 1;
+        let winner = values.iter().position(|value| value == best).unwrap();
+        *expected
+            .entry(showdown.shown_hands[winner].player_name.clone())
+            .or_insert(0) += amount;
+    }
+    if !has_tie {
+        assert_eq!(showdown.winnings, expected);
+    }
+    harness.run_until("clients see both boards", |harness| {
+        (0..3).all(|index| {
+            harness
+                .current_room_info(index)
+                .view
+                .play
+                .as_ref()
+                .and_then(|play| play.showdown.as_ref())
+                .is_some_and(|showdown| showdown.second_board.as_ref() == Some(&second_board))
+        })
+    });
+
+    // 牌局记录中保留两组公共牌
+    let play_id = harness.play_list().plays[0].play_id;
+    harness.run_until("play recorded", |harness| {
+        harness
+            .hand_history()
+            .iter()
+            .any(|record| record.play_id == play_id)
+    });
+    let record = harness
+        .hand_history()
+        .into_iter()
+        .find(|record| record.play_id == play_id)
+        .unwrap();
+    assert_eq!(record.board, first_board);
+    assert_eq!(record.second_board, Some(second_board));
+    assert_eq!(record.winnings, showdown.winnings);
+    assert!(record
+        .shown_hands
+        .iter()
+        .all(|shown_hand| shown_hand.second_category.is_some()));
+}
+
+// 创建带前注或跨注的房间，所有玩家都成为参与者并开局
+fn start_forced_bets_play(
+    harness: &mut TestHarness,
+    player_names: &[&str],
+    forced_bets: ForcedBets,
+) -> (u64, u64) {
+    let room_id = setup_room_with(harness, player_names, |harness, owner| {
+        harness.set_new_room_forced_bets(owner, forced_bets)
+    });
+    assert!((0..player_names.len())
+        .all(|index| harness.current_room_info(index).view.forced_bets == forced_bets));
+    let indexes = (0..player_names.len()).collect::<Vec<_>>();
+    switch_to_participant(harness, room_id, &indexes);
+    let play_id = start_play(harness, room_id);
+    (room_id, play_id)
+}
+
+#[test]
+fn straddle_and_per_player_ante_change_preflop_action() {
+    let mut harness = TestHarness::new();
+    let forced_bets = ForcedBets {
+        ante_kind: AnteKind::PerPlayer,
+        ante: 1,
+        straddle: true,
+    };
+    let (room_id, play_id) = start_forced_bets_play(
+        &mut harness,
+        &["Alice", "Bob", "Carol", "Dave"],
+        forced_bets,
+    );
+    let play = &harness.play_list().plays[0];
+    let (small_blind, big_blind) = (play.small_blind, play.big_blind);
+    let straddle_name = play.straddle_name.clone().unwrap();
+    assert_eq!(play.pot, 4 + small_blind + big_blind + big_blind * 2);
+    assert_eq!(play.current_bet, big_blind * 2);
+    assert_eq!(play.bets.get(&straddle_name), Some(&(big_blind * 2)));
+    assert_eq!(play.total_bets.values().sum::<u32>(), play.pot);
+    // 跨注者在大盲注之后，翻牌前从跨注者的下家开始行动
+    let positions = play
+        .participants
+        .iter()
+        .map(|player| player.player_name.clone())
+        .collect::<Vec<_>>();
+    let position = |name: &str| positions.iter().position(|n| n == name).unwrap();
+    let big_blind_position = position(play.big_blind_name.as_ref().unwrap());
+    let straddle_position = position(&straddle_name);
+    assert_eq!(
+        straddle_position,
+        (big_blind_position + 1) % positions.len()
+    );
+    assert_eq!(
+        position(play.current_player_name.as_ref().unwrap()),
+        (straddle_position + 1) % positions.len()
+    );
+    harness.run_until("clients see straddle", |harness| {
+        (0..4).all(|index| {
+            harness
+                .current_room_info(index)
+                .view
+                .play
+                .as_ref()
+                .is_some_and(|play| play.straddle_name.as_ref() == Some(&straddle_name))
+        })
+    });
+
+    // 跨注后最小加注额为跨注金额
+    let (index, _, _) = current_player_index(&harness).unwrap();
+    assert!(!try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::Raise,
+        big_blind * 4 - 1
+    ));
+    assert!(try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::Raise,
+        big_blind * 4
+    ));
+    call_until_round_ends(&mut harness, play_id, room_id);
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.round, Round::Flop);
+    assert_eq!(play.pot, 4 + big_blind * 4 * 4);
+    assert_clients_in_sync(&harness, room_id);
+}
+
+#[test]
+fn big_blind_ante_is_dead_money_in_the_main_pot() {
+    let mut harness = TestHarness::new();
+    let forced_bets = ForcedBets {
+        ante_kind: AnteKind::BigBlind,
+        ante: 3,
+        straddle: false,
+    };
+    let (room_id, play_id) =
+        start_forced_bets_play(&mut harness, &["Alice", "Bob", "Carol"], forced_bets);
+    let play = &harness.play_list().plays[0];
+    let (small_blind, big_blind) = (play.small_blind, play.big_blind);
+    let big_blind_name = play.big_blind_name.clone().unwrap();
+    assert_eq!(play.dead_money, 3);
+    assert_eq!(play.pot, small_blind + big_blind + 3);
+    assert_eq!(play.total_bets.get(&big_blind_name), Some(&big_blind));
+    let starting_chips = ServerSettings::default().stakes.starting_chips;
+    assert_eq!(
+        play.participants
+            .iter()
+            .find(|player| player.player_name == big_blind_name)
+            .unwrap()
+            .chips,
+        starting_chips - big_blind - 3
+    );
+    // 大盲注前注不改变行动顺序
+    let first_name = play
+        .participants
+        .iter()
+        .map(|player| &player.player_name)
+        .find(|name| **name != big_blind_name && play.small_blind_name.as_ref() != Some(*name));
+    assert_eq!(play.current_player_name.as_ref(), first_name);
+
+    while harness.play_list().plays[0].round != Round::Showdown {
+        call_until_round_ends(&mut harness, play_id, room_id);
+    }
+    let showdown = harness.play_list().plays[0].showdown.clone().unwrap();
+    assert_eq!(showdown.winnings.values().sum::<u32>(), big_blind * 3 + 3);
+}
+
+fn sit_out(harness: &mut TestHarness, room_id: u64, index: usize, sitting_out: bool) {
+    harness.send_client_event(index, SitOutEvent { sitting_out });
+    let client_id = harness.client_id(index);
+    harness.run_until("sitting out changed", |harness| {
+        harness
+            .room_list()
+            .get(room_id)
+            .unwrap()
+            .players
+            .iter()
+            .any(|player| player.player_client_id == client_id && player.sitting_out == sitting_out)
+    });
+}
+
+fn missed_blinds_of(harness: &TestHarness, room_id: u64, index: usize) -> MissedBlinds {
+    let client_id = harness.client_id(index);
+    harness
+        .room_list()
+        .get(room_id)
+        .unwrap()
+        .players
+        .iter()
+        .find(|player| player.player_client_id == client_id)
+        .unwrap()
+        .missed_blinds
+}
+
+#[test]
+fn sitting_out_skips_player_and_posts_missed_blinds_on_return() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol", "Dave"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2, 3]);
+    sit_out(&mut harness, room_id, 3, true);
+
+    // 第一局：Alice庄家，Bob小盲注，Carol大盲注，Dave不发牌
+    let play_id = start_play(&mut harness, room_id);
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.participants.len(), 3);
+    assert!(play.hole_cards.get("Dave").is_none());
+    assert_eq!(
+        missed_blinds_of(&harness, room_id, 3),
+        MissedBlinds::default()
+    );
+
+    // 第二局大盲注从Carol移到Alice，跳过了Dave
+    let play_id = fold_until_next_play(&mut harness, room_id, play_id);
+    assert_eq!(
+        harness.play_list().plays[0].big_blind_name.as_deref(),
+        Some("Alice")
+    );
+    assert_eq!(
+        missed_blinds_of(&harness, room_id, 3),
+        MissedBlinds {
+            big_blinds: 1,
+            small_blind: false,
+        }
+    );
+
+    // 第三局小盲注移到Alice，再次跳过Dave
+    let play_id = fold_until_next_play(&mut harness, room_id, play_id);
+    let missed_blinds = MissedBlinds {
+        big_blinds: 1,
+        small_blind: true,
+    };
+    assert_eq!(missed_blinds_of(&harness, room_id, 3), missed_blinds);
+    harness.run_until("clients see missed blinds", |harness| {
+        harness.current_room_info(3).view.players[3].missed_blinds == missed_blinds
+    });
+
+    // 回到牌桌后的下一局补交大盲注（活注）和小盲注（死钱）
+    sit_out(&mut harness, room_id, 3, false);
+    let play_id = fold_until_next_play(&mut harness, room_id, play_id);
+    let play = &harness.play_list().plays[0];
+    let (small_blind, big_blind) = (play.small_blind, play.big_blind);
+    assert_eq!(play.participants.len(), 4);
+    assert_eq!(play.dealer_name.as_deref(), Some("Dave"));
+    assert_eq!(play.bets.get("Dave"), Some(&big_blind));
+    assert_eq!(play.dead_money, small_blind);
+    assert_eq!(play.pot, small_blind + big_blind * 2 + small_blind);
+    assert_eq!(
+        missed_blinds_of(&harness, room_id, 3),
+        MissedBlinds::default()
+    );
+
+    // 已补交的大盲注算作跟注，所有人跟平后进入翻牌圈
+    call_until_round_ends(&mut harness, play_id, room_id);
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.round, Round::Flop);
+    assert_eq!(play.pot, big_blind * 4 + small_blind);
+    harness.run_until("clients in sync", |harness| {
+        let seq = harness.room_list().get(room_id).unwrap().sync_seq;
+        (0..4).all(|index| harness.current_room_info(index).view.seq == Some(seq))
+    });
+    assert_clients_in_sync(&harness, room_id);
+}
+
+#[test]
+fn sitting_out_too_long_leaves_the_seat() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().seat.max_sit_out_orbits = 1;
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol", "Dave"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2, 3]);
+    sit_out(&mut harness, room_id, 3, true);
+    let play_id = start_play(&mut harness, room_id);
+    fold_until_next_play(&mut harness, room_id, play_id);
+
+    // 错过一次大盲注后离开座位，筹码兑回账户
+    let initial_bankroll = ServerSettings::default().bank.initial_bankroll;
+    harness.run_until("Dave becomes spectator", |harness| {
+        harness.current_room_info(3).my_role == PlayerRole::Spectator
+    });
+    let dave = &harness.room_list().get(room_id).unwrap().players[3];
+    assert_eq!(dave.player_role, PlayerRole::Spectator);
+    assert!(!dave.sitting_out);
+    assert_eq!(dave.missed_blinds, MissedBlinds::default());
+    assert_eq!(dave.chips, 0);
+    assert_eq!(harness.account(3).unwrap().balance, initial_bankroll);
+}
@@ -0,0 +1,217 @@
+use bevy::prelude::NextState;
+use texas_holdem_client::{
+    lobby::{CancelQuickSeatEvent, QuickSeatEvent},
+    request::RequestKind,
+    room::SwitchPlayerRoleEvent,
+    AppState,
+};
+use texas_holdem_common::{variant::GameVariant, PlayerRole, RoomState};
+
+use crate::{
+    common::TestHarness,
+    fixtures::{chips_of, create_room_with, try_enter_room, ROOM_PASSWORD},
+};
+
+// 快速入座，入座后等到收到房间快照，返回是否已入座
+fn quick_seat(
+    harness: &mut TestHarness,
+    index: usize,
+    variant: GameVariant,
+    max_big_blind: Option<u32>,
+) -> bool {
+    harness.send_client_event(
+        index,
+        QuickSeatEvent {
+            variant,
+            min_big_blind: None,
+            max_big_blind,
+        },
+    );
+    harness.tick();
+    harness.run_until("quick seat response", |harness| {
+        !harness.is_request_pending(index, RequestKind::QuickSeat)
+    });
+    harness.run_until("quick seat synced", |harness| {
+        harness.app_state(index) == AppState::Lobby
+            || harness.current_room_info(index).view.seq.is_some()
+    });
+    harness.app_state(index) == AppState::Gaming
+}
+
+#[test]
+fn quick_seat_opens_tables_from_templates_and_seats_waiting_players() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().seat.table_seats = 3;
+    let players: Vec<usize> = ["Alice", "Bob", "Carol", "Dave", "Eve"]
+        .into_iter()
+        .map(|player_name| harness.add_client(player_name))
+        .collect();
+
+    // 没有房间时按模板开桌，之后的玩家坐进同一桌，坐满后自动开局
+    assert!(quick_seat(
+        &mut harness,
+        players[0],
+        GameVariant::Holdem,
+        None
+    ));
+    let room_id = harness.current_room_info(players[0]).view.room_id;
+    let room = harness.room_list().get(room_id).unwrap();
+    assert_eq!(room.room_state, RoomState::Playing);
+    assert_eq!((room.small_blind, room.big_blind), (1, 2));
+    assert_eq!(room.owner_name, "Quick Seat");
+    for index in players[1..3].iter().copied() {
+        assert!(quick_seat(
+            &mut harness,
+            index,
+            GameVariant::Holdem,
+            Some(2)
+        ));
+        assert_eq!(harness.current_room_info(index).view.room_id, room_id);
+    }
+    for index in players[..3].iter().copied() {
+        assert_eq!(
+            harness.current_room_info(index).my_role,
+            PlayerRole::Participant
+        );
+        assert_eq!(chips_of(&harness, room_id, index), 1000);
+    }
+    harness.run_until("quick seat table starts", |harness| {
+        harness
+            .play_list()
+            .plays
+            .iter()
+            .any(|play| play.room_id == room_id)
+    });
+
+    // 没有模板时不开新桌，满桌时排队
+    harness.server_settings_mut().quick_seat.templates.clear();
+    assert!(!quick_seat(
+        &mut harness,
+        players[3],
+        GameVariant::Omaha,
+        None
+    ));
+    assert!(harness.quick_seat_state(players[3]).waiting.is_none());
+    assert!(!quick_seat(
+        &mut harness,
+        players[3],
+        GameVariant::Holdem,
+        None
+    ));
+    assert!(!quick_seat(
+        &mut harness,
+        players[4],
+        GameVariant::Holdem,
+        None
+    ));
+    let waiting = |harness: &TestHarness, index: usize| {
+        harness
+            .quick_seat_state(index)
+            .waiting
+            .map(|waiting| (waiting.room_id, waiting.position))
+    };
+    assert_eq!(waiting(&harness, players[3]), Some((room_id, 1)));
+    assert_eq!(waiting(&harness, players[4]), Some((room_id, 2)));
+
+    // 退出队列后不会被安排入座
+    harness.send_client_event(players[4], CancelQuickSeatEvent);
+    harness.tick();
+    harness.run_until("quick seat cancelled", |harness| {
+        !harness.is_request_pending(players[4], RequestKind::QuickSeat)
+    });
+    assert_eq!(waiting(&harness, players[4]), None);
+    assert_eq!(
+        harness.room_list().get(room_id).unwrap().waiting_list.len(),
+        1
+    );
+
+    // 有人离开座位后队首的玩家自动入座
+    harness.send_client_event(
+        players[2],
+        SwitchPlayerRoleEvent {
+            room_id,
+            target_player_role: PlayerRole::Spectator,
+        },
+    );
+    harness.run_until("waiting player seated", |harness| {
+        harness.app_state(players[3]) == AppState::Gaming
+            && harness.current_room_info(players[3]).my_role == PlayerRole::Participant
+    });
+    assert_eq!(harness.current_room_info(players[3]).view.room_id, room_id);
+    assert_eq!(waiting(&harness, players[3]), None);
+    let room = harness.room_list().get(room_id).unwrap();
+    assert!(room.waiting_list.is_empty());
+    assert_eq!(room.participant_count(), 3);
+    assert_eq!(harness.app_state(players[4]), AppState::Lobby);
+}
+
+#[test]
+fn waiting_player_in_another_room_is_told_the_queue_was_cancelled() {
+    let mut harness = TestHarness::new();
+    harness.server_settings_mut().seat.table_seats = 3;
+    let players: Vec<usize> = ["Alice", "Bob", "Carol", "Dave"]
+        .into_iter()
+        .map(|player_name| harness.add_client(player_name))
+        .collect();
+    for index in players[..3].iter().copied() {
+        assert!(quick_seat(&mut harness, index, GameVariant::Holdem, None));
+    }
+    let room_id = harness.current_room_info(players[0]).view.room_id;
+    harness.server_settings_mut().quick_seat.templates.clear();
+    assert!(!quick_seat(
+        &mut harness,
+        players[3],
+        GameVariant::Holdem,
+        None
+    ));
+    assert!(harness.quick_seat_state(players[3]).waiting.is_some());
+
+    // 排队期间进入其他房间
+    let (_, other_room_id) = create_room_with(&mut harness, "Erin", |_, _| {});
+    assert!(try_enter_room(
+        &mut harness,
+        players[3],
+        other_room_id,
+        ROOM_PASSWORD
+    ));
+
+    // 空出座位时不入座，回到大厅后收到取消排队的通知
+    harness.send_client_event(
+        players[2],
+        SwitchPlayerRoleEvent {
+            room_id,
+            target_player_role: PlayerRole::Spectator,
+        },
+    );
+    harness.run_until("waiting list drained", |harness| {
+        harness
+            .room_list()
+            .get(room_id)
+            .unwrap()
+            .waiting_list
+            .is_empty()
+    });
+    harness.clients[players[3]]
+        .world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Lobby);
+    harness.run_until("quick seat cancelled", |harness| {
+        harness.quick_seat_state(players[3]).waiting.is_none()
+    });
+    assert_eq!(
+        harness
+            .room_list()
+            .get(room_id)
+            .unwrap()
+            .participant_count(),
+        2
+    );
+    assert_eq!(
+        harness
+            .room_list()
+            .room_of_client(harness.client_id(players[3]))
+            .unwrap()
+            .room_id,
+        other_room_id
+    );
+}
@@ -0,0 +1,243 @@
+use texas_holdem_client::{
+    lobby::{JoinByInviteEvent, PlayerName},
+    request::RequestKind,
+    AppState,
+};
+use texas_holdem_common::{channel::RoomAdminCommand, RoundAction};
+use texas_holdem_server::config::ServerSettings;
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        current_player_index, fold_until_next_play, room_admin, setup_room, start_play,
+        switch_to_participant, try_action, try_enter_room, ROOM_PASSWORD,
+    },
+};
+
+#[test]
+fn owner_kicks_bans_and_locks_the_room() {
+    let mut harness = TestHarness::new();
+    let initial_bankroll = ServerSettings::default().bank.initial_bankroll;
+    let room_id = setup_room(&mut harness, &["Alice", "Bob", "Carol", "Dave"]);
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2, 3]);
+    let play_id = start_play(&mut harness, room_id);
+    let bob_id = harness.client_id(1);
+    let carol_id = harness.client_id(2);
+
+    // 房主身份按连接校验，其他玩家不能使用房主命令
+    room_admin(
+        &mut harness,
+        1,
+        RoomAdminCommand::Kick {
+            player_client_id: carol_id,
+        },
+    );
+    assert!(harness.room_list().room_of_client(carol_id).is_some());
+
+    // 对局中被请出的玩家立即弃牌，本局结束后筹码兑回账户
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::Kick {
+            player_client_id: bob_id,
+        },
+    );
+    assert!(harness.play_list().plays[0].folded_names.contains("Bob"));
+    assert!(harness.room_list().room_of_client(bob_id).is_none());
+    harness.run_until("Bob back in lobby", |harness| {
+        harness.app_state(1) == AppState::Lobby
+    });
+    fold_until_next_play(&mut harness, room_id, play_id);
+    assert!(harness.account(1).unwrap().tables.is_empty());
+    assert_eq!(harness.bank().total(), initial_bankroll * 4);
+
+    // 被禁止的玩家不能再次进入，解除后可以进入
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::Ban {
+            player_client_id: carol_id,
+        },
+    );
+    harness.run_until("ban synced", |harness| {
+        harness.app_state(2) == AppState::Lobby
+            && harness.current_room_info(0).view.banned_names == vec!["Carol".to_string()]
+    });
+    assert!(!try_enter_room(&mut harness, 2, room_id, ROOM_PASSWORD));
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::Unban {
+            player_name: "Carol".to_string(),
+        },
+    );
+    assert!(try_enter_room(&mut harness, 2, room_id, ROOM_PASSWORD));
+
+    // 锁定后不能进入，修改密码后旧密码失效
+    let eve = harness.add_client("Eve");
+    room_admin(&mut harness, 0, RoomAdminCommand::Lock(true));
+    assert!(!try_enter_room(&mut harness, eve, room_id, ROOM_PASSWORD));
+    room_admin(&mut harness, 0, RoomAdminCommand::Lock(false));
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::ChangePassword("changed".to_string()),
+    );
+    assert!(!try_enter_room(&mut harness, eve, room_id, ROOM_PASSWORD));
+    assert!(try_enter_room(&mut harness, eve, room_id, "changed"));
+
+    while let Some((index, play_id, _)) = current_player_index(&harness) {
+        assert!(try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::Fold,
+            0
+        ));
+    }
+    harness.run_until("hand with kicked players settles", |harness| {
+        harness.play_list().plays.is_empty() && harness.account(2).unwrap().tables.is_empty()
+    });
+    assert_eq!(harness.bank().total(), initial_bankroll * 5);
+    assert_eq!(harness.bank().reconcile().unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn owner_and_bans_follow_the_session_not_the_client_id() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room(&mut harness, &["Alice", "Bob"]);
+    let owner_id = harness.client_id(0);
+    let bob_id = harness.client_id(1);
+
+    // 房主断开后，冒用其连接id但没有会话令牌的客户端不能使用房主命令
+    harness.disconnect_client(0);
+    let mallory = harness.add_spoofed_client("Mallory", owner_id);
+    assert_eq!(harness.client_id(mallory), owner_id);
+    assert!(try_enter_room(
+        &mut harness,
+        mallory,
+        room_id,
+        ROOM_PASSWORD
+    ));
+    room_admin(&mut harness, mallory, RoomAdminCommand::Lock(true));
+    assert!(!harness.room_list().get(room_id).unwrap().locked);
+    room_admin(
+        &mut harness,
+        mallory,
+        RoomAdminCommand::Kick {
+            player_client_id: bob_id,
+        },
+    );
+    assert!(harness.room_list().room_of_client(bob_id).is_some());
+    assert!(!harness.current_room_info(mallory).is_owner);
+
+    // 房主凭令牌重新连接后恢复房主身份
+    harness.reconnect_client(0);
+    assert_ne!(harness.client_id(0), owner_id);
+    assert!(try_enter_room(&mut harness, 0, room_id, ROOM_PASSWORD));
+    assert!(harness.current_room_info(0).is_owner);
+    room_admin(&mut harness, 0, RoomAdminCommand::Lock(true));
+    assert!(harness.room_list().get(room_id).unwrap().locked);
+    room_admin(&mut harness, 0, RoomAdminCommand::Lock(false));
+
+    // 被禁止的玩家重新连接并改名后仍不能进入
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::Ban {
+            player_client_id: bob_id,
+        },
+    );
+    harness.run_until("Bob banned", |harness| {
+        harness.app_state(1) == AppState::Lobby
+    });
+    harness.disconnect_client(1);
+    harness.reconnect_client(1);
+    harness.clients[1].world.resource_mut::<PlayerName>().0 = "Robert".to_string();
+    assert!(!try_enter_room(&mut harness, 1, room_id, ROOM_PASSWORD));
+}
+
+// 尝试用邀请码进入房间，返回是否成功
+fn try_join_by_invite(harness: &mut TestHarness, index: usize, invite_code: &str) -> bool {
+    harness.send_client_event(
+        index,
+        JoinByInviteEvent {
+            invite_code: invite_code.to_string(),
+        },
+    );
+    harness.tick();
+    harness.run_until("join by invite response", |harness| {
+        !harness.is_request_pending(index, RequestKind::EnterRoom)
+    });
+    harness.run_until("joined room synced", |harness| {
+        harness.app_state(index) == AppState::Lobby
+            || harness.current_room_info(index).view.seq.is_some()
+    });
+    harness.app_state(index) == AppState::Gaming
+}
+
+fn create_invite(harness: &mut TestHarness) -> String {
+    room_admin(harness, 0, RoomAdminCommand::CreateInvite);
+    harness.current_room_info(0).invite.clone().unwrap().code
+}
+
+#[test]
+fn private_rooms_use_hashed_passwords_invites_and_attempt_limits() {
+    let mut harness = TestHarness::new();
+    {
+        let mut settings = harness.server_settings_mut();
+        settings.access.invite_valid_secs = 2;
+        settings.access.max_failed_attempts = 3;
+        settings.access.attempt_window_secs = 2.0;
+    }
+    let room_id = setup_room(&mut harness, &["Alice"]);
+    let room = harness.room_list().get(room_id).unwrap();
+    assert!(room.is_private());
+    assert!(room.check_password(ROOM_PASSWORD));
+    assert!(!room.check_password("wrong"));
+
+    // 连续输错密码后暂时不能再尝试，换一个连接也不行，过了时间窗口后恢复
+    let bob = harness.add_client("Bob");
+    for _ in 0..3 {
+        assert!(!try_enter_room(&mut harness, bob, room_id, "wrong"));
+    }
+    assert!(!try_enter_room(&mut harness, bob, room_id, ROOM_PASSWORD));
+    let bob_again = harness.add_client("Bob");
+    assert!(!try_enter_room(
+        &mut harness,
+        bob_again,
+        room_id,
+        ROOM_PASSWORD
+    ));
+    for _ in 0..130 {
+        harness.tick();
+    }
+    assert!(try_enter_room(&mut harness, bob, room_id, ROOM_PASSWORD));
+
+    // 邀请码不需要密码，过期或撤销后失效
+    let carol = harness.add_client("Carol");
+    let invite_code = create_invite(&mut harness);
+    assert!(try_join_by_invite(&mut harness, carol, &invite_code));
+    assert_eq!(harness.current_room_info(carol).view.room_id, room_id);
+    let dave = harness.add_client("Dave");
+    for _ in 0..130 {
+        harness.tick();
+    }
+    assert!(!try_join_by_invite(&mut harness, dave, &invite_code));
+    let invite_code = create_invite(&mut harness);
+    room_admin(&mut harness, 0, RoomAdminCommand::RevokeInvites);
+    assert!(!try_join_by_invite(&mut harness, dave, &invite_code));
+    let invite_code = create_invite(&mut harness);
+    assert!(try_join_by_invite(&mut harness, dave, &invite_code));
+
+    // 取消密码后房间变为公开
+    room_admin(
+        &mut harness,
+        0,
+        RoomAdminCommand::ChangePassword(String::new()),
+    );
+    assert!(!harness.room_list().get(room_id).unwrap().is_private());
+    let eve = harness.add_client("Eve");
+    assert!(try_enter_room(&mut harness, eve, room_id, ""));
+}
@@ -0,0 +1,127 @@
+use texas_holdem_common::{
+    spectator::{SpectatorConfig, SpectatorDelay},
+    Player,
+};
+use texas_holdem_server::config::ServerSettings;
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        chips_of, create_room_with, fold_until_next_play, setup_room_with, start_play,
+        switch_to_participant, try_enter_room, ROOM_PASSWORD,
+    },
+};
+
+// Alice创建带旁观设置的房间，Bob、Carol进入后三人入座
+fn setup_spectator_room(harness: &mut TestHarness, spectators: SpectatorConfig) -> u64 {
+    let room_id = setup_room_with(harness, &["Alice", "Bob", "Carol"], |harness, owner| {
+        harness.set_new_room_spectators(owner, spectators)
+    });
+    switch_to_participant(harness, room_id, &[0, 1, 2]);
+    room_id
+}
+
+// 某个客户端看到的玩家
+fn viewed_player(harness: &TestHarness, index: usize, player_index: usize) -> Player {
+    let client_id = harness.client_id(player_index);
+    harness
+        .current_room_info(index)
+        .view
+        .players
+        .iter()
+        .find(|player| player.player_client_id == client_id)
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn spectators_are_capped_delayed_by_a_hand_and_hidden_from_participants() {
+    let mut harness = TestHarness::new();
+    let starting_chips = ServerSettings::default().stakes.starting_chips;
+    let spectators = SpectatorConfig {
+        max_spectators: 1,
+        delay: SpectatorDelay::Hand,
+        hide_names: true,
+    };
+    let (_, room_id) = create_room_with(&mut harness, "Alice", |harness, owner| {
+        harness.set_new_room_spectators(owner, spectators)
+    });
+
+    // 进入房间的玩家先旁观，旁观位满时需等前一位入座
+    for (player_name, previous) in [("Bob", 0), ("Carol", 1)] {
+        let index = harness.add_client(player_name);
+        assert!(!try_enter_room(&mut harness, index, room_id, ROOM_PASSWORD));
+        switch_to_participant(&mut harness, room_id, &[previous]);
+        assert!(try_enter_room(&mut harness, index, room_id, ROOM_PASSWORD));
+    }
+    switch_to_participant(&mut harness, room_id, &[2]);
+    let dave = harness.add_client("Dave");
+    assert!(try_enter_room(&mut harness, dave, room_id, ROOM_PASSWORD));
+    let eve = harness.add_client("Eve");
+    assert!(!try_enter_room(&mut harness, eve, room_id, ROOM_PASSWORD));
+
+    // 参与者看不到旁观者的名字，旁观者之间可见
+    harness.run_until("Dave visible to Alice", |harness| {
+        harness
+            .current_room_info(0)
+            .view
+            .players
+            .iter()
+            .any(|player| player.player_client_id == harness.client_id(dave))
+    });
+    assert_eq!(
+        viewed_player(&harness, 0, dave).player_name,
+        format!("Spectator {}", harness.user_id(dave).unwrap())
+    );
+    assert_eq!(viewed_player(&harness, dave, dave).player_name, "Dave");
+
+    // 旁观者在一局结束后才能看到这一局
+    let play_id = start_play(&mut harness, room_id);
+    for _ in 0..60 {
+        harness.tick();
+    }
+    assert!(harness.current_room_info(dave).view.play.is_none());
+    fold_until_next_play(&mut harness, room_id, play_id);
+    harness.run_until("Dave sees the first hand settle", |harness| {
+        (0..3).any(|index| viewed_player(harness, dave, index).chips != starting_chips)
+    });
+    for index in 0..3 {
+        assert_eq!(
+            viewed_player(&harness, dave, index).chips,
+            chips_of(&harness, room_id, index)
+        );
+    }
+    assert!(harness.current_room_info(dave).view.play.is_none());
+
+    // 入座后立即看到实时的牌局，参与者看到真实名字
+    switch_to_participant(&mut harness, room_id, &[dave]);
+    harness.run_until("Dave sees the live hand", |harness| {
+        harness.current_room_info(dave).view.play.is_some()
+            && viewed_player(harness, 0, dave).player_name == "Dave"
+    });
+}
+
+#[test]
+fn seconds_delay_holds_back_the_play_for_spectators() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_spectator_room(
+        &mut harness,
+        SpectatorConfig {
+            delay: SpectatorDelay::Seconds(2),
+            ..Default::default()
+        },
+    );
+    let dave = harness.add_client("Dave");
+    assert!(try_enter_room(&mut harness, dave, room_id, ROOM_PASSWORD));
+    assert_eq!(viewed_player(&harness, 0, dave).player_name, "Dave");
+
+    start_play(&mut harness, room_id);
+    for _ in 0..100 {
+        harness.tick();
+    }
+    assert!(harness.current_room_info(dave).view.play.is_none());
+    harness.run_until("Dave sees the delayed play", |harness| {
+        harness.current_room_info(dave).view.play.is_some()
+    });
+    assert!(harness.current_room_info(0).view.play.is_some());
+}
@@ -0,0 +1,319 @@
+use bevy::prelude::NextState;
+use texas_holdem_client::{
+    room::{SetRoomStateEvent, SwitchPlayerRoleEvent},
+    AppState,
+};
+use texas_holdem_common::{
+    tournament::{BlindLevel, LevelUp, TournamentConfig},
+    PlayerRole, RoomState, RoundAction,
+};
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        assert_clients_in_sync, create_room_with, current_player_index, setup_room_with,
+        switch_to_participant, try_enter_room, ROOM_PASSWORD,
+    },
+};
+
+// 每位玩家轮到时都全下，直到锦标赛结束
+fn play_all_in_until_finished(harness: &mut TestHarness, tournament_room_id: u64) {
+    while harness
+        .tournament_of(tournament_room_id)
+        .unwrap()
+        .is_running()
+    {
+        match current_player_index(harness) {
+            Some((index, play_id, room_id)) => {
+                harness.send_action(index, play_id, RoundAction::AllIn, 0);
+                let seq = harness.room_list().get(room_id).unwrap().sync_seq;
+                harness.run_until("all-in applied", |harness| {
+                    harness.room_list().get(room_id).unwrap().sync_seq > seq
+                });
+            }
+            None => harness.tick(),
+        }
+    }
+}
+
+// 客户端所在的服务端房间id
+fn server_room_of(harness: &TestHarness, index: usize) -> u64 {
+    harness
+        .room_list()
+        .room_of_client(harness.client_id(index))
+        .unwrap()
+        .room_id
+}
+
+#[test]
+fn tournament_runs_to_completion() {
+    let mut harness = TestHarness::new();
+    let config = TournamentConfig {
+        seats: 3,
+        tables: 1,
+        starting_stack: 200,
+        buy_in: 100,
+        blind_levels: vec![
+            BlindLevel {
+                small_blind: 10,
+                big_blind: 20,
+            },
+            BlindLevel {
+                small_blind: 20,
+                big_blind: 40,
+            },
+        ],
+        level_up: LevelUp::Hands(1),
+        payouts: vec![70, 30],
+    };
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_tournament(owner, config),
+    );
+    let room = harness.room_list().get(room_id).unwrap();
+    assert_eq!(room.room_state, RoomState::Registering);
+    assert_eq!((room.small_blind, room.big_blind), (10, 20));
+
+    // 坐满后自动开始
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    harness.run_until("tournament running", |harness| {
+        harness.room_list().get(room_id).unwrap().room_state == RoomState::Running
+    });
+    // 开始后不能再切换角色
+    harness.send_client_event(
+        2,
+        SwitchPlayerRoleEvent {
+            room_id,
+            target_player_role: PlayerRole::Spectator,
+        },
+    );
+
+    play_all_in_until_finished(&mut harness, room_id);
+    assert_eq!(
+        harness.room_list().get(room_id).unwrap().room_state,
+        RoomState::Finished
+    );
+
+    let room = harness.room_list().get(room_id).unwrap();
+    let tournament = harness.tournament_of(room_id).unwrap();
+    assert_eq!(tournament.prize_pool, 300);
+    assert!(tournament.level >= 1);
+    assert_eq!((room.small_blind, room.big_blind), (20, 40));
+    let mut places: Vec<u32> = tournament
+        .results
+        .iter()
+        .map(|result| result.place)
+        .collect();
+    places.sort_unstable();
+    assert_eq!(places, vec![1, 2, 3]);
+    let prizes: u32 = tournament.results.iter().map(|result| result.prize).sum();
+    assert_eq!(prizes, tournament.prize_pool);
+    // 冠军拿走所有筹码，其余玩家转为旁观者
+    let winner = &tournament
+        .results
+        .iter()
+        .find(|result| result.place == 1)
+        .unwrap()
+        .player_name;
+    for player in room.players.iter() {
+        if &player.player_name == winner {
+            assert_eq!(player.chips, 600);
+            assert_eq!(player.player_role, PlayerRole::Participant);
+        } else {
+            assert_eq!(player.chips, 0);
+            assert_eq!(player.player_role, PlayerRole::Spectator);
+        }
+    }
+    assert!(harness.play_list().plays.is_empty());
+
+    harness.run_until("clients see tournament finished", |harness| {
+        let sync_seq = harness.room_list().get(room_id).unwrap().sync_seq;
+        (0..3).all(|index| harness.current_room_info(index).view.seq == Some(sync_seq))
+    });
+    assert_clients_in_sync(&harness, room_id);
+    let results = &harness.tournament_of(room_id).unwrap().results;
+    for index in 0..3 {
+        let view = &harness.current_room_info(index).view;
+        let view_tournament = view.tournament.as_ref().unwrap();
+        assert_eq!(&view_tournament.results, results);
+        assert_eq!((view.small_blind, view.big_blind), (20, 40));
+    }
+}
+
+#[test]
+fn tournament_player_cannot_leave_for_another_room() {
+    let mut harness = TestHarness::new();
+    let config = TournamentConfig {
+        seats: 3,
+        tables: 1,
+        starting_stack: 200,
+        buy_in: 100,
+        blind_levels: vec![BlindLevel {
+            small_blind: 10,
+            big_blind: 20,
+        }],
+        level_up: LevelUp::Hands(1),
+        payouts: vec![100],
+    };
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_tournament(owner, config),
+    );
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    harness.run_until("tournament running", |harness| {
+        harness.room_list().get(room_id).unwrap().room_state == RoomState::Running
+    });
+    let (_, cash_room_id) = create_room_with(&mut harness, "Dave", |_, _| {});
+
+    // 参赛玩家回到大厅后不能进入其他房间，仍留在锦标赛桌上
+    harness.clients[0]
+        .world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Lobby);
+    harness.tick();
+    assert!(!try_enter_room(
+        &mut harness,
+        0,
+        cash_room_id,
+        ROOM_PASSWORD
+    ));
+    assert_eq!(server_room_of(&harness, 0), room_id);
+
+    play_all_in_until_finished(&mut harness, room_id);
+    let tournament = harness.tournament_of(room_id).unwrap();
+    let mut places: Vec<u32> = tournament
+        .results
+        .iter()
+        .map(|result| result.place)
+        .collect();
+    places.sort_unstable();
+    assert_eq!(places, vec![1, 2, 3]);
+    assert_eq!(
+        harness.room_list().get(room_id).unwrap().room_state,
+        RoomState::Finished
+    );
+}
+
+#[test]
+fn multi_table_tournament_consolidates_to_final_table() {
+    let mut harness = TestHarness::new();
+    let config = TournamentConfig {
+        seats: 3,
+        tables: 2,
+        starting_stack: 200,
+        buy_in: 100,
+        blind_levels: vec![BlindLevel {
+            small_blind: 10,
+            big_blind: 20,
+        }],
+        level_up: LevelUp::Seconds(600),
+        payouts: vec![50, 30, 20],
+    };
+    let player_names = ["Alice", "Bob", "Carol", "Dave", "Erin"];
+    let room_id = setup_room_with(&mut harness, &player_names, |harness, owner| {
+        harness.set_new_room_tournament(owner, config)
+    });
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2, 3, 4]);
+
+    // 未报满时房主提前开始，5人分到两张桌
+    harness.send_client_event(
+        0,
+        SetRoomStateEvent {
+            target_room_state: RoomState::Running,
+        },
+    );
+    harness.run_until("clients seated at their tables", |harness| {
+        harness
+            .tournament_of(room_id)
+            .is_some_and(|tournament| tournament.tables.len() == 2)
+            && (0..5).all(|index| {
+                let room = harness
+                    .room_list()
+                    .room_of_client(harness.client_id(index))
+                    .unwrap();
+                let view = &harness.current_room_info(index).view;
+                view.room_id == room.room_id && view.seq == Some(room.sync_seq)
+            })
+    });
+    let tables = harness.tournament_of(room_id).unwrap().tables.clone();
+    let mut table_sizes: Vec<usize> = tables
+        .iter()
+        .map(|table| harness.room_list().get(*table).unwrap().players.len())
+        .collect();
+    table_sizes.sort_unstable();
+    assert_eq!(table_sizes, vec![2, 3]);
+    let new_table = harness.room_list().get(tables[1]).unwrap();
+    assert_eq!(new_table.room_state, RoomState::Running);
+    assert_eq!(new_table.tournament_id, Some(1));
+    for index in 0..5 {
+        assert_eq!(
+            harness.current_room_info(index).my_role,
+            PlayerRole::Participant
+        );
+    }
+    let initial_rooms: Vec<u64> = (0..5)
+        .map(|index| server_room_of(&harness, index))
+        .collect();
+
+    play_all_in_until_finished(&mut harness, room_id);
+
+    let tournament = harness.tournament_of(room_id).unwrap();
+    // 拆桌后只剩决赛桌
+    assert_eq!(tournament.tables.len(), 1);
+    let final_table = tournament.tables[0];
+    let mut places: Vec<u32> = tournament
+        .results
+        .iter()
+        .map(|result| result.place)
+        .collect();
+    places.sort_unstable();
+    assert_eq!(places, vec![1, 2, 3, 4, 5]);
+    let prizes: u32 = tournament.results.iter().map(|result| result.prize).sum();
+    assert_eq!(prizes, 500);
+    let winner = &tournament
+        .results
+        .iter()
+        .find(|result| result.place == 1)
+        .unwrap()
+        .player_name;
+    let final_room = harness.room_list().get(final_table).unwrap();
+    assert_eq!(final_room.room_state, RoomState::Finished);
+    let champion = final_room
+        .players
+        .iter()
+        .find(|player| &player.player_name == winner)
+        .unwrap();
+    assert_eq!(champion.chips, 1000);
+    for table in tables.iter() {
+        assert_eq!(
+            harness.room_list().get(*table).unwrap().room_state,
+            RoomState::Finished
+        );
+    }
+    assert!(harness.play_list().plays.is_empty());
+
+    // 被移桌的客户端跟随到新桌并保持同步
+    harness.run_until("clients in sync with their tables", |harness| {
+        (0..5).all(|index| {
+            let room = harness
+                .room_list()
+                .room_of_client(harness.client_id(index))
+                .unwrap();
+            let view = &harness.current_room_info(index).view;
+            view.room_id == room.room_id && view.seq == Some(room.sync_seq)
+        })
+    });
+    assert!((0..5).any(|index| server_room_of(&harness, index) != initial_rooms[index]));
+    for index in 0..5 {
+        let room = harness
+            .room_list()
+            .get(server_room_of(&harness, index))
+            .unwrap();
+        let view = &harness.current_room_info(index).view;
+        assert_eq!(view.room_state, room.room_state);
+        assert_eq!(view.players.len(), room.players.len());
+        assert_eq!(view.tournament.as_ref().unwrap().results.len(), 5);
+    }
+}
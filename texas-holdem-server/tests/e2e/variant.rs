@@ -0,0 +1,257 @@
+use texas_holdem_client::room::PlayerActionEvent;
+use texas_holdem_common::{
+    betting::{BettingStructure, FIXED_LIMIT_RAISE_CAP},
+    hand::HandCategory,
+    variant::GameVariant,
+    Card, Round, RoundAction,
+};
+
+use crate::{
+    common::TestHarness,
+    fixtures::{
+        call_until_round_ends, current_player_index, setup_room_with, start_play,
+        switch_to_participant, try_action,
+    },
+};
+
+#[test]
+fn pot_limit_omaha_deals_four_cards_and_caps_raises() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_variant(owner, GameVariant::Omaha),
+    );
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+    harness.run_until("clients receive four hole cards", |harness| {
+        (0..3).all(|index| harness.current_room_info(index).view.hole_cards.len() == 4)
+    });
+    assert!((0..3).all(|index| harness.current_room_info(index).view.variant == GameVariant::Omaha));
+
+    // 底池限注：最多加注到 当前下注 + 跟注后的底池
+    let (index, _, _) = current_player_index(&harness).unwrap();
+    let play = &harness.play_list().plays[0];
+    let max_raise_to = play.current_bet * 2 + play.pot;
+    assert!(!try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::Raise,
+        max_raise_to + 1
+    ));
+    assert!(!try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::AllIn,
+        0
+    ));
+    assert!(try_action(
+        &mut harness,
+        index,
+        play_id,
+        room_id,
+        RoundAction::Raise,
+        max_raise_to
+    ));
+    assert_eq!(harness.play_list().plays[0].current_bet, max_raise_to);
+
+    // 其余玩家跟注或过牌直到摊牌
+    while harness.play_list().plays[0].round != Round::Showdown {
+        call_until_round_ends(&mut harness, play_id, room_id);
+    }
+    let showdown = harness.play_list().plays[0].showdown.clone().unwrap();
+    assert_eq!(showdown.shown_hands.len(), 3);
+    assert!(showdown
+        .shown_hands
+        .iter()
+        .all(|shown_hand| shown_hand.cards.len() == 4));
+}
+
+#[test]
+fn fixed_limit_enforces_bet_sizes_and_raise_cap() {
+    let mut harness = TestHarness::new();
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_betting(owner, BettingStructure::FixedLimit),
+    );
+    assert!((0..3).all(|index| {
+        harness.current_room_info(index).view.betting == BettingStructure::FixedLimit
+    }));
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+    let big_blind = harness.play_list().plays[0].big_blind;
+
+    // 翻牌前大盲注算第一次下注，每次只能加注一个小注，加到封顶为止
+    for raises in 1..FIXED_LIMIT_RAISE_CAP {
+        let (index, _, _) = current_player_index(&harness).unwrap();
+        let current_bet = harness.play_list().plays[0].current_bet;
+        assert!(!try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::Raise,
+            current_bet + big_blind * 2
+        ));
+        assert!(try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::Raise,
+            current_bet + big_blind
+        ));
+        assert_eq!(harness.play_list().plays[0].raises, raises + 1);
+    }
+    let (index, _, _) = current_player_index(&harness).unwrap();
+    let current_bet = harness.play_list().plays[0].current_bet;
+    assert_eq!(current_bet, big_blind * FIXED_LIMIT_RAISE_CAP);
+    harness.run_until("client sees raise cap", |harness| {
+        let view = &harness.current_room_info(index).view;
+        view.play.as_ref().is_some_and(|play| {
+            play.raises == FIXED_LIMIT_RAISE_CAP
+                && view
+                    .betting
+                    .raise_range(&play.bet_state(&play.current_player_name.clone().unwrap()))
+                    .is_none()
+        })
+    });
+    for (action, amount) in [
+        (RoundAction::Raise, current_bet + big_blind),
+        (RoundAction::AllIn, 0),
+    ] {
+        assert!(!try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            action,
+            amount
+        ));
+    }
+    call_until_round_ends(&mut harness, play_id, room_id);
+
+    // 翻牌圈下注一个小注，转牌圈下注一个大注，通过客户端的行动事件发送
+    for (round, bet_size) in [(Round::Flop, big_blind), (Round::Turn, big_blind * 2)] {
+        assert_eq!(harness.play_list().plays[0].round, round);
+        let (index, _, _) = current_player_index(&harness).unwrap();
+        assert!(!try_action(
+            &mut harness,
+            index,
+            play_id,
+            room_id,
+            RoundAction::Bet,
+            bet_size + 1
+        ));
+        harness.run_until("client sees its turn", |harness| {
+            let view = &harness.current_room_info(index).view;
+            view.seq == Some(harness.room_list().get(room_id).unwrap().sync_seq)
+        });
+        harness.send_client_event(
+            index,
+            PlayerActionEvent {
+                action: RoundAction::Bet,
+                amount: bet_size,
+            },
+        );
+        harness.run_until("bet applied", |harness| {
+            harness.play_list().plays[0].current_bet == bet_size
+        });
+        call_until_round_ends(&mut harness, play_id, room_id);
+    }
+    call_until_round_ends(&mut harness, play_id, room_id);
+    assert_eq!(harness.play_list().plays[0].round, Round::Showdown);
+    let showdown = harness.play_list().plays[0].showdown.clone().unwrap();
+    assert_eq!(
+        showdown.winnings.values().sum::<u32>(),
+        (big_blind * FIXED_LIMIT_RAISE_CAP + big_blind + big_blind * 2) * 3
+    );
+}
+
+#[test]
+fn short_deck_uses_36_cards_and_its_own_hand_ranking() {
+    // 同花大于葫芦，A-6-7-8-9为顺子
+    let flush = [
+        Card::Heart(1),
+        Card::Heart(7),
+        Card::Heart(9),
+        Card::Heart(11),
+        Card::Heart(12),
+    ];
+    let full_house = [
+        Card::Spade(13),
+        Card::Heart(13),
+        Card::Club(13),
+        Card::Spade(10),
+        Card::Club(10),
+    ];
+    let wheel = [
+        Card::Spade(1),
+        Card::Heart(6),
+        Card::Club(7),
+        Card::Diamond(8),
+        Card::Spade(9),
+    ];
+    let short_deck = GameVariant::ShortDeck;
+    assert!(
+        short_deck.evaluate(&flush[..2], &flush[2..])
+            > short_deck.evaluate(&full_house[..2], &full_house[2..])
+    );
+    assert!(
+        GameVariant::Holdem.evaluate(&flush[..2], &flush[2..])
+            < GameVariant::Holdem.evaluate(&full_house[..2], &full_house[2..])
+    );
+    let wheel_value = short_deck.evaluate(&wheel[..2], &wheel[2..]);
+    assert_eq!(wheel_value.category, HandCategory::Straight);
+    assert_eq!(wheel_value.ranks, vec![9]);
+    assert_eq!(
+        GameVariant::Holdem
+            .evaluate(&wheel[..2], &wheel[2..])
+            .category,
+        HandCategory::HighCard
+    );
+
+    let mut harness = TestHarness::new();
+    let room_id = setup_room_with(
+        &mut harness,
+        &["Alice", "Bob", "Carol"],
+        |harness, owner| harness.set_new_room_variant(owner, GameVariant::ShortDeck),
+    );
+    switch_to_participant(&mut harness, room_id, &[0, 1, 2]);
+    let play_id = start_play(&mut harness, room_id);
+    let play = &harness.play_list().plays[0];
+    assert_eq!(play.card_pool.len() + 2 * 3, 36);
+
+    while harness.play_list().plays[0].round != Round::Showdown {
+        call_until_round_ends(&mut harness, play_id, room_id);
+    }
+    let play = &harness.play_list().plays[0];
+    let board_cards = play.board_cards();
+    let mut dealt_cards = board_cards.clone();
+    dealt_cards.extend(play.hole_cards.values().flatten());
+    dealt_cards.extend(play.card_pool.iter());
+    assert_eq!(dealt_cards.len(), 36);
+    assert!(dealt_cards.iter().all(|card| card.rank() >= 6));
+
+    // 赢家的牌力按短牌规则最大
+    let showdown = play.showdown.clone().unwrap();
+    let values: Vec<_> = showdown
+        .shown_hands
+        .iter()
+        .map(|shown_hand| {
+            (
+                shown_hand.player_name.clone(),
+                short_deck.evaluate(&shown_hand.cards, &board_cards),
+            )
+        })
+        .collect();
+    let best = values.iter().map(|(_, value)| value).max().unwrap();
+    for (player_name, value) in values.iter() {
+        assert_eq!(showdown.winnings.contains_key(player_name), value == best);
+    }
+}